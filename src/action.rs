@@ -0,0 +1,73 @@
+use serde::Deserialize;
+
+/// 动作序列 ofd:Actions，可出现在图元、页面与大纲节点中
+#[derive(Debug, Deserialize, Default)]
+#[serde(default, rename_all = "PascalCase")]
+pub(crate) struct Actions {
+    pub(crate) action: Vec<Action>,
+}
+
+impl Actions {
+    /// 第一个可作为链接的动作的目标，URI 与跳转动作按出现的顺序取第一个
+    pub(crate) fn link_target(&self) -> Option<LinkTarget> {
+        self.action.iter().find_map(Action::link_target)
+    }
+
+    /// 第一个带有目标页面的跳转动作所指向的页面标识
+    pub(crate) fn goto_page_id(&self) -> Option<&str> {
+        self.action.iter().find_map(Action::goto_page_id)
+    }
+}
+
+/// 动作 CT_Action，只读取跳转与 URI 两类，声音、视频等动作忽略；
+/// 动作的触发区域 Region 不读取，以所在图元的外接矩形作为触发区域
+#[derive(Debug, Deserialize, Default)]
+#[serde(default, rename_all = "PascalCase")]
+pub(crate) struct Action {
+    pub(crate) goto: Option<Goto>,
+    #[serde(rename = "URI")]
+    pub(crate) uri: Option<Uri>,
+}
+
+impl Action {
+    fn link_target(&self) -> Option<LinkTarget> {
+        if let Some(uri) = self.uri.as_ref().map(|u| u.uri.trim()).filter(|u| !u.is_empty()) {
+            return Some(LinkTarget::Uri(uri.to_string()));
+        }
+        self.goto_page_id().map(|id| LinkTarget::Page(id.to_string()))
+    }
+
+    fn goto_page_id(&self) -> Option<&str> {
+        self.goto.as_ref()?.dest.as_ref()
+            .map(|dest| dest.page_id.trim())
+            .filter(|id| !id.is_empty())
+    }
+}
+
+#[derive(Debug, Deserialize, Default)]
+#[serde(default, rename_all = "PascalCase")]
+pub(crate) struct Goto {
+    pub(crate) dest: Option<Dest>,
+}
+
+/// 跳转目标 CT_Dest，只使用目标页面
+#[derive(Debug, Deserialize, Default)]
+pub(crate) struct Dest {
+    #[serde(rename = "PageID", default)]
+    pub(crate) page_id: String,
+}
+
+/// URI 动作的目标地址
+#[derive(Debug, Deserialize, Default)]
+pub(crate) struct Uri {
+    #[serde(rename = "URI", default)]
+    pub(crate) uri: String,
+}
+
+/// 链接的目标
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub(crate) enum LinkTarget {
+    Uri(String),
+    /// 文档内的页面，值为页面标识
+    Page(String),
+}
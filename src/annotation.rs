@@ -0,0 +1,138 @@
+use serde::Deserialize;
+use xml::reader::{EventReader, XmlEvent};
+
+use crate::color::CTColor;
+use crate::page::{collect_objects, resolve_line_width, DrawStyle, PageObject};
+use crate::resource::DrawParam;
+use crate::st_types::{deserialize_st, deserialize_st_opt, STArray, STBox};
+
+/// 注释入口文件 Annotations.xml
+#[derive(Debug, Deserialize, Default)]
+#[serde(default, rename_all = "PascalCase")]
+pub(crate) struct Annotations {
+    pub(crate) page: Vec<AnnotationPage>,
+}
+
+impl Annotations {
+    pub(crate) fn from_xml(xml: &str) -> Result<Annotations, serde_xml_rs::Error> {
+        serde_xml_rs::from_str(xml)
+    }
+}
+
+/// 一个页面的注释，FileLoc 为分页注释文件，路径相对于 Annotations.xml 所在目录
+#[derive(Debug, Deserialize, Default)]
+#[serde(default, rename_all = "PascalCase")]
+pub(crate) struct AnnotationPage {
+    #[serde(rename = "PageID")]
+    pub(crate) page_id: String,
+    pub(crate) file_loc: String,
+}
+
+/// 分页注释文件 PageAnnot，其中的各个注释属于同一页面
+#[derive(Debug, Deserialize, Default)]
+#[serde(default, rename_all = "PascalCase")]
+pub(crate) struct PageAnnot {
+    pub(crate) annot: Vec<Annot>,
+}
+
+impl PageAnnot {
+    pub(crate) fn from_xml(xml: &str) -> Result<PageAnnot, serde_xml_rs::Error> {
+        serde_xml_rs::from_str(xml)
+    }
+}
+
+/// 一个注释 ofd:Annot，类型可以为 Link、Path、Highlight、Stamp、Watermark
+/// 各类注释都按外观绘制，因此只读取外观、边框与可见性，Remark 与 Parameters 不读取
+#[derive(Debug, Deserialize)]
+pub(crate) struct Annot {
+    /// 为 false 时注释不显示
+    #[serde(rename = "Visible", default = "default_true")]
+    pub(crate) visible: bool,
+    #[serde(rename = "Appearance")]
+    pub(crate) appearance: Option<Appearance>,
+    /// 沿外观的外接矩形绘制的边框，未定义时不绘制
+    #[serde(rename = "Border")]
+    pub(crate) border: Option<AnnotBorder>,
+}
+
+/// 注释边框，线宽缺省为规范的缺省线宽，为 0 时不绘制；颜色缺省为黑色，没有虚线样式时为实线
+#[derive(Debug, Deserialize)]
+pub(crate) struct AnnotBorder {
+    #[serde(rename = "LineWidth")]
+    pub(crate) line_width: Option<f64>,
+    #[serde(rename = "DashOffset")]
+    pub(crate) dash_offset: Option<f64>,
+    #[serde(rename = "DashPattern", deserialize_with = "deserialize_st_opt", default)]
+    pub(crate) dash_pattern: Option<STArray>,
+    #[serde(rename = "BorderColor")]
+    pub(crate) border_color: Option<CTColor>,
+}
+
+impl AnnotBorder {
+    /// 线宽为 0 或负数时不绘制边框
+    pub(crate) fn is_visible(&self) -> bool {
+        self.line_width.is_none_or(|w| w > 0.0)
+    }
+
+    /// 勾边使用的样式，端点与连接方式取缺省值
+    pub(crate) fn style(&self) -> DrawStyle {
+        DrawStyle {
+            line_width: resolve_line_width(self.line_width, &DrawParam::default()),
+            dash_offset: self.dash_offset,
+            dash_pattern: self.dash_pattern.clone(),
+            stroke_color: self.border_color.clone().unwrap_or_default(),
+            ..DrawStyle::default()
+        }
+    }
+}
+
+fn default_true() -> bool {
+    true
+}
+
+/// 注释的静态外观，其中图元的坐标相对于 Boundary 的左上角，并裁剪到 Boundary 内
+#[derive(Debug, Deserialize)]
+pub(crate) struct Appearance {
+    #[serde(rename = "Boundary", deserialize_with = "deserialize_st")]
+    pub(crate) boundary: STBox,
+    #[serde(rename = "$value", default)]
+    pub(crate) objects: Vec<PageObject>,
+}
+
+impl Appearance {
+    /// 按绘制顺序遍历其中的所有图元，嵌套的 PageBlock 会被展开
+    pub(crate) fn objects(&self) -> Vec<&PageObject> {
+        let mut result = Vec::new();
+        collect_objects(&self.objects, &mut result);
+        result
+    }
+}
+
+/// 页面内容或注释中的一个 URI 动作
+#[derive(Debug, Clone, PartialEq)]
+pub struct LinkInfo {
+    /// 目标地址，对应 URI 节点的 URI 属性
+    pub url: String,
+    /// 所在页面的序号，从 0 开始
+    pub page_index: usize,
+}
+
+/// 按出现顺序收集 XML 中各 ofd:URI 动作的目标地址
+/// 动作可以挂在图元、页面或注释外观中的任意图元上，因此不按结构解析，只查找 URI 节点
+pub(crate) fn collect_uris(xml: &str) -> Result<Vec<String>, serde_xml_rs::Error> {
+    let mut uris = Vec::new();
+    for event in EventReader::from_str(xml) {
+        if let XmlEvent::StartElement { name, attributes, .. } = event? {
+            if name.local_name != "URI" {
+                continue;
+            }
+            if let Some(uri) = attributes.iter().find(|a| a.name.local_name == "URI") {
+                let uri = uri.value.trim();
+                if !uri.is_empty() {
+                    uris.push(uri.to_string());
+                }
+            }
+        }
+    }
+    Ok(uris)
+}
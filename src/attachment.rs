@@ -0,0 +1,61 @@
+use serde::Deserialize;
+
+/// 附件列表文件 Attachments.xml
+#[derive(Debug, Deserialize, Default)]
+#[serde(default, rename_all = "PascalCase")]
+pub(crate) struct Attachments {
+    pub(crate) attachment: Vec<Attachment>,
+}
+
+impl Attachments {
+    pub(crate) fn from_xml(xml: &str) -> Result<Attachments, serde_xml_rs::Error> {
+        serde_xml_rs::from_str(xml)
+    }
+}
+
+/// 一个附件 CT_Attachment，FileLoc 为附件文件，路径相对于 Attachments.xml 所在目录
+#[derive(Debug, Deserialize, Default)]
+#[serde(default)]
+pub(crate) struct Attachment {
+    #[serde(rename = "ID")]
+    pub(crate) id: String,
+    #[serde(rename = "Name")]
+    pub(crate) name: String,
+    #[serde(rename = "Format")]
+    pub(crate) format: Option<String>,
+    #[serde(rename = "FileLoc")]
+    pub(crate) file_loc: String,
+}
+
+/// 文档中的一个附件
+#[derive(Debug, Clone, PartialEq)]
+pub struct AttachmentInfo {
+    pub id: String,
+    /// 附件名称，read_attachment 按此名称查找
+    pub name: String,
+    /// 附件格式，如 xml、pdf，未声明时为空字符串
+    pub format: String,
+    /// 附件文件在包内的完整路径
+    pub location: String,
+}
+
+/// 多媒体资源的类型，无法识别的类型按图像处理
+#[derive(Debug, Deserialize, Default, Clone, Copy, PartialEq, Eq)]
+pub enum MediaType {
+    Audio,
+    Video,
+    #[default]
+    #[serde(other)]
+    Image,
+}
+
+/// 资源文件中声明的音频或视频，不参与渲染，可按标识读取其内容
+#[derive(Debug, Clone, PartialEq)]
+pub struct MediaInfo {
+    pub id: String,
+    pub media_type: MediaType,
+    /// 声明的格式，如 MP4、MP3，未声明时为空字符串
+    pub format: String,
+    /// 多媒体文件在包内的完整路径
+    pub location: String,
+}
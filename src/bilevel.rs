@@ -0,0 +1,187 @@
+use image::{Rgba, RgbaImage};
+
+use crate::ccitt;
+use crate::render::luminance;
+use crate::jbig2;
+
+/// 二值图像允许的最大像素数，防止损坏的尺寸字段造成过大的内存分配
+const MAX_PIXELS: usize = 1 << 28;
+
+/// 二值图像，每个像素占一个字节，1 为黑色，0 为白色
+#[derive(Debug, Clone)]
+pub(crate) struct Bitmap {
+    pub(crate) width: usize,
+    pub(crate) height: usize,
+    pixels: Vec<u8>,
+}
+
+impl Bitmap {
+    /// 创建全部为 value 的图像，尺寸为 0 或过大时返回 None
+    pub(crate) fn new(width: usize, height: usize, value: u8) -> Option<Bitmap> {
+        let size = width.checked_mul(height).filter(|size| *size <= MAX_PIXELS)?;
+        Some(Bitmap { width, height, pixels: vec![value; size] })
+    }
+
+    /// 读取像素，超出图像范围的位置按白色处理
+    pub(crate) fn get(&self, x: isize, y: isize) -> u8 {
+        if x < 0 || y < 0 || x as usize >= self.width || y as usize >= self.height {
+            return 0;
+        }
+        self.pixels[y as usize * self.width + x as usize]
+    }
+
+    pub(crate) fn row(&self, y: usize) -> &[u8] {
+        &self.pixels[y * self.width..(y + 1) * self.width]
+    }
+
+    pub(crate) fn row_mut(&mut self, y: usize) -> &mut [u8] {
+        &mut self.pixels[y * self.width..(y + 1) * self.width]
+    }
+
+    pub(crate) fn copy_row(&mut self, from: usize, to: usize) {
+        self.pixels.copy_within(from * self.width..(from + 1) * self.width, to * self.width);
+    }
+
+    /// 在高度方向上扩展到 height 行，新增的行取 value
+    pub(crate) fn grow(&mut self, height: usize, value: u8) -> Option<()> {
+        if height <= self.height {
+            return Some(());
+        }
+        let size = self.width.checked_mul(height).filter(|size| *size <= MAX_PIXELS)?;
+        self.pixels.resize(size, value);
+        self.height = height;
+        Some(())
+    }
+
+    /// 将 region 按组合方式合成到 (x, y) 处，超出本图像的部分丢弃
+    pub(crate) fn compose(&mut self, region: &Bitmap, x: usize, y: usize, operator: CombinationOperator) {
+        if x >= self.width {
+            return;
+        }
+        for row in 0..region.height {
+            let target_y = y.saturating_add(row);
+            if target_y >= self.height {
+                break;
+            }
+            let source = &region.pixels[row * region.width..(row + 1) * region.width];
+            let width = region.width.min(self.width.saturating_sub(x));
+            let target = &mut self.pixels[target_y * self.width + x..target_y * self.width + x + width];
+            for (target, source) in target.iter_mut().zip(source) {
+                *target = operator.apply(*target, *source);
+            }
+        }
+    }
+
+    /// 由彩色图像生成二值图像，先与白色背景合成，亮度低于一半的像素为黑色
+    pub(crate) fn from_rgba(image: &RgbaImage) -> Bitmap {
+        let pixels = image
+            .pixels()
+            .map(|pixel| {
+                let [r, g, b, a] = pixel.0.map(u32::from);
+                let over_white = |c: u32| ((c * a + 255 * (255 - a)) / 255) as u8;
+                u8::from(luminance([over_white(r), over_white(g), over_white(b), 255]) < 128)
+            })
+            .collect();
+        Bitmap { width: image.width() as usize, height: image.height() as usize, pixels }
+    }
+
+    /// 扩展为灰度图像，黑色像素为 0，白色像素为 255
+    pub(crate) fn to_rgba(&self) -> RgbaImage {
+        RgbaImage::from_fn(self.width as u32, self.height as u32, |x, y| {
+            let gray = if self.pixels[y as usize * self.width + x as usize] != 0 { 0 } else { 255 };
+            Rgba([gray, gray, gray, 255])
+        })
+    }
+}
+
+/// 区域与页面合成的方式
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub(crate) enum CombinationOperator {
+    Or,
+    And,
+    Xor,
+    Xnor,
+    Replace,
+}
+
+impl CombinationOperator {
+    /// 由 JBIG2 中的编号得到合成方式，未定义的编号返回 None
+    pub(crate) fn from_code(code: u8) -> Option<CombinationOperator> {
+        match code {
+            0 => Some(CombinationOperator::Or),
+            1 => Some(CombinationOperator::And),
+            2 => Some(CombinationOperator::Xor),
+            3 => Some(CombinationOperator::Xnor),
+            4 => Some(CombinationOperator::Replace),
+            _ => None,
+        }
+    }
+
+    fn apply(self, target: u8, source: u8) -> u8 {
+        match self {
+            CombinationOperator::Or => target | source,
+            CombinationOperator::And => target & source,
+            CombinationOperator::Xor => target ^ source,
+            CombinationOperator::Xnor => 1 - (target ^ source),
+            CombinationOperator::Replace => source,
+        }
+    }
+}
+
+/// 二值图像解码失败的原因
+#[derive(Debug)]
+pub(crate) enum BilevelError {
+    /// 数据使用了尚未支持的编码特性，如 JBIG2 的文本区域
+    Unsupported(String),
+    /// 数据损坏或被截断
+    Corrupt,
+}
+
+/// image 库不能解码、需要单独处理的二值图像格式
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub(crate) enum BilevelFormat {
+    Jbig2,
+    /// CCITT Group 4 编码，通常封装在 TIFF 中
+    Ccitt,
+}
+
+impl BilevelFormat {
+    pub(crate) fn name(self) -> &'static str {
+        match self {
+            BilevelFormat::Jbig2 => "JBIG2",
+            BilevelFormat::Ccitt => "CCITT",
+        }
+    }
+}
+
+const JBIG2_MAGIC: &[u8] = &[0x97, 0x4A, 0x42, 0x32, 0x0D, 0x0A, 0x1A, 0x0A];
+
+/// 依次按文件头、多媒体资源的 Format 与文件扩展名判断是否为二值图像格式
+/// TIFF 只在 image 库之外处理，因此也归入 CCITT，其中不是 Group 4 编码的在解码时报告不支持
+pub(crate) fn detect(format: Option<&str>, file_name: &str, data: &[u8]) -> Option<BilevelFormat> {
+    if data.starts_with(JBIG2_MAGIC) {
+        return Some(BilevelFormat::Jbig2);
+    }
+    if data.starts_with(b"II*\0") || data.starts_with(b"MM\0*") {
+        return Some(BilevelFormat::Ccitt);
+    }
+    // 文件头可以识别为其他图像格式时，以文件头为准
+    if image::guess_format(data).is_ok() {
+        return None;
+    }
+    let extension = file_name.rsplit_once('.').map(|(_, ext)| ext).unwrap_or("");
+    [format.unwrap_or(""), extension].iter().find_map(|name| match name.trim().to_ascii_uppercase().as_str() {
+        "JB2" | "JBIG2" | "JBG2" => Some(BilevelFormat::Jbig2),
+        "CCITT" | "G4" | "CCITTFAX" | "TIF" | "TIFF" => Some(BilevelFormat::Ccitt),
+        _ => None,
+    })
+}
+
+/// 解码二值图像并扩展为灰度图像
+pub(crate) fn decode(format: BilevelFormat, data: &[u8]) -> Result<RgbaImage, BilevelError> {
+    let bitmap = match format {
+        BilevelFormat::Jbig2 => jbig2::decode(data)?,
+        BilevelFormat::Ccitt => ccitt::decode_tiff(data)?,
+    };
+    Ok(bitmap.to_rgba())
+}
@@ -0,0 +1,472 @@
+use std::collections::HashMap;
+use std::sync::OnceLock;
+
+use crate::bilevel::{BilevelError, Bitmap, CombinationOperator};
+
+/// 白色游程的终止码（0-63）与组合码（64-1728），码字以二进制字符串表示
+const WHITE_CODES: &[(&str, u16)] = &[
+    ("00110101", 0), ("000111", 1), ("0111", 2), ("1000", 3), ("1011", 4), ("1100", 5), ("1110", 6), ("1111", 7),
+    ("10011", 8), ("10100", 9), ("00111", 10), ("01000", 11), ("001000", 12), ("000011", 13), ("110100", 14),
+    ("110101", 15), ("101010", 16), ("101011", 17), ("0100111", 18), ("0001100", 19), ("0001000", 20),
+    ("0010111", 21), ("0000011", 22), ("0000100", 23), ("0101000", 24), ("0101011", 25), ("0010011", 26),
+    ("0100100", 27), ("0011000", 28), ("00000010", 29), ("00000011", 30), ("00011010", 31), ("00011011", 32),
+    ("00010010", 33), ("00010011", 34), ("00010100", 35), ("00010101", 36), ("00010110", 37), ("00010111", 38),
+    ("00101000", 39), ("00101001", 40), ("00101010", 41), ("00101011", 42), ("00101100", 43), ("00101101", 44),
+    ("00000100", 45), ("00000101", 46), ("00001010", 47), ("00001011", 48), ("01010010", 49), ("01010011", 50),
+    ("01010100", 51), ("01010101", 52), ("00100100", 53), ("00100101", 54), ("01011000", 55), ("01011001", 56),
+    ("01011010", 57), ("01011011", 58), ("01001010", 59), ("01001011", 60), ("00110010", 61), ("00110011", 62),
+    ("00110100", 63),
+    ("11011", 64), ("10010", 128), ("010111", 192), ("0110111", 256), ("00110110", 320), ("00110111", 384),
+    ("01100100", 448), ("01100101", 512), ("01101000", 576), ("01100111", 640), ("011001100", 704),
+    ("011001101", 768), ("011010010", 832), ("011010011", 896), ("011010100", 960), ("011010101", 1024),
+    ("011010110", 1088), ("011010111", 1152), ("011011000", 1216), ("011011001", 1280), ("011011010", 1344),
+    ("011011011", 1408), ("010011000", 1472), ("010011001", 1536), ("010011010", 1600), ("011000", 1664),
+    ("010011011", 1728),
+];
+
+/// 黑色游程的终止码与组合码
+const BLACK_CODES: &[(&str, u16)] = &[
+    ("0000110111", 0), ("010", 1), ("11", 2), ("10", 3), ("011", 4), ("0011", 5), ("0010", 6), ("00011", 7),
+    ("000101", 8), ("000100", 9), ("0000100", 10), ("0000101", 11), ("0000111", 12), ("00000100", 13),
+    ("00000111", 14), ("000011000", 15), ("0000010111", 16), ("0000011000", 17), ("0000001000", 18),
+    ("00001100111", 19), ("00001101000", 20), ("00001101100", 21), ("00000110111", 22), ("00000101000", 23),
+    ("00000010111", 24), ("00000011000", 25), ("000011001010", 26), ("000011001011", 27), ("000011001100", 28),
+    ("000011001101", 29), ("000001101000", 30), ("000001101001", 31), ("000001101010", 32), ("000001101011", 33),
+    ("000011010010", 34), ("000011010011", 35), ("000011010100", 36), ("000011010101", 37), ("000011010110", 38),
+    ("000011010111", 39), ("000001101100", 40), ("000001101101", 41), ("000011011010", 42), ("000011011011", 43),
+    ("000001010100", 44), ("000001010101", 45), ("000001010110", 46), ("000001010111", 47), ("000001100100", 48),
+    ("000001100101", 49), ("000001010010", 50), ("000001010011", 51), ("000000100100", 52), ("000000110111", 53),
+    ("000000111000", 54), ("000000100111", 55), ("000000101000", 56), ("000001011000", 57), ("000001011001", 58),
+    ("000000101011", 59), ("000000101100", 60), ("000001011010", 61), ("000001100110", 62), ("000001100111", 63),
+    ("0000001111", 64), ("000011001000", 128), ("000011001001", 192), ("000001011011", 256), ("000000110011", 320),
+    ("000000110100", 384), ("000000110101", 448), ("0000001101100", 512), ("0000001101101", 576),
+    ("0000001001010", 640), ("0000001001011", 704), ("0000001001100", 768), ("0000001001101", 832),
+    ("0000001110010", 896), ("0000001110011", 960), ("0000001110100", 1024), ("0000001110101", 1088),
+    ("0000001110110", 1152), ("0000001110111", 1216), ("0000001010010", 1280), ("0000001010011", 1344),
+    ("0000001010100", 1408), ("0000001010101", 1472), ("0000001011010", 1536), ("0000001011011", 1600),
+    ("0000001100100", 1664), ("0000001100101", 1728),
+];
+
+/// 黑白共用的扩展组合码（1792-2560）
+const EXTENDED_CODES: &[(&str, u16)] = &[
+    ("00000001000", 1792), ("00000001100", 1856), ("00000001101", 1920), ("000000010010", 1984),
+    ("000000010011", 2048), ("000000010100", 2112), ("000000010101", 2176), ("000000010110", 2240),
+    ("000000010111", 2304), ("000000011100", 2368), ("000000011101", 2432), ("000000011110", 2496),
+    ("000000011111", 2560),
+];
+
+/// 游程码字的最大长度
+const MAX_CODE_BITS: u32 = 13;
+
+/// 按 13 位前缀直接查表得到 (码字长度, 游程)，长度为 0 表示无效码字
+type RunTable = Vec<(u8, u16)>;
+
+fn build_table(codes: &[(&str, u16)]) -> RunTable {
+    let mut table = vec![(0u8, 0u16); 1 << MAX_CODE_BITS];
+    for (code, run) in codes.iter().chain(EXTENDED_CODES) {
+        let len = code.len() as u32;
+        let prefix = u32::from_str_radix(code, 2).unwrap_or(0) << (MAX_CODE_BITS - len);
+        for entry in &mut table[prefix as usize..(prefix + (1 << (MAX_CODE_BITS - len))) as usize] {
+            *entry = (len as u8, *run);
+        }
+    }
+    table
+}
+
+fn run_table(white: bool) -> &'static RunTable {
+    static WHITE: OnceLock<RunTable> = OnceLock::new();
+    static BLACK: OnceLock<RunTable> = OnceLock::new();
+    if white {
+        WHITE.get_or_init(|| build_table(WHITE_CODES))
+    } else {
+        BLACK.get_or_init(|| build_table(BLACK_CODES))
+    }
+}
+
+/// 按位读取，高位在前，越过数据末尾的位按 0 读取
+struct BitReader<'a> {
+    data: &'a [u8],
+    pos: usize,
+}
+
+impl<'a> BitReader<'a> {
+    fn new(data: &'a [u8]) -> BitReader<'a> {
+        BitReader { data, pos: 0 }
+    }
+
+    /// 预读 n 位（n 不超过 24），不移动位置
+    fn peek(&self, n: u32) -> u32 {
+        let byte = self.pos / 8;
+        let value = (0..4).fold(0u32, |value, i| value << 8 | *self.data.get(byte + i).unwrap_or(&0) as u32);
+        (value << (self.pos % 8)) >> (32 - n)
+    }
+
+    fn consume(&mut self, n: u32) {
+        self.pos += n as usize;
+    }
+
+    fn is_exhausted(&self) -> bool {
+        self.pos >= self.data.len() * 8
+    }
+}
+
+/// 二维编码的模式
+enum Mode {
+    Pass,
+    Horizontal,
+    Vertical(isize),
+}
+
+fn read_mode(reader: &mut BitReader) -> Result<Mode, BilevelError> {
+    let bits = reader.peek(7);
+    let (len, mode) = match bits {
+        _ if bits >> 6 == 0b1 => (1, Mode::Vertical(0)),
+        _ if bits >> 4 == 0b011 => (3, Mode::Vertical(1)),
+        _ if bits >> 4 == 0b010 => (3, Mode::Vertical(-1)),
+        _ if bits >> 4 == 0b001 => (3, Mode::Horizontal),
+        _ if bits >> 3 == 0b0001 => (4, Mode::Pass),
+        _ if bits >> 1 == 0b000011 => (6, Mode::Vertical(2)),
+        _ if bits >> 1 == 0b000010 => (6, Mode::Vertical(-2)),
+        0b0000011 => (7, Mode::Vertical(3)),
+        0b0000010 => (7, Mode::Vertical(-3)),
+        0b0000001 => return Err(BilevelError::Unsupported("CCITT uncompressed mode".to_string())),
+        _ => return Err(BilevelError::Corrupt),
+    };
+    reader.consume(len);
+    Ok(mode)
+}
+
+/// 读取一个完整的游程：若干组合码加一个终止码
+fn read_run(reader: &mut BitReader, white: bool) -> Result<usize, BilevelError> {
+    let table = run_table(white);
+    let mut run = 0;
+    loop {
+        if reader.is_exhausted() {
+            return Err(BilevelError::Corrupt);
+        }
+        let (len, value) = table[reader.peek(MAX_CODE_BITS) as usize];
+        if len == 0 {
+            return Err(BilevelError::Corrupt);
+        }
+        reader.consume(len as u32);
+        run += value as usize;
+        if value < 64 {
+            return Ok(run);
+        }
+    }
+}
+
+/// 解码 CCITT T.6（Group 4）编码的数据，得到 width × height 的二值图像
+/// 第一行的参考行为全白行；遇到块结束码（EOFB）时提前结束，其余行保持白色
+pub(crate) fn decode_g4(data: &[u8], width: usize, height: usize) -> Result<Bitmap, BilevelError> {
+    let mut bitmap = Bitmap::new(width, height, 0).filter(|_| width > 0).ok_or(BilevelError::Corrupt)?;
+    let mut reader = BitReader::new(data);
+    // 参考行与当前行的变化点位置，偶数下标处由白变黑，奇数下标处由黑变白
+    let mut reference: Vec<usize> = Vec::new();
+    let mut changes: Vec<usize> = Vec::new();
+    for y in 0..height {
+        if reader.peek(12) == 1 {
+            break;
+        }
+        changes.clear();
+        let mut a0: isize = -1;
+        let mut white = true;
+        let mut scan = 0;
+        // 每种模式至少推进 a0 或消耗码字，损坏的数据可能使 a0 停滞，按行宽限制模式数
+        let mut remaining_modes = 2 * width + 8;
+        while a0 < width as isize {
+            remaining_modes = remaining_modes.checked_sub(1).ok_or(BilevelError::Corrupt)?;
+            if reader.is_exhausted() {
+                return Err(BilevelError::Corrupt);
+            }
+            // b1 为参考行上 a0 右侧第一个与 a0 颜色相反的变化点，b2 为其后的下一个变化点
+            // a0 只向右移动，因此查找的起点也只向右移动
+            while scan < reference.len() && reference[scan] as isize <= a0 {
+                scan += 1;
+            }
+            let b_index = if (scan % 2 == 0) == white { scan } else { scan + 1 };
+            let b1 = reference.get(b_index).copied().unwrap_or(width);
+            let b2 = reference.get(b_index + 1).copied().unwrap_or(width);
+            let start = a0.max(0) as usize;
+            match read_mode(&mut reader)? {
+                Mode::Pass => a0 = b2 as isize,
+                Mode::Horizontal => {
+                    let a1 = (start + read_run(&mut reader, white)?).min(width);
+                    let a2 = (a1 + read_run(&mut reader, !white)?).min(width);
+                    changes.push(a1);
+                    changes.push(a2);
+                    a0 = a2 as isize;
+                }
+                Mode::Vertical(delta) => {
+                    let a1 = b1 as isize + delta;
+                    if a1 < start as isize || (a0 >= 0 && a1 <= a0) || a1 > width as isize {
+                        return Err(BilevelError::Corrupt);
+                    }
+                    changes.push(a1 as usize);
+                    a0 = a1;
+                    white = !white;
+                }
+            }
+        }
+        let row = bitmap.row_mut(y);
+        for pair in changes.chunks(2) {
+            let end = pair.get(1).copied().unwrap_or(width);
+            row[pair[0].min(width)..end.min(width)].fill(1);
+        }
+        std::mem::swap(&mut reference, &mut changes);
+    }
+    Ok(bitmap)
+}
+
+/// 按位写入，高位在前
+struct BitWriter {
+    data: Vec<u8>,
+    bits: usize,
+}
+
+impl BitWriter {
+    /// 写入以二进制字符串表示的码字
+    fn put(&mut self, code: &str) {
+        for bit in code.bytes() {
+            if self.bits.is_multiple_of(8) {
+                self.data.push(0);
+            }
+            if bit == b'1' {
+                let last = self.data.len() - 1;
+                self.data[last] |= 0x80 >> (self.bits % 8);
+            }
+            self.bits += 1;
+        }
+    }
+}
+
+/// 按游程查找码字：组合码与扩展组合码以 64 的倍数为键，终止码以 0-63 为键
+type CodeTable = HashMap<u16, &'static str>;
+
+fn code_table(white: bool) -> &'static CodeTable {
+    static WHITE: OnceLock<CodeTable> = OnceLock::new();
+    static BLACK: OnceLock<CodeTable> = OnceLock::new();
+    let build = |codes: &'static [(&'static str, u16)]| codes.iter().chain(EXTENDED_CODES).map(|(code, run)| (*run, *code)).collect();
+    if white {
+        WHITE.get_or_init(|| build(WHITE_CODES))
+    } else {
+        BLACK.get_or_init(|| build(BLACK_CODES))
+    }
+}
+
+/// 写入一个完整的游程：若干扩展组合码、至多一个组合码和一个终止码
+fn write_run(writer: &mut BitWriter, mut run: usize, white: bool) {
+    let table = code_table(white);
+    while run >= 2560 {
+        writer.put(table[&2560]);
+        run -= 2560;
+    }
+    if run >= 64 {
+        writer.put(table[&((run / 64 * 64) as u16)]);
+        run %= 64;
+    }
+    writer.put(table[&(run as u16)]);
+}
+
+/// 按 CCITT T.6（Group 4）编码二值图像，第一行的参考行为全白行，末尾写入块结束码（EOFB）
+pub(crate) fn encode_g4(bitmap: &Bitmap) -> Vec<u8> {
+    let width = bitmap.width;
+    let mut writer = BitWriter { data: Vec::new(), bits: 0 };
+    // 与解码相同，偶数下标处由白变黑，奇数下标处由黑变白
+    let mut reference: Vec<usize> = Vec::new();
+    let mut changes: Vec<usize> = Vec::new();
+    for y in 0..bitmap.height {
+        changes.clear();
+        let mut previous = 0;
+        for (x, &pixel) in bitmap.row(y).iter().enumerate() {
+            if pixel != previous {
+                changes.push(x);
+                previous = pixel;
+            }
+        }
+
+        let mut a0: isize = -1;
+        let mut white = true;
+        let mut next = 0;
+        let mut scan = 0;
+        while a0 < width as isize {
+            // a1 为当前行上 a0 右侧的第一个变化点，a2 为其后的下一个变化点
+            while next < changes.len() && changes[next] as isize <= a0 {
+                next += 1;
+            }
+            let a1 = changes.get(next).copied().unwrap_or(width);
+            let a2 = changes.get(next + 1).copied().unwrap_or(width);
+            while scan < reference.len() && reference[scan] as isize <= a0 {
+                scan += 1;
+            }
+            let b_index = if (scan % 2 == 0) == white { scan } else { scan + 1 };
+            let b1 = reference.get(b_index).copied().unwrap_or(width);
+            let b2 = reference.get(b_index + 1).copied().unwrap_or(width);
+
+            if b2 < a1 {
+                writer.put("0001");
+                a0 = b2 as isize;
+            } else if a1.abs_diff(b1) <= 3 {
+                let code = match a1 as isize - b1 as isize {
+                    0 => "1",
+                    1 => "011",
+                    2 => "000011",
+                    3 => "0000011",
+                    -1 => "010",
+                    -2 => "000010",
+                    _ => "0000010",
+                };
+                writer.put(code);
+                a0 = a1 as isize;
+                white = !white;
+            } else {
+                writer.put("001");
+                write_run(&mut writer, a1 - a0.max(0) as usize, white);
+                write_run(&mut writer, a2 - a1, !white);
+                a0 = a2 as isize;
+            }
+        }
+        std::mem::swap(&mut reference, &mut changes);
+    }
+    writer.put("000000000001");
+    writer.put("000000000001");
+    writer.data
+}
+
+/// TIFF 中用到的标签
+const TAG_IMAGE_WIDTH: u16 = 256;
+const TAG_IMAGE_LENGTH: u16 = 257;
+const TAG_COMPRESSION: u16 = 259;
+const TAG_PHOTOMETRIC: u16 = 262;
+const TAG_FILL_ORDER: u16 = 266;
+const TAG_STRIP_OFFSETS: u16 = 273;
+const TAG_ROWS_PER_STRIP: u16 = 278;
+const TAG_STRIP_BYTE_COUNTS: u16 = 279;
+
+const COMPRESSION_CCITT_G4: u32 = 4;
+
+/// 解码 TIFF 封装的 Group 4 图像，只读取第一个图像目录
+/// 各条带独立编码，逐条解码后按行拼接；PhotometricInterpretation 为 1 时 0 表示黑色，需反色
+pub(crate) fn decode_tiff(data: &[u8]) -> Result<Bitmap, BilevelError> {
+    let tiff = Tiff::parse(data).ok_or(BilevelError::Corrupt)?;
+    let compression = tiff.value(TAG_COMPRESSION).unwrap_or(1);
+    if compression != COMPRESSION_CCITT_G4 {
+        return Err(BilevelError::Unsupported(format!("TIFF compression {}", compression)));
+    }
+    let width = tiff.value(TAG_IMAGE_WIDTH).ok_or(BilevelError::Corrupt)? as usize;
+    let height = tiff.value(TAG_IMAGE_LENGTH).ok_or(BilevelError::Corrupt)? as usize;
+    let rows_per_strip = (tiff.value(TAG_ROWS_PER_STRIP).unwrap_or(u32::MAX) as usize).clamp(1, height.max(1));
+    let offsets = tiff.values(TAG_STRIP_OFFSETS).ok_or(BilevelError::Corrupt)?;
+    let counts = tiff.values(TAG_STRIP_BYTE_COUNTS).ok_or(BilevelError::Corrupt)?;
+    let reverse_bits = tiff.value(TAG_FILL_ORDER) == Some(2);
+    let invert = tiff.value(TAG_PHOTOMETRIC) == Some(1);
+
+    let mut bitmap = Bitmap::new(width, height, 0).ok_or(BilevelError::Corrupt)?;
+    for (strip, (offset, count)) in offsets.iter().zip(&counts).enumerate() {
+        let first_row = strip * rows_per_strip;
+        if first_row >= height {
+            break;
+        }
+        let rows = rows_per_strip.min(height - first_row);
+        let mut encoded = data.get(*offset as usize..(*offset as usize).saturating_add(*count as usize))
+            .ok_or(BilevelError::Corrupt)?
+            .to_vec();
+        if reverse_bits {
+            encoded.iter_mut().for_each(|b| *b = b.reverse_bits());
+        }
+        let decoded = decode_g4(&encoded, width, rows)?;
+        bitmap.compose(&decoded, 0, first_row, CombinationOperator::Replace);
+    }
+    if invert {
+        for y in 0..height {
+            bitmap.row_mut(y).iter_mut().for_each(|p| *p ^= 1);
+        }
+    }
+    Ok(bitmap)
+}
+
+/// TIFF 文件的第一个图像目录
+struct Tiff<'a> {
+    data: &'a [u8],
+    little_endian: bool,
+    ifd: usize,
+    entries: usize,
+}
+
+impl<'a> Tiff<'a> {
+    fn parse(data: &'a [u8]) -> Option<Tiff<'a>> {
+        let little_endian = match data.get(..4)? {
+            b"II*\0" => true,
+            b"MM\0*" => false,
+            _ => return None,
+        };
+        let mut tiff = Tiff { data, little_endian, ifd: 0, entries: 0 };
+        tiff.ifd = tiff.u32(4)? as usize;
+        tiff.entries = tiff.u16(tiff.ifd)? as usize;
+        Some(tiff)
+    }
+
+    fn u16(&self, offset: usize) -> Option<u16> {
+        let bytes: [u8; 2] = self.data.get(offset..offset + 2)?.try_into().ok()?;
+        Some(if self.little_endian { u16::from_le_bytes(bytes) } else { u16::from_be_bytes(bytes) })
+    }
+
+    fn u32(&self, offset: usize) -> Option<u32> {
+        let bytes: [u8; 4] = self.data.get(offset..offset + 4)?.try_into().ok()?;
+        Some(if self.little_endian { u32::from_le_bytes(bytes) } else { u32::from_be_bytes(bytes) })
+    }
+
+    /// 标签的全部取值，只支持 SHORT 与 LONG 类型；不超过 4 字节的取值直接存放在目录项中
+    fn values(&self, tag: u16) -> Option<Vec<u32>> {
+        let entry = (0..self.entries)
+            .map(|i| self.ifd + 2 + i * 12)
+            .find(|entry| self.u16(*entry) == Some(tag))?;
+        let size = match self.u16(entry + 2)? {
+            3 => 2,
+            4 => 4,
+            _ => return None,
+        };
+        let count = self.u32(entry + 4)? as usize;
+        let start = if count * size <= 4 { entry + 8 } else { self.u32(entry + 8)? as usize };
+        (0..count)
+            .map(|i| match size {
+                2 => self.u16(start + i * 2).map(u32::from),
+                _ => self.u32(start + i * 4),
+            })
+            .collect()
+    }
+
+    fn value(&self, tag: u16) -> Option<u32> {
+        self.values(tag)?.first().copied()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// 按 T.6 手工编码的 8 × 2 图像：第一行 x 2..5 为黑，水平模式（白 2、黑 3）后接 V0；
+    /// 第二行 x 3..6 为黑，两个 VR1 后接 V0；最后为块结束码
+    const G4_STREAM: [u8; 6] = [0x2F, 0x5B, 0x80, 0x08, 0x00, 0x80];
+
+    #[test]
+    fn decodes_a_known_g4_stream() {
+        let bitmap = decode_g4(&G4_STREAM, 8, 2).unwrap();
+        assert_eq!(bitmap.row(0), [0, 0, 1, 1, 1, 0, 0, 0]);
+        assert_eq!(bitmap.row(1), [0, 0, 0, 1, 1, 1, 0, 0]);
+    }
+
+    #[test]
+    fn rows_after_the_end_of_block_stay_white() {
+        let bitmap = decode_g4(&G4_STREAM, 8, 4).unwrap();
+        assert_eq!(bitmap.row(1), [0, 0, 0, 1, 1, 1, 0, 0]);
+        assert_eq!(bitmap.row(2), [0; 8]);
+        assert_eq!(bitmap.row(3), [0; 8]);
+    }
+
+    #[test]
+    fn encoding_reproduces_the_known_stream() {
+        let bitmap = decode_g4(&G4_STREAM, 8, 2).unwrap();
+        assert_eq!(encode_g4(&bitmap), G4_STREAM);
+    }
+}
@@ -0,0 +1,251 @@
+use std::collections::HashMap;
+
+use serde::Deserialize;
+
+use crate::resource::{parse_components, ColorSpace, ColorSpaceType};
+use crate::st_types::{deserialize_st, STPos};
+
+/// 颜色 CT_Color
+/// Value 为各通道的值，以空格分隔；ColorSpace 引用资源中的颜色空间，缺省时为 RGB
+#[derive(Debug, Deserialize, Default, Clone)]
+pub(crate) struct CTColor {
+    #[serde(rename = "Value")]
+    pub(crate) value: Option<String>,
+    #[serde(rename = "Index")]
+    pub(crate) index: Option<usize>,
+    #[serde(rename = "ColorSpace")]
+    pub(crate) color_space: Option<String>,
+    #[serde(rename = "Alpha")]
+    pub(crate) alpha: Option<u8>,
+    #[serde(rename = "AxialShd")]
+    pub(crate) axial_shd: Option<Box<AxialShd>>,
+    #[serde(rename = "RadialShd")]
+    pub(crate) radial_shd: Option<Box<RadialShd>>,
+    /// 叠印：印刷时颜色不挖空下层油墨，只在模拟叠印时生效
+    #[serde(rename = "Overprint", default)]
+    pub(crate) overprint: bool,
+}
+
+/// 颜色的取值：单一颜色或渐变
+#[derive(Debug, Clone)]
+pub(crate) enum Color {
+    /// 各通道分量，按颜色空间解释
+    Solid(Vec<f64>),
+    Axial(AxialShd),
+    Radial(RadialShd),
+}
+
+/// 轴向渐变 ofd:AxialShd，颜色沿起点到终点的方向变化，坐标位于所在图元的坐标系中
+#[derive(Debug, Deserialize, Clone)]
+pub(crate) struct AxialShd {
+    #[serde(rename = "MapType", default)]
+    pub(crate) map_type: MapType,
+    /// 0 不延伸，1 在起点一侧延伸，2 在终点一侧延伸，3 两侧都延伸
+    #[serde(rename = "Extend", default)]
+    pub(crate) extend: u8,
+    #[serde(rename = "StartPoint", deserialize_with = "deserialize_st")]
+    pub(crate) start_point: STPos,
+    #[serde(rename = "EndPoint", deserialize_with = "deserialize_st")]
+    pub(crate) end_point: STPos,
+    #[serde(rename = "$value", default)]
+    pub(crate) segments: Vec<Segment>,
+}
+
+/// 径向渐变 ofd:RadialShd，颜色由起始圆向终止圆变化
+/// 暂不支持椭圆（Eccentricity 与 Angle），均按圆处理
+#[derive(Debug, Deserialize, Clone)]
+pub(crate) struct RadialShd {
+    #[serde(rename = "MapType", default)]
+    pub(crate) map_type: MapType,
+    #[serde(rename = "Extend", default)]
+    pub(crate) extend: u8,
+    #[serde(rename = "StartPoint", deserialize_with = "deserialize_st")]
+    pub(crate) start_point: STPos,
+    #[serde(rename = "StartRadius", default)]
+    pub(crate) start_radius: f64,
+    #[serde(rename = "EndPoint", deserialize_with = "deserialize_st")]
+    pub(crate) end_point: STPos,
+    #[serde(rename = "EndRadius")]
+    pub(crate) end_radius: f64,
+    #[serde(rename = "$value", default)]
+    pub(crate) segments: Vec<Segment>,
+}
+
+/// 渐变在起止范围之外的映射方式，Repeat 与 Reflect 以起止范围为一个周期
+#[derive(Debug, Deserialize, Default, Clone, Copy, PartialEq, Eq)]
+pub(crate) enum MapType {
+    Repeat,
+    Reflect,
+    #[default]
+    #[serde(other)]
+    Direct,
+}
+
+/// 渐变的颜色段，Position 为 0 到 1 之间的位置，缺省时各颜色段均匀分布
+#[derive(Debug, Deserialize, Clone)]
+pub(crate) struct Segment {
+    #[serde(rename = "Position")]
+    pub(crate) position: Option<f64>,
+    #[serde(rename = "Color")]
+    pub(crate) color: CTColor,
+}
+
+impl CTColor {
+    pub(crate) fn color(&self) -> Color {
+        match (&self.axial_shd, &self.radial_shd) {
+            (Some(shd), _) => Color::Axial(shd.as_ref().clone()),
+            (None, Some(shd)) => Color::Radial(shd.as_ref().clone()),
+            (None, None) => Color::Solid(self.components()),
+        }
+    }
+
+    /// 渐变的各颜色段，按位置排列；颜色段未引用颜色空间时沿用本颜色的颜色空间
+    pub(crate) fn color_stops(&self) -> Vec<(f64, CTColor)> {
+        let segments = match self.color() {
+            Color::Solid(_) => return Vec::new(),
+            Color::Axial(shd) => shd.segments,
+            Color::Radial(shd) => shd.segments,
+        };
+        let last = segments.len().saturating_sub(1).max(1) as f64;
+        let mut stops: Vec<(f64, CTColor)> = segments
+            .into_iter()
+            .enumerate()
+            .map(|(i, segment)| {
+                let mut color = segment.color;
+                color.color_space = color.color_space.or_else(|| self.color_space.clone());
+                (segment.position.unwrap_or(i as f64 / last).clamp(0.0, 1.0), color)
+            })
+            .collect();
+        stops.sort_by(|a, b| a.0.total_cmp(&b.0));
+        stops
+    }
+
+    /// 解析 Value 中的各通道分量
+    pub(crate) fn components(&self) -> Vec<f64> {
+        parse_components(self.value.as_deref().unwrap_or(""))
+    }
+
+    /// 颜色的透明度，0 为全透明，255 为不透明
+    /// 优先取 Alpha 属性；部分生成器将透明度作为 Value 的最后一个分量写出，
+    /// 分量个数比颜色空间的通道数多一个时，按同样的取值范围换算为透明度；都没有时不透明
+    pub(crate) fn alpha(&self, color_spaces: &HashMap<String, ColorSpace>) -> u8 {
+        if let Some(alpha) = self.alpha {
+            return alpha;
+        }
+        let default_space = ColorSpace::default();
+        let color_space = self.color_space.as_ref().and_then(|id| color_spaces.get(id)).unwrap_or(&default_space);
+        let components = self.components();
+        if self.index.is_none() && components.len() == color_space.channels() + 1 {
+            let max = color_space.component_max();
+            return (components[components.len() - 1].clamp(0.0, max) / max * 255.0).round() as u8;
+        }
+        255
+    }
+
+    /// 是否按叠印绘制：只有标记了叠印的 CMYK 单一颜色参与，渐变与其他颜色空间照常挖空下层
+    pub(crate) fn overprints(&self, color_spaces: &HashMap<String, ColorSpace>) -> bool {
+        self.overprint
+            && matches!(self.color(), Color::Solid(_))
+            && self.color_space.as_ref().and_then(|id| color_spaces.get(id))
+                .is_some_and(|cs| cs.color_space_type == ColorSpaceType::CMYK)
+    }
+
+    /// 按引用的颜色空间转换为 RGB，未引用或找不到颜色空间时按 RGB 处理
+    /// 颜色空间带调色板时按序号取调色板中的颜色，再按颜色空间的类型转换，序号越界时记录警告
+    /// 渐变取第一个颜色段的颜色，供只能使用单一颜色的场合近似
+    pub(crate) fn to_rgb(&self, color_spaces: &HashMap<String, ColorSpace>) -> [u8; 3] {
+        let components = match self.color() {
+            Color::Solid(components) => components,
+            Color::Axial(_) | Color::Radial(_) => {
+                return self.color_stops().first().map(|(_, color)| color.to_rgb(color_spaces)).unwrap_or([0, 0, 0]);
+            }
+        };
+        let color_space = self.color_space.as_ref().and_then(|id| color_spaces.get(id));
+        match color_space {
+            Some(color_space) => match self.palette_index(color_space, &components) {
+                Some(index) => match color_space.palette_components(index) {
+                    Some(entry) => color_space.to_rgb(&entry),
+                    None => {
+                        log::warn!(
+                            "palette index {} out of range for color space {} with {} entries",
+                            index,
+                            color_space.id,
+                            color_space.palette_len(),
+                        );
+                        // 显式的 Index 越界时 Value 仍可能是完整的颜色，仅有索引时无颜色可用，按黑色处理
+                        match self.index {
+                            Some(_) => color_space.to_rgb(&components),
+                            None => [0, 0, 0],
+                        }
+                    }
+                },
+                None => color_space.to_rgb(&components),
+            },
+            None => ColorSpace::default().to_rgb(&components),
+        }
+    }
+
+    /// 颜色在调色板中的序号，优先取 Index 属性
+    /// 颜色空间带调色板而 Value 只有一个非负整数分量时，该分量即为序号
+    fn palette_index(&self, color_space: &ColorSpace, components: &[f64]) -> Option<usize> {
+        color_space.palette.as_ref()?;
+        if self.index.is_some() {
+            return self.index;
+        }
+        match components {
+            [value] if *value >= 0.0 && value.fract() == 0.0 => Some(*value as usize),
+            _ => None,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::resource::Res;
+
+    /// 解析资源文件中的颜色空间，xml 为 ofd:ColorSpaces 的内容
+    fn color_spaces(xml: &str) -> HashMap<String, ColorSpace> {
+        let res = Res::from_xml(&format!(
+            "<ofd:Res xmlns:ofd=\"http://www.ofdspec.org/2016\"><ofd:ColorSpaces>{}</ofd:ColorSpaces></ofd:Res>",
+            xml,
+        )).unwrap();
+        res.color_spaces.color_space.into_iter().map(|cs| (cs.id.clone(), cs)).collect()
+    }
+
+    fn color(xml: &str) -> CTColor {
+        serde_xml_rs::from_str(xml).unwrap()
+    }
+
+    /// 调色板依次为青色与黑色的 CMYK 颜色空间
+    const CMYK_PALETTE: &str = "<ofd:ColorSpace ID=\"1\" Type=\"CMYK\"><ofd:Palette>\
+        <ofd:CV>255 0 0 0</ofd:CV><ofd:CV>0 0 0 255</ofd:CV></ofd:Palette></ofd:ColorSpace>";
+
+    #[test]
+    fn indexed_cmyk_palette_resolves_to_rgb() {
+        let spaces = color_spaces(CMYK_PALETTE);
+        assert_eq!(color("<ofd:FillColor ColorSpace=\"1\" Index=\"0\"/>").to_rgb(&spaces), [0, 255, 255]);
+        assert_eq!(color("<ofd:FillColor ColorSpace=\"1\" Index=\"1\"/>").to_rgb(&spaces), [0, 0, 0]);
+    }
+
+    #[test]
+    fn palette_index_prefers_index_then_a_single_integer_value() {
+        let spaces = color_spaces(&format!("{}<ofd:ColorSpace ID=\"2\" Type=\"CMYK\"/>", CMYK_PALETTE));
+        let palette = &spaces["1"];
+        let index = |xml: &str| {
+            let color = color(xml);
+            let Color::Solid(components) = color.color() else { unreachable!() };
+            color.palette_index(palette, &components)
+        };
+        assert_eq!(index("<ofd:FillColor ColorSpace=\"1\" Index=\"1\" Value=\"0\"/>"), Some(1));
+        assert_eq!(index("<ofd:FillColor ColorSpace=\"1\" Value=\"1\"/>"), Some(1));
+        assert_eq!(index("<ofd:FillColor ColorSpace=\"1\" Value=\"0.5\"/>"), None);
+        assert_eq!(index("<ofd:FillColor ColorSpace=\"1\" Value=\"255 0 0 0\"/>"), None);
+        let without_palette = color("<ofd:FillColor ColorSpace=\"2\" Index=\"1\"/>");
+        assert_eq!(without_palette.palette_index(&spaces["2"], &[1.0]), None);
+
+        assert_eq!(color("<ofd:FillColor ColorSpace=\"1\" Value=\"0\"/>").to_rgb(&spaces), [0, 255, 255]);
+        // Value 只是越界的序号时没有颜色可用，按黑色处理
+        assert_eq!(color("<ofd:FillColor ColorSpace=\"1\" Value=\"5\"/>").to_rgb(&spaces), [0, 0, 0]);
+    }
+}
@@ -1,47 +1,291 @@
 use serde::Deserialize;
 
+use crate::action::Actions;
+use crate::st_types::{deserialize_st, deserialize_st_opt, STBox};
+
 
 #[derive(Debug, Deserialize, Default)]
-#[serde(rename_all = "PascalCase")]
+#[serde(default, rename_all = "PascalCase")]
 pub(crate) struct Document{
-    common_data: CommonData,
-    custom_tags: String,
-    annotations: String,
-    pages: PageRefs,
+    pub(crate) common_data: CommonData,
+    pub(crate) custom_tags: String,
+    pub(crate) annotations: String,
+    /// 附件列表文件 Attachments.xml 的路径，相对于 Document.xml 所在目录
+    pub(crate) attachments: String,
+    pub(crate) pages: PageRefs,
+    pub(crate) permissions: Option<CTPermission>,
+    /// 文档大纲（书签），未声明时为 None
+    pub(crate) outlines: Option<Outlines>,
 }
 
 impl Document {
     pub(crate) fn from_xml(xml: &str) -> Result<Document, serde_xml_rs::Error> {
-        serde_xml_rs::from_str(xml)
+        let mut document: Document = serde_xml_rs::from_str(xml)?;
+        document.pages.sort_by_index();
+        Ok(document)
     }
 }
 
-pub(crate) struct PageArea{
-    
+/// 文档的权限声明 CT_Permission，未声明的权限缺省为允许
+#[derive(Debug, Deserialize, Default)]
+#[serde(default, rename_all = "PascalCase")]
+pub(crate) struct CTPermission {
+    edit: Option<bool>,
+    annot: Option<bool>,
+    export: Option<bool>,
+    signature: Option<bool>,
+    watermark: Option<bool>,
+    print_screen: Option<bool>,
+    print: Option<PrintPermission>,
+    valid_period: Option<ValidPeriod>,
 }
 
 #[derive(Debug, Deserialize, Default)]
+struct PrintPermission {
+    #[serde(rename = "Printable", default = "default_true")]
+    printable: bool,
+    /// 允许打印的份数，负数表示不限制
+    #[serde(rename = "Copies")]
+    copies: Option<i32>,
+}
+
+#[derive(Debug, Deserialize, Default)]
+struct ValidPeriod {
+    #[serde(rename = "StartDate")]
+    start_date: Option<String>,
+    #[serde(rename = "EndDate")]
+    end_date: Option<String>,
+}
+
+fn default_true() -> bool {
+    true
+}
+
+/// 文档声明的使用权限，仅作报告，渲染时不做限制
+#[derive(Debug, Clone, PartialEq)]
+pub struct Permissions {
+    /// 是否允许打印
+    pub print: bool,
+    /// 允许打印的份数，None 表示不限制
+    pub print_copies: Option<u32>,
+    /// 是否允许导出、复制内容，对应 Export
+    pub copy: bool,
+    /// 是否允许编辑，对应 Edit
+    pub modify: bool,
+    /// 是否允许添加或修改注释
+    pub annotate: bool,
+    /// 是否允许签名
+    pub sign: bool,
+    /// 是否允许添加水印
+    pub watermark: bool,
+    /// 是否允许截屏
+    pub print_screen: bool,
+    /// 权限的有效期起止时间，未声明时为 None
+    pub valid_from: Option<String>,
+    pub valid_until: Option<String>,
+}
+
+impl From<&CTPermission> for Permissions {
+    fn from(permission: &CTPermission) -> Self {
+        let print = permission.print.as_ref();
+        let period = permission.valid_period.as_ref();
+        Permissions {
+            print: print.is_none_or(|p| p.printable),
+            print_copies: print.and_then(|p| p.copies).and_then(|c| u32::try_from(c).ok()),
+            copy: permission.export.unwrap_or(true),
+            modify: permission.edit.unwrap_or(true),
+            annotate: permission.annot.unwrap_or(true),
+            sign: permission.signature.unwrap_or(true),
+            watermark: permission.watermark.unwrap_or(true),
+            print_screen: permission.print_screen.unwrap_or(true),
+            valid_from: period.and_then(|p| p.start_date.clone()),
+            valid_until: period.and_then(|p| p.end_date.clone()),
+        }
+    }
+}
+
+/// 页面区域 CT_PageArea，各区域均以毫米为单位
+/// 页面未指定时继承文档 CommonData 中的缺省区域
+#[derive(Debug, Deserialize, Default, Clone)]
 #[serde(rename_all = "PascalCase")]
-struct CommonData {
+pub(crate) struct PageArea{
+    /// 物理区域，页面的实际大小
+    #[serde(deserialize_with = "deserialize_st")]
+    pub(crate) physical_box: STBox,
+    /// 显示区域
+    #[serde(deserialize_with = "deserialize_st_opt", default)]
+    pub(crate) application_box: Option<STBox>,
+    /// 版心区域
+    #[serde(deserialize_with = "deserialize_st_opt", default)]
+    pub(crate) content_box: Option<STBox>,
+    /// 出血区域
+    #[serde(deserialize_with = "deserialize_st_opt", default)]
+    pub(crate) bleed_box: Option<STBox>,
+    /// 页面的顺时针旋转角度，只取 0、90、180、270，由页面或文档的 Rotate 决定
+    #[serde(skip)]
+    pub(crate) rotation: u16,
+}
+
+impl PageArea {
+    /// 旋转后页面的显示宽高（毫米），旋转 90 或 270 度时宽高互换
+    pub(crate) fn display_size(&self) -> (f64, f64) {
+        let b = self.physical_box;
+        match self.rotation {
+            90 | 270 => (b.h, b.w),
+            _ => (b.w, b.h),
+        }
+    }
+
+    /// 页面坐标到显示坐标的变换 [xx, yx, xy, yy, x0, y0]，与 cairo 的 Matrix 参数顺序相同
+    /// 显示坐标以旋转后页面的左上角为原点，未旋转时即平移物理区域的左上角；两者的 Y 轴均向下，
+    /// 各输出后端应通过 render::page_transform 使用该变换，见其说明
+    pub(crate) fn display_transform(&self) -> [f64; 6] {
+        let STBox { x, y, w, h } = self.physical_box;
+        match self.rotation {
+            90 => [0.0, 1.0, -1.0, 0.0, y + h, -x],
+            180 => [-1.0, 0.0, 0.0, -1.0, x + w, y + h],
+            270 => [0.0, -1.0, 1.0, 0.0, -y, x + w],
+            _ => [1.0, 0.0, 0.0, 1.0, -x, -y],
+        }
+    }
+}
+
+/// 将 Rotate 的角度规范为 0、90、180、270，不是 90 的整数倍时返回 None
+pub(crate) fn normalize_rotation(degrees: i32) -> Option<u16> {
+    let degrees = degrees.rem_euclid(360);
+    (degrees % 90 == 0).then_some(degrees as u16)
+}
+
+#[derive(Debug, Deserialize, Default)]
+#[serde(default, rename_all = "PascalCase")]
+pub(crate) struct CommonData {
     #[serde(rename = "MaxUnitID")]
     max_unit_id: i32,
-    public_res: String,
-    template_page: PageRef,
-    document_res: String,
+    pub(crate) page_area: Option<PageArea>,
+    /// 页面的缺省顺时针旋转角度，页面自身的 Rotate 优先
+    pub(crate) rotate: Option<i32>,
+    /// 公共资源文件，可以有多个
+    pub(crate) public_res: Vec<String>,
+    pub(crate) template_page: Vec<TemplatePage>,
+    /// 文档资源文件，可以有多个
+    pub(crate) document_res: Vec<String>,
 }
 
+/// 模板页 CT_TemplatePage，页面通过 Template 节点引用
 #[derive(Debug, Deserialize, Default)]
-#[serde(rename_all = "PascalCase")]
-struct PageRefs{
-    page : Vec<PageRef>,
+pub(crate) struct TemplatePage {
+    #[serde(rename = "ID")]
+    pub(crate) id: String,
+    #[serde(rename = "Name")]
+    pub(crate) name: Option<String>,
+    /// 模板的缺省绘制层次，页面引用时可覆盖
+    #[serde(rename = "ZOrder")]
+    pub(crate) z_order: Option<ZOrder>,
+    #[serde(rename = "BaseLoc")]
+    pub(crate) base_loc: String,
+}
+
+/// 模板相对于页面内容的绘制层次，缺省为背景层
+#[derive(Debug, Deserialize, Default, Clone, Copy, PartialEq, Eq)]
+pub(crate) enum ZOrder {
+    Foreground,
+    #[default]
+    #[serde(other)]
+    Background,
+}
+
+impl CommonData {
+    /// 文档中对象标识的最大值，缺省或非法时为 0
+    pub(crate) fn max_unit_id(&self) -> usize {
+        self.max_unit_id.max(0) as usize
+    }
+}
+
+#[derive(Debug, Deserialize, Default)]
+#[serde(default, rename_all = "PascalCase")]
+pub(crate) struct PageRefs{
+    pub(crate) page : Vec<PageRef>,
+}
+
+impl PageRefs {
+    /// 部分生成器通过页面的 Index/Order 属性表示页序，而不是声明顺序
+    /// 存在该属性时按其排序，未声明的页面保持原有的相对顺序排在最后
+    fn sort_by_index(&mut self) {
+        if self.page.iter().any(|p| p.order().is_some()) {
+            self.page.sort_by_key(|p| p.order().unwrap_or(i64::MAX));
+        }
+    }
+}
+
+/// 页面的基本信息
+#[derive(Debug, Clone)]
+pub struct PageInfo {
+    /// 页面标识
+    pub id: String,
+    /// 页面内容文件在包内的完整路径
+    pub location: String,
+}
+
+/// 页面方向，由页面物理区域的宽高决定，宽高相等时视为纵向
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Orientation {
+    Portrait,
+    Landscape,
 }
 
 #[derive(Debug, Deserialize, Default)]
-struct PageRef{
+pub(crate) struct PageRef{
     #[serde(rename = "ID")]
-    id: String,
+    pub(crate) id: String,
     #[serde(rename = "BaseLoc")]
-    base_loc: String,
+    pub(crate) base_loc: String,
+    /// 页面的显示页码，如前言使用的罗马数字，部分生成器在页面节点上给出
+    #[serde(rename = "Label")]
+    pub(crate) label: Option<String>,
+    #[serde(rename = "Index")]
+    index: Option<i64>,
+    #[serde(rename = "Order")]
+    order: Option<i64>,
 }
 
+impl PageRef {
+    fn order(&self) -> Option<i64> {
+        self.index.or(self.order)
+    }
+}
+
+
+
+/// 文档大纲 ofd:Outlines
+#[derive(Debug, Deserialize, Default)]
+#[serde(default, rename_all = "PascalCase")]
+pub(crate) struct Outlines {
+    pub(crate) outline_elem: Vec<OutlineElem>,
+}
 
+/// 大纲节点 CT_OutlineElem，可以嵌套，通过 Goto 动作指向目标页面
+#[derive(Debug, Deserialize, Default)]
+pub(crate) struct OutlineElem {
+    #[serde(rename = "Title", default)]
+    pub(crate) title: String,
+    #[serde(rename = "Actions")]
+    pub(crate) actions: Option<Actions>,
+    #[serde(rename = "OutlineElem", default)]
+    pub(crate) outline_elem: Vec<OutlineElem>,
+}
+
+impl OutlineElem {
+    /// 第一个带有目标页面的 Goto 动作所指向的页面标识
+    pub(crate) fn dest_page_id(&self) -> Option<&str> {
+        self.actions.as_ref()?.goto_page_id()
+    }
+}
+
+/// 文档大纲中的一项
+#[derive(Debug, Clone, PartialEq)]
+pub struct OutlineItem {
+    pub title: String,
+    /// 目标页面的序号，从 0 开始；没有跳转动作或目标页面不存在时为 None
+    pub dest_page: Option<usize>,
+    pub children: Vec<OutlineItem>,
+}
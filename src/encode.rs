@@ -0,0 +1,140 @@
+use std::io::Cursor;
+
+use image::codecs::jpeg::JpegEncoder;
+use image::{DynamicImage, ExtendedColorType, ImageEncoder, ImageError, RgbaImage};
+
+/// 缺省的有损压缩质量，取值 1 到 100
+pub const DEFAULT_QUALITY: u8 = 85;
+
+/// AVIF 编码速度，1 最慢、压缩率最高，10 最快
+#[cfg(feature = "modern-formats")]
+const AVIF_SPEED: u8 = 6;
+
+/// 渲染结果的输出格式
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ImageFormat {
+    Png,
+    /// 不含透明度，透明像素直接丢弃 alpha 通道
+    Jpeg,
+    Bmp,
+    /// 启用 modern-formats 特性时按 quality 有损压缩，否则为无损压缩
+    WebP,
+    /// 需要启用 modern-formats 特性
+    Avif,
+}
+
+impl ImageFormat {
+    /// 按文件扩展名（不区分大小写）确定格式，不支持的扩展名返回 None
+    pub fn from_extension(ext: &str) -> Option<ImageFormat> {
+        match ext.to_ascii_lowercase().as_str() {
+            "png" => Some(ImageFormat::Png),
+            "jpg" | "jpeg" => Some(ImageFormat::Jpeg),
+            "bmp" => Some(ImageFormat::Bmp),
+            "webp" => Some(ImageFormat::WebP),
+            "avif" => Some(ImageFormat::Avif),
+            _ => None,
+        }
+    }
+
+    /// 当前编译的特性下能否编码该格式
+    pub fn is_supported(self) -> bool {
+        self != ImageFormat::Avif || cfg!(feature = "modern-formats")
+    }
+
+    /// 格式是否使用 quality 参数
+    pub fn is_lossy(self) -> bool {
+        match self {
+            ImageFormat::Jpeg | ImageFormat::Avif => true,
+            ImageFormat::WebP => cfg!(feature = "modern-formats"),
+            ImageFormat::Png | ImageFormat::Bmp => false,
+        }
+    }
+}
+
+/// 将渲染结果编码为 format 格式，quality 取值 1 到 100，只对有损格式生效
+/// 格式在当前特性下不可用时返回 ImageError::Unsupported
+pub(crate) fn encode(image: RgbaImage, format: ImageFormat, quality: u8) -> Result<Vec<u8>, ImageError> {
+    let quality = quality.clamp(1, 100);
+    let mut output = Cursor::new(Vec::new());
+    match format {
+        ImageFormat::Png => image.write_to(&mut output, image::ImageFormat::Png)?,
+        ImageFormat::Bmp => image.write_to(&mut output, image::ImageFormat::Bmp)?,
+        ImageFormat::Jpeg => {
+            let rgb = DynamicImage::ImageRgba8(image).to_rgb8();
+            JpegEncoder::new_with_quality(&mut output, quality)
+                .write_image(rgb.as_raw(), rgb.width(), rgb.height(), ExtendedColorType::Rgb8)?;
+        }
+        ImageFormat::WebP => return encode_webp(image, quality),
+        ImageFormat::Avif => return encode_avif(image, quality),
+    }
+    Ok(output.into_inner())
+}
+
+#[cfg(feature = "modern-formats")]
+fn encode_webp(image: RgbaImage, quality: u8) -> Result<Vec<u8>, ImageError> {
+    let encoded = webp::Encoder::from_rgba(image.as_raw(), image.width(), image.height()).encode(quality as f32);
+    Ok(encoded.to_vec())
+}
+
+/// image 自带的 WebP 编码器只支持无损压缩，忽略 quality
+#[cfg(not(feature = "modern-formats"))]
+fn encode_webp(image: RgbaImage, _quality: u8) -> Result<Vec<u8>, ImageError> {
+    let mut output = Cursor::new(Vec::new());
+    image.write_to(&mut output, image::ImageFormat::WebP)?;
+    Ok(output.into_inner())
+}
+
+#[cfg(feature = "modern-formats")]
+fn encode_avif(image: RgbaImage, quality: u8) -> Result<Vec<u8>, ImageError> {
+    let mut output = Vec::new();
+    image::codecs::avif::AvifEncoder::new_with_speed_quality(&mut output, AVIF_SPEED, quality)
+        .write_image(image.as_raw(), image.width(), image.height(), ExtendedColorType::Rgba8)?;
+    Ok(output)
+}
+
+#[cfg(not(feature = "modern-formats"))]
+fn encode_avif(_image: RgbaImage, _quality: u8) -> Result<Vec<u8>, ImageError> {
+    Err(ImageError::Unsupported(image::error::UnsupportedError::from_format_and_kind(
+        image::ImageFormat::Avif.into(),
+        image::error::UnsupportedErrorKind::Format(image::ImageFormat::Avif.into()),
+    )))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn sample() -> RgbaImage {
+        RgbaImage::from_fn(31, 17, |x, y| image::Rgba([(x * 8) as u8, (y * 15) as u8, 128, 255]))
+    }
+
+    #[test]
+    fn png_round_trips_losslessly() {
+        let data = encode(sample(), ImageFormat::Png, DEFAULT_QUALITY).unwrap();
+        assert_eq!(image::load_from_memory(&data).unwrap().to_rgba8(), sample());
+    }
+
+    #[cfg(feature = "modern-formats")]
+    #[test]
+    fn webp_decodes_back_to_the_same_dimensions() {
+        let data = encode(sample(), ImageFormat::WebP, 50).unwrap();
+        let decoded = image::load_from_memory_with_format(&data, image::ImageFormat::WebP).unwrap();
+        assert_eq!((decoded.width(), decoded.height()), (31, 17));
+    }
+
+    #[cfg(feature = "modern-formats")]
+    #[test]
+    fn lower_webp_quality_gives_smaller_output() {
+        let image = RgbaImage::from_fn(128, 128, |x, y| image::Rgba([(x ^ y) as u8, (x * y) as u8, (x + y) as u8, 255]));
+        let low = encode(image.clone(), ImageFormat::WebP, 10).unwrap();
+        let high = encode(image, ImageFormat::WebP, 95).unwrap();
+        assert!(low.len() < high.len());
+    }
+
+    #[cfg(not(feature = "modern-formats"))]
+    #[test]
+    fn avif_is_unsupported_without_the_feature() {
+        assert!(!ImageFormat::Avif.is_supported());
+        assert!(matches!(encode(sample(), ImageFormat::Avif, DEFAULT_QUALITY), Err(ImageError::Unsupported(_))));
+    }
+}
@@ -0,0 +1,405 @@
+use std::collections::HashMap;
+use std::sync::Arc;
+
+use crate::ofd::OfdError;
+use crate::path::PathSegment;
+use crate::resource::Font;
+use crate::st_types::STPos;
+
+/// 复合字形允许的最大嵌套层数，防止损坏的字体造成无限递归
+const MAX_COMPOSITE_DEPTH: usize = 8;
+
+/// 用户提供的后备字体，文字对象的字体没有可用的嵌入字形文件时使用
+/// 先按字体的 FamilyName 与 FontName 查找按字族注册的字体，都没有时使用缺省字体
+#[derive(Debug, Default)]
+pub struct FontRegistry {
+    /// 按小写的字族名索引
+    fallbacks: HashMap<String, Arc<TrueTypeFont>>,
+    default_font: Option<Arc<TrueTypeFont>>,
+}
+
+impl FontRegistry {
+    /// 注册字族 family 的后备字体，字族名不区分大小写，同名的字体被替换
+    /// data 须为 TrueType 字形文件，无法解析时返回 InvalidFont
+    pub fn register_fallback(&mut self, family: &str, data: Vec<u8>) -> Result<(), OfdError> {
+        let font = TrueTypeFont::parse(data).ok_or_else(|| OfdError::InvalidFont(family.to_string()))?;
+        self.fallbacks.insert(family.trim().to_lowercase(), Arc::new(font));
+        Ok(())
+    }
+
+    /// 设置缺省字体，没有按字族注册的字体可用时使用
+    pub fn set_default_font(&mut self, data: Vec<u8>) -> Result<(), OfdError> {
+        let font = TrueTypeFont::parse(data).ok_or_else(|| OfdError::InvalidFont("default".to_string()))?;
+        self.default_font = Some(Arc::new(font));
+        Ok(())
+    }
+
+    /// 是否注册了任何后备字体
+    pub fn is_empty(&self) -> bool {
+        self.fallbacks.is_empty() && self.default_font.is_none()
+    }
+
+    /// 字体资源对应的后备字体，font 为 None 表示引用的字体资源不存在
+    pub(crate) fn resolve(&self, font: Option<&Font>) -> Option<Arc<TrueTypeFont>> {
+        font.into_iter()
+            .flat_map(|f| f.family_name.iter().chain([&f.font_name]))
+            .find_map(|name| self.fallbacks.get(&name.trim().to_lowercase()))
+            .or(self.default_font.as_ref())
+            .cloned()
+    }
+}
+
+/// 嵌入的 TrueType 字形文件，只读取绘制所需的表：字符映射、字形轮廓与水平步进
+/// CFF 轮廓（OTTO）的字体与损坏的字体不支持，解析时返回 None
+#[derive(Debug)]
+pub(crate) struct TrueTypeFont {
+    data: Vec<u8>,
+    units_per_em: f64,
+    long_loca: bool,
+    num_glyphs: u16,
+    num_h_metrics: u16,
+    cmap: Option<(usize, u16)>,
+    loca: usize,
+    glyf: usize,
+    hmtx: usize,
+    /// OS/2 表中的嵌入许可 fsType，没有 OS/2 表时为 0（可安装嵌入）
+    fs_type: u16,
+}
+
+impl TrueTypeFont {
+    pub(crate) fn parse(data: Vec<u8>) -> Option<TrueTypeFont> {
+        let version = read_u32(&data, 0)?;
+        if version != 0x0001_0000 && version != u32::from_be_bytes(*b"true") {
+            return None;
+        }
+        let num_tables = read_u16(&data, 4)? as usize;
+        // 表的范围超出文件长度时视为字体损坏（如被截断），整个字体不可用
+        let table = |tag: &[u8; 4]| -> Option<usize> {
+            let record = (0..num_tables)
+                .map(|i| 12 + i * 16)
+                .find(|record| data.get(*record..*record + 4) == Some(tag.as_slice()))?;
+            let offset = read_u32(&data, record + 8)? as usize;
+            let length = read_u32(&data, record + 12)? as usize;
+            (offset.checked_add(length)? <= data.len()).then_some(offset)
+        };
+
+        let head = table(b"head")?;
+        let maxp = table(b"maxp")?;
+        let hhea = table(b"hhea")?;
+        let loca = table(b"loca")?;
+        let glyf = table(b"glyf")?;
+        let hmtx = table(b"hmtx")?;
+        let cmap = table(b"cmap").and_then(|cmap| find_cmap_subtable(&data, cmap));
+
+        let units_per_em = read_u16(&data, head + 18).filter(|u| *u > 0)? as f64;
+        let long_loca = read_u16(&data, head + 50)? != 0;
+        let num_glyphs = read_u16(&data, maxp + 4)?;
+        let num_h_metrics = read_u16(&data, hhea + 34)?;
+        let fs_type = table(b"OS/2").and_then(|os2| read_u16(&data, os2 + 8)).unwrap_or(0);
+
+        Some(TrueTypeFont {
+            data,
+            units_per_em,
+            long_loca,
+            num_glyphs,
+            num_h_metrics,
+            cmap,
+            loca,
+            glyf,
+            hmtx,
+            fs_type,
+        })
+    }
+
+    /// 字体许可是否允许嵌入到输出文件中
+    /// fsType 的低 4 位为 2（受限许可）或第 9 位（只允许嵌入位图）置位时不允许
+    pub(crate) fn allows_embedding(&self) -> bool {
+        self.fs_type & 0x000f != 0x0002 && self.fs_type & 0x0200 == 0
+    }
+
+    /// 字形文件的原始数据
+    pub(crate) fn data(&self) -> &[u8] {
+        &self.data
+    }
+
+    pub(crate) fn units_per_em(&self) -> f64 {
+        self.units_per_em
+    }
+
+    /// 字符对应的字形索引，字体中没有该字符时返回 None
+    pub(crate) fn glyph_index(&self, c: char) -> Option<u16> {
+        let (offset, format) = self.cmap?;
+        let code = c as u32;
+        let data = &self.data;
+        let glyph = match format {
+            4 => {
+                if code > 0xFFFF {
+                    return None;
+                }
+                let seg_count = read_u16(data, offset + 6)? as usize / 2;
+                let ends = offset + 14;
+                let starts = ends + seg_count * 2 + 2;
+                let deltas = starts + seg_count * 2;
+                let range_offsets = deltas + seg_count * 2;
+                let segment = (0..seg_count).find(|i| {
+                    read_u16(data, ends + i * 2).is_some_and(|end| code <= end as u32)
+                })?;
+                let start = read_u16(data, starts + segment * 2)? as u32;
+                if code < start {
+                    return None;
+                }
+                let delta = read_u16(data, deltas + segment * 2)?;
+                let range_offset = read_u16(data, range_offsets + segment * 2)? as usize;
+                if range_offset == 0 {
+                    (code as u16).wrapping_add(delta)
+                } else {
+                    let address = range_offsets + segment * 2 + range_offset + (code - start) as usize * 2;
+                    match read_u16(data, address)? {
+                        0 => 0,
+                        glyph => glyph.wrapping_add(delta),
+                    }
+                }
+            }
+            12 => {
+                let groups = read_u32(data, offset + 12)? as usize;
+                (0..groups).map(|i| offset + 16 + i * 12).find_map(|group| {
+                    let start = read_u32(data, group)?;
+                    let end = read_u32(data, group + 4)?;
+                    if code < start || code > end {
+                        return None;
+                    }
+                    Some((read_u32(data, group + 8)? + code - start) as u16)
+                })?
+            }
+            _ => return None,
+        };
+        Some(glyph).filter(|g| *g != 0 && *g < self.num_glyphs)
+    }
+
+    /// 字形的水平步进，单位为字体单位
+    pub(crate) fn advance(&self, glyph: u16) -> f64 {
+        let index = glyph.min(self.num_h_metrics.saturating_sub(1)) as usize;
+        read_u16(&self.data, self.hmtx + index * 4).unwrap_or(0) as f64
+    }
+
+    /// 字形轮廓，坐标为字体单位且 y 轴向上；空白字形返回空列表
+    pub(crate) fn outline(&self, glyph: u16) -> Vec<PathSegment> {
+        let mut segments = Vec::new();
+        self.append_outline(glyph, [1.0, 0.0, 0.0, 1.0, 0.0, 0.0], 0, &mut segments);
+        segments
+    }
+
+    fn glyph_range(&self, glyph: u16) -> Option<(usize, usize)> {
+        if glyph >= self.num_glyphs {
+            return None;
+        }
+        let index = glyph as usize;
+        let (start, end) = if self.long_loca {
+            (read_u32(&self.data, self.loca + index * 4)? as usize, read_u32(&self.data, self.loca + index * 4 + 4)? as usize)
+        } else {
+            (read_u16(&self.data, self.loca + index * 2)? as usize * 2, read_u16(&self.data, self.loca + index * 2 + 2)? as usize * 2)
+        };
+        (end > start).then_some((self.glyf + start, self.glyf + end))
+    }
+
+    /// 按变换 [a, b, c, d, e, f] 将字形轮廓追加到 segments 中
+    fn append_outline(&self, glyph: u16, transform: [f64; 6], depth: usize, segments: &mut Vec<PathSegment>) -> Option<()> {
+        let (start, end) = self.glyph_range(glyph)?;
+        let data = self.data.get(start..end)?;
+        let contours = read_u16(data, 0)? as i16;
+        if contours >= 0 {
+            append_simple_glyph(data, contours as usize, &transform, segments)
+        } else if depth < MAX_COMPOSITE_DEPTH {
+            self.append_composite_glyph(data, &transform, depth, segments)
+        } else {
+            None
+        }
+    }
+
+    fn append_composite_glyph(&self, data: &[u8], transform: &[f64; 6], depth: usize, segments: &mut Vec<PathSegment>) -> Option<()> {
+        const ARG_1_AND_2_ARE_WORDS: u16 = 0x0001;
+        const ARGS_ARE_XY_VALUES: u16 = 0x0002;
+        const WE_HAVE_A_SCALE: u16 = 0x0008;
+        const MORE_COMPONENTS: u16 = 0x0020;
+        const WE_HAVE_AN_X_AND_Y_SCALE: u16 = 0x0040;
+        const WE_HAVE_A_TWO_BY_TWO: u16 = 0x0080;
+
+        let mut offset = 10;
+        loop {
+            let flags = read_u16(data, offset)?;
+            let component = read_u16(data, offset + 2)?;
+            offset += 4;
+            let (arg1, arg2) = if flags & ARG_1_AND_2_ARE_WORDS != 0 {
+                offset += 4;
+                (read_u16(data, offset - 4)? as i16 as f64, read_u16(data, offset - 2)? as i16 as f64)
+            } else {
+                offset += 2;
+                (*data.get(offset - 2)? as i8 as f64, *data.get(offset - 1)? as i8 as f64)
+            };
+            // 按点对齐的组件不常见，按无偏移处理
+            let (dx, dy) = if flags & ARGS_ARE_XY_VALUES != 0 { (arg1, arg2) } else { (0.0, 0.0) };
+            let f2dot14 = |at: usize| read_u16(data, at).map(|v| v as i16 as f64 / 16384.0);
+            let (a, b, c, d) = if flags & WE_HAVE_A_SCALE != 0 {
+                offset += 2;
+                let s = f2dot14(offset - 2)?;
+                (s, 0.0, 0.0, s)
+            } else if flags & WE_HAVE_AN_X_AND_Y_SCALE != 0 {
+                offset += 4;
+                (f2dot14(offset - 4)?, 0.0, 0.0, f2dot14(offset - 2)?)
+            } else if flags & WE_HAVE_A_TWO_BY_TWO != 0 {
+                offset += 8;
+                (f2dot14(offset - 8)?, f2dot14(offset - 6)?, f2dot14(offset - 4)?, f2dot14(offset - 2)?)
+            } else {
+                (1.0, 0.0, 0.0, 1.0)
+            };
+
+            // 先应用组件自身的变换，再应用外层变换
+            let [ta, tb, tc, td, te, tf] = *transform;
+            let combined = [
+                a * ta + b * tc,
+                a * tb + b * td,
+                c * ta + d * tc,
+                c * tb + d * td,
+                dx * ta + dy * tc + te,
+                dx * tb + dy * td + tf,
+            ];
+            self.append_outline(component, combined, depth + 1, segments);
+
+            if flags & MORE_COMPONENTS == 0 {
+                return Some(());
+            }
+        }
+    }
+}
+
+/// 在字体的 cmap 表中选择 Unicode 子表，优先使用完整的 Unicode 映射（格式 12）
+fn find_cmap_subtable(data: &[u8], cmap: usize) -> Option<(usize, u16)> {
+    let count = read_u16(data, cmap + 2)? as usize;
+    let mut best: Option<(usize, u16)> = None;
+    for i in 0..count {
+        let record = cmap + 4 + i * 8;
+        let platform = read_u16(data, record)?;
+        let encoding = read_u16(data, record + 2)?;
+        let offset = cmap + read_u32(data, record + 4)? as usize;
+        let format = read_u16(data, offset)?;
+        let unicode = platform == 0 || (platform == 3 && (encoding == 1 || encoding == 10));
+        if !unicode {
+            continue;
+        }
+        match format {
+            12 => return Some((offset, format)),
+            4 if best.is_none() => best = Some((offset, format)),
+            _ => {}
+        }
+    }
+    best
+}
+
+fn append_simple_glyph(data: &[u8], contours: usize, transform: &[f64; 6], segments: &mut Vec<PathSegment>) -> Option<()> {
+    const ON_CURVE: u8 = 0x01;
+    const X_SHORT: u8 = 0x02;
+    const Y_SHORT: u8 = 0x04;
+    const REPEAT: u8 = 0x08;
+    const X_SAME_OR_POSITIVE: u8 = 0x10;
+    const Y_SAME_OR_POSITIVE: u8 = 0x20;
+
+    let mut end_points = Vec::with_capacity(contours);
+    for i in 0..contours {
+        end_points.push(read_u16(data, 10 + i * 2)? as usize);
+    }
+    let point_count = end_points.last().map_or(0, |last| last + 1);
+    let instructions = read_u16(data, 10 + contours * 2)? as usize;
+    let mut offset = 12 + contours * 2 + instructions;
+
+    let mut flags = Vec::with_capacity(point_count);
+    while flags.len() < point_count {
+        let flag = *data.get(offset)?;
+        offset += 1;
+        flags.push(flag);
+        if flag & REPEAT != 0 {
+            let repeat = *data.get(offset)?;
+            offset += 1;
+            for _ in 0..repeat {
+                flags.push(flag);
+            }
+        }
+    }
+    flags.truncate(point_count);
+
+    let mut read_coords = |short: u8, same_or_positive: u8| -> Option<Vec<f64>> {
+        let mut value = 0i32;
+        let mut coords = Vec::with_capacity(point_count);
+        for flag in &flags {
+            if flag & short != 0 {
+                let delta = *data.get(offset)? as i32;
+                offset += 1;
+                value += if flag & same_or_positive != 0 { delta } else { -delta };
+            } else if flag & same_or_positive == 0 {
+                value += read_u16(data, offset)? as i16 as i32;
+                offset += 2;
+            }
+            coords.push(value as f64);
+        }
+        Some(coords)
+    };
+    let xs = read_coords(X_SHORT, X_SAME_OR_POSITIVE)?;
+    let ys = read_coords(Y_SHORT, Y_SAME_OR_POSITIVE)?;
+
+    let [a, b, c, d, e, f] = *transform;
+    let point = |i: usize| STPos {
+        x: a * xs[i] + c * ys[i] + e,
+        y: b * xs[i] + d * ys[i] + f,
+    };
+    let midpoint = |p: STPos, q: STPos| STPos { x: (p.x + q.x) / 2.0, y: (p.y + q.y) / 2.0 };
+
+    let mut start = 0;
+    for end in end_points {
+        if end < start || end >= point_count {
+            return None;
+        }
+        let indices: Vec<usize> = (start..=end).collect();
+        start = end + 1;
+        let on_curve = |i: usize| flags[i] & ON_CURVE != 0;
+
+        // 轮廓的起点必须在曲线上：首点不在曲线上时改用末点，都不在时取两者的中点
+        let first = indices[0];
+        let last = indices[indices.len() - 1];
+        let (origin, rest) = if on_curve(first) {
+            (point(first), &indices[1..])
+        } else if on_curve(last) {
+            (point(last), &indices[..indices.len() - 1])
+        } else {
+            (midpoint(point(last), point(first)), &indices[..])
+        };
+        segments.push(PathSegment::MoveTo { to: origin });
+
+        let mut control: Option<STPos> = None;
+        for &i in rest {
+            let p = point(i);
+            if on_curve(i) {
+                match control.take() {
+                    Some(c) => segments.push(PathSegment::QuadTo { ctrl: c, to: p }),
+                    None => segments.push(PathSegment::LineTo { to: p }),
+                }
+            } else {
+                if let Some(c) = control {
+                    segments.push(PathSegment::QuadTo { ctrl: c, to: midpoint(c, p) });
+                }
+                control = Some(p);
+            }
+        }
+        if let Some(c) = control {
+            segments.push(PathSegment::QuadTo { ctrl: c, to: origin });
+        }
+        segments.push(PathSegment::Close);
+    }
+    Some(())
+}
+
+fn read_u16(data: &[u8], offset: usize) -> Option<u16> {
+    data.get(offset..offset + 2).map(|b| u16::from_be_bytes([b[0], b[1]]))
+}
+
+fn read_u32(data: &[u8], offset: usize) -> Option<u32> {
+    data.get(offset..offset + 4).map(|b| u32::from_be_bytes([b[0], b[1], b[2], b[3]]))
+}
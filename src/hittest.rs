@@ -0,0 +1,129 @@
+use cairo::{Context, Format, ImageSurface};
+
+use crate::document::ZOrder;
+use crate::page::{FillRule, ImageObject, ObjectKind, Page, PageObject, PathObject};
+use crate::render::{append_path, object_matrix, set_stroke_style, to_cairo_matrix};
+use crate::resource::ResourceManager;
+use crate::st_types::{STBox, STMatrix, STPath, STPos};
+
+/// 位于页面中某点的图元
+#[derive(Debug, Clone)]
+pub struct ObjectRef {
+    pub id: String,
+    pub kind: ObjectKind,
+    /// 外接矩形，页面坐标系（毫米）
+    pub boundary: STBox,
+    /// 文字对象的文字内容，其他图元为 None
+    pub text: Option<String>,
+}
+
+/// 查找页面及其模板中包含 point 的图元，point 为页面坐标（毫米），结果按从上到下的顺序排列
+/// 图元须包含在外接矩形内，此外：
+/// - 图形对象按实际的路径判断，填充的路径判断是否在填充区域内，勾边的路径判断是否在线条上
+/// - 图像对象判断是否在经 CTM 映射后的图像内
+/// - 复合对象判断是否在经 CTM 映射后的矢量图形范围内
+/// - 文字对象只判断外接矩形
+///
+/// 页块本身不返回，其中的图元按上述规则判断；模板中与页面图元标识相同的图元不重复返回
+pub(crate) fn objects_at(
+    page: &Page,
+    templates: &[(Page, ZOrder)],
+    point: STPos,
+    resources: &ResourceManager,
+) -> Result<Vec<ObjectRef>, cairo::Error> {
+    let surface = ImageSurface::create(Format::A8, 1, 1)?;
+    let tester = HitTester { cr: Context::new(&surface)?, resources, point };
+    let mut hits = Vec::new();
+    let backgrounds = templates.iter().filter(|(_, z)| *z == ZOrder::Background).map(|(t, _)| (t, Some(page)));
+    let foregrounds = templates.iter().filter(|(_, z)| *z == ZOrder::Foreground).map(|(t, _)| (t, Some(page)));
+    for (p, exclude) in backgrounds.chain([(page, None)]).chain(foregrounds) {
+        for layer in p.layers() {
+            tester.collect(&layer.objects, exclude, layer.draw_param.as_deref(), &mut hits)?;
+        }
+    }
+    hits.reverse();
+    Ok(hits)
+}
+
+struct HitTester<'a> {
+    cr: Context,
+    resources: &'a ResourceManager,
+    point: STPos,
+}
+
+impl HitTester<'_> {
+    fn collect(&self, objects: &[PageObject], exclude: Option<&Page>, layer_draw_param: Option<&str>, hits: &mut Vec<ObjectRef>) -> Result<(), cairo::Error> {
+        for object in objects {
+            if exclude.is_some_and(|page| page.contains_object(object.id())) {
+                continue;
+            }
+            if let PageObject::PageBlock(block) = object {
+                self.collect(&block.objects, exclude, layer_draw_param, hits)?;
+                continue;
+            }
+            let Some(boundary) = object.boundary().filter(|b| b.contains(&self.point)) else {
+                continue;
+            };
+            let hit = match object {
+                PageObject::PathObject(o) => self.hits_path(o, layer_draw_param)?,
+                PageObject::ImageObject(o) => self.hits_unit_square(o),
+                PageObject::CompositeObject(o) => {
+                    match self.resources.vector_graphic(&o.resource_id).filter(|u| u.width > 0.0 && u.height > 0.0) {
+                        Some(unit) => self.to_object(&o.boundary, o.ctm)
+                            .is_some_and(|p| p.x >= 0.0 && p.y >= 0.0 && p.x <= unit.width && p.y <= unit.height),
+                        None => true,
+                    }
+                }
+                _ => true,
+            };
+            if hit {
+                let text = match object {
+                    PageObject::TextObject(o) => Some(o.text_codes().map(|c| c.text.as_str()).collect()),
+                    _ => None,
+                };
+                hits.push(ObjectRef { id: object.id().to_string(), kind: object.kind(), boundary, text });
+            }
+        }
+        Ok(())
+    }
+
+    /// 点在图元坐标系中的位置，CTM 为奇异矩阵时返回 None
+    fn to_object(&self, boundary: &STBox, ctm: Option<STMatrix>) -> Option<STPos> {
+        object_matrix(boundary, ctm).invert().map(|m| m.apply(self.point))
+    }
+
+    fn hits_unit_square(&self, object: &ImageObject) -> bool {
+        self.to_object(&object.boundary, object.ctm)
+            .is_some_and(|p| (0.0..=1.0).contains(&p.x) && (0.0..=1.0).contains(&p.y))
+    }
+
+    /// 路径数据无法解析时按外接矩形判断
+    fn hits_path(&self, object: &PathObject, layer_draw_param: Option<&str>) -> Result<bool, cairo::Error> {
+        let Ok(path) = object.abbreviated_data.parse::<STPath>() else {
+            return Ok(true);
+        };
+        let matrix = object_matrix(&object.boundary, object.ctm);
+        if matrix.invert().is_none() {
+            return Ok(false);
+        }
+        let param = self.resources.effective_draw_param(object.draw_param.as_deref(), layer_draw_param);
+        let cr = &self.cr;
+        cr.save()?;
+        cr.new_path();
+        cr.transform(to_cairo_matrix(&matrix));
+        append_path(cr, &path.to_render_path());
+        cr.set_fill_rule(match object.rule {
+            FillRule::NonZero => cairo::FillRule::Winding,
+            FillRule::EvenOdd => cairo::FillRule::EvenOdd,
+        });
+        let (x, y) = cr.device_to_user(self.point.x, self.point.y)?;
+        let mut hit = object.fill && cr.in_fill(x, y)?;
+        if !hit && object.stroke {
+            set_stroke_style(cr, &object.style(&param));
+            hit = cr.in_stroke(x, y)?;
+        }
+        cr.new_path();
+        cr.restore()?;
+        Ok(hit)
+    }
+}
@@ -0,0 +1,108 @@
+use std::fmt;
+
+use qcms::{DataType, Intent, Profile, Transform};
+
+use crate::resource::ColorSpaceType;
+
+/// 由颜色空间引用的 ICC 描述文件建立的到 sRGB 的颜色转换
+pub(crate) struct IccTransform {
+    transform: Transform,
+    channels: usize,
+}
+
+impl fmt::Debug for IccTransform {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("IccTransform").field("channels", &self.channels).finish_non_exhaustive()
+    }
+}
+
+impl IccTransform {
+    /// 解析描述文件并建立转换，描述文件无法解析或与颜色空间的类型不符时返回 None
+    pub(crate) fn new(data: &[u8], space: ColorSpaceType) -> Option<IccTransform> {
+        let input = Profile::new_from_slice(data, false)?;
+        let output = Profile::new_sRGB();
+        let (input_type, channels) = match space {
+            ColorSpaceType::GRAY => (DataType::Gray8, 1),
+            ColorSpaceType::RGB => (DataType::RGB8, 3),
+            ColorSpaceType::CMYK => (DataType::CMYK, 4),
+        };
+        let transform = Transform::new_to(&input, &output, input_type, DataType::RGB8, Intent::Perceptual)?;
+        Some(IccTransform { transform, channels })
+    }
+
+    /// 将 0 到 255 之间的各通道分量转换为 sRGB，分量不足的按 0 补齐
+    pub(crate) fn to_rgb(&self, components: &[u8]) -> [u8; 3] {
+        let mut src = components.to_vec();
+        src.resize(self.channels, 0);
+        let mut dst = [0u8; 3];
+        self.transform.convert(&src, &mut dst);
+        dst
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::sync::Arc;
+
+    use super::*;
+    use crate::resource::ColorSpace;
+
+    /// 只含 A2B0（lut8，2 × 2 × 2 × 2 的网格）的 CMYK 描述文件，连接空间为 Lab
+    /// 网格中的颜色只取决于 K：K 为 0 时为白色，K 为满值时为黑色，C、M、Y 不影响结果
+    fn cmyk_profile() -> Vec<u8> {
+        let mut lut = b"mft1\0\0\0\0".to_vec();
+        lut.extend([4, 3, 2, 0]);
+        for i in 0..9 {
+            let one = if i % 4 == 0 { 0x0001_0000u32 } else { 0 };
+            lut.extend(one.to_be_bytes());
+        }
+        for _ in 0..4 {
+            lut.extend(0..=255u8);
+        }
+        for i in 0..16 {
+            lut.extend(if i & 1 == 0 { [255, 128, 128] } else { [0, 128, 128] });
+        }
+        for _ in 0..3 {
+            lut.extend(0..=255u8);
+        }
+
+        let size = 128 + 4 + 12 + lut.len();
+        let mut header = vec![0u8; 128];
+        header[0..4].copy_from_slice(&(size as u32).to_be_bytes());
+        header[12..16].copy_from_slice(b"prtr");
+        header[16..20].copy_from_slice(b"CMYK");
+        header[20..24].copy_from_slice(b"Lab ");
+        header[36..40].copy_from_slice(b"acsp");
+        let mut profile = header;
+        profile.extend(1u32.to_be_bytes());
+        profile.extend(b"A2B0");
+        profile.extend(144u32.to_be_bytes());
+        profile.extend((lut.len() as u32).to_be_bytes());
+        profile.extend(lut);
+        profile
+    }
+
+    #[test]
+    fn cmyk_profile_overrides_the_naive_conversion() {
+        let naive = ColorSpace { color_space_type: ColorSpaceType::CMYK, ..ColorSpace::default() };
+        let cyan = naive.to_rgb(&[255.0, 0.0, 0.0, 0.0]);
+        assert_eq!(cyan, [0, 255, 255]);
+
+        let icc = IccTransform::new(&cmyk_profile(), ColorSpaceType::CMYK).unwrap();
+        let managed = ColorSpace { icc: Some(Arc::new(icc)), ..naive };
+        // 描述文件忽略青色分量，同样的输入得到接近白色的中性色
+        let converted = managed.to_rgb(&[255.0, 0.0, 0.0, 0.0]);
+        assert_ne!(converted, cyan);
+        assert!(converted.iter().all(|&v| v > 240), "got {:?}", converted);
+        let black = managed.to_rgb(&[0.0, 0.0, 0.0, 255.0]);
+        assert!(black.iter().all(|&v| v < 16), "got {:?}", black);
+    }
+
+    #[test]
+    fn unparsable_profile_has_no_transform() {
+        assert!(IccTransform::new(b"not a profile", ColorSpaceType::CMYK).is_none());
+        let mut truncated = cmyk_profile();
+        truncated.truncate(200);
+        assert!(IccTransform::new(&truncated, ColorSpaceType::CMYK).is_none());
+    }
+}
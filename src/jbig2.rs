@@ -0,0 +1,380 @@
+use crate::bilevel::{BilevelError, Bitmap, CombinationOperator};
+use crate::ccitt;
+
+const FILE_HEADER_MAGIC: &[u8] = &[0x97, 0x4A, 0x42, 0x32, 0x0D, 0x0A, 0x1A, 0x0A];
+
+/// 段类型，只列出解码一页通用区域所需的与需要报告不支持的类型
+const SEGMENT_SYMBOL_DICTIONARY: u8 = 0;
+const SEGMENT_INTERMEDIATE_TEXT_REGION: u8 = 4;
+const SEGMENT_IMMEDIATE_TEXT_REGION: u8 = 6;
+const SEGMENT_IMMEDIATE_LOSSLESS_TEXT_REGION: u8 = 7;
+const SEGMENT_PATTERN_DICTIONARY: u8 = 16;
+const SEGMENT_INTERMEDIATE_HALFTONE_REGION: u8 = 20;
+const SEGMENT_IMMEDIATE_HALFTONE_REGION: u8 = 22;
+const SEGMENT_IMMEDIATE_LOSSLESS_HALFTONE_REGION: u8 = 23;
+const SEGMENT_INTERMEDIATE_GENERIC_REGION: u8 = 36;
+const SEGMENT_IMMEDIATE_GENERIC_REGION: u8 = 38;
+const SEGMENT_IMMEDIATE_LOSSLESS_GENERIC_REGION: u8 = 39;
+const SEGMENT_INTERMEDIATE_REFINEMENT_REGION: u8 = 40;
+const SEGMENT_IMMEDIATE_REFINEMENT_REGION: u8 = 42;
+const SEGMENT_IMMEDIATE_LOSSLESS_REFINEMENT_REGION: u8 = 43;
+const SEGMENT_PAGE_INFORMATION: u8 = 48;
+const SEGMENT_END_OF_PAGE: u8 = 49;
+const SEGMENT_END_OF_STRIPE: u8 = 50;
+const SEGMENT_END_OF_FILE: u8 = 51;
+
+/// 段数据长度未知时的取值
+const UNKNOWN_LENGTH: u32 = u32::MAX;
+
+/// 区域段信息字段的长度：宽、高、x、y 各 4 字节，加 1 字节标志
+const REGION_INFO_LENGTH: usize = 17;
+
+/// 各模板的 TPGDON 伪像素所用的上下文，与普通像素共用上下文状态
+const TPGD_CONTEXTS: [usize; 4] = [0x9B25, 0x0795, 0x00E5, 0x0195];
+
+#[derive(Debug)]
+struct SegmentHeader {
+    segment_type: u8,
+    page: u32,
+    data_length: u32,
+}
+
+/// 解码 JBIG2 文件中的第一页，只支持由通用区域（算术编码或 MMR 编码）构成的页面
+/// 符号字典、文本区域、半色调区域与细化区域返回 Unsupported；没有文件头的嵌入式数据流按顺序组织处理
+pub(crate) fn decode(data: &[u8]) -> Result<Bitmap, BilevelError> {
+    let segments = read_segments(data).ok_or(BilevelError::Corrupt)?;
+    let mut page: Option<Bitmap> = None;
+    let mut page_number = None;
+    let mut default_pixel = 0;
+    let mut striped = false;
+    for (header, data) in segments {
+        if header.page != 0 && *page_number.get_or_insert(header.page) != header.page {
+            continue;
+        }
+        match header.segment_type {
+            SEGMENT_PAGE_INFORMATION => {
+                if page.is_some() {
+                    break;
+                }
+                let width = read_u32(data, 0).ok_or(BilevelError::Corrupt)?;
+                let height = read_u32(data, 4).ok_or(BilevelError::Corrupt)?;
+                let flags = *data.get(16).ok_or(BilevelError::Corrupt)?;
+                default_pixel = (flags >> 2) & 1;
+                // 高度未知时按条带逐步扩展
+                striped = height == u32::MAX;
+                let height = if striped { 0 } else { height as usize };
+                page = Some(Bitmap::new(width as usize, height, default_pixel).ok_or(BilevelError::Corrupt)?);
+            }
+            SEGMENT_IMMEDIATE_GENERIC_REGION | SEGMENT_IMMEDIATE_LOSSLESS_GENERIC_REGION => {
+                let page = page.as_mut().ok_or(BilevelError::Corrupt)?;
+                if header.data_length == UNKNOWN_LENGTH {
+                    return Err(BilevelError::Unsupported("JBIG2 generic region of unknown length".to_string()));
+                }
+                let info = RegionInfo::parse(data).ok_or(BilevelError::Corrupt)?;
+                let region = decode_generic_region(&data[REGION_INFO_LENGTH..], info.width, info.height)?;
+                if striped {
+                    page.grow(info.y.saturating_add(info.height), default_pixel).ok_or(BilevelError::Corrupt)?;
+                }
+                page.compose(&region, info.x, info.y, info.operator);
+            }
+            SEGMENT_END_OF_STRIPE => {
+                if let (Some(page), true) = (page.as_mut(), striped) {
+                    let end_row = read_u32(data, 0).ok_or(BilevelError::Corrupt)? as usize;
+                    page.grow(end_row.saturating_add(1), default_pixel).ok_or(BilevelError::Corrupt)?;
+                }
+            }
+            SEGMENT_END_OF_PAGE | SEGMENT_END_OF_FILE => break,
+            SEGMENT_SYMBOL_DICTIONARY => return Err(unsupported("symbol dictionary")),
+            SEGMENT_INTERMEDIATE_TEXT_REGION | SEGMENT_IMMEDIATE_TEXT_REGION | SEGMENT_IMMEDIATE_LOSSLESS_TEXT_REGION => {
+                return Err(unsupported("text region"));
+            }
+            SEGMENT_PATTERN_DICTIONARY => return Err(unsupported("pattern dictionary")),
+            SEGMENT_INTERMEDIATE_HALFTONE_REGION | SEGMENT_IMMEDIATE_HALFTONE_REGION | SEGMENT_IMMEDIATE_LOSSLESS_HALFTONE_REGION => {
+                return Err(unsupported("halftone region"));
+            }
+            SEGMENT_INTERMEDIATE_GENERIC_REGION => return Err(unsupported("intermediate generic region")),
+            SEGMENT_INTERMEDIATE_REFINEMENT_REGION | SEGMENT_IMMEDIATE_REFINEMENT_REGION | SEGMENT_IMMEDIATE_LOSSLESS_REFINEMENT_REGION => {
+                return Err(unsupported("refinement region"));
+            }
+            // 配置、码表、扩展等段不影响通用区域的解码
+            _ => {}
+        }
+    }
+    page.ok_or(BilevelError::Corrupt)
+}
+
+fn unsupported(feature: &str) -> BilevelError {
+    BilevelError::Unsupported(format!("JBIG2 {}", feature))
+}
+
+fn read_u32(data: &[u8], offset: usize) -> Option<u32> {
+    Some(u32::from_be_bytes(data.get(offset..offset.checked_add(4)?)?.try_into().ok()?))
+}
+
+/// 读取所有段的段头与段数据
+/// 顺序组织时段头与段数据交替出现；随机访问组织时先是全部段头（以文件结束段结尾），再依次是各段数据
+fn read_segments(data: &[u8]) -> Option<Vec<(SegmentHeader, &[u8])>> {
+    let (mut offset, sequential) = if data.starts_with(FILE_HEADER_MAGIC) {
+        let flags = *data.get(8)?;
+        // 页数已知时文件头中带有 4 字节的页数
+        (if flags & 0x02 == 0 { 13 } else { 9 }, flags & 0x01 == 1)
+    } else {
+        (0, true)
+    };
+
+    let mut headers = Vec::new();
+    let mut segments = Vec::new();
+    while offset < data.len() {
+        let (header, next) = read_segment_header(data, offset)?;
+        offset = next;
+        let end_of_file = header.segment_type == SEGMENT_END_OF_FILE;
+        if sequential {
+            // 长度未知的段只允许出现在最后，取其后的全部数据
+            let length = if header.data_length == UNKNOWN_LENGTH { data.len() - offset } else { header.data_length as usize };
+            let segment_data = data.get(offset..offset.checked_add(length)?)?;
+            offset += length;
+            segments.push((header, segment_data));
+        } else {
+            headers.push(header);
+        }
+        if end_of_file {
+            break;
+        }
+    }
+    for header in headers {
+        let length = header.data_length as usize;
+        let segment_data = data.get(offset..offset.checked_add(length)?)?;
+        offset += length;
+        segments.push((header, segment_data));
+    }
+    Some(segments)
+}
+
+/// 读取 offset 处的段头，返回段头与段头之后的位置
+fn read_segment_header(data: &[u8], offset: usize) -> Option<(SegmentHeader, usize)> {
+    let number = read_u32(data, offset)?;
+    let flags = *data.get(offset + 4)?;
+    let mut pos = offset + 5;
+    // 引用段的个数：高 3 位为 7 时使用 4 字节的长格式，其后为保留标志位
+    let first = *data.get(pos)?;
+    let referred_count = if first >> 5 == 7 {
+        let count = (read_u32(data, pos)? & 0x1FFF_FFFF) as usize;
+        pos = pos.checked_add(4 + (count + 1).div_ceil(8))?;
+        count
+    } else {
+        pos += 1;
+        (first >> 5) as usize
+    };
+    let referred_size = match number {
+        0..=256 => 1,
+        257..=65536 => 2,
+        _ => 4,
+    };
+    pos = pos.checked_add(referred_count.checked_mul(referred_size)?)?;
+    let page = if flags & 0x40 != 0 {
+        let page = read_u32(data, pos)?;
+        pos += 4;
+        page
+    } else {
+        let page = *data.get(pos)? as u32;
+        pos += 1;
+        page
+    };
+    let data_length = read_u32(data, pos)?;
+    Some((SegmentHeader { segment_type: flags & 0x3F, page, data_length }, pos + 4))
+}
+
+/// 区域段信息字段
+struct RegionInfo {
+    width: usize,
+    height: usize,
+    x: usize,
+    y: usize,
+    operator: CombinationOperator,
+}
+
+impl RegionInfo {
+    fn parse(data: &[u8]) -> Option<RegionInfo> {
+        Some(RegionInfo {
+            width: read_u32(data, 0)? as usize,
+            height: read_u32(data, 4)? as usize,
+            x: read_u32(data, 8)? as usize,
+            y: read_u32(data, 12)? as usize,
+            operator: CombinationOperator::from_code(*data.get(16)? & 0x07)?,
+        })
+    }
+}
+
+/// 解码通用区域段中区域信息之后的部分：标志、自适应模板像素与编码数据
+fn decode_generic_region(data: &[u8], width: usize, height: usize) -> Result<Bitmap, BilevelError> {
+    let flags = *data.first().ok_or(BilevelError::Corrupt)?;
+    let mmr = flags & 0x01 != 0;
+    let template = (flags >> 1) & 0x03;
+    let tpgdon = flags & 0x08 != 0;
+    if mmr {
+        return ccitt::decode_g4(&data[1..], width, height);
+    }
+    if flags & 0x10 != 0 {
+        return Err(unsupported("extended generic template"));
+    }
+    let at_count = if template == 0 { 4 } else { 1 };
+    let at_bytes = data.get(1..1 + at_count * 2).ok_or(BilevelError::Corrupt)?;
+    let at: Vec<(isize, isize)> = at_bytes.chunks(2).map(|p| (p[0] as i8 as isize, p[1] as i8 as isize)).collect();
+    let pixels = template_pixels(template, &at);
+
+    let mut bitmap = Bitmap::new(width, height, 0).ok_or(BilevelError::Corrupt)?;
+    let mut decoder = ArithmeticDecoder::new(&data[1 + at_count * 2..]);
+    let mut contexts = vec![0u8; 1 << 16];
+    // TPGDON 时每行先解码一位，为 1 表示本行与上一行相同
+    let mut same_as_above = false;
+    for y in 0..height {
+        if tpgdon {
+            same_as_above ^= decoder.decode(&mut contexts, TPGD_CONTEXTS[template as usize]) == 1;
+            if same_as_above {
+                if y > 0 {
+                    bitmap.copy_row(y - 1, y);
+                }
+                continue;
+            }
+        }
+        for x in 0..width {
+            let context = pixels.iter()
+                .fold(0usize, |context, (dx, dy)| context << 1 | bitmap.get(x as isize + dx, y as isize + dy) as usize);
+            if decoder.decode(&mut contexts, context) == 1 {
+                bitmap.row_mut(y)[x] = 1;
+            }
+        }
+    }
+    Ok(bitmap)
+}
+
+/// 各模板构成上下文的参考像素，相对当前像素的偏移，按上下文的位从高到低排列
+/// at 为自适应模板像素，模板 0 有 4 个，其余模板有 1 个
+fn template_pixels(template: u8, at: &[(isize, isize)]) -> Vec<(isize, isize)> {
+    match template {
+        0 => vec![
+            at[3], (-1, -2), (0, -2), (1, -2), at[2], at[1], (-2, -1), (-1, -1),
+            (0, -1), (1, -1), (2, -1), at[0], (-4, 0), (-3, 0), (-2, 0), (-1, 0),
+        ],
+        1 => vec![
+            (-1, -2), (0, -2), (1, -2), (2, -2), (-2, -1), (-1, -1), (0, -1),
+            (1, -1), (2, -1), at[0], (-3, 0), (-2, 0), (-1, 0),
+        ],
+        2 => vec![(-1, -2), (0, -2), (1, -2), (-2, -1), (-1, -1), (0, -1), (1, -1), at[0], (-2, 0), (-1, 0)],
+        _ => vec![(-3, -1), (-2, -1), (-1, -1), (0, -1), (1, -1), at[0], (-4, 0), (-3, 0), (-2, 0), (-1, 0)],
+    }
+}
+
+/// MQ 算术解码器的概率估计表：Qe、MPS 后的下一状态、LPS 后的下一状态、LPS 时是否交换 MPS
+const QE_TABLE: [(u32, u8, u8, bool); 47] = [
+    (0x5601, 1, 1, true), (0x3401, 2, 6, false), (0x1801, 3, 9, false), (0x0AC1, 4, 12, false),
+    (0x0521, 5, 29, false), (0x0221, 38, 33, false), (0x5601, 7, 6, true), (0x5401, 8, 14, false),
+    (0x4801, 9, 14, false), (0x3801, 10, 14, false), (0x3001, 11, 17, false), (0x2401, 12, 18, false),
+    (0x1C01, 13, 20, false), (0x1601, 29, 21, false), (0x5601, 15, 14, true), (0x5401, 16, 14, false),
+    (0x5101, 17, 15, false), (0x4801, 18, 16, false), (0x3801, 19, 17, false), (0x3401, 20, 18, false),
+    (0x3001, 21, 19, false), (0x2801, 22, 19, false), (0x2401, 23, 20, false), (0x2201, 24, 21, false),
+    (0x1C01, 25, 22, false), (0x1801, 26, 23, false), (0x1601, 27, 24, false), (0x1401, 28, 25, false),
+    (0x1201, 29, 26, false), (0x1101, 30, 27, false), (0x0AC1, 31, 28, false), (0x09C1, 32, 29, false),
+    (0x08A1, 33, 30, false), (0x0521, 34, 31, false), (0x0441, 35, 32, false), (0x02A1, 36, 33, false),
+    (0x0221, 37, 34, false), (0x0141, 38, 35, false), (0x0111, 39, 36, false), (0x0085, 40, 37, false),
+    (0x0049, 41, 38, false), (0x0025, 42, 39, false), (0x0015, 43, 40, false), (0x0009, 44, 41, false),
+    (0x0005, 45, 42, false), (0x0001, 45, 43, false), (0x5601, 46, 46, false),
+];
+
+/// JBIG2 的 MQ 算术解码器（T.88 附录 E），越过数据末尾时按 0xFF 填充
+struct ArithmeticDecoder<'a> {
+    data: &'a [u8],
+    pos: usize,
+    c_high: u32,
+    c_low: u32,
+    ct: i32,
+    a: u32,
+}
+
+impl<'a> ArithmeticDecoder<'a> {
+    fn new(data: &'a [u8]) -> ArithmeticDecoder<'a> {
+        let mut decoder = ArithmeticDecoder { data, pos: 0, c_high: 0, c_low: 0, ct: 0, a: 0 };
+        decoder.c_high = decoder.byte(0);
+        decoder.byte_in();
+        decoder.c_high = ((decoder.c_high << 7) & 0xFFFF) | ((decoder.c_low >> 9) & 0x7F);
+        decoder.c_low = (decoder.c_low << 7) & 0xFFFF;
+        decoder.ct -= 7;
+        decoder.a = 0x8000;
+        decoder
+    }
+
+    fn byte(&self, pos: usize) -> u32 {
+        *self.data.get(pos).unwrap_or(&0xFF) as u32
+    }
+
+    fn byte_in(&mut self) {
+        if self.byte(self.pos) == 0xFF {
+            if self.byte(self.pos + 1) > 0x8F {
+                self.c_low += 0xFF00;
+                self.ct = 8;
+            } else {
+                self.pos += 1;
+                self.c_low += self.byte(self.pos) << 9;
+                self.ct = 7;
+            }
+        } else {
+            self.pos += 1;
+            self.c_low += self.byte(self.pos) << 8;
+            self.ct = 8;
+        }
+        if self.c_low > 0xFFFF {
+            self.c_high += self.c_low >> 16;
+            self.c_low &= 0xFFFF;
+        }
+    }
+
+    /// 按上下文解码一位，contexts 中每项的高位为状态编号，最低位为 MPS
+    fn decode(&mut self, contexts: &mut [u8], context: usize) -> u8 {
+        let mut index = (contexts[context] >> 1) as usize;
+        let mut mps = contexts[context] & 1;
+        let (qe, next_mps, next_lps, switch) = QE_TABLE[index];
+        let mut a = self.a - qe;
+        let bit;
+        if self.c_high < qe {
+            if a < qe {
+                bit = mps;
+                index = next_mps as usize;
+            } else {
+                bit = 1 - mps;
+                if switch {
+                    mps = bit;
+                }
+                index = next_lps as usize;
+            }
+            a = qe;
+        } else {
+            self.c_high -= qe;
+            if a & 0x8000 != 0 {
+                self.a = a;
+                return mps;
+            }
+            if a < qe {
+                bit = 1 - mps;
+                if switch {
+                    mps = bit;
+                }
+                index = next_lps as usize;
+            } else {
+                bit = mps;
+                index = next_mps as usize;
+            }
+        }
+        while a & 0x8000 == 0 {
+            if self.ct == 0 {
+                self.byte_in();
+            }
+            a <<= 1;
+            self.c_high = ((self.c_high << 1) & 0xFFFF) | ((self.c_low >> 15) & 1);
+            self.c_low = (self.c_low << 1) & 0xFFFF;
+            self.ct -= 1;
+        }
+        self.a = a;
+        contexts[context] = (index as u8) << 1 | mps;
+        bit
+    }
+}
@@ -0,0 +1,46 @@
+mod ofd;
+mod action;
+mod document;
+mod st_types;
+mod path;
+mod page;
+mod color;
+mod resource;
+mod font;
+mod render;
+mod svg;
+mod text;
+mod pdf;
+mod quantize;
+mod tiff;
+mod package;
+mod tags;
+mod annotation;
+mod attachment;
+mod seal;
+mod bilevel;
+mod ccitt;
+mod jbig2;
+mod icc;
+mod hittest;
+mod watermark;
+mod encode;
+#[cfg(feature = "async")]
+mod nonblocking;
+
+pub mod testing;
+
+pub use annotation::LinkInfo;
+pub use attachment::{AttachmentInfo, MediaInfo, MediaType};
+pub use encode::{ImageFormat, DEFAULT_QUALITY};
+pub use document::{Orientation, OutlineItem, PageInfo, Permissions};
+pub use font::FontRegistry;
+pub use hittest::ObjectRef;
+pub use ofd::{DocMetadata, DocUsage, MergedDoc, ObjectIter, OfdDoc, OfdError, OpenOptions, ParseMode, RenderContext, ValidationIssue, ValidationIssueKind, Value, Warning};
+pub use package::ReadLimits;
+pub use page::{ObjectInfo, ObjectKind, ResolvedObject};
+pub use path::PathSegment;
+pub use render::{AntiAlias, ColorMode, LayerFilter, PaperSize, RenderOptions, RenderTarget, RenderedBuffer, TiffCompression};
+pub use st_types::{ParseSTError, STBox, STMatrix, STPath, STPos};
+pub use tags::TaggedObject;
+pub use text::{Paragraph, SearchOptions, TextHit, TextRun};
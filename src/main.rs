@@ -1,16 +1,155 @@
-mod ofd;
-mod document;
-mod st_types;
-
-fn main() {
-    let start_time = std::time::Instant::now();
-    let ret = ofd::OfdDoc::open("data/fapiao.ofd");
-    if let Err(e) = ret {
-        println!("{:?}", e);
-        return;
-    }
-    let doc = ret.unwrap();
-    let attributes = doc.info();
-    println!("{:?}", attributes);
-    println!("Elapsed time: {:?}", start_time.elapsed());
+use std::path::{Path, PathBuf};
+use std::process::ExitCode;
+
+use clap::{Parser, ValueEnum};
+use image::DynamicImage;
+use ofd2img::{OfdDoc, RenderOptions, RenderTarget};
+
+/// 命令行参数，帮助文字面向用户，用英文书写
+#[derive(Debug, Parser)]
+#[command(
+    name = "ofd2img",
+    version,
+    about = "Convert OFD documents to PNG, JPEG, SVG or PDF",
+    after_help = "Without OUTPUT, prints the document information.\n\
+        PDF output is written to OUTPUT as a single file. Other formats write page_1.png, page_2.png, ...\n\
+        into the OUTPUT directory, or to OUTPUT itself when a single page is selected and OUTPUT ends with\n\
+        the format's extension. Pages are numbered from 1; all pages are converted by default."
+)]
+struct Args {
+    #[arg(help = "OFD file, or a directory holding an unpacked OFD package")]
+    input: String,
+    #[arg(help = "Output file or directory; without it the document information is printed")]
+    output: Option<PathBuf>,
+    #[arg(short, long, value_enum, help = "Output format, defaults to the extension of OUTPUT, or png")]
+    format: Option<Format>,
+    #[arg(short, long, value_parser = parse_dpi, help = "Rendering resolution in dots per inch")]
+    dpi: Option<f32>,
+    #[arg(short, long, help = "Pages to convert, numbered from 1, e.g. 1,3-5")]
+    pages: Option<String>,
+}
+
+/// 输出格式
+#[derive(Debug, Clone, Copy, PartialEq, ValueEnum)]
+enum Format {
+    Png,
+    #[value(alias = "jpg")]
+    Jpeg,
+    Svg,
+    Pdf,
+}
+
+impl Format {
+    /// 按文件扩展名识别格式，不区分大小写
+    fn from_extension(extension: &str) -> Option<Format> {
+        <Format as ValueEnum>::from_str(extension, true).ok()
+    }
+
+    fn extension(self) -> &'static str {
+        match self {
+            Format::Png => "png",
+            Format::Jpeg => "jpg",
+            Format::Svg => "svg",
+            Format::Pdf => "pdf",
+        }
+    }
+}
+
+/// 解析 --dpi 的值，只接受有限的正数
+fn parse_dpi(value: &str) -> Result<f32, String> {
+    match value.parse::<f32>() {
+        Ok(dpi) if dpi.is_finite() && dpi > 0.0 => Ok(dpi),
+        _ => Err(format!("invalid DPI '{}'", value)),
+    }
+}
+
+/// 解析 1,3-5 形式的页码范围，页码从 1 开始，返回从 0 开始的页面序号，保持给出的顺序
+fn parse_pages(spec: &str, page_count: usize) -> Result<Vec<usize>, String> {
+    let mut pages = Vec::new();
+    for item in spec.split(',').map(str::trim) {
+        let invalid = || format!("invalid page range '{}'", item);
+        let (start, end) = match item.split_once('-') {
+            Some((start, end)) => (start.trim(), end.trim()),
+            None => (item, item),
+        };
+        let start: usize = start.parse().map_err(|_| invalid())?;
+        let end: usize = end.parse().map_err(|_| invalid())?;
+        if start == 0 || start > end {
+            return Err(invalid());
+        }
+        if end > page_count {
+            return Err(format!("page {} is out of range, the document has {} pages", end, page_count));
+        }
+        pages.extend(start - 1..end);
+    }
+    Ok(pages)
+}
+
+fn run(args: Args) -> Result<(), String> {
+    let mut doc = OfdDoc::open(&args.input).map_err(|e| format!("failed to open {}: {}", args.input, e))?;
+    let Some(output) = args.output else {
+        println!("{}", doc.info());
+        return Ok(());
+    };
+    let pages = match &args.pages {
+        Some(spec) => parse_pages(spec, doc.page_count())?,
+        None => (0..doc.page_count()).collect(),
+    };
+    let format = args.format
+        .or_else(|| output.extension().and_then(|e| e.to_str()).and_then(Format::from_extension))
+        .unwrap_or(Format::Png);
+    let mut opts = RenderOptions::default();
+    if let Some(dpi) = args.dpi {
+        opts.target = RenderTarget::Dpi(dpi);
+    }
+
+    if format == Format::Pdf {
+        let pdf = doc.pages_to_pdf(&pages, &opts).map_err(|e| e.to_string())?;
+        return std::fs::write(&output, pdf).map_err(|e| format!("failed to write {}: {}", output.display(), e));
+    }
+
+    let single_file = pages.len() == 1
+        && output.extension().and_then(|e| e.to_str()).and_then(Format::from_extension) == Some(format);
+    if !single_file {
+        std::fs::create_dir_all(&output).map_err(|e| format!("failed to create {}: {}", output.display(), e))?;
+    }
+    for index in pages {
+        let path = if single_file {
+            output.clone()
+        } else {
+            output.join(format!("page_{}.{}", index + 1, format.extension()))
+        };
+        write_page(&mut doc, index, format, &opts, &path).map_err(|e| format!("page {}: {}", index + 1, e))?;
+    }
+    Ok(())
+}
+
+fn write_page(doc: &mut OfdDoc, index: usize, format: Format, opts: &RenderOptions, path: &Path) -> Result<(), String> {
+    match format {
+        Format::Svg => {
+            let svg = doc.render_page_svg(index, opts).map_err(|e| e.to_string())?;
+            std::fs::write(path, svg).map_err(|e| format!("failed to write {}: {}", path.display(), e))
+        }
+        Format::Png | Format::Jpeg => {
+            let image = doc.render_page(index, opts).map_err(|e| e.to_string())?;
+            // JPEG 不支持透明度，去掉透明通道后保存
+            let saved = match format {
+                Format::Jpeg => DynamicImage::ImageRgba8(image).to_rgb8().save(path),
+                _ => image.save(path),
+            };
+            saved.map_err(|e| format!("failed to write {}: {}", path.display(), e))
+        }
+        Format::Pdf => unreachable!("PDF output is written as a single file"),
+    }
+}
+
+fn main() -> ExitCode {
+    env_logger::init();
+    match run(Args::parse()) {
+        Ok(()) => ExitCode::SUCCESS,
+        Err(e) => {
+            eprintln!("error: {}", e);
+            ExitCode::FAILURE
+        }
+    }
 }
@@ -0,0 +1,59 @@
+use image::RgbaImage;
+use tokio::task::{self, JoinError};
+
+use crate::ofd::{OfdDoc, OfdError};
+use crate::render::RenderOptions;
+
+/// 阻塞任务中的 panic 原样传递给调用方
+fn propagate_panic(e: JoinError) -> ! {
+    std::panic::resume_unwind(e.into_panic())
+}
+
+impl OfdDoc {
+    /// 在 tokio 的阻塞线程池中打开 OFD 文件，不占用异步运行时的工作线程
+    pub async fn open_async(file_path: &str) -> Result<OfdDoc, OfdError> {
+        let file_path = file_path.to_string();
+        task::spawn_blocking(move || OfdDoc::open(&file_path))
+            .await
+            .unwrap_or_else(|e| propagate_panic(e))
+    }
+
+    /// 在 tokio 的阻塞线程池中渲染一页，渲染期间文档移入阻塞任务，完成后连同结果一起交还
+    pub async fn render_page_async(mut self, page_index: usize, opts: RenderOptions) -> (OfdDoc, Result<RgbaImage, OfdError>) {
+        task::spawn_blocking(move || {
+            let result = self.render_page(page_index, &opts);
+            (self, result)
+        })
+        .await
+        .unwrap_or_else(|e| propagate_panic(e))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::render::RenderTarget;
+
+    const TEST_OFD: &str = concat!(env!("CARGO_MANIFEST_DIR"), "/data/test.ofd");
+
+    #[tokio::test]
+    async fn open_async_matches_open() {
+        let from_async = OfdDoc::open_async(TEST_OFD).await.unwrap();
+        let from_sync = OfdDoc::open(TEST_OFD).unwrap();
+        assert_eq!(from_async.attributes, from_sync.attributes);
+        assert_eq!(from_async.page_count(), from_sync.page_count());
+    }
+
+    #[tokio::test]
+    async fn open_async_reports_a_missing_file() {
+        assert!(matches!(OfdDoc::open_async("missing.ofd").await, Err(OfdError::IoError(_))));
+    }
+
+    #[tokio::test]
+    async fn render_page_async_hands_back_the_document() {
+        let doc = OfdDoc::open_async(TEST_OFD).await.unwrap();
+        let opts = RenderOptions { target: RenderTarget::Dpi(36.0), ..RenderOptions::default() };
+        let (mut doc, image) = doc.render_page_async(0, opts.clone()).await;
+        assert_eq!(image.unwrap(), doc.render_page(0, &opts).unwrap());
+    }
+}
@@ -1,12 +1,49 @@
-use std::{collections::HashMap, fs::File};
-use std::io::{self, BufReader, Read};
+use std::{collections::{HashMap, HashSet, VecDeque}, fs::File, path::Path, sync::Arc};
+use std::io::{self, Cursor, Read, Seek};
 
 use serde::ser::{SerializeMap, SerializeSeq};
+use chrono::{NaiveDate, NaiveDateTime};
 use thiserror::Error;
-use zip::ZipArchive;
+use image::error::{DecodingError, ImageFormatHint};
+use image::{ImageError, RgbaImage};
+use rayon::prelude::*;
 use serde::{Deserialize, Serialize, Serializer};
+use xml::common::Position;
+use xml::namespace::{NS_EMPTY_URI, NS_NO_PREFIX, NS_XMLNS_PREFIX, NS_XML_PREFIX};
+use xml::reader::{EventReader, XmlEvent};
+use ::tiff::encoder::TiffEncoder;
 
-use crate::document::Document;
+use crate::annotation::{self, Annot, Annotations, LinkInfo, PageAnnot};
+use crate::attachment::{AttachmentInfo, Attachments, MediaInfo, MediaType};
+use crate::bilevel::{self, BilevelError};
+use crate::encode::{self, ImageFormat, DEFAULT_QUALITY};
+use crate::document::{normalize_rotation, Document, OutlineElem, OutlineItem, Orientation, PageArea, PageInfo, Permissions, ZOrder};
+use crate::page::{self, ObjectInfo, Page, PageObject, ResolvedObject};
+use crate::font::{FontRegistry, TrueTypeFont};
+use crate::hittest::{self, ObjectRef};
+use crate::icc::IccTransform;
+use crate::package::{DirSource, Package, PackageSource, ReadLimits, ReadSeek, ZipSource};
+use crate::pdf::{PdfWriter, SearchablePdfWriter};
+use crate::quantize;
+use crate::render::{self, ColorMode, PageAssets, RenderOptions, RenderTarget, RenderedBuffer};
+use crate::resource::{Res, ResourceManager};
+use crate::st_types::{STBox, STPos};
+use crate::seal::{self, Signature, Signatures};
+use crate::svg;
+use crate::tags::{self, CustomTags, TaggedObject};
+use crate::testing::{self, DiffResult};
+use crate::tiff;
+use crate::text::{self, Paragraph, SearchOptions, TextHit, TextRun};
+use crate::watermark;
+
+/// 每英寸的毫米数
+const MM_PER_INCH: f64 = 25.4;
+
+/// 缩略图渲染时的最低分辨率
+const MIN_THUMBNAIL_DPI: f64 = 36.0;
+
+/// 已解码图像缓存的字节数上限，超出时按加入顺序淘汰最早的图像
+const IMAGE_CACHE_BYTES: usize = 64 << 20;
 
 #[derive(Debug)]
 pub enum Value {
@@ -59,21 +96,142 @@ impl Serialize for Value {
     }
 }
 
+/// 文档类型 DocInfo.DocUsage，缺省为 Normal
+#[derive(Debug, Clone, PartialEq, Eq, Default)]
+pub enum DocUsage {
+    #[default]
+    Normal,
+    EBook,
+    ENewsPaper,
+    EMagzine,
+    Other(String),
+}
+
+impl From<&str> for DocUsage {
+    fn from(value: &str) -> Self {
+        match value.trim() {
+            "" | "Normal" => DocUsage::Normal,
+            "EBook" => DocUsage::EBook,
+            "ENewsPaper" => DocUsage::ENewsPaper,
+            "EMagzine" => DocUsage::EMagzine,
+            other => DocUsage::Other(other.to_string()),
+        }
+    }
+}
+
+/// 文档一致性检查发现的问题
+#[derive(Debug, Clone)]
+pub struct ValidationIssue {
+    pub kind: ValidationIssueKind,
+    pub message: String,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ValidationIssueKind {
+    /// 页面内容无法读取或解析
+    UnreadablePage,
+    /// 同一页面中存在重复的图元标识
+    DuplicateObjectId,
+    /// 页面或模板页的 BaseLoc 指向的文件不存在
+    MissingEntry,
+    /// 资源文件无法读取或解析，此时不再检查资源引用
+    UnreadableResources,
+    /// 图元或图层引用的绘制参数不存在
+    DanglingDrawParam,
+    /// 文字对象引用的字型不存在
+    DanglingFont,
+    /// 图像或复合对象引用的多媒体或矢量图形资源不存在
+    DanglingResource,
+    /// 页面引用的模板页不存在
+    MissingTemplate,
+    /// MaxUnitID 小于文档中实际使用的最大标识
+    MaxUnitIdTooSmall,
+}
+
+/// 遇到不符合规范的内容时的处理方式
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum ParseMode {
+    /// 任何无法读取或解析的内容都立即返回错误
+    Strict,
+    /// 尽力显示：无法读取或解析的可选内容（资源文件、模板页、附件列表、页面内容）记录警告后跳过，
+    /// 无法读取的页面按空白页处理；OFD.xml 与 Document.xml 仍必须有效
+    #[default]
+    Lenient,
+}
+
+/// 打开文档的选项
+#[derive(Debug, Clone, Default)]
+pub struct OpenOptions {
+    pub mode: ParseMode,
+    /// 加密压缩包的密码，为 None 时按未加密的包打开
+    pub password: Option<Vec<u8>>,
+    pub read_limits: ReadLimits,
+}
+
+/// 宽松模式下跳过的内容
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Warning {
+    /// 出错的包内文件
+    pub entry: String,
+    pub message: String,
+}
+
 #[derive(Error, Debug)]
 pub enum OfdError {
     #[error("Zip error: {0}")]
     ZipError(zip::result::ZipError),
+    #[error("Entry not found in package: {name}")]
+    EntryNotFound { name: String },
+    #[error("Password required to read encrypted entry: {name}")]
+    PasswordRequired { name: String },
+    #[error("Incorrect password for encrypted package")]
+    InvalidPassword,
+    #[error("Package is not encrypted")]
+    NotEncrypted,
     #[error("IO error: {0}")]
     IoError(io::Error),
-    #[error("Serde XML error: {0}")]
-    SerdeXmlError(serde_xml_rs::Error),
+    #[error("Failed to parse {entry}: {source} near `{snippet}`")]
+    XmlParse { entry: String, snippet: String, source: serde_xml_rs::Error },
+    #[error("Cairo error: {0}")]
+    CairoError(cairo::Error),
+    #[error("Surface error: {0}")]
+    SurfaceError(cairo::BorrowError),
+    #[error("Invalid document index: {0}")]
+    InvalidDocumentIndex(usize),
+    #[error("Invalid page index: {0}")]
+    InvalidPageIndex(usize),
+    #[error("Page area not found for page: {0}")]
+    MissingPageArea(usize),
+    #[error("Image error: {0}")]
+    ImageError(image::ImageError),
+    #[error("Resource not found: {0}")]
+    ResourceNotFound(String),
+    #[error("Layer {id} not found on page {page_index}")]
+    LayerNotFound { page_index: usize, id: String },
+    #[error("Image sizes differ: {a:?} and {b:?}")]
+    ImageSizeMismatch { a: (u32, u32), b: (u32, u32) },
+    #[error("TIFF error: {0}")]
+    TiffError(::tiff::TiffError),
+    #[error("Unsupported image format: {format}")]
+    UnsupportedImageFormat { format: String },
+    #[error("Unsupported output format: {ext}")]
+    UnsupportedOutputFormat { ext: String },
+    #[error("Invalid font data: {0}")]
+    InvalidFont(String),
+    #[error("Entry {name} exceeds the size limit of {limit} bytes")]
+    ResourceTooLarge { name: String, limit: u64 },
+    #[error("Cannot allocate a pixel buffer for a {width}x{height} image")]
+    BufferTooLarge { width: u32, height: u32 },
+    #[error("Failed to render page {0}: {1}")]
+    PageError(usize, Box<OfdError>),
 }
 
 #[derive(Debug, Deserialize, Default)]
 #[serde(rename_all = "PascalCase")]
 struct OfdNode {
-    #[serde(rename = "DocBody")]
-    doc_body: DocBody,
+    /// 一个包中可以含有多个文档
+    #[serde(rename = "DocBody", default)]
+    doc_body: Vec<DocBody>,
 }
 
 #[derive(Debug, Deserialize, Default)]
@@ -81,6 +239,12 @@ struct OfdNode {
 struct DocBody {
     doc_info: DocInfo,
     doc_root: String,
+    /// 预先生成的文档缩略图，路径相对于包的根目录
+    #[serde(default)]
+    thumbnail: Option<String>,
+    /// 签名列表文件，路径相对于包的根目录
+    #[serde(default)]
+    signatures: Option<String>,
 }
 
 #[derive(Debug, Deserialize, Default)]
@@ -103,6 +267,58 @@ struct DocInfo {
 }
 
 impl DocInfo {
+    fn metadata(&self) -> DocMetadata {
+        DocMetadata {
+            doc_id: self.doc_id.clone(),
+            title: self.title.clone(),
+            author: self.author.clone(),
+            subject: self.subject.clone(),
+            abstract_text: self.abstract_text.clone(),
+            creation_date: self.creation_date.clone(),
+            mod_date: self.mod_date.clone(),
+            doc_usage: self.doc_usage.clone(),
+            cover: self.cover.clone(),
+            creator: self.creator.clone(),
+            creator_version: self.creator_version.clone(),
+            keywords: self.keywords.as_ref().map_or(Vec::new(), |k| k.to_list()),
+            custom_datas: self.custom_datas.as_ref().map_or(HashMap::new(), |c| c.to_map()),
+        }
+    }
+}
+
+/// 文档元数据，对应 DocInfo，未声明的字段为空字符串
+#[derive(Debug, Clone, Default)]
+pub struct DocMetadata {
+    pub doc_id: String,
+    pub title: String,
+    pub author: String,
+    pub subject: String,
+    /// 摘要
+    pub abstract_text: String,
+    pub creation_date: String,
+    pub mod_date: String,
+    pub doc_usage: String,
+    /// 封面图像的路径
+    pub cover: String,
+    pub creator: String,
+    pub creator_version: String,
+    pub keywords: Vec<String>,
+    /// 用户自定义元数据，键为 CustomData 的 Name
+    pub custom_datas: HashMap<String, String>,
+}
+
+impl DocMetadata {
+    /// 解析创建日期，无法识别的写法返回 None
+    pub fn creation_date(&self) -> Option<NaiveDateTime> {
+        parse_date_time(&self.creation_date)
+    }
+
+    /// 解析最近修改日期，无法识别的写法返回 None
+    pub fn mod_date(&self) -> Option<NaiveDateTime> {
+        parse_date_time(&self.mod_date)
+    }
+
+    /// 以 DocInfo 中的节点名为键的属性表，关键词以逗号连接
     fn attributes(&self) -> HashMap<String, String> {
         let mut map = HashMap::new();
         map.insert("DocId".to_string(), self.doc_id.clone());
@@ -116,13 +332,25 @@ impl DocInfo {
         map.insert("Cover".to_string(), self.cover.clone());
         map.insert("Creator".to_string(), self.creator.clone());
         map.insert("CreatorVersion".to_string(), self.creator_version.clone());
-        map.insert("Keywords".to_string(), self.keywords.as_ref().map_or("".to_string(), |k| k.to_list().join(",")));
+        map.insert("Keywords".to_string(), self.keywords.join(","));
         map
     }
+}
 
-    fn custom_datas(&self) -> HashMap<String, String> {
-        return self.custom_datas.as_ref().map_or(HashMap::new(), |c| c.to_map());
-    }
+/// 解析 xs:dateTime 或 xs:date 形式的日期，只有日期时取当天零点
+/// 时区后缀（Z、+08:00 等）被忽略，按文档中书写的本地时间返回；日期与时间之间也接受空格分隔
+fn parse_date_time(value: &str) -> Option<NaiveDateTime> {
+    let value = value.trim();
+    let value = value.strip_suffix('Z').unwrap_or(value);
+    // 去掉形如 +08:00 的时区偏移，日期中的 - 位于偏移之前，不会被误判
+    let value = match value.len().checked_sub(6).and_then(|i| value.get(i..).map(|tz| (i, tz))) {
+        Some((i, tz)) if tz.starts_with(['+', '-']) && tz.as_bytes()[3] == b':' && value.len() > 10 => &value[..i],
+        _ => value,
+    };
+    NaiveDateTime::parse_from_str(value, "%Y-%m-%dT%H:%M:%S%.f")
+        .or_else(|_| NaiveDateTime::parse_from_str(value, "%Y-%m-%d %H:%M:%S%.f"))
+        .ok()
+        .or_else(|| NaiveDate::parse_from_str(value, "%Y-%m-%d").ok()?.and_hms_opt(0, 0, 0))
 }
 
 #[derive(Debug, Deserialize, Default)]
@@ -182,58 +410,4122 @@ impl OfdNode {
     }
 }
 
+/// 读取根节点上声明的命名空间，键为前缀（缺省命名空间为空字符串），值为命名空间 URI
+/// 反序列化按节点的本地名匹配，不依赖前缀，因此使用 ofd: 以外前缀的文件同样可以解析
+fn root_namespaces(xml: &str) -> HashMap<String, String> {
+    for event in EventReader::from_str(xml) {
+        match event {
+            Ok(XmlEvent::StartElement { namespace, .. }) => {
+                return namespace.0.into_iter()
+                    .filter(|(prefix, uri)| match prefix.as_str() {
+                        NS_XML_PREFIX | NS_XMLNS_PREFIX => false,
+                        NS_NO_PREFIX => uri != NS_EMPTY_URI,
+                        _ => true,
+                    })
+                    .collect();
+            }
+            Ok(_) => {}
+            Err(_) => break,
+        }
+    }
+    HashMap::new()
+}
+
+/// 已读取完渲染所需内容的页面，渲染时不再访问压缩包
+#[derive(Debug)]
+struct PreparedPage {
+    /// 页面在 Pages 中声明的标识
+    id: String,
+    page: Page,
+    templates: Vec<(Page, ZOrder)>,
+    area: PageArea,
+    assets: PageAssets,
+}
+
+impl PreparedPage {
+    fn render<F>(&self, resources: &ResourceManager, opts: &RenderOptions, overlay: F) -> Result<RgbaImage, OfdError>
+    where
+        F: FnOnce(&cairo::Context) -> Result<(), cairo::Error>,
+    {
+        let mut pixels = Vec::new();
+        let (width, height) = self.render_into(resources, opts, overlay, &mut pixels)?;
+        Ok(RgbaImage::from_raw(width, height, pixels).expect("buffer holds width * height pixels"))
+    }
+
+    /// 同 render，将 RGBA 像素写入 buffer 并返回宽高，buffer 已有的容量足够时不重新分配
+    fn render_into<F>(&self, resources: &ResourceManager, opts: &RenderOptions, overlay: F, buffer: &mut Vec<u8>) -> Result<(u32, u32), OfdError>
+    where
+        F: FnOnce(&cairo::Context) -> Result<(), cairo::Error>,
+    {
+        let surface = render::render_page(&self.page, &self.templates, &self.area, resources, &self.assets, opts, overlay)
+            .map_err(OfdError::CairoError)?;
+        let (width, height) = (surface.width() as u32, surface.height() as u32);
+        let len = (width as usize).checked_mul(height as usize).and_then(|n| n.checked_mul(4))
+            .ok_or(OfdError::BufferTooLarge { width, height })?;
+        buffer.try_reserve(len.saturating_sub(buffer.len())).map_err(|_| OfdError::BufferTooLarge { width, height })?;
+        render::surface_to_buffer(surface, buffer).map_err(OfdError::SurfaceError)?;
+        // 颜色模式与减色处理按图像进行，借用 buffer 的存储，不复制像素
+        let mut image = RgbaImage::from_raw(width, height, std::mem::take(buffer)).expect("buffer holds width * height pixels");
+        render::apply_color_mode(&mut image, opts.color_mode);
+        if let Some(max_colors) = opts.quantize_colors {
+            quantize::median_cut(&mut image, max_colors as usize);
+        }
+        *buffer = image.into_raw();
+        Ok((width, height))
+    }
+
+    /// 只渲染页面坐标系中 region 范围内的内容，见 render::render_region
+    fn render_region(&self, resources: &ResourceManager, opts: &RenderOptions, region: &STBox, dpi: f32) -> Result<RgbaImage, OfdError> {
+        let surface = render::render_region(&self.page, &self.templates, &self.area, resources, &self.assets, opts, region, dpi)
+            .map_err(OfdError::CairoError)?;
+        render::surface_to_image(surface).map_err(OfdError::SurfaceError)
+    }
+
+    /// 以矢量形式将页面写入 PDF 的新一页，页面大小与位图渲染时的画布相同
+    fn write_pdf_page(&self, writer: &mut PdfWriter, resources: &ResourceManager, opts: &RenderOptions) -> Result<(), cairo::Error> {
+        let canvas = render::Canvas::new(&self.area, opts);
+        writer.retain_fonts(self.assets.fonts.values());
+        let cr = writer.begin_page(canvas.width, canvas.height)?;
+        render::draw_page(&cr, &self.page, &self.templates, &self.area, resources, &self.assets, opts, Some(writer.fonts()), |_| Ok(()))?;
+        cr.show_page()
+    }
+}
+
+/// 预先读取了全部页面与资源的渲染上下文，由 OfdDoc::render_context 创建
+/// 资源表、页面内容、模板、图像、字体、签章与注释只解析一次，之后的渲染不再访问压缩包，
+/// 适合将同一文档反复渲染为不同格式或分辨率；所有页面的内容会同时驻留内存
+#[derive(Debug)]
+pub struct RenderContext {
+    resources: ResourceManager,
+    pages: Vec<PreparedPage>,
+}
+
+impl RenderContext {
+    /// 页数，与创建时文档的页数相同
+    pub fn page_count(&self) -> usize {
+        self.pages.len()
+    }
+
+    fn prepared(&self, page_index: usize) -> Result<&PreparedPage, OfdError> {
+        self.pages.get(page_index).ok_or(OfdError::InvalidPageIndex(page_index))
+    }
+
+    /// 渲染指定页面，page_index 从 0 开始
+    pub fn render_page(&self, page_index: usize, opts: &RenderOptions) -> Result<RgbaImage, OfdError> {
+        self.prepared(page_index)?.render(&self.resources, opts, |_| Ok(()))
+    }
+
+    /// 将指定页面输出为 SVG 文本，page_index 从 0 开始
+    pub fn render_page_svg(&self, page_index: usize, opts: &RenderOptions) -> Result<String, OfdError> {
+        let prepared = self.prepared(page_index)?;
+        Ok(svg::render_page_svg(&prepared.id, &prepared.page, &prepared.templates, &prepared.area, &self.resources, opts))
+    }
+}
+
+/// 将多个 OFD 文档按顺序拼接成的连续页面序列，由 OfdDoc::concat 创建
+/// 每个源文档仍从各自的压缩包读取页面，资源表也各自独立，因此不同文档中相同的资源标识不会冲突；
+/// 页面取自各源文档当前选中的文档
+#[derive(Debug)]
+pub struct MergedDoc {
+    docs: Vec<OfdDoc>,
+}
+
+impl MergedDoc {
+    /// 总页数，即各源文档页数之和
+    pub fn page_count(&self) -> usize {
+        self.docs.iter().map(OfdDoc::page_count).sum()
+    }
+
+    /// 源文档的数量
+    pub fn source_count(&self) -> usize {
+        self.docs.len()
+    }
+
+    /// 拆分为源文档，顺序与拼接时相同
+    pub fn into_inner(self) -> Vec<OfdDoc> {
+        self.docs
+    }
+
+    /// 将合并后的页面序号映射为（源文档序号，源文档中的页面序号）
+    pub fn locate(&self, page_index: usize) -> Option<(usize, usize)> {
+        let mut offset = 0;
+        for (source, doc) in self.docs.iter().enumerate() {
+            let count = doc.page_count();
+            if page_index < offset + count {
+                return Some((source, page_index - offset));
+            }
+            offset += count;
+        }
+        None
+    }
+
+    fn source(&mut self, page_index: usize) -> Result<(&mut OfdDoc, usize), OfdError> {
+        let (source, local) = self.locate(page_index).ok_or(OfdError::InvalidPageIndex(page_index))?;
+        Ok((&mut self.docs[source], local))
+    }
+
+    /// 渲染指定页面，page_index 为合并后的序号，从 0 开始
+    pub fn render_page(&mut self, page_index: usize, opts: &RenderOptions) -> Result<RgbaImage, OfdError> {
+        let (doc, local) = self.source(page_index)?;
+        doc.render_page(local, opts)
+    }
+
+    /// 将指定页面输出为 SVG 文本，page_index 为合并后的序号
+    pub fn render_page_svg(&mut self, page_index: usize, opts: &RenderOptions) -> Result<String, OfdError> {
+        let (doc, local) = self.source(page_index)?;
+        doc.render_page_svg(local, opts)
+    }
+
+    /// 按顺序渲染全部页面
+    pub fn render_all(&mut self, opts: &RenderOptions) -> Result<Vec<RgbaImage>, OfdError> {
+        (0..self.page_count()).map(|index| self.render_page(index, opts)).collect()
+    }
+
+    /// 将全部页面导出为一个多页 PDF，规则同 OfdDoc::to_pdf，每个源文档的资源只解析一次
+    pub fn to_pdf(&mut self, opts: &RenderOptions) -> Result<Vec<u8>, OfdError> {
+        let mut writer = PdfWriter::new().map_err(OfdError::CairoError)?;
+        for doc in &mut self.docs {
+            let resources = doc.load_resources()?;
+            for index in 0..doc.page_count() {
+                let prepared = doc.prepare_page(index, &resources, opts)?;
+                prepared.write_pdf_page(&mut writer, &resources, opts).map_err(OfdError::CairoError)?;
+            }
+        }
+        writer.finish().map_err(OfdError::CairoError)
+    }
+}
+
+/// OfdDoc::iter_objects 返回的迭代器，逐页读取页面与模板，同一时刻只保留一页的图元
+/// 页面读取失败时给出该错误，之后继续处理下一页
+#[derive(Debug)]
+pub struct ObjectIter<'a> {
+    doc: &'a mut OfdDoc,
+    next_page: usize,
+    pending: std::vec::IntoIter<ResolvedObject>,
+}
+
+impl Iterator for ObjectIter<'_> {
+    type Item = Result<ResolvedObject, OfdError>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        loop {
+            if let Some(object) = self.pending.next() {
+                return Some(Ok(object));
+            }
+            if self.next_page >= self.doc.page_count() {
+                return None;
+            }
+            let page_index = self.next_page;
+            self.next_page += 1;
+            match self.doc.resolve_page_objects(page_index) {
+                Ok(objects) => self.pending = objects.into_iter(),
+                Err(e) => return Some(Err(e)),
+            }
+        }
+    }
+}
+
+/// 错误信息中 XML 片段的最大字符数
+const XML_SNIPPET_CHARS: usize = 80;
+
+/// 解析包内文件 entry 的 XML 内容，失败时在错误中附带文件名与出错位置附近的片段
+fn parse_xml<T>(entry: &str, content: &str, parse: impl FnOnce(&str) -> Result<T, serde_xml_rs::Error>) -> Result<T, OfdError> {
+    parse(content).map_err(|source| {
+        let snippet = xml_snippet(content, &source);
+        log::warn!("failed to parse {}: {}", entry, source);
+        OfdError::XmlParse { entry: entry.to_string(), snippet, source }
+    })
+}
+
+/// 语法错误取出错位置前后的内容，其他错误没有位置信息，取文件末尾的内容（常见的原因是文件被截断）
+fn xml_snippet(content: &str, error: &serde_xml_rs::Error) -> String {
+    let chars: Vec<char> = content.chars().collect();
+    let center = match error {
+        serde_xml_rs::Error::Syntax { source } => {
+            let pos = source.position();
+            let line_start: usize = content.split_inclusive('\n')
+                .take(pos.row as usize)
+                .map(|line| line.chars().count())
+                .sum();
+            (line_start + pos.column as usize).min(chars.len())
+        }
+        _ => chars.len(),
+    };
+    let start = center.saturating_sub(XML_SNIPPET_CHARS / 2);
+    let end = (start + XML_SNIPPET_CHARS).min(chars.len());
+    let start = end.saturating_sub(XML_SNIPPET_CHARS);
+    chars[start..end].iter().collect::<String>().split_whitespace().collect::<Vec<_>>().join(" ")
+}
+
+/// 标识中可解析为非负整数的最大值，没有时为 0
+fn max_numeric_id<'a>(ids: impl IntoIterator<Item = &'a str>) -> u64 {
+    ids.into_iter().filter_map(|id| id.trim().parse::<u64>().ok()).max().unwrap_or(0)
+}
+
+/// 页面中引用了资源表中不存在的资源的图层与图元，返回问题类型与被引用的标识
+fn dangling_references(page: &Page, resources: &ResourceManager) -> Vec<(ValidationIssueKind, String)> {
+    let mut dangling = Vec::new();
+    let check_draw_param = |id: Option<&str>, dangling: &mut Vec<(ValidationIssueKind, String)>| {
+        if let Some(id) = id.map(str::trim).filter(|id| !id.is_empty() && resources.get_draw_param(id).is_none()) {
+            dangling.push((ValidationIssueKind::DanglingDrawParam, id.to_string()));
+        }
+    };
+    for layer in page.layers() {
+        check_draw_param(layer.draw_param.as_deref(), &mut dangling);
+    }
+    for object in page.objects() {
+        check_draw_param(object.alpha_and_draw_param().1, &mut dangling);
+        let missing = match object {
+            PageObject::TextObject(o) if resources.get_font(&o.font).is_none() => {
+                Some((ValidationIssueKind::DanglingFont, &o.font))
+            }
+            PageObject::ImageObject(o) if resources.media_file(&o.resource_id).is_none() => {
+                Some((ValidationIssueKind::DanglingResource, &o.resource_id))
+            }
+            PageObject::CompositeObject(o) if resources.vector_graphic(&o.resource_id).is_none() => {
+                Some((ValidationIssueKind::DanglingResource, &o.resource_id))
+            }
+            _ => None,
+        };
+        dangling.extend(missing.map(|(kind, id)| (kind, id.clone())));
+    }
+    dangling
+}
+
+/// 对象标识的取值范围为 u32，超出时取最大值
+fn unit_id(value: usize) -> u32 {
+    u32::try_from(value).unwrap_or(u32::MAX)
+}
+
+/// 文件路径所在的目录，不含末尾的 /
+fn parent_dir(path: &str) -> &str {
+    path.rsplit_once('/').map_or("", |(dir, _)| dir)
+}
+
+/// 将 loc 相对 base_dir 解析为包内的文件名
+/// 以 / 开头的 loc 为包内绝对路径，忽略 base_dir；. 与 .. 会被规范化，越过根目录的 .. 被忽略
+pub(crate) fn resolve_path(base_dir: &str, loc: &str) -> String {
+    let (base, loc) = match loc.strip_prefix('/') {
+        Some(absolute) => ("", absolute),
+        None => (base_dir, loc),
+    };
+    let mut segments: Vec<&str> = Vec::new();
+    for segment in base.split('/').chain(loc.split('/')) {
+        match segment {
+            "" | "." => {}
+            ".." => {
+                segments.pop();
+            }
+            _ => segments.push(segment),
+        }
+    }
+    segments.join("/")
+}
+
+/// 将图片缩放到较长的一边为 max_px 像素，保持宽高比
+fn fit_longer_side(image: RgbaImage, max_px: u32) -> RgbaImage {
+    let scale = max_px as f64 / image.width().max(image.height()).max(1) as f64;
+    let width = ((image.width() as f64 * scale).round() as u32).clamp(1, max_px);
+    let height = ((image.height() as f64 * scale).round() as u32).clamp(1, max_px);
+    if (width, height) == image.dimensions() {
+        return image;
+    }
+    image::imageops::resize(&image, width, height, image::imageops::FilterType::Triangle)
+}
+
+/// 解码图像资源，image 库支持的格式直接解码，JBIG2 与 CCITT 编码的二值图像单独解码
+/// format 为多媒体资源声明的格式，name 为资源文件名，两者都只用于识别格式
+fn decode_image(data: &[u8], format: Option<&str>, name: &str) -> Result<RgbaImage, OfdError> {
+    let Some(bilevel_format) = bilevel::detect(format, name, data) else {
+        return match image::load_from_memory(data) {
+            Ok(image) => Ok(image.to_rgba8()),
+            Err(ImageError::Unsupported(_)) => Err(OfdError::UnsupportedImageFormat {
+                format: format.unwrap_or(name).to_string(),
+            }),
+            Err(e) => Err(OfdError::ImageError(e)),
+        };
+    };
+    bilevel::decode(bilevel_format, data).map_err(|e| match e {
+        BilevelError::Unsupported(format) => OfdError::UnsupportedImageFormat { format },
+        BilevelError::Corrupt => OfdError::ImageError(ImageError::Decoding(DecodingError::new(
+            ImageFormatHint::Name(bilevel_format.name().to_string()),
+            "corrupt or truncated data",
+        ))),
+    })
+}
+
+/// 读取文档声明的附件列表，文档未声明附件或 Attachments.xml 无法读取时为空，后者记录警告
+/// 附件只是文档的附属内容，不影响文档的打开
+fn load_attachments(
+    package: &mut Package,
+    doc_root: &str,
+    document: &Document,
+    mode: ParseMode,
+    warnings: &mut Vec<Warning>,
+) -> Result<Vec<AttachmentInfo>, OfdError> {
+    let loc = document.attachments.trim();
+    if loc.is_empty() {
+        return Ok(Vec::new());
+    }
+    let name = resolve_path(parent_dir(doc_root), loc);
+    let attachments = match package.read_entry_string(&name) {
+        Ok(content) => parse_xml(&name, &content, Attachments::from_xml),
+        Err(OfdError::EntryNotFound { .. }) if mode == ParseMode::Lenient => return Ok(Vec::new()),
+        Err(e) => Err(e),
+    };
+    let attachments = match tolerate(mode, warnings, &name, attachments)? {
+        Some(attachments) => attachments,
+        None => return Ok(Vec::new()),
+    };
+    let base_dir = parent_dir(&name);
+    Ok(attachments.attachment.into_iter()
+        .filter(|a| !a.file_loc.trim().is_empty())
+        .map(|a| AttachmentInfo {
+            id: a.id,
+            name: a.name,
+            format: a.format.unwrap_or_default(),
+            location: resolve_path(base_dir, a.file_loc.trim()),
+        })
+        .collect())
+}
+
+/// 按资源标识缓存的已解码图像，总字节数不超过 budget，超出时先淘汰最早加入的图像
+/// 单张超过 budget 的图像不缓存，只用于当前页面
+#[derive(Debug)]
+struct ImageCache {
+    images: HashMap<String, Arc<RgbaImage>>,
+    /// 图像加入缓存的顺序，用于淘汰
+    order: VecDeque<String>,
+    bytes: usize,
+    budget: usize,
+}
+
+impl ImageCache {
+    fn new(budget: usize) -> ImageCache {
+        ImageCache { images: HashMap::new(), order: VecDeque::new(), bytes: 0, budget }
+    }
+
+    fn get(&self, id: &str) -> Option<&Arc<RgbaImage>> {
+        self.images.get(id)
+    }
+
+    fn insert(&mut self, id: &str, image: Arc<RgbaImage>) {
+        let size = image.as_raw().len();
+        if size > self.budget || self.images.contains_key(id) {
+            return;
+        }
+        while self.bytes + size > self.budget {
+            let Some(oldest) = self.order.pop_front() else {
+                break;
+            };
+            if let Some(evicted) = self.images.remove(&oldest) {
+                self.bytes -= evicted.as_raw().len();
+            }
+        }
+        self.bytes += size;
+        self.order.push_back(id.to_string());
+        self.images.insert(id.to_string(), image);
+    }
+
+    fn clear(&mut self) {
+        self.images.clear();
+        self.order.clear();
+        self.bytes = 0;
+    }
+}
+
+/// 处理可选内容的读取结果：严格模式下原样返回错误；宽松模式下记录警告，以 None 表示跳过该内容
+fn tolerate<T>(mode: ParseMode, warnings: &mut Vec<Warning>, entry: &str, result: Result<T, OfdError>) -> Result<Option<T>, OfdError> {
+    match result {
+        Ok(value) => Ok(Some(value)),
+        Err(e) if mode == ParseMode::Strict => Err(e),
+        Err(e) => {
+            log::warn!("skipping {}: {}", entry, e);
+            let warning = Warning { entry: entry.to_string(), message: e.to_string() };
+            if !warnings.contains(&warning) {
+                warnings.push(warning);
+            }
+            Ok(None)
+        }
+    }
+}
+
 #[derive(Debug)]
 pub struct OfdDoc {
-    node: OfdNode,
-    zip_archive: ZipArchive<BufReader<File>>,
-    document: Document,
+    doc_bodies: Vec<DocBody>,
+    package: Package,
+    documents: Vec<Document>,
+    /// 各文档的附件，与 documents 一一对应，打开时读取
+    attachments: Vec<Vec<AttachmentInfo>>,
+    /// 当前选中的文档，页面相关的操作均针对该文档
+    current: usize,
+    /// 当前文档的元数据，切换文档时随之更新
     pub attributes: HashMap<String, String>,
     pub custom_datas: HashMap<String, String>,
+    /// OFD.xml 根节点声明的命名空间
+    namespaces: HashMap<String, String>,
+    /// 按资源标识缓存的已解码图像，在多次渲染之间复用，总字节数不超过 IMAGE_CACHE_BYTES，切换文档时清空
+    image_cache: ImageCache,
+    /// 按资源标识缓存的嵌入字体，None 表示没有嵌入字形文件或无法使用，切换文档时清空
+    /// 与图像不同，字体缓存不设字节上限：条目数不超过文档声明的字体数，单个字形文件的大小受 read_limits 限制
+    font_cache: HashMap<String, Option<Arc<TrueTypeFont>>>,
+    /// 用户注册的后备字体，在所有文档间共用
+    font_registry: FontRegistry,
+    /// 各文档最近分配的对象标识，与 documents 一一对应，初始为文档声明的 MaxUnitID
+    last_unit_ids: Vec<u32>,
+    mode: ParseMode,
+    /// 宽松模式下跳过的内容，同一问题只记录一次
+    warnings: Vec<Warning>,
 }
 
 impl OfdDoc {
+    /// 打开 OFD 文件，file_path 为目录时按已解压的 OFD 包读取
     pub fn open(file_path: &str) -> Result<OfdDoc, OfdError> {
+        if Path::new(file_path).is_dir() {
+            return OfdDoc::open_package(Box::new(DirSource::new(file_path)), &OpenOptions::default());
+        }
         let file = File::open(file_path).map_err(OfdError::IoError)?;
-        let reader = io::BufReader::new(file);
-        let mut zip = ZipArchive::new(reader).map_err(OfdError::ZipError)?;
-    
-        let mut content = String::new();
-    
-        // Find the OFD.xml file and parse the content to ofd object.
-        {
-            let mut ofd_file = zip.by_name("OFD.xml").map_err(OfdError::ZipError)?;
-            ofd_file.read_to_string(&mut content).map_err(OfdError::IoError)?;
+        OfdDoc::open_reader(io::BufReader::new(file))
+    }
+
+    /// 按 options 打开 OFD 文件，file_path 为目录时按已解压的 OFD 包读取，此时不能指定密码
+    pub fn open_with_options(file_path: &str, options: &OpenOptions) -> Result<OfdDoc, OfdError> {
+        if Path::new(file_path).is_dir() {
+            if options.password.is_some() {
+                return Err(OfdError::NotEncrypted);
+            }
+            return OfdDoc::open_package(Box::new(DirSource::new(file_path)), options);
+        }
+        let file = File::open(file_path).map_err(OfdError::IoError)?;
+        let reader: Box<dyn ReadSeek> = Box::new(io::BufReader::new(file));
+        let source = match &options.password {
+            Some(password) => ZipSource::with_password(reader, password)?,
+            None => ZipSource::new(reader)?,
+        };
+        OfdDoc::open_package(Box::new(source), options)
+    }
+
+    /// 从内存中的 OFD 字节打开文档，数据会被复制一份
+    pub fn open_from_bytes(data: &[u8]) -> Result<OfdDoc, OfdError> {
+        OfdDoc::open_reader(Cursor::new(data.to_vec()))
+    }
+
+    /// 从任意可读、可定位的数据源打开文档
+    pub fn open_reader<R: Read + Seek + Send + 'static>(reader: R) -> Result<OfdDoc, OfdError> {
+        let reader: Box<dyn ReadSeek> = Box::new(reader);
+        OfdDoc::open_package(Box::new(ZipSource::new(reader)?), &OpenOptions::default())
+    }
+
+    /// 打开加密的 OFD 压缩包，只有部分条目加密时其余条目照常读取
+    /// 密码错误时返回 InvalidPassword；压缩包没有加密的条目或 file_path 为目录时返回 NotEncrypted
+    pub fn open_with_password(file_path: &str, password: &[u8]) -> Result<OfdDoc, OfdError> {
+        if Path::new(file_path).is_dir() {
+            return Err(OfdError::NotEncrypted);
         }
+        let file = File::open(file_path).map_err(OfdError::IoError)?;
+        OfdDoc::open_reader_with_password(io::BufReader::new(file), password)
+    }
+
+    /// 同 open_with_password，从内存中的 OFD 字节打开
+    pub fn open_from_bytes_with_password(data: &[u8], password: &[u8]) -> Result<OfdDoc, OfdError> {
+        OfdDoc::open_reader_with_password(Cursor::new(data.to_vec()), password)
+    }
+
+    /// 同 open_with_password，从任意可读、可定位的数据源打开
+    pub fn open_reader_with_password<R: Read + Seek + Send + 'static>(reader: R, password: &[u8]) -> Result<OfdDoc, OfdError> {
+        let reader: Box<dyn ReadSeek> = Box::new(reader);
+        OfdDoc::open_package(Box::new(ZipSource::with_password(reader, password)?), &OpenOptions::default())
+    }
+
+    pub(crate) fn open_package(source: Box<dyn PackageSource>, options: &OpenOptions) -> Result<OfdDoc, OfdError> {
+        let mut package = Package::new(source);
+        package.set_limits(options.read_limits);
+        let mut warnings = Vec::new();
+        // Find the OFD.xml file and parse the content to ofd object.
+        let content = package.read_entry_string("OFD.xml")?;
     
         // Parse the XML content into an OfdNode.
-        let ofd_node: OfdNode = OfdNode::from_xml(&content).map_err(OfdError::SerdeXmlError)?;
+        let ofd_node = parse_xml("OFD.xml", &content, OfdNode::from_xml)?;
+        let namespaces = root_namespaces(&content);
 
-        content.clear();
-        {
-            let mut doc_file = zip.by_name(&ofd_node.doc_body.doc_root).map_err(OfdError::ZipError)?;
-            doc_file.read_to_string(&mut content).map_err(OfdError::IoError)?;
+        if ofd_node.doc_body.is_empty() {
+            return Err(OfdError::InvalidDocumentIndex(0));
+        }
+        log::debug!("package contains {} documents", ofd_node.doc_body.len());
+        let mut documents = Vec::with_capacity(ofd_node.doc_body.len());
+        let mut attachments = Vec::with_capacity(ofd_node.doc_body.len());
+        for doc_body in &ofd_node.doc_body {
+            let doc_root = resolve_path("", &doc_body.doc_root);
+            let content = package.read_entry_string(&doc_root)?;
+            let document = parse_xml(&doc_root, &content, Document::from_xml)?;
+            log::debug!("document {} has {} pages", doc_root, document.pages.page.len());
+            attachments.push(load_attachments(&mut package, &doc_root, &document, options.mode, &mut warnings)?);
+            documents.push(document);
         }
 
-        let document: Document = Document::from_xml(&content).map_err(OfdError::SerdeXmlError)?;
-
-        let attributes = ofd_node.doc_body.doc_info.attributes();
-        let custom_datas = ofd_node.doc_body.doc_info.custom_datas();
+        let last_unit_ids = documents.iter().map(|d| unit_id(d.common_data.max_unit_id())).collect();
+        let metadata = ofd_node.doc_body[0].doc_info.metadata();
+        let attributes = metadata.attributes();
+        let custom_datas = metadata.custom_datas;
     
         let ofd_result = OfdDoc {
-            node: ofd_node,
-            zip_archive: zip,
-            document,
+            doc_bodies: ofd_node.doc_body,
+            package,
+            documents,
+            attachments,
+            current: 0,
             attributes,
             custom_datas,
+            namespaces,
+            image_cache: ImageCache::new(IMAGE_CACHE_BYTES),
+            font_cache: HashMap::new(),
+            font_registry: FontRegistry::default(),
+            last_unit_ids,
+            mode: options.mode,
+            warnings,
         };
     
         Ok(ofd_result)
     }
 
+    /// 包内所有文件的名称（以 / 分隔，相对于包的根目录），按名称排序，用于排查路径无法解析等问题
+    /// 已解压的目录同样递归列出其中的文件
+    pub fn list_entries(&self) -> Vec<String> {
+        self.package.entry_names()
+    }
+
+    /// 读取包内任意文件的原始字节，name 相对于包的根目录，与内部读取一样受 ReadLimits 的限制
+    pub fn read_entry(&mut self, name: &str) -> Result<Vec<u8>, OfdError> {
+        self.package.read_entry(&resolve_path("", name))
+    }
+
+    /// 打开文档时指定的解析模式
+    pub fn parse_mode(&self) -> ParseMode {
+        self.mode
+    }
+
+    /// 宽松模式下跳过的内容，按发现的先后排列；内容在渲染等操作中按需读取，因此列表会随之增长
+    pub fn warnings(&self) -> &[Warning] {
+        &self.warnings
+    }
+
+    /// OFD.xml 根节点声明的命名空间，键为前缀（缺省命名空间为空字符串），值为命名空间 URI
+    /// 如 ofd -> http://www.ofdspec.org/2016
+    pub fn namespaces(&self) -> &HashMap<String, String> {
+        &self.namespaces
+    }
+
+    /// 包中的文档个数
+    pub fn document_count(&self) -> usize {
+        self.documents.len()
+    }
+
+    /// 当前选中的文档序号，打开后缺省为 0
+    pub fn current_document(&self) -> usize {
+        self.current
+    }
+
+    /// 选中第 index 个文档，之后的页面、资源与元数据操作均针对该文档
+    pub fn select_document(&mut self, index: usize) -> Result<(), OfdError> {
+        if index >= self.documents.len() {
+            return Err(OfdError::InvalidDocumentIndex(index));
+        }
+        if index != self.current {
+            self.clear_cache();
+        }
+        self.current = index;
+        let metadata = self.doc_body().doc_info.metadata();
+        self.attributes = metadata.attributes();
+        self.custom_datas = metadata.custom_datas;
+        Ok(())
+    }
+
+    /// 释放缓存的已解码图像与嵌入字体，之后的渲染会重新读取
+    /// 字体缓存没有容量上限，长期持有含大量嵌入字体的文档时可调用此方法释放内存
+    pub fn clear_cache(&mut self) {
+        self.image_cache.clear();
+        self.font_cache.clear();
+    }
+
+    /// 注册字族 family 的后备字体，文字对象的字体没有可用的嵌入字形文件且字族名相同时使用
+    /// data 须为 TrueType 字形文件，无法解析时返回 InvalidFont
+    pub fn register_fallback(&mut self, family: &str, data: Vec<u8>) -> Result<(), OfdError> {
+        self.font_registry.register_fallback(family, data)?;
+        self.font_cache.clear();
+        Ok(())
+    }
+
+    /// 设置缺省字体，文字对象的字体既无嵌入字形文件、又无同名的后备字体时使用
+    pub fn set_default_font(&mut self, data: Vec<u8>) -> Result<(), OfdError> {
+        self.font_registry.set_default_font(data)?;
+        self.font_cache.clear();
+        Ok(())
+    }
+
+    /// 已注册的后备字体
+    pub fn font_registry(&self) -> &FontRegistry {
+        &self.font_registry
+    }
+
+    /// 设置读取包内文件的大小限制，并重新开始累计读取的字节数
+    /// 打开文档时读取的 OFD.xml 与 Document.xml 使用缺省限制，之后读取的页面、资源与图像等使用新的限制
+    pub fn set_read_limits(&mut self, limits: ReadLimits) {
+        self.package.set_limits(limits);
+    }
+
+    /// 当前的读取大小限制
+    pub fn read_limits(&self) -> ReadLimits {
+        self.package.limits()
+    }
+
+    /// 依次选中 doc_index 指定的文档（None 时为全部文档）并调用 f，按文档顺序合并结果
+    /// 结束或出错后恢复原先选中的文档
+    fn for_documents<T>(
+        &mut self,
+        doc_index: Option<usize>,
+        mut f: impl FnMut(&mut OfdDoc) -> Result<Vec<T>, OfdError>,
+    ) -> Result<Vec<T>, OfdError> {
+        let indices = match doc_index {
+            Some(index) if index >= self.documents.len() => return Err(OfdError::InvalidDocumentIndex(index)),
+            Some(index) => index..index + 1,
+            None => 0..self.documents.len(),
+        };
+        let previous = self.current;
+        let mut results = Vec::new();
+        for index in indices {
+            self.select_document(index)?;
+            match f(self) {
+                Ok(items) => results.extend(items),
+                Err(e) => {
+                    self.select_document(previous)?;
+                    return Err(e);
+                }
+            }
+        }
+        self.select_document(previous)?;
+        Ok(results)
+    }
+
+    fn doc_body(&self) -> &DocBody {
+        &self.doc_bodies[self.current]
+    }
+
+    fn document(&self) -> &Document {
+        &self.documents[self.current]
+    }
+
+    /// 文档元数据
+    pub fn metadata(&self) -> DocMetadata {
+        self.doc_body().doc_info.metadata()
+    }
+
+    /// 以 JSON 字符串返回文档元数据，包含 attributes 与 custom_datas 两部分
     pub fn info(&self) -> String {
+        let metadata = self.metadata();
         let mut map: HashMap<String, HashMap<String,String>> = HashMap::new();
-        map.insert("attributes".to_string(), self.attributes.clone());
-        map.insert("custom_datas".to_string(), self.custom_datas.clone());
+        map.insert("attributes".to_string(), metadata.attributes());
+        map.insert("custom_datas".to_string(), metadata.custom_datas);
         serde_json::to_string(&map).unwrap()
     }
+
+    /// 读取 DocBody 中声明的缩略图文件内容，未声明时返回 None
+    pub fn embedded_thumbnail(&mut self) -> Result<Option<Vec<u8>>, OfdError> {
+        let loc = match self.doc_body().thumbnail.as_deref().map(str::trim) {
+            Some(loc) if !loc.is_empty() => resolve_path("", loc),
+            _ => return Ok(None),
+        };
+        self.package.read_entry(&loc).map(Some)
+    }
+
+    /// 文档声明的使用权限，未声明时返回 None
+    pub fn permissions(&self) -> Option<Permissions> {
+        self.document().permissions.as_ref().map(Permissions::from)
+    }
+
+    /// 页面缩略图，较长的一边为 max_px 像素
+    /// DocBody 中声明的缩略图即首页预览，首页优先使用该图片并缩放到目标大小，缺失或无法解码时再渲染页面；
+    /// 渲染时按页面物理区域直接计算分辨率，避免先按原始分辨率渲染再缩小；分辨率过低时文字难以辨认，
+    /// 因此不低于 MIN_THUMBNAIL_DPI，此时渲染结果再缩小到目标大小
+    pub fn thumbnail(&mut self, page_index: usize, max_px: u32) -> Result<RgbaImage, OfdError> {
+        let physical_box = self.page_size(page_index)?;
+        let max_px = max_px.max(1);
+        if page_index == 0 {
+            let embedded = self.embedded_thumbnail().ok().flatten();
+            if let Some(image) = embedded.and_then(|data| image::load_from_memory(&data).ok()) {
+                return Ok(fit_longer_side(image.to_rgba8(), max_px));
+            }
+        }
+        let longer_mm = physical_box.w.max(physical_box.h);
+        if longer_mm <= 0.0 {
+            return Err(OfdError::MissingPageArea(page_index));
+        }
+        let dpi = (max_px as f64 * MM_PER_INCH / longer_mm).max(MIN_THUMBNAIL_DPI);
+        let opts = RenderOptions { target: RenderTarget::Dpi(dpi as f32), ..RenderOptions::default() };
+        let image = self.render_page(page_index, &opts)?;
+        Ok(fit_longer_side(image, max_px))
+    }
+
+    /// 当前文档声明的对象标识最大值 MaxUnitID，缺省或非法时为 0
+    pub fn max_unit_id(&self) -> u32 {
+        unit_id(self.document().common_data.max_unit_id())
+    }
+
+    /// 为当前文档分配一个新的对象标识，从 MaxUnitID + 1 开始依次递增，不与文档中已有的标识冲突
+    /// 标识只在内存中分配，文档本身的 MaxUnitID 不变
+    pub fn next_unit_id(&mut self) -> u32 {
+        let last = &mut self.last_unit_ids[self.current];
+        *last = last.saturating_add(1);
+        *last
+    }
+
+    /// 文档类型，未声明时为 Normal
+    pub fn doc_usage(&self) -> DocUsage {
+        DocUsage::from(self.doc_body().doc_info.doc_usage.as_str())
+    }
+
+    /// 统计文档中所有图元（含模板页）使用的填充色与勾边色，按出现次数从多到少排序
+    pub fn color_histogram(&mut self) -> Result<Vec<([u8; 3], usize)>, OfdError> {
+        let resources = self.load_resources()?;
+
+        let page_locs: Vec<String> = self.document().pages.page.iter().map(|p| p.base_loc.clone())
+            .chain(self.document().common_data.template_page.iter().map(|t| t.base_loc.clone()))
+            .filter(|loc| !loc.is_empty())
+            .collect();
+
+        let mut counts: HashMap<[u8; 3], usize> = HashMap::new();
+        for loc in page_locs {
+            let content = self.read_doc_string(&loc)?;
+            let page = parse_xml(&self.resolve_doc_loc(&loc), &content, Page::from_xml)?;
+            for object in page.objects_with_capacity(self.objects_capacity()) {
+                for color in [object.fill_color(), object.stroke_color()].into_iter().flatten() {
+                    *counts.entry(color.to_rgb(&resources.color_spaces)).or_insert(0) += 1;
+                }
+            }
+        }
+
+        let mut histogram: Vec<([u8; 3], usize)> = counts.into_iter().collect();
+        histogram.sort_by(|a, b| b.1.cmp(&a.1).then(a.0.cmp(&b.0)));
+        Ok(histogram)
+    }
+
+    /// 渲染指定页面，page_index 从 0 开始
+    pub fn render_page(&mut self, page_index: usize, opts: &RenderOptions) -> Result<RgbaImage, OfdError> {
+        self.render_page_with(page_index, opts, |_, _| Ok(()))
+    }
+
+    /// 同 render_page，将 RGBA 像素（每像素 4 字节，逐行排列）写入调用方提供的 buffer 并返回宽高
+    /// buffer 的长度调整为宽 × 高 × 4，已有的容量足够时不重新分配，适合在连续翻页时反复使用同一块内存；
+    /// 像素数据无法分配时返回 BufferTooLarge，此时 buffer 的内容不变
+    pub fn render_page_into(&mut self, page_index: usize, opts: &RenderOptions, buffer: &mut Vec<u8>) -> Result<(u32, u32), OfdError> {
+        let resources = self.load_resources()?;
+        let prepared = self.prepare_page(page_index, &resources, opts)?;
+        prepared.render_into(&resources, opts, |_| Ok(()), buffer)
+    }
+
+    /// 渲染指定页面并返回紧密排列的 RGBA8 像素，字节布局见 RenderedBuffer
+    pub fn render_page_raw(&mut self, page_index: usize, opts: &RenderOptions) -> Result<RenderedBuffer, OfdError> {
+        self.render_page(page_index, opts).map(RenderedBuffer::from)
+    }
+
+    /// 渲染指定页面，自上而下逐行以 (行号, 该行 RGBA 数据) 调用 f，便于对接流式编码器
+    /// cairo 需要在整页画布上光栅化，因此目前仍会先缓存整页图像再逐行回调，并不能降低峰值内存
+    pub fn render_page_scanlines(
+        &mut self,
+        page_index: usize,
+        opts: &RenderOptions,
+        mut f: impl FnMut(u32, &[u8]),
+    ) -> Result<(), OfdError> {
+        let image = self.render_page(page_index, opts)?;
+        let row_len = image.width() as usize * 4;
+        if row_len == 0 {
+            return Ok(());
+        }
+        for (y, row) in image.as_raw().chunks_exact(row_len).enumerate() {
+            f(y as u32, row);
+        }
+        Ok(())
+    }
+
+    /// 按给定分辨率依次渲染所有页面，资源只解析一次
+    /// 任一页面渲染失败时立即返回，错误中带有失败页面的序号
+    pub fn render_all(&mut self, dpi: f32) -> Result<Vec<RgbaImage>, OfdError> {
+        self.render_all_pages_with_progress(dpi, None)
+    }
+
+    /// 同 render_all，每渲染完一页以 (已完成页数, 总页数) 调用一次 progress，最后一次为 (n, n)
+    /// 只用于逐页渲染；render_all_parallel 中各页完成的顺序不确定，不提供进度回调
+    pub fn render_all_pages_with_progress(
+        &mut self,
+        dpi: f32,
+        mut progress: Option<Box<dyn FnMut(usize, usize)>>,
+    ) -> Result<Vec<RgbaImage>, OfdError> {
+        let opts = RenderOptions { target: RenderTarget::Dpi(dpi), ..RenderOptions::default() };
+        self.render_all_with_progress(&opts, |completed, total| {
+            if let Some(progress) = progress.as_mut() {
+                progress(completed, total);
+            }
+        })
+    }
+
+    /// 按 opts 依次渲染所有页面，每处理完一页以 (已处理页数, 总页数) 调用一次 on_progress
+    /// 页面渲染失败时先为该页调用 on_progress 再返回错误，调用方可以据此得知停在哪一页
+    pub fn render_all_with_progress(
+        &mut self,
+        opts: &RenderOptions,
+        mut on_progress: impl FnMut(usize, usize),
+    ) -> Result<Vec<RgbaImage>, OfdError> {
+        let resources = self.load_resources()?;
+        let total = self.page_count();
+        let mut images = Vec::with_capacity(total);
+        for index in 0..total {
+            let result = self.render_page_with_resources(index, &resources, opts, |_, _| Ok(()));
+            on_progress(index + 1, total);
+            images.push(result.map_err(|e| OfdError::PageError(index, Box::new(e)))?);
+        }
+        Ok(images)
+    }
+
+    /// 按 opts 依次渲染所有页面，每渲染完一页即以 (页面序号, 图像) 交给 sink，不在内存中累积页面图像
+    /// 文档级资源只解析一次；页面内容与页面图像在交给 sink 后即释放，
+    /// 峰值内存约为一页加上已解码图像缓存，后者不超过 IMAGE_CACHE_BYTES
+    /// sink 返回错误时停止渲染并原样返回该错误
+    pub fn render_pages_streaming(
+        &mut self,
+        opts: &RenderOptions,
+        mut sink: impl FnMut(usize, RgbaImage) -> Result<(), OfdError>,
+    ) -> Result<(), OfdError> {
+        let resources = self.load_resources()?;
+        for index in 0..self.page_count() {
+            let image = self.render_page_with_resources(index, &resources, opts, |_, _| Ok(()))
+                .map_err(|e| OfdError::PageError(index, Box::new(e)))?;
+            sink(index, image)?;
+        }
+        Ok(())
+    }
+
+    /// 渲染包中 doc_index 指定文档的所有页面，None 时按文档顺序渲染全部文档的页面
+    /// 不改变当前选中的文档；页面渲染失败时错误中的页面序号为所在文档中的序号
+    pub fn render_documents(&mut self, doc_index: Option<usize>, opts: &RenderOptions) -> Result<Vec<RgbaImage>, OfdError> {
+        self.for_documents(doc_index, |doc| doc.render_all_with_progress(opts, |_, _| {}))
+    }
+
+    /// 只渲染指定页面中标识为 layer_id 的图层，绘制在 opts 的背景色之上，返回 PNG 编码的数据
+    /// 图层可以位于页面或其引用的模板页中；签章与注释不属于任何图层，不绘制；页面中没有该图层时返回 LayerNotFound
+    pub fn render_layer(&mut self, page_index: usize, layer_id: &str, opts: &RenderOptions) -> Result<Vec<u8>, OfdError> {
+        let page = self.load_page(page_index)?;
+        let templates = self.load_templates(&page)?;
+        let found = Self::drawing_order(&page, &templates).iter().any(|p| p.layers().iter().any(|layer| layer.id == layer_id));
+        if !found {
+            return Err(OfdError::LayerNotFound { page_index, id: layer_id.to_string() });
+        }
+        let opts = RenderOptions { layer_id: Some(layer_id.to_string()), draw_seals: false, draw_annotations: false, ..opts.clone() };
+        let image = self.render_page(page_index, &opts)?;
+        let mut png = Cursor::new(Vec::new());
+        image.write_to(&mut png, image::ImageFormat::Png).map_err(OfdError::ImageError)?;
+        Ok(png.into_inner())
+    }
+
+    /// 只渲染页面中 region 范围内的内容，region 为页面坐标（毫米），输出图像恰好对应 region，用于局部放大
+    /// 与 region 不相交的图元不参与光栅化；页面有旋转时输出随之旋转，region 宽或高不大于 0 时返回 1 像素的图像
+    pub fn render_region(&mut self, page_index: usize, region: STBox, dpi: f32) -> Result<RgbaImage, OfdError> {
+        let opts = RenderOptions { target: RenderTarget::Dpi(dpi), ..RenderOptions::default() };
+        let resources = self.load_resources()?;
+        let prepared = self.prepare_page(page_index, &resources, &opts)?;
+        prepared.render_region(&resources, &opts, &region, dpi)
+    }
+
+    /// 渲染指定页面并返回渲染结果的哈希，见 testing::image_hash；opts 相同时结果相同即视觉上完全一致
+    pub fn page_render_hash(&mut self, page_index: usize, opts: &RenderOptions) -> Result<u64, OfdError> {
+        let image = self.render_page(page_index, opts)?;
+        Ok(testing::image_hash(&image))
+    }
+
+    /// 以相同的 opts 分别渲染本文档与 other 中的同一页面并逐像素比较，不同的像素在差异图中标为红色
+    /// 两页的渲染尺寸不同时只比较重叠区域，并在结果中标明
+    pub fn diff_page(&mut self, other: &mut OfdDoc, page_index: usize, opts: &RenderOptions) -> Result<DiffResult, OfdError> {
+        let a = self.render_page(page_index, opts)?;
+        let b = other.render_page(page_index, opts)?;
+        Ok(testing::diff_images(&a, &b))
+    }
+
+    /// 渲染指定页面并写入 path，按扩展名（不区分大小写）选择格式：png、jpg/jpeg、bmp、webp、avif 与 svg
+    /// SVG 由矢量导出得到，不经过光栅化；JPEG 不支持透明度，去掉透明通道后保存；有损格式使用缺省质量 DEFAULT_QUALITY
+    /// avif 需要启用 modern-formats 特性，未启用时与其他扩展名一样返回 UnsupportedOutputFormat
+    pub fn save_page(&mut self, page_index: usize, path: &str, opts: &RenderOptions) -> Result<(), OfdError> {
+        let ext = Path::new(path).extension().and_then(|e| e.to_str()).unwrap_or_default().to_ascii_lowercase();
+        if ext == "svg" {
+            let svg = self.render_page_svg(page_index, opts)?;
+            return std::fs::write(path, svg).map_err(OfdError::IoError);
+        }
+        let format = ImageFormat::from_extension(&ext)
+            .filter(|format| format.is_supported())
+            .ok_or(OfdError::UnsupportedOutputFormat { ext })?;
+        let data = self.encode_page(page_index, format, DEFAULT_QUALITY, opts)?;
+        std::fs::write(path, data).map_err(OfdError::IoError)
+    }
+
+    /// 渲染指定页面并编码为 format 格式，quality 取值 1 到 100，只对 JPEG、AVIF 与有损的 WebP 生效
+    /// AVIF 与有损 WebP 需要启用 modern-formats 特性，未启用时 AVIF 返回 UnsupportedOutputFormat
+    pub fn encode_page(&mut self, page_index: usize, format: ImageFormat, quality: u8, opts: &RenderOptions) -> Result<Vec<u8>, OfdError> {
+        if !format.is_supported() {
+            return Err(OfdError::UnsupportedOutputFormat { ext: format!("{:?}", format).to_ascii_lowercase() });
+        }
+        let image = self.render_page(page_index, opts)?;
+        encode::encode(image, format, quality).map_err(OfdError::ImageError)
+    }
+
+    /// 渲染所有页面并自上而下拼接为一张长图，用于连续滚动阅读，返回 PNG 编码的数据
+    /// page_gap_px 为相邻页面之间的间隔像素数，宽度不足的页面按 opts.center_pages 居中或左对齐
+    pub fn render_continuous(&mut self, opts: &RenderOptions, page_gap_px: u32) -> Result<Vec<u8>, OfdError> {
+        self.render_continuous_with_progress(opts, page_gap_px, |_, _| {})
+    }
+
+    /// 同 render_continuous，进度回调的含义与 render_all_with_progress 相同
+    pub fn render_continuous_with_progress(
+        &mut self,
+        opts: &RenderOptions,
+        page_gap_px: u32,
+        on_progress: impl FnMut(usize, usize),
+    ) -> Result<Vec<u8>, OfdError> {
+        let images = self.render_all_with_progress(opts, on_progress)?;
+        let strip = render::stack_pages(&images, page_gap_px, opts).ok_or_else(|| OfdError::BufferTooLarge {
+            width: images.iter().map(|i| i.width()).max().unwrap_or(0),
+            height: u32::MAX,
+        })?;
+        let mut png = Cursor::new(Vec::new());
+        strip.write_to(&mut png, image::ImageFormat::Png).map_err(OfdError::ImageError)?;
+        Ok(png.into_inner())
+    }
+
+    /// 按给定分辨率并行渲染所有页面，结果按页序排列
+    /// 压缩包不能在线程间共享，因此先在当前线程读取所有页面的内容与图像、字体，再并行光栅化；
+    /// 所有页面的解析结果与解码后的图像会同时驻留内存，页数很多时内存占用明显高于 render_all
+    pub fn render_all_parallel(&mut self, dpi: f32) -> Result<Vec<RgbaImage>, OfdError> {
+        let opts = RenderOptions { target: RenderTarget::Dpi(dpi), ..RenderOptions::default() };
+        let resources = self.load_resources()?;
+        let prepared = (0..self.page_count())
+            .map(|index| self.prepare_page(index, &resources, &opts).map_err(|e| OfdError::PageError(index, Box::new(e))))
+            .collect::<Result<Vec<PreparedPage>, OfdError>>()?;
+        prepared
+            .par_iter()
+            .enumerate()
+            .map(|(index, page)| {
+                page.render(&resources, &opts, |_| Ok(()))
+                    .map_err(|e| OfdError::PageError(index, Box::new(e)))
+            })
+            .collect()
+    }
+
+    /// 渲染指定页面，并在 object_ids 对应图元的外接矩形上叠加半透明的高亮色
+    pub fn render_page_highlighting(
+        &mut self,
+        page_index: usize,
+        object_ids: &[String],
+        highlight: [u8; 4],
+        opts: &RenderOptions,
+    ) -> Result<RgbaImage, OfdError> {
+        self.render_page_with(page_index, opts, |cr, page| {
+            let [r, g, b, a] = highlight;
+            cr.set_source_rgba(r as f64 / 255.0, g as f64 / 255.0, b as f64 / 255.0, a as f64 / 255.0);
+            for object in object_ids.iter().filter_map(|id| page.object_by_id(id)) {
+                if let Some(boundary) = object.boundary() {
+                    cr.rectangle(boundary.x, boundary.y, boundary.w, boundary.h);
+                    cr.fill()?;
+                }
+            }
+            Ok(())
+        })
+    }
+
+    /// 将指定页面输出为 SVG 文本，page_index 从 0 开始
+    pub fn render_page_svg(&mut self, page_index: usize, opts: &RenderOptions) -> Result<String, OfdError> {
+        let resources = self.load_resources()?;
+        self.render_page_svg_with(page_index, &resources, opts)
+    }
+
+    /// 将所有页面分别输出为 SVG 文本，资源只解析一次
+    pub fn to_svg_all(&mut self, opts: &RenderOptions) -> Result<Vec<String>, OfdError> {
+        let resources = self.load_resources()?;
+        (0..self.page_count())
+            .map(|index| self.render_page_svg_with(index, &resources, opts))
+            .collect()
+    }
+
+    fn render_page_svg_with(
+        &mut self,
+        page_index: usize,
+        resources: &ResourceManager,
+        opts: &RenderOptions,
+    ) -> Result<String, OfdError> {
+        let page = self.load_page(page_index)?;
+        let templates = self.load_templates(&page)?;
+        let area = self.page_area(page_index, &page)?;
+        Ok(svg::render_page_svg(&self.page_id(page_index)?, &page, &templates, &area, resources, opts))
+    }
+
+    /// 一次性读取当前文档的资源表与全部页面（含模板、图像、字体、签章与注释），创建可反复渲染的上下文
+    /// 签章与注释总是预先读取，渲染时再按 opts 决定是否绘制；本身为 OFD 的印章按缺省分辨率光栅化
+    pub fn render_context(&mut self) -> Result<RenderContext, OfdError> {
+        let opts = RenderOptions::default();
+        let resources = self.load_resources()?;
+        let pages = (0..self.page_count())
+            .map(|index| self.prepare_page(index, &resources, &opts).map_err(|e| OfdError::PageError(index, Box::new(e))))
+            .collect::<Result<Vec<PreparedPage>, OfdError>>()?;
+        log::debug!("prepared render context with {} pages", pages.len());
+        Ok(RenderContext { resources, pages })
+    }
+
+    /// 将多个文档按顺序拼接为一个连续的页面序列，例如把多张单页发票当作一个文档渲染或导出
+    pub fn concat(docs: Vec<OfdDoc>) -> MergedDoc {
+        MergedDoc { docs }
+    }
+
+    /// 按绘制顺序遍历当前文档所有页面（含引用的模板页）中的图元，不进行渲染；页块展开为其中的图元
+    /// 页面在迭代到时才读取，适合在不渲染的情况下分析或导出内容；模板中与页面图元标识相同的图元不重复给出
+    pub fn iter_objects(&mut self) -> ObjectIter<'_> {
+        ObjectIter { doc: self, next_page: 0, pending: Vec::new().into_iter() }
+    }
+
+    fn resolve_page_objects(&mut self, page_index: usize) -> Result<Vec<ResolvedObject>, OfdError> {
+        let page = self.load_page(page_index)?;
+        let templates = self.load_templates(&page)?;
+        let area = self.page_area(page_index, &page)?;
+        let display = render::page_transform(&area);
+        let mut resolved = Vec::new();
+        for p in OfdDoc::drawing_order(&page, &templates) {
+            let from_template = !std::ptr::eq(p, &page);
+            for object in p.objects() {
+                if from_template && page.contains_object(object.id()) {
+                    continue;
+                }
+                let Some(boundary) = object.boundary() else {
+                    continue;
+                };
+                resolved.push(ResolvedObject {
+                    page_index,
+                    object: ObjectInfo::from(object),
+                    ctm: render::object_matrix(&boundary, object.ctm()).then(&display),
+                    boundary,
+                    from_template,
+                });
+            }
+        }
+        Ok(resolved)
+    }
+
+    /// 按标识查找页面中的图元，不含模板页中的图元
+    pub fn find_object(&mut self, page_index: usize, id: &str) -> Result<Option<ObjectInfo>, OfdError> {
+        let page = self.load_page(page_index)?;
+        Ok(page.object_by_id(id).map(ObjectInfo::from))
+    }
+
+    /// 页面（含引用的模板）中位于 point 处的图元，point 为页面坐标（毫米），按从上到下的顺序排列
+    /// 图形对象按实际路径判断，图像与复合对象按经 CTM 映射后的范围判断，文字对象按外接矩形判断
+    pub fn objects_at(&mut self, page_index: usize, point: STPos) -> Result<Vec<ObjectRef>, OfdError> {
+        let resources = self.load_resources()?;
+        let page = self.load_page(page_index)?;
+        let templates = self.load_templates(&page)?;
+        hittest::objects_at(&page, &templates, point, &resources).map_err(OfdError::CairoError)
+    }
+
+    /// 将页面（含引用的模板）中的指定图元输出为独立的 SVG，图元不存在时返回 None
+    pub fn object_to_svg(&mut self, page_index: usize, object_id: &str) -> Result<Option<String>, OfdError> {
+        let resources = self.load_resources()?;
+        let page = self.load_page(page_index)?;
+        if page.contains_object(object_id) {
+            return Ok(page.object_to_svg(object_id, &resources));
+        }
+        let templates = self.load_templates(&page)?;
+        Ok(templates.iter().find_map(|(template, _)| template.object_to_svg(object_id, &resources)))
+    }
+
+    /// 检查文档的一致性，收集发现的所有问题：
+    /// - 页面与模板页的 BaseLoc 指向的文件存在，内容可以解析，且图元标识不重复
+    /// - 图层与图元引用的绘制参数、字型、多媒体与矢量图形都在资源表中
+    /// - 页面引用的模板页已在 CommonData 中声明
+    /// - MaxUnitID 不小于页面、模板页、图层、图元与资源中实际使用的最大数字标识
+    pub fn validate(&mut self) -> Result<(), Vec<ValidationIssue>> {
+        let mut issues = Vec::new();
+        let issue = |kind, message: String| ValidationIssue { kind, message };
+        let resources = match self.load_resources() {
+            Ok(resources) => Some(resources),
+            Err(e) => {
+                issues.push(issue(ValidationIssueKind::UnreadableResources, e.to_string()));
+                None
+            }
+        };
+        let common_data = &self.document().common_data;
+        let mut max_id = resources.as_ref().map_or(0, |r| max_numeric_id(r.resource_ids()));
+        max_id = max_id.max(max_numeric_id(self.document().pages.page.iter().map(|p| p.id.as_str())));
+        max_id = max_id.max(max_numeric_id(common_data.template_page.iter().map(|t| t.id.as_str())));
+        let template_ids: HashSet<String> = common_data.template_page.iter().map(|t| t.id.clone()).collect();
+
+        let mut pages: Vec<(String, Result<String, OfdError>)> = (0..self.page_count())
+            .map(|index| (format!("page {}", index), self.page_loc(index)))
+            .collect();
+        pages.extend(common_data.template_page.iter().map(|t| (format!("template page {}", t.id), Ok(t.base_loc.clone()))));
+        for (name, loc) in pages {
+            let loc = match loc {
+                Ok(loc) => loc,
+                Err(e) => {
+                    issues.push(issue(ValidationIssueKind::UnreadablePage, format!("{}: {}", name, e)));
+                    continue;
+                }
+            };
+            // 通过包的查找读取，与打开和渲染时一样能找到文件名经过百分号编码的条目
+            let entry = self.resolve_doc_loc(&loc);
+            let page = match self.read_doc_string(&loc).and_then(|content| parse_xml(&entry, &content, Page::from_xml)) {
+                Ok(page) => page,
+                Err(OfdError::EntryNotFound { .. }) => {
+                    issues.push(issue(ValidationIssueKind::MissingEntry, format!("{}: entry {} not found", name, entry)));
+                    continue;
+                }
+                Err(e) => {
+                    issues.push(issue(ValidationIssueKind::UnreadablePage, format!("{}: {}", name, e)));
+                    continue;
+                }
+            };
+            for id in page.duplicate_ids() {
+                issues.push(issue(ValidationIssueKind::DuplicateObjectId, format!("{}: duplicate object id {}", name, id)));
+            }
+            for reference in page.template.iter().filter(|t| !template_ids.contains(&t.template_id)) {
+                issues.push(issue(ValidationIssueKind::MissingTemplate, format!("{}: template page {} not found", name, reference.template_id)));
+            }
+            if let Some(resources) = &resources {
+                for (kind, id) in dangling_references(&page, resources) {
+                    issues.push(issue(kind, format!("{}: reference to missing resource {}", name, id)));
+                }
+            }
+            let layers = page.layers();
+            max_id = max_id
+                .max(max_numeric_id(layers.iter().map(|layer| layer.id.as_str())))
+                .max(max_numeric_id(page.objects().into_iter().map(PageObject::id)));
+        }
+
+        let declared = self.document().common_data.max_unit_id() as u64;
+        if max_id > declared {
+            issues.push(issue(ValidationIssueKind::MaxUnitIdTooSmall, format!("MaxUnitID {} is smaller than the largest id in use {}", declared, max_id)));
+        }
+        if issues.is_empty() { Ok(()) } else { Err(issues) }
+    }
+
+    /// 页面中的文字，含模板页中的文字，按绘制顺序排列
+    pub fn text_runs(&mut self, page_index: usize) -> Result<Vec<TextRun>, OfdError> {
+        let page = self.load_page(page_index)?;
+        let templates = self.load_templates(&page)?;
+        Ok(text::collect_text_runs(&Self::drawing_order(&page, &templates)))
+    }
+
+    /// 页面是否带有水印，判定规则见 watermark_text
+    pub fn has_watermark(&mut self, page_index: usize) -> Result<bool, OfdError> {
+        Ok(self.watermark_text(page_index)?.is_some())
+    }
+
+    /// 页面中水印的文字，没有水印时返回 None
+    /// 按启发式规则判定：背景层、前景层或模板页中至少两个内容相同、倾斜放置且半透明的文字对象
+    pub fn watermark_text(&mut self, page_index: usize) -> Result<Option<String>, OfdError> {
+        let page = self.load_page(page_index)?;
+        let templates = self.load_templates(&page)?;
+        let resources = self.load_resources()?;
+        let templates: Vec<&Page> = templates.iter().map(|(t, _)| t).collect();
+        Ok(watermark::detect_watermark(&page, &templates, &resources))
+    }
+
+    /// 将页面中按行或按词拆开的文字对象重建为段落，含模板页中的文字，按阅读顺序排列
+    /// 段落内的换行由行间距推断，不影响 page_text 等其他文字接口
+    pub fn paragraphs(&mut self, page_index: usize) -> Result<Vec<Paragraph>, OfdError> {
+        Ok(text::paragraphs(&self.text_runs(page_index)?))
+    }
+
+    /// 在所有页面中查找文字，区分大小写
+    pub fn search(&mut self, query: &str) -> Result<Vec<TextHit>, OfdError> {
+        self.search_with_options(query, &SearchOptions::default())
+    }
+
+    /// 按 opts 在所有页面中查找文字，在每个文字对象（含模板页中的）的全部文字中匹配
+    /// 返回的外接矩形为页面坐标系下的毫米值，按渲染分辨率缩放即可在图像上定位
+    pub fn search_with_options(&mut self, query: &str, opts: &SearchOptions) -> Result<Vec<TextHit>, OfdError> {
+        let mut hits = Vec::new();
+        for page_index in 0..self.page_count() {
+            let runs = self.text_runs(page_index)?;
+            hits.extend(
+                text::search_runs(&runs, query, opts)
+                    .into_iter()
+                    .map(|(text, bbox)| TextHit { page_index, text, bbox }),
+            );
+        }
+        Ok(hits)
+    }
+
+    /// 页面中的纯文本，含模板页中的文字，按阅读顺序排列，各行以换行分隔
+    /// 只读取文字内容，不需要加载字体
+    pub fn page_text(&mut self, page_index: usize) -> Result<String, OfdError> {
+        Ok(text::plain_text(&self.text_runs(page_index)?))
+    }
+
+    /// doc_index 指定文档中每页的纯文本，None 时按文档顺序返回全部文档的页面，不改变当前选中的文档
+    pub fn documents_text(&mut self, doc_index: Option<usize>) -> Result<Vec<String>, OfdError> {
+        self.for_documents(doc_index, |doc| (0..doc.page_count()).map(|index| doc.page_text(index)).collect())
+    }
+
+    /// 将文档导出为多页 PDF，页面顺序不变，每页大小取页面的物理区域（指定 fit_paper 时为纸张大小）
+    /// 路径与文字以矢量绘制，允许嵌入的字体以文字写出，可以选取与检索，其余文字按字形轮廓或系统字体输出；
+    /// 图像原样嵌入；opts.target 不影响输出
+    pub fn to_pdf(&mut self, opts: &RenderOptions) -> Result<Vec<u8>, OfdError> {
+        let pages: Vec<usize> = (0..self.page_count()).collect();
+        self.pages_to_pdf(&pages, opts)
+    }
+
+    /// 同 to_pdf，只按给定顺序导出 page_indices 中的页面，页面序号从 0 开始
+    pub fn pages_to_pdf(&mut self, page_indices: &[usize], opts: &RenderOptions) -> Result<Vec<u8>, OfdError> {
+        if let Some(&index) = page_indices.iter().find(|&&index| index >= self.page_count()) {
+            return Err(OfdError::InvalidPageIndex(index));
+        }
+        let resources = self.load_resources()?;
+        let mut writer = PdfWriter::new().map_err(OfdError::CairoError)?;
+        for &index in page_indices {
+            let prepared = self.prepare_page(index, &resources, opts)?;
+            prepared.write_pdf_page(&mut writer, &resources, opts).map_err(OfdError::CairoError)?;
+        }
+        writer.finish().map_err(OfdError::CairoError)
+    }
+
+    /// 将文档导出为多页 TIFF，每页一个 IFD，颜色模式与压缩方式取自 opts.color_mode 与 opts.tiff_compression
+    /// TIFF 页面不含透明度，未指定背景色时使用白色背景
+    pub fn to_tiff(&mut self, opts: &RenderOptions) -> Result<Vec<u8>, OfdError> {
+        let mut opts = opts.clone();
+        if opts.background.is_none_or(|[_, _, _, a]| a < 255) {
+            opts.background = Some([255, 255, 255, 255]);
+        }
+        let mut output = Cursor::new(Vec::new());
+        let mut encoder = TiffEncoder::new(&mut output).map_err(OfdError::TiffError)?;
+        for index in 0..self.page_count() {
+            let image = self.render_page(index, &opts)?;
+            let dpi = self.effective_dpi(index, &opts)?;
+            tiff::write_page(&mut encoder, &image, &opts, dpi).map_err(OfdError::TiffError)?;
+        }
+        Ok(output.into_inner())
+    }
+
+    /// 生成可检索的 PDF：每页为渲染后的位图，下方叠放不可见的文字层
+    /// 位图必须不透明才能遮住文字层，未指定背景色时使用白色背景
+    /// 文字层按页面的物理区域定位，因此忽略 fit_paper，每页的大小即页面的物理区域
+    pub fn to_searchable_pdf(&mut self, opts: &RenderOptions) -> Result<Vec<u8>, OfdError> {
+        let mut opts = opts.clone();
+        opts.fit_paper = None;
+        if opts.background.is_none_or(|[_, _, _, a]| a < 255) {
+            opts.background = Some([255, 255, 255, 255]);
+        }
+        let mut writer = SearchablePdfWriter::new().map_err(OfdError::CairoError)?;
+        for index in 0..self.page_count() {
+            let image = self.render_page(index, &opts)?;
+            let page = self.load_page(index)?;
+            let area = self.page_area(index, &page)?;
+            let runs = self.text_runs(index)?;
+            writer.add_page(&image, &area, &runs).map_err(OfdError::CairoError)?;
+        }
+        writer.finish().map_err(OfdError::CairoError)
+    }
+
+    /// 读取文档的自定义标引，返回各标引节点与其引用的页面图元；文档没有自定义标引时返回空列表
+    pub fn tagged_objects(&mut self) -> Result<Vec<TaggedObject>, OfdError> {
+        let loc = self.document().custom_tags.trim().to_string();
+        if loc.is_empty() {
+            return Ok(Vec::new());
+        }
+        let content = self.read_doc_string(&loc)?;
+        let entry = self.resolve_doc_loc(&loc);
+        let custom_tags = parse_xml(&entry, &content, CustomTags::from_xml)?;
+        let base_dir = parent_dir(&entry).to_string();
+        let mut tagged = Vec::new();
+        for tag in custom_tags.custom_tag.iter().filter(|t| !t.file_loc.trim().is_empty()) {
+            let name = resolve_path(&base_dir, tag.file_loc.trim());
+            let content = self.package.read_entry_string(&name)?;
+            tagged.extend(parse_xml(&name, &content, |content| tags::collect_tagged_objects(&tag.type_id, content))?);
+        }
+        Ok(tagged)
+    }
+
+    /// 收集当前文档中所有 URI 动作的目标地址，包括页面图元上的动作与注释外观中的动作
+    /// 结果按页序排列，同一页内页面内容中的链接在注释中的链接之前；文档级动作不属于任何页面，不包含在内
+    pub fn links(&mut self) -> Result<Vec<LinkInfo>, OfdError> {
+        let mut links = Vec::new();
+        let locations: Vec<String> = self.page_refs().map(|p| p.location).collect();
+        for (page_index, loc) in locations.iter().enumerate() {
+            let content = self.package.read_entry_string(loc)?;
+            let uris = parse_xml(loc, &content, annotation::collect_uris)?;
+            links.extend(uris.into_iter().map(|url| LinkInfo { url, page_index }));
+        }
+
+        for (page_index, name) in self.annotation_files()? {
+            let content = self.package.read_entry_string(&name)?;
+            let uris = parse_xml(&name, &content, annotation::collect_uris)?;
+            links.extend(uris.into_iter().map(|url| LinkInfo { url, page_index }));
+        }
+        links.sort_by_key(|link| link.page_index);
+        Ok(links)
+    }
+
+    /// 读取 Annotations.xml，返回各分页注释文件所属页面的序号与包内文件名
+    /// 引用的页面不在文档中的注释文件记录警告后跳过
+    fn annotation_files(&mut self) -> Result<Vec<(usize, String)>, OfdError> {
+        let loc = self.document().annotations.trim().to_string();
+        if loc.is_empty() {
+            return Ok(Vec::new());
+        }
+        let content = self.read_doc_string(&loc)?;
+        let entry = self.resolve_doc_loc(&loc);
+        let annotations = parse_xml(&entry, &content, Annotations::from_xml)?;
+        let base_dir = parent_dir(&entry).to_string();
+        let mut files = Vec::new();
+        for page in annotations.page.iter().filter(|p| !p.file_loc.trim().is_empty()) {
+            match self.page_refs().position(|p| p.id == page.page_id.trim()) {
+                Some(page_index) => files.push((page_index, resolve_path(&base_dir, page.file_loc.trim()))),
+                None => log::warn!("annotations {} reference page {} which is not in the document", page.file_loc, page.page_id),
+            }
+        }
+        Ok(files)
+    }
+
+    /// 读取指定页面上可见且带有外观的注释
+    fn load_page_annotations(&mut self, page_index: usize) -> Result<Vec<Annot>, OfdError> {
+        let mut annots = Vec::new();
+        for (_, name) in self.annotation_files()?.into_iter().filter(|(index, _)| *index == page_index) {
+            let content = self.package.read_entry_string(&name)?;
+            let page_annot = parse_xml(&name, &content, PageAnnot::from_xml)?;
+            annots.extend(page_annot.annot.into_iter().filter(|a| a.visible && a.appearance.is_some()));
+        }
+        Ok(annots)
+    }
+
+    /// 当前文档的附件列表，文档没有附件时为空
+    pub fn attachments(&self) -> Vec<AttachmentInfo> {
+        self.attachments[self.current].clone()
+    }
+
+    /// 按名称读取当前文档中附件的内容，名称重复时取第一个
+    pub fn read_attachment(&mut self, name: &str) -> Result<Vec<u8>, OfdError> {
+        let location = self.attachments[self.current].iter()
+            .find(|a| a.name == name)
+            .map(|a| a.location.clone())
+            .ok_or_else(|| OfdError::ResourceNotFound(name.to_string()))?;
+        self.package.read_entry(&location)
+    }
+
+    /// 当前文档资源中声明的音频与视频，按标识排序；它们不参与渲染
+    pub fn media(&mut self) -> Result<Vec<MediaInfo>, OfdError> {
+        Ok(self.load_resources()?.audio_video())
+    }
+
+    /// 按标识读取当前文档中音频或视频的内容
+    pub fn read_media(&mut self, id: &str) -> Result<Vec<u8>, OfdError> {
+        let location = self.media()?.into_iter()
+            .find(|m| m.id == id)
+            .map(|m| m.location)
+            .ok_or_else(|| OfdError::ResourceNotFound(id.to_string()))?;
+        self.package.read_entry(&location)
+    }
+
+    /// 页面的物理区域（毫米），页面未指定时使用文档的缺省页面区域，不考虑页面的旋转
+    pub fn page_size(&mut self, page_index: usize) -> Result<STBox, OfdError> {
+        let page = self.load_page(page_index)?;
+        Ok(self.page_area(page_index, &page)?.physical_box)
+    }
+
+    /// 页面的顺时针旋转角度，为 0、90、180 或 270，渲染结果已按此旋转
+    pub fn page_rotation(&mut self, page_index: usize) -> Result<u16, OfdError> {
+        let page = self.load_page(page_index)?;
+        Ok(self.page_area(page_index, &page)?.rotation)
+    }
+
+    /// 页面方向，取文档缺省页面区域（CommonData 的 PageArea 与 Rotate）旋转后的宽高比较
+    /// 只使用打开时已解析的 Document.xml，不读取页面内容，因此不考虑页面自身声明的 Area 与 Rotate，
+    /// 需要逐页准确的结果时使用 page_size 与 page_rotation；页面不存在或文档没有缺省页面区域时返回 None
+    pub fn page_orientation(&self, page_index: usize) -> Option<Orientation> {
+        self.document().pages.page.get(page_index)?;
+        let common_data = &self.document().common_data;
+        let mut area = common_data.page_area.clone()?;
+        area.rotation = common_data.rotate.and_then(normalize_rotation).unwrap_or(0);
+        let (width, height) = area.display_size();
+        if width > height {
+            Some(Orientation::Landscape)
+        } else {
+            Some(Orientation::Portrait)
+        }
+    }
+
+    fn render_page_with<F>(&mut self, page_index: usize, opts: &RenderOptions, overlay: F) -> Result<RgbaImage, OfdError>
+    where
+        F: FnOnce(&cairo::Context, &Page) -> Result<(), cairo::Error>,
+    {
+        let resources = self.load_resources()?;
+        self.render_page_with_resources(page_index, &resources, opts, overlay)
+    }
+
+    /// 使用已加载的资源表渲染页面，批量渲染时避免重复解析资源文件
+    fn render_page_with_resources<F>(
+        &mut self,
+        page_index: usize,
+        resources: &ResourceManager,
+        opts: &RenderOptions,
+        overlay: F,
+    ) -> Result<RgbaImage, OfdError>
+    where
+        F: FnOnce(&cairo::Context, &Page) -> Result<(), cairo::Error>,
+    {
+        let prepared = self.prepare_page(page_index, resources, opts)?;
+        prepared.render(resources, opts, |cr| overlay(cr, &prepared.page))
+    }
+
+    /// 读取页面渲染所需的全部内容：页面、模板、页面区域以及引用的图像与字体，需要时还有签章图像
+    /// 签章只是页面上的附加外观，读取失败时记录警告，不影响页面渲染
+    fn prepare_page(&mut self, page_index: usize, resources: &ResourceManager, opts: &RenderOptions) -> Result<PreparedPage, OfdError> {
+        let page = self.load_page(page_index)?;
+        let templates = self.load_templates(&page)?;
+        let area = self.page_area(page_index, &page)?;
+        let pages = Self::drawing_order(&page, &templates);
+        let seals = if opts.draw_seals {
+            self.load_page_seals(page_index, opts).unwrap_or_else(|e| {
+                log::warn!("failed to read signatures for page {}: {}", page_index, e);
+                Vec::new()
+            })
+        } else {
+            Vec::new()
+        };
+        let mut objects: Vec<&PageObject> = Vec::with_capacity(self.objects_capacity());
+        for page in &pages {
+            page.append_objects(&mut objects);
+        }
+        let mut assets = PageAssets {
+            images: self.load_page_images(objects.clone(), resources)?,
+            fonts: self.load_page_fonts(objects, resources)?,
+            seals,
+            annotations: Vec::new(),
+        };
+        // 注释与签章一样只是页面上的附加外观，读取失败时记录警告，不影响页面渲染
+        if opts.draw_annotations {
+            let annotations = self.load_page_annotations(page_index).unwrap_or_else(|e| {
+                log::warn!("failed to read annotations for page {}: {}", page_index, e);
+                Vec::new()
+            });
+            let objects: Vec<&PageObject> = annotations.iter()
+                .flat_map(|a| a.appearance.iter().flat_map(|appearance| appearance.objects()))
+                .collect();
+            match self.load_page_images(objects.clone(), resources) {
+                Ok(images) => assets.images.extend(images),
+                Err(e) => log::warn!("failed to read annotation images for page {}: {}", page_index, e),
+            }
+            for (id, font) in self.load_page_fonts(objects, resources)? {
+                assets.fonts.entry(id).or_insert(font);
+            }
+            assets.annotations = annotations;
+        }
+        Ok(PreparedPage { id: self.page_id(page_index)?, page, templates, area, assets })
+    }
+
+    /// 读取显示在指定页面上的签章图像及其外接矩形，无法解析出印章图像的签章记录警告后跳过
+    fn load_page_seals(&mut self, page_index: usize, opts: &RenderOptions) -> Result<Vec<(STBox, RgbaImage)>, OfdError> {
+        let loc = match self.doc_body().signatures.as_deref().map(str::trim) {
+            Some(loc) if !loc.is_empty() => resolve_path("", loc),
+            _ => return Ok(Vec::new()),
+        };
+        let page_id = self.page_id(page_index)?;
+        let content = self.package.read_entry_string(&loc)?;
+        let signatures = parse_xml(&loc, &content, Signatures::from_xml)?;
+
+        let mut seals = Vec::new();
+        for reference in &signatures.signature {
+            let signature_loc = resolve_path(parent_dir(&loc), &reference.base_loc);
+            let content = self.package.read_entry_string(&signature_loc)?;
+            let signature = parse_xml(&signature_loc, &content, Signature::from_xml)?;
+            let boundaries: Vec<STBox> = signature.signed_info.stamp_annot.iter()
+                .filter(|annot| annot.page_ref == page_id)
+                .map(|annot| annot.boundary)
+                .collect();
+            if boundaries.is_empty() {
+                continue;
+            }
+            match self.load_seal_image(&signature, parent_dir(&signature_loc), opts) {
+                Ok(Some(image)) => seals.extend(boundaries.into_iter().map(|boundary| (boundary, image.clone()))),
+                Ok(None) => log::warn!("signature {} has no seal picture", reference.id),
+                Err(e) => log::warn!("failed to load seal of signature {}: {}", reference.id, e),
+            }
+        }
+        Ok(seals)
+    }
+
+    /// 依次在签名值与电子印章文件中查找印章图像并解码
+    /// 印章图像本身为 OFD 时渲染其第一页，不再绘制其中的签章
+    fn load_seal_image(&mut self, signature: &Signature, base_dir: &str, opts: &RenderOptions) -> Result<Option<RgbaImage>, OfdError> {
+        let locs = [Some(signature.signed_value.as_str()), signature.signed_info.seal.as_ref().map(|s| s.base_loc.as_str())];
+        for loc in locs.into_iter().flatten().map(str::trim).filter(|loc| !loc.is_empty()) {
+            let data = self.package.read_entry(&resolve_path(base_dir, loc))?;
+            let picture = match seal::find_seal_picture(&data) {
+                Some(picture) => picture,
+                None => continue,
+            };
+            let image = if picture.kind == "ofd" {
+                // 印章按分辨率渲染，页面按像素尺寸输出时使用缺省分辨率，印章图像随后再拉伸到外接矩形
+                let seal_opts = RenderOptions {
+                    target: RenderTarget::Dpi(opts.target.dpi().unwrap_or(render::DEFAULT_DPI)),
+                    background: None,
+                    draw_seals: false,
+                    fit_paper: None,
+                    print_marks: false,
+                    quantize_colors: None,
+                    color_mode: ColorMode::Color,
+                    ..opts.clone()
+                };
+                OfdDoc::open_from_bytes(picture.data)?.render_page(0, &seal_opts)?
+            } else {
+                image::load_from_memory(picture.data).map_err(OfdError::ImageError)?.to_rgba8()
+            };
+            return Ok(Some(image));
+        }
+        Ok(None)
+    }
+
+    /// 页面位图的实际分辨率，按像素尺寸输出时由页面的画布大小换算
+    fn effective_dpi(&mut self, page_index: usize, opts: &RenderOptions) -> Result<f64, OfdError> {
+        if let Some(dpi) = opts.target.dpi() {
+            return Ok(dpi as f64);
+        }
+        let page = self.load_page(page_index)?;
+        let area = self.page_area(page_index, &page)?;
+        Ok(render::Canvas::new(&area, opts).raster(opts.target).scale * MM_PER_INCH)
+    }
+
+    /// 读取并解析指定页面的内容，宽松模式下无法读取或解析的页面按空白页处理
+    fn load_page(&mut self, page_index: usize) -> Result<Page, OfdError> {
+        let loc = self.page_loc(page_index)?;
+        let page = self.read_page(page_index);
+        let entry = self.resolve_doc_loc(&loc);
+        Ok(tolerate(self.mode, &mut self.warnings, &entry, page)?.unwrap_or_default())
+    }
+
+    fn page_loc(&self, page_index: usize) -> Result<String, OfdError> {
+        Ok(self.document().pages.page.get(page_index)
+            .ok_or(OfdError::InvalidPageIndex(page_index))?
+            .base_loc
+            .clone())
+    }
+
+    /// 读取并解析指定页面的内容，不论解析模式如何，出错时都返回错误
+    fn read_page(&mut self, page_index: usize) -> Result<Page, OfdError> {
+        let loc = self.page_loc(page_index)?;
+        let content = self.read_doc_string(&loc)?;
+        let mut page = parse_xml(&self.resolve_doc_loc(&loc), &content, Page::from_xml)?;
+        if let Ok(area) = self.page_area(page_index, &page) {
+            page.default_boundaries(area.physical_box);
+        }
+        log::debug!("page {} has {} layers and {} objects", page_index, page.layers().len(), page.objects().len());
+        Ok(page)
+    }
+
+    /// 按绘制顺序排列页面与模板：背景模板、页面、前景模板
+    fn drawing_order<'a>(page: &'a Page, templates: &'a [(Page, ZOrder)]) -> Vec<&'a Page> {
+        let backgrounds = templates.iter().filter(|(_, z)| *z == ZOrder::Background).map(|(t, _)| t);
+        let foregrounds = templates.iter().filter(|(_, z)| *z == ZOrder::Foreground).map(|(t, _)| t);
+        backgrounds.chain([page]).chain(foregrounds).collect()
+    }
+
+    /// 加载页面引用的模板页及其绘制层次，引用了不存在的模板时记录警告并跳过
+    fn load_templates(&mut self, page: &Page) -> Result<Vec<(Page, ZOrder)>, OfdError> {
+        let mut templates = Vec::with_capacity(page.template.len());
+        for reference in &page.template {
+            let template_page = self.document().common_data.template_page.iter()
+                .find(|t| t.id == reference.template_id);
+            let (loc, z_order) = match template_page {
+                Some(t) => (t.base_loc.clone(), reference.z_order.or(t.z_order).unwrap_or_default()),
+                None => {
+                    log::warn!("template page {} not found", reference.template_id);
+                    continue;
+                }
+            };
+            let entry = self.resolve_doc_loc(&loc);
+            let template = self.read_doc_string(&loc).and_then(|content| parse_xml(&entry, &content, Page::from_xml));
+            let Some(mut template) = tolerate(self.mode, &mut self.warnings, &entry, template)? else {
+                continue;
+            };
+            let area = template.area.as_ref()
+                .or(page.area.as_ref())
+                .or(self.document().common_data.page_area.as_ref());
+            if let Some(area) = area {
+                template.default_boundaries(area.physical_box);
+            }
+            log::debug!("template page {} has {} objects, z-order {:?}", reference.template_id, template.objects().len(), z_order);
+            templates.push((template, z_order));
+        }
+        Ok(templates)
+    }
+
+    /// 页面的有效区域，页面自身的 Area 优先，其次为文档 CommonData 中的 PageArea
+    /// 两者都没有时（不符合规范的文档）以页面内容的外接矩形作为物理区域，使文档仍可显示
+    /// 页面的 Rotate 优先，其次为 CommonData 中的缺省值，不是 90 的整数倍时忽略并记录警告
+    fn page_area(&self, page_index: usize, page: &Page) -> Result<PageArea, OfdError> {
+        let common_data = &self.document().common_data;
+        let mut area = match page.area.as_ref().or(common_data.page_area.as_ref()) {
+            Some(area) => area.clone(),
+            None => {
+                let physical_box = page.content_bounds().ok_or(OfdError::MissingPageArea(page_index))?;
+                log::warn!("page {} has no page area, using content bounds {:?}", page_index, physical_box);
+                PageArea { physical_box, ..PageArea::default() }
+            }
+        };
+        if let Some(rotate) = page.rotate.or(common_data.rotate) {
+            area.rotation = normalize_rotation(rotate).unwrap_or_else(|| {
+                log::warn!("page {} has unsupported rotation {}, ignored", page_index, rotate);
+                0
+            });
+        }
+        Ok(area)
+    }
+
+    /// 读取 CommonData 引用的全部公共资源与文档资源，按声明顺序合并为资源表，标识相同时后读取的优先
+    /// 资源中引用的文件相对于资源文件所在目录与其 BaseLoc 解析
+    fn load_resources(&mut self) -> Result<ResourceManager, OfdError> {
+        let common_data = &self.document().common_data;
+        let res_locs: Vec<String> = common_data.public_res.iter()
+            .chain(&common_data.document_res)
+            .map(|loc| loc.trim().to_string())
+            .filter(|loc| !loc.is_empty())
+            .collect();
+        let mut resources = ResourceManager::default();
+        for loc in &res_locs {
+            let entry = self.resolve_doc_loc(loc);
+            let res = self.read_doc_string(loc).and_then(|content| parse_xml(&entry, &content, Res::from_xml));
+            let Some(res) = tolerate(self.mode, &mut self.warnings, &entry, res)? else {
+                continue;
+            };
+            let base_dir = resolve_path(parent_dir(&entry), &res.base_loc);
+            log::debug!("loading resources {} with base directory {}", loc, base_dir);
+            resources.add(&base_dir, res);
+        }
+        self.load_color_profiles(&mut resources);
+        Ok(resources)
+    }
+
+    /// 读取颜色空间引用的 ICC 描述文件，无法读取或解析时记录警告，该颜色空间按近似公式转换
+    fn load_color_profiles(&mut self, resources: &mut ResourceManager) {
+        for (id, name) in resources.profile_files() {
+            let Some(space) = resources.color_spaces.get(&id).map(|cs| cs.color_space_type) else {
+                continue;
+            };
+            match self.package.read_entry(&name) {
+                Ok(data) => match IccTransform::new(&data, space) {
+                    Some(icc) => {
+                        log::debug!("loaded ICC profile {} for color space {}", name, id);
+                        resources.set_icc(&id, icc);
+                    }
+                    None => log::warn!("ICC profile {} of color space {} is invalid, using built-in conversion", name, id),
+                },
+                Err(e) => log::warn!("failed to read ICC profile {} of color space {}: {}", name, id, e),
+            }
+        }
+    }
+
+    /// 按需读取嵌入字形文件的内容，字型没有嵌入字形文件时返回 None
+    fn load_font_data(&mut self, resources: &ResourceManager, font_id: &str) -> Result<Option<Vec<u8>>, OfdError> {
+        match resources.font_file(font_id) {
+            Some(name) => self.package.read_entry(name).map(Some),
+            None => Ok(None),
+        }
+    }
+
+    /// 解析页面中文字对象引用的字体，依次尝试嵌入字形文件、按字族注册的后备字体与缺省字体
+    /// 字形文件无法读取或已损坏时记录警告并尝试后备字体，都没有时绘制时改用系统字体，不影响页面渲染
+    fn load_page_fonts(&mut self, objects: Vec<&PageObject>, resources: &ResourceManager) -> Result<HashMap<String, Arc<TrueTypeFont>>, OfdError> {
+        let mut fonts: HashMap<String, Arc<TrueTypeFont>> = HashMap::new();
+        let objects = resources.with_composite_objects(objects);
+        for object in objects {
+            let font_id = match object {
+                PageObject::TextObject(o) => &o.font,
+                _ => continue,
+            };
+            // 每个字体只尝试加载一次，无法使用的字体同样缓存，损坏的字体不重复报告
+            if let Some(cached) = self.font_cache.get(font_id) {
+                if let Some(font) = cached {
+                    fonts.insert(font_id.clone(), font.clone());
+                }
+                continue;
+            }
+            if resources.get_font(font_id).is_none() {
+                log::warn!("text object references unknown font {}, falling back", font_id);
+            }
+            let font = match self.load_font_data(resources, font_id) {
+                Ok(Some(data)) => TrueTypeFont::parse(data).map(Arc::new).or_else(|| {
+                    log::warn!("embedded font {} is corrupt or unsupported, falling back", font_id);
+                    None
+                }),
+                Ok(None) => None,
+                Err(e) => {
+                    log::warn!("failed to read embedded font {}: {}, falling back", font_id, e);
+                    None
+                }
+            };
+            let font = match font {
+                Some(font) => {
+                    log::debug!("loaded embedded font {}", font_id);
+                    Some(font)
+                }
+                None => self.font_registry.resolve(resources.get_font(font_id)).inspect(|_| {
+                    log::debug!("using registered fallback font for {}", font_id);
+                }),
+            };
+            if let Some(font) = &font {
+                fonts.insert(font_id.clone(), font.clone());
+            }
+            self.font_cache.insert(font_id.clone(), font);
+        }
+        Ok(fonts)
+    }
+
+    /// 解码页面中图像对象引用的图像，引用的资源不存在时返回 ResourceNotFound
+    fn load_page_images(&mut self, objects: Vec<&PageObject>, resources: &ResourceManager) -> Result<HashMap<String, Arc<RgbaImage>>, OfdError> {
+        let mut images: HashMap<String, Arc<RgbaImage>> = HashMap::new();
+        let resource_ids: Vec<&str> = resources.with_composite_objects(objects)
+            .into_iter()
+            .filter_map(|object| match object {
+                PageObject::ImageObject(o) => Some(o.resource_id.as_str()),
+                _ => None,
+            })
+            .collect();
+        if resource_ids.is_empty() {
+            return Ok(images);
+        }
+
+        for id in resource_ids {
+            if images.contains_key(id) {
+                continue;
+            }
+            if let Some(image) = self.image_cache.get(id) {
+                images.insert(id.to_string(), image.clone());
+                continue;
+            }
+            // 引用音频或视频的图像对象没有可绘制的图像，按缺少图像数据跳过
+            if let Some(media_type @ (MediaType::Audio | MediaType::Video)) = resources.media_type(id) {
+                log::debug!("image object references {:?} resource {}, not rendered", media_type, id);
+                continue;
+            }
+            // 未知资源、缺失的文件与无法解码的图像按解析模式处理，宽松模式下只跳过该图像
+            let (entry, image) = match resources.media_file(id) {
+                Some(name) => {
+                    log::debug!("decoding image {} from {}", id, name);
+                    let image = self.package.read_entry(name)
+                        .and_then(|data| decode_image(&data, resources.media_format(id), name));
+                    (name.to_string(), image)
+                }
+                None => (id.to_string(), Err(OfdError::ResourceNotFound(id.to_string()))),
+            };
+            let Some(image) = tolerate(self.mode, &mut self.warnings, &entry, image)? else {
+                continue;
+            };
+            let image = Arc::new(image);
+            self.image_cache.insert(id, image.clone());
+            images.insert(id.to_string(), image);
+        }
+        Ok(images)
+    }
+
+    /// 收集当前文档中一个页面的图元时预分配的容量，由 MaxUnitID 估计
+    pub(crate) fn objects_capacity(&self) -> usize {
+        page::objects_capacity(self.document().common_data.max_unit_id())
+    }
+
+    /// 页面在 Pages 中声明的标识
+    fn page_id(&self, page_index: usize) -> Result<String, OfdError> {
+        self.document().pages.page.get(page_index)
+            .map(|page| page.id.clone())
+            .ok_or(OfdError::InvalidPageIndex(page_index))
+    }
+
+    /// 文档的页数，不包含模板页
+    pub fn page_count(&self) -> usize {
+        self.document().pages.page.len()
+    }
+
+    /// 页面的显示页码，页面声明了 Label 时使用该值，否则为从 1 开始的序号
+    pub fn page_label(&self, page_index: usize) -> String {
+        self.document().pages.page.get(page_index)
+            .and_then(|p| p.label.as_deref())
+            .map(str::trim)
+            .filter(|label| !label.is_empty())
+            .map_or_else(|| (page_index + 1).to_string(), str::to_string)
+    }
+
+    /// 当前文档的大纲（书签）树，文档没有大纲时为空
+    pub fn outlines(&self) -> Vec<OutlineItem> {
+        let Some(outlines) = &self.document().outlines else {
+            return Vec::new();
+        };
+        let page_indices: HashMap<&str, usize> = self.document().pages.page.iter()
+            .enumerate()
+            .map(|(index, p)| (p.id.as_str(), index))
+            .collect();
+        fn convert(elem: &OutlineElem, page_indices: &HashMap<&str, usize>) -> OutlineItem {
+            OutlineItem {
+                title: elem.title.clone(),
+                dest_page: elem.dest_page_id().and_then(|id| page_indices.get(id).copied()),
+                children: elem.outline_elem.iter().map(|child| convert(child, page_indices)).collect(),
+            }
+        }
+        outlines.outline_elem.iter().map(|elem| convert(elem, &page_indices)).collect()
+    }
+
+    /// 按文档顺序遍历页面信息，不解析页面内容
+    pub fn page_refs(&self) -> impl Iterator<Item = PageInfo> + '_ {
+        self.document().pages.page.iter().map(|p| PageInfo {
+            id: p.id.clone(),
+            location: self.resolve_doc_loc(&p.base_loc),
+        })
+    }
+
+    /// 将相对于 Document.xml 所在目录的路径转换为压缩包内的文件名
+    fn resolve_doc_loc(&self, loc: &str) -> String {
+        resolve_path(parent_dir(&self.doc_body().doc_root), loc)
+    }
+
+    /// 读取相对于 Document.xml 所在目录的文件内容
+    fn read_doc_string(&mut self, loc: &str) -> Result<String, OfdError> {
+        let name = self.resolve_doc_loc(loc);
+        self.package.read_entry_string(&name)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::render::{AntiAlias, LayerFilter, PaperSize};
+    use crate::st_types::STMatrix;
+    use crate::testing::fixture::{self, OfdBuilder};
+
+    const TEST_OFD: &str = concat!(env!("CARGO_MANIFEST_DIR"), "/data/test.ofd");
+
+    fn low_res() -> RenderOptions {
+        RenderOptions { target: RenderTarget::Dpi(36.0), ..RenderOptions::default() }
+    }
+
+    #[test]
+    fn open_from_bytes_matches_open() {
+        let mut from_file = OfdDoc::open(TEST_OFD).unwrap();
+        let mut from_bytes = OfdDoc::open_from_bytes(&std::fs::read(TEST_OFD).unwrap()).unwrap();
+        assert_eq!(from_bytes.page_count(), from_file.page_count());
+        assert_eq!(from_bytes.attributes, from_file.attributes);
+        assert_eq!(from_bytes.custom_datas, from_file.custom_datas);
+        assert_eq!(from_bytes.list_entries(), from_file.list_entries());
+        assert_eq!(
+            from_bytes.page_render_hash(0, &low_res()).unwrap(),
+            from_file.page_render_hash(0, &low_res()).unwrap(),
+        );
+    }
+
+    #[test]
+    fn open_reader_accepts_a_cursor() {
+        let doc = OfdDoc::open_reader(Cursor::new(std::fs::read(TEST_OFD).unwrap())).unwrap();
+        assert_eq!(doc.page_count(), 1);
+    }
+
+    #[test]
+    fn open_from_bytes_rejects_data_that_is_not_a_zip() {
+        assert!(matches!(OfdDoc::open_from_bytes(b"not an ofd package"), Err(OfdError::ZipError(_))));
+    }
+
+    #[test]
+    fn resolve_path_joins_relative_locations_to_the_document_directory() {
+        let doc_dir = parent_dir("Doc_0/Document.xml");
+        assert_eq!(resolve_path(doc_dir, "Pages/Page_0/Content.xml"), "Doc_0/Pages/Page_0/Content.xml");
+        assert_eq!(resolve_path(parent_dir("Document.xml"), "Pages/Page_0/Content.xml"), "Pages/Page_0/Content.xml");
+    }
+
+    #[test]
+    fn resolve_path_treats_a_leading_slash_as_package_root() {
+        assert_eq!(resolve_path("Doc_0", "/Doc_0/Res/image.png"), "Doc_0/Res/image.png");
+        assert_eq!(resolve_path("", "/OFD.xml"), "OFD.xml");
+    }
+
+    #[test]
+    fn resolve_path_normalizes_dot_segments() {
+        assert_eq!(resolve_path("Doc_0/Pages", "../Res/./image.png"), "Doc_0/Res/image.png");
+        assert_eq!(resolve_path("Doc_0", "../../OFD.xml"), "OFD.xml");
+    }
+
+    #[test]
+    fn embedded_thumbnail_returns_the_declared_entry_unchanged() {
+        let thumbnail = vec![0x89, b'P', b'N', b'G', 0x0d, 0x0a, 0x1a, 0x0a, 0, 1, 2, 3];
+        let mut doc = OfdBuilder::new()
+            .page("")
+            .doc_body("<ofd:Thumbnail>Doc_0/Thumbnail.png</ofd:Thumbnail>")
+            .file("Doc_0/Thumbnail.png", thumbnail.clone())
+            .open();
+        assert_eq!(doc.embedded_thumbnail().unwrap(), Some(thumbnail));
+    }
+
+    #[test]
+    fn embedded_thumbnail_is_none_when_not_declared() {
+        let mut doc = OfdBuilder::new().page("").open();
+        assert_eq!(doc.embedded_thumbnail().unwrap(), None);
+    }
+
+    /// 带有自身页面区域与属性的页面内容
+    fn page_content(attributes: &str, physical_box: &str) -> String {
+        format!(
+            "<ofd:Page xmlns:ofd=\"http://www.ofdspec.org/2016\" {}><ofd:Area><ofd:PhysicalBox>{}</ofd:PhysicalBox></ofd:Area></ofd:Page>",
+            attributes, physical_box,
+        )
+    }
+
+    #[test]
+    fn page_orientation_follows_the_rotated_document_page_area() {
+        let mut doc = OfdBuilder::new().page("").open();
+        assert_eq!(doc.page_orientation(0), Some(Orientation::Landscape));
+        doc.documents[0].common_data.rotate = Some(90);
+        assert_eq!(doc.page_orientation(0), Some(Orientation::Portrait));
+        doc.documents[0].common_data.rotate = Some(180);
+        assert_eq!(doc.page_orientation(0), Some(Orientation::Landscape));
+    }
+
+    #[test]
+    fn page_orientation_is_none_without_a_page_or_page_area() {
+        let mut doc = OfdBuilder::new().page("").open();
+        assert_eq!(doc.page_orientation(1), None);
+        doc.documents[0].common_data.page_area = None;
+        assert_eq!(doc.page_orientation(0), None);
+    }
+
+    fn date_time(y: i32, m: u32, d: u32, h: u32, min: u32, s: u32) -> Option<NaiveDateTime> {
+        NaiveDate::from_ymd_opt(y, m, d)?.and_hms_opt(h, min, s)
+    }
+
+    #[test]
+    fn parse_date_time_accepts_a_full_date_time() {
+        assert_eq!(parse_date_time("2020-01-02T10:11:12"), date_time(2020, 1, 2, 10, 11, 12));
+        assert_eq!(parse_date_time("2020-01-02 10:11:12"), date_time(2020, 1, 2, 10, 11, 12));
+    }
+
+    #[test]
+    fn parse_date_time_accepts_a_date_only_value() {
+        assert_eq!(parse_date_time("2020-01-02"), date_time(2020, 1, 2, 0, 0, 0));
+    }
+
+    #[test]
+    fn parse_date_time_drops_a_trailing_time_zone() {
+        assert_eq!(parse_date_time("2020-01-02T10:11:12Z"), date_time(2020, 1, 2, 10, 11, 12));
+        assert_eq!(parse_date_time("2020-01-02T10:11:12+08:00"), date_time(2020, 1, 2, 10, 11, 12));
+        assert_eq!(parse_date_time("2020-01-02T10:11:12-05:00"), date_time(2020, 1, 2, 10, 11, 12));
+    }
+
+    #[test]
+    fn parse_date_time_returns_none_for_empty_or_malformed_values() {
+        assert_eq!(parse_date_time(""), None);
+        assert_eq!(parse_date_time("  "), None);
+        assert_eq!(parse_date_time("yesterday"), None);
+    }
+
+    fn png_bytes(image: &RgbaImage) -> Vec<u8> {
+        let mut data = Cursor::new(Vec::new());
+        image.write_to(&mut data, image::ImageFormat::Png).unwrap();
+        data.into_inner()
+    }
+
+    #[test]
+    fn thumbnail_of_a_portrait_page_fits_the_height() {
+        let mut doc = OfdBuilder::new()
+            .page("")
+            .file("Doc_0/Pages/Page_0/Content.xml", page_content("", "0 0 30 40"))
+            .open();
+        let thumbnail = doc.thumbnail(0, 256).unwrap();
+        assert_eq!(thumbnail.height(), 256);
+        assert!(thumbnail.width() <= 256);
+    }
+
+    #[test]
+    fn thumbnail_prefers_the_embedded_thumbnail_for_the_first_page() {
+        let embedded = RgbaImage::from_pixel(60, 30, image::Rgba([255, 0, 0, 255]));
+        let mut doc = OfdBuilder::new()
+            .page("")
+            .page("")
+            .doc_body("<ofd:Thumbnail>Doc_0/Thumbnail.png</ofd:Thumbnail>")
+            .file("Doc_0/Thumbnail.png", png_bytes(&embedded))
+            .open();
+        let thumbnail = doc.thumbnail(0, 20).unwrap();
+        assert_eq!(thumbnail.dimensions(), (20, 10));
+        assert_eq!(thumbnail.get_pixel(10, 5), &image::Rgba([255, 0, 0, 255]));
+        // 其余页面仍然渲染，空白页面为白色
+        let rendered = doc.thumbnail(1, 20).unwrap();
+        assert_eq!(rendered.width(), 20);
+        assert_ne!(rendered.get_pixel(10, 5), &image::Rgba([255, 0, 0, 255]));
+    }
+
+    #[test]
+    fn thumbnail_renders_when_the_embedded_thumbnail_cannot_be_decoded() {
+        let mut doc = OfdBuilder::new()
+            .page("")
+            .doc_body("<ofd:Thumbnail>Doc_0/Thumbnail.png</ofd:Thumbnail>")
+            .file("Doc_0/Thumbnail.png", b"broken".to_vec())
+            .open();
+        let thumbnail = doc.thumbnail(0, 20).unwrap();
+        // 固定页面为 40×30 毫米
+        assert_eq!(thumbnail.dimensions(), (20, 15));
+    }
+
+    #[test]
+    fn render_all_pages_with_progress_reports_every_page() {
+        let calls = std::rc::Rc::new(std::cell::RefCell::new(Vec::new()));
+        let recorded = calls.clone();
+        let mut doc = OfdBuilder::new().page("").page("").page("").open();
+        let images = doc
+            .render_all_pages_with_progress(36.0, Some(Box::new(move |completed, total| {
+                recorded.borrow_mut().push((completed, total));
+            })))
+            .unwrap();
+        assert_eq!(images.len(), 3);
+        assert_eq!(*calls.borrow(), vec![(1, 3), (2, 3), (3, 3)]);
+    }
+
+    #[test]
+    fn render_all_with_progress_counts_each_page() {
+        let mut doc = OfdBuilder::new().page("").page("").open();
+        let mut count = 0;
+        doc.render_all_with_progress(&low_res(), |_, _| count += 1).unwrap();
+        assert_eq!(count, doc.page_count());
+    }
+
+    fn strict() -> OpenOptions {
+        OpenOptions { mode: ParseMode::Strict, ..OpenOptions::default() }
+    }
+
+    #[test]
+    fn render_all_with_progress_reports_the_failing_page_before_returning() {
+        // 第二页的内容文件是空的，严格模式下该页渲染失败
+        let mut doc = OfdBuilder::new()
+            .page("")
+            .page("")
+            .page("")
+            .file("Doc_0/Pages/Page_1/Content.xml", Vec::new())
+            .open_with(&strict());
+        let mut last = None;
+        let result = doc.render_all_with_progress(&low_res(), |completed, total| last = Some((completed, total)));
+        assert!(matches!(result, Err(OfdError::PageError(1, _))));
+        assert_eq!(last, Some((2, 3)));
+    }
+
+    #[test]
+    fn res_base_loc_is_the_base_directory_of_media_files() {
+        let mut doc = OfdBuilder::new()
+            .page("")
+            .file("Doc_0/PublicRes.xml", "<ofd:Res xmlns:ofd=\"http://www.ofdspec.org/2016\" BaseLoc=\"Image\">\
+                <ofd:MultiMedias><ofd:MultiMedia ID=\"5\" Type=\"Image\"><ofd:MediaFile>logo.png</ofd:MediaFile>\
+                </ofd:MultiMedia></ofd:MultiMedias></ofd:Res>")
+            .open();
+        let resources = doc.load_resources().unwrap();
+        assert_eq!(resources.media_file("5"), Some("Doc_0/Image/logo.png"));
+    }
+
+    /// 红色填充、蓝色描边的正方形，填充区域为页面上 (10, 5) 到 (30, 25) 毫米
+    const SQUARE: &str = "<ofd:PathObject ID=\"20\" Boundary=\"0 0 40 30\" Fill=\"true\" Stroke=\"true\" LineWidth=\"2\">\
+        <ofd:FillColor Value=\"255 0 0\"/><ofd:StrokeColor Value=\"0 0 255\"/>\
+        <ofd:AbbreviatedData>M 10 5 L 30 5 L 30 25 L 10 25 C</ofd:AbbreviatedData></ofd:PathObject>";
+
+    #[test]
+    fn scanlines_concatenate_to_the_full_render() {
+        let mut doc = OfdBuilder::new().page(SQUARE).open();
+        let full = doc.render_page(0, &low_res()).unwrap();
+        let mut rows = Vec::new();
+        let mut joined = Vec::new();
+        doc.render_page_scanlines(0, &low_res(), |y, row| {
+            rows.push(y);
+            joined.extend_from_slice(row);
+        }).unwrap();
+        assert_eq!(rows, (0..full.height()).collect::<Vec<_>>());
+        assert_eq!(joined, full.into_raw());
+    }
+
+    #[test]
+    fn streaming_delivers_each_page_once_in_order() {
+        let mut doc = OfdBuilder::new()
+            .page("")
+            .page("")
+            .page("")
+            .file("Doc_0/Pages/Page_1/Content.xml", page_content("", "0 0 30 40"))
+            .open();
+        let mut delivered = Vec::new();
+        doc.render_pages_streaming(&low_res(), |index, image| {
+            delivered.push((index, image.dimensions()));
+            Ok(())
+        }).unwrap();
+        let indices: Vec<usize> = delivered.iter().map(|(index, _)| *index).collect();
+        assert_eq!(indices, vec![0, 1, 2]);
+        // 第二页为纵向，宽高与其余两页相反
+        let (landscape, portrait) = (delivered[0].1, delivered[1].1);
+        assert!(landscape.0 > landscape.1);
+        assert_eq!(portrait, (landscape.1, landscape.0));
+        assert_eq!(delivered[2].1, landscape);
+    }
+
+    #[test]
+    fn streaming_stops_at_the_first_sink_error() {
+        let mut doc = OfdBuilder::new().page("").page("").page("").open();
+        let mut delivered = Vec::new();
+        let result = doc.render_pages_streaming(&low_res(), |index, _| {
+            delivered.push(index);
+            if index == 1 { Err(OfdError::InvalidPageIndex(index)) } else { Ok(()) }
+        });
+        assert!(matches!(result, Err(OfdError::InvalidPageIndex(1))));
+        assert_eq!(delivered, vec![0, 1]);
+    }
+
+    fn image_of_bytes(bytes: usize) -> Arc<RgbaImage> {
+        Arc::new(RgbaImage::new(bytes as u32 / 4, 1))
+    }
+
+    #[test]
+    fn image_cache_evicts_the_oldest_image_past_its_budget() {
+        let mut cache = ImageCache::new(100);
+        cache.insert("a", image_of_bytes(40));
+        cache.insert("b", image_of_bytes(40));
+        cache.insert("c", image_of_bytes(40));
+        assert!(cache.get("a").is_none());
+        assert!(cache.get("b").is_some());
+        assert!(cache.get("c").is_some());
+        assert_eq!(cache.bytes, 80);
+    }
+
+    #[test]
+    fn image_cache_skips_an_image_larger_than_its_budget() {
+        let mut cache = ImageCache::new(100);
+        cache.insert("a", image_of_bytes(40));
+        cache.insert("huge", image_of_bytes(200));
+        assert!(cache.get("huge").is_none());
+        assert!(cache.get("a").is_some());
+        cache.clear();
+        assert_eq!(cache.bytes, 0);
+        assert!(cache.get("a").is_none());
+    }
+
+    #[test]
+    fn decoded_images_are_shared_across_pages() {
+        let logo = RgbaImage::from_pixel(4, 4, image::Rgba([0, 128, 0, 255]));
+        let image_object = "<ofd:ImageObject ID=\"20\" Boundary=\"0 0 10 10\" CTM=\"10 0 0 10 0 0\" ResourceID=\"5\"/>";
+        let mut doc = OfdBuilder::new()
+            .resources("<ofd:MultiMedias><ofd:MultiMedia ID=\"5\" Type=\"Image\"><ofd:MediaFile>logo.png</ofd:MediaFile>\
+                </ofd:MultiMedia></ofd:MultiMedias>")
+            .file("Doc_0/Res/logo.png", png_bytes(&logo))
+            .page(image_object)
+            .page(image_object)
+            .open();
+        let resources = doc.load_resources().unwrap();
+        let page = doc.load_page(0).unwrap();
+        let first = doc.load_page_images(page.objects(), &resources).unwrap();
+        let page = doc.load_page(1).unwrap();
+        let second = doc.load_page_images(page.objects(), &resources).unwrap();
+        assert!(Arc::ptr_eq(&first["5"], &second["5"]));
+        assert_eq!(doc.image_cache.bytes, 4 * 4 * 4);
+    }
+
+    /// 以测试字体书写 HI 的文字对象
+    const TEXT: &str = "<ofd:TextObject ID=\"20\" Boundary=\"0 0 40 30\" Font=\"10\" Size=\"10\">\
+        <ofd:FillColor Value=\"0 0 0\"/><ofd:TextCode X=\"2\" Y=\"20\">HI</ofd:TextCode></ofd:TextObject>";
+
+    /// 页面中只有 TEXT 的文档，fs_type 为测试字体的嵌入许可
+    fn text_document(fs_type: Option<u16>) -> OfdBuilder {
+        OfdBuilder::new()
+            .resources("<ofd:Fonts><ofd:Font ID=\"10\" FontName=\"Fixture\"><ofd:FontFile>font.ttf</ofd:FontFile></ofd:Font></ofd:Fonts>")
+            .file("Doc_0/Res/font.ttf", fixture::truetype_font(fs_type))
+            .page(TEXT)
+    }
+
+    #[test]
+    fn pdf_text_from_an_embeddable_font_can_be_extracted() {
+        let pdf = text_document(None).open().to_pdf(&RenderOptions::default()).unwrap();
+        let text = pdf_extract::extract_text_from_mem(&pdf).unwrap();
+        assert!(text.contains("HI"), "extracted {:?}", text);
+    }
+
+    #[test]
+    fn pdf_text_from_a_restricted_font_is_written_as_outlines() {
+        // fsType 2 为受限许可，不允许嵌入
+        let pdf = text_document(Some(2)).open().to_pdf(&RenderOptions::default()).unwrap();
+        let text = pdf_extract::extract_text_from_mem(&pdf).unwrap();
+        assert!(!text.contains("HI"), "extracted {:?}", text);
+    }
+
+    /// XML 条目不超过 2048 字节，足以读取生成的 OFD.xml 与 Document.xml
+    fn small_xml_limit(mode: ParseMode) -> OpenOptions {
+        let read_limits = ReadLimits { max_xml_size: 2048, ..ReadLimits::default() };
+        OpenOptions { mode, read_limits, ..OpenOptions::default() }
+    }
+
+    #[test]
+    fn page_content_over_the_xml_limit_is_rejected() {
+        let mut doc = OfdBuilder::new().page(&SQUARE.repeat(20)).open_with(&small_xml_limit(ParseMode::Strict));
+        match doc.render_page(0, &low_res()) {
+            Err(OfdError::ResourceTooLarge { name, limit }) => {
+                assert_eq!(name, "Doc_0/Pages/Page_0/Content.xml");
+                assert_eq!(limit, 2048);
+            }
+            other => panic!("expected ResourceTooLarge, got {:?}", other.map(|image| image.dimensions())),
+        }
+    }
+
+    #[test]
+    fn page_content_over_the_xml_limit_is_skipped_in_lenient_mode() {
+        let mut doc = OfdBuilder::new().page(&SQUARE.repeat(20)).open_with(&small_xml_limit(ParseMode::Lenient));
+        assert!(doc.render_page(0, &low_res()).is_ok());
+        assert_eq!(doc.warnings().len(), 1);
+        assert_eq!(doc.warnings()[0].entry, "Doc_0/Pages/Page_0/Content.xml");
+    }
+
+    #[test]
+    fn diff_page_of_the_same_document_has_no_changes() {
+        let builder = OfdBuilder::new().page(SQUARE);
+        let (mut doc, mut same) = (builder.open(), builder.open());
+        let diff = doc.diff_page(&mut same, 0, &low_res()).unwrap();
+        assert_eq!(diff.changed_pixels, 0);
+        assert!(diff.total_pixels > 0);
+        assert!(!diff.size_mismatch);
+    }
+
+    #[test]
+    fn diff_page_counts_the_changed_pixels_of_a_modified_copy() {
+        let mut doc = OfdBuilder::new().page(SQUARE).open();
+        let mut modified = OfdBuilder::new().page(&SQUARE.replace("255 0 0", "0 255 0")).open();
+        let diff = doc.diff_page(&mut modified, 0, &low_res()).unwrap();
+        assert!(diff.changed_pixels > 0);
+        assert!(diff.changed_pixels < diff.total_pixels);
+        assert_eq!(diff.diff_image.dimensions(), doc.render_page(0, &low_res()).unwrap().dimensions());
+    }
+
+    /// 每毫米一个像素，页面为 40 × 30 像素
+    fn pixel_per_mm() -> RenderOptions {
+        RenderOptions { target: RenderTarget::Dpi(25.4), ..RenderOptions::default() }
+    }
+
+    #[test]
+    fn path_is_filled_then_stroked() {
+        let image = OfdBuilder::new().page(SQUARE).open().render_page(0, &pixel_per_mm()).unwrap();
+        assert_eq!(image.dimensions(), (40, 30));
+        // 内部为填充色，左边线 x = 10 处为 2 毫米宽的描边，覆盖在填充之上
+        assert_eq!(image.get_pixel(20, 15), &image::Rgba([255, 0, 0, 255]));
+        assert_eq!(image.get_pixel(10, 15), &image::Rgba([0, 0, 255, 255]));
+        assert_eq!(image.get_pixel(9, 15), &image::Rgba([0, 0, 255, 255]));
+        assert_ne!(image.get_pixel(5, 15), &image::Rgba([0, 0, 255, 255]));
+    }
+
+    #[test]
+    fn path_without_fill_or_stroke_is_skipped() {
+        let hidden = SQUARE.replace("Fill=\"true\" Stroke=\"true\"", "Fill=\"false\" Stroke=\"false\"");
+        let blank = OfdBuilder::new().page("").open().render_page(0, &pixel_per_mm()).unwrap();
+        let image = OfdBuilder::new().page(&hidden).open().render_page(0, &pixel_per_mm()).unwrap();
+        assert_eq!(image, blank);
+    }
+
+    #[test]
+    fn truncated_page_xml_error_names_the_page_entry() {
+        let mut doc = OfdBuilder::new()
+            .page(SQUARE)
+            .file("Doc_0/Pages/Page_0/Content.xml", "<ofd:Page xmlns:ofd=\"http://www.ofdspec.org/2016\"><ofd:Content><ofd:Layer ID=\"1\">")
+            .open_with(&strict());
+        match doc.render_page(0, &low_res()) {
+            Err(OfdError::XmlParse { entry, snippet, .. }) => {
+                assert_eq!(entry, "Doc_0/Pages/Page_0/Content.xml");
+                assert!(snippet.contains("ofd:Layer"), "snippet {:?}", snippet);
+            }
+            other => panic!("expected XmlParse, got {:?}", other.map(|image| image.dimensions())),
+        }
+    }
+
+    #[test]
+    fn truncated_annotations_error_names_the_annotations_entry() {
+        let mut doc = OfdBuilder::new()
+            .page("")
+            .document("<ofd:Annotations>Annots/Annotations.xml</ofd:Annotations>")
+            .file("Doc_0/Annots/Annotations.xml", "<ofd:Annotations xmlns:ofd=\"http://www.ofdspec.org/2016\"><ofd:Page PageID=\"1\">")
+            .open();
+        assert!(matches!(doc.links(), Err(OfdError::XmlParse { entry, .. }) if entry == "Doc_0/Annots/Annotations.xml"));
+    }
+
+    #[cfg(feature = "modern-formats")]
+    #[test]
+    fn encode_page_as_webp_decodes_to_the_rendered_size() {
+        let mut doc = OfdBuilder::new().page(SQUARE).open();
+        let data = doc.encode_page(0, ImageFormat::WebP, 75, &pixel_per_mm()).unwrap();
+        let decoded = image::load_from_memory_with_format(&data, image::ImageFormat::WebP).unwrap();
+        assert_eq!((decoded.width(), decoded.height()), (40, 30));
+    }
+
+    /// 引用了无法解码的图像的页面
+    fn corrupt_image_document(options: &OpenOptions) -> OfdDoc {
+        OfdBuilder::new()
+            .resources("<ofd:MultiMedias><ofd:MultiMedia ID=\"5\" Type=\"Image\"><ofd:MediaFile>logo.png</ofd:MediaFile>\
+                </ofd:MultiMedia></ofd:MultiMedias>")
+            .file("Doc_0/Res/logo.png", b"not an image".to_vec())
+            .page("<ofd:ImageObject ID=\"20\" Boundary=\"0 0 10 10\" CTM=\"10 0 0 10 0 0\" ResourceID=\"5\"/>")
+            .open_with(options)
+    }
+
+    #[test]
+    fn corrupt_image_is_skipped_with_a_warning_in_lenient_mode() {
+        let mut doc = corrupt_image_document(&OpenOptions::default());
+        let image = doc.render_page(0, &pixel_per_mm()).unwrap();
+        assert_eq!((image.width(), image.height()), (40, 30));
+        assert_eq!(doc.warnings().len(), 1);
+        assert_eq!(doc.warnings()[0].entry, "Doc_0/Res/logo.png");
+    }
+
+    #[test]
+    fn corrupt_image_fails_the_page_in_strict_mode() {
+        let mut doc = corrupt_image_document(&strict());
+        assert!(doc.render_page(0, &pixel_per_mm()).is_err());
+    }
+
+    #[test]
+    fn unknown_image_resource_is_skipped_with_a_warning_in_lenient_mode() {
+        let mut doc = OfdBuilder::new()
+            .page("<ofd:ImageObject ID=\"20\" Boundary=\"0 0 10 10\" CTM=\"10 0 0 10 0 0\" ResourceID=\"99\"/>")
+            .open();
+        assert!(doc.render_page(0, &pixel_per_mm()).is_ok());
+        assert_eq!(doc.warnings()[0].entry, "99");
+    }
+
+    #[test]
+    fn reused_buffer_matches_fresh_renders() {
+        let mut doc = OfdBuilder::new().page(SQUARE).page("").page(SQUARE).open();
+        let opts = pixel_per_mm();
+        let mut buffer = Vec::new();
+        for page_index in 0..doc.page_count() {
+            let size = doc.render_page_into(page_index, &opts, &mut buffer).unwrap();
+            let fresh = doc.render_page(page_index, &opts).unwrap();
+            assert_eq!(size, fresh.dimensions());
+            assert_eq!(buffer, fresh.into_raw());
+        }
+    }
+
+    #[test]
+    fn reused_buffer_keeps_its_allocation_for_same_size_pages() {
+        let mut doc = OfdBuilder::new().page(SQUARE).page(SQUARE).open();
+        let mut buffer = Vec::new();
+        doc.render_page_into(0, &pixel_per_mm(), &mut buffer).unwrap();
+        let pointer = buffer.as_ptr();
+        doc.render_page_into(1, &pixel_per_mm(), &mut buffer).unwrap();
+        assert_eq!(buffer.as_ptr(), pointer);
+        assert_eq!(buffer.len(), 40 * 30 * 4);
+    }
+
+    #[test]
+    fn svg_goto_links_resolve_to_page_ids() {
+        let link = "<ofd:PathObject ID=\"20\" Boundary=\"0 0 40 30\" Fill=\"true\">\
+            <ofd:Actions><ofd:Action Event=\"CLICK\"><ofd:Goto><ofd:Dest Type=\"XYZ\" PageID=\"2\"/></ofd:Goto></ofd:Action></ofd:Actions>\
+            <ofd:AbbreviatedData>M 10 5 L 30 5 L 30 25 L 10 25 C</ofd:AbbreviatedData></ofd:PathObject>";
+        let mut doc = OfdBuilder::new().page(link).page("").open();
+        let svgs = doc.to_svg_all(&RenderOptions::default()).unwrap();
+        assert!(svgs[0].contains("id=\"ofd-page-1\""));
+        assert!(svgs[0].contains("href=\"#ofd-page-2\""));
+        assert!(svgs[1].contains("id=\"ofd-page-2\""));
+    }
+
+    #[test]
+    fn clean_document_passes_validation() {
+        let mut doc = text_document(None).page(SQUARE).open();
+        assert!(doc.validate().is_ok());
+    }
+
+    #[test]
+    fn dangling_font_is_reported_once() {
+        let mut doc = OfdBuilder::new().page(TEXT).page(SQUARE).open();
+        let issues = doc.validate().unwrap_err();
+        assert_eq!(issues.len(), 1, "{:?}", issues);
+        assert_eq!(issues[0].kind, ValidationIssueKind::DanglingFont);
+        assert!(issues[0].message.contains("10"));
+    }
+
+    #[test]
+    fn max_unit_id_below_the_ids_in_use_is_reported() {
+        let mut doc = OfdBuilder::new().page(SQUARE).max_unit_id(5).open();
+        let issues = doc.validate().unwrap_err();
+        assert!(issues.iter().any(|issue| issue.kind == ValidationIssueKind::MaxUnitIdTooSmall));
+    }
+
+    /// 斜边与文字的边缘都会产生部分覆盖的像素
+    fn alpha_values(antialias: AntiAlias) -> HashSet<u8> {
+        let triangle = "<ofd:PathObject ID=\"30\" Boundary=\"0 0 40 30\" Fill=\"true\">\
+            <ofd:AbbreviatedData>M 5 5 L 35 5 L 5 25 C</ofd:AbbreviatedData></ofd:PathObject>";
+        let mut doc = OfdBuilder::new()
+            .resources("<ofd:Fonts><ofd:Font ID=\"10\" FontName=\"Fixture\"><ofd:FontFile>font.ttf</ofd:FontFile></ofd:Font></ofd:Fonts>")
+            .file("Doc_0/Res/font.ttf", fixture::truetype_font(None))
+            .page(&format!("{}{}", TEXT, triangle))
+            .open();
+        let opts = RenderOptions { background: None, antialias, ..pixel_per_mm() };
+        doc.render_page(0, &opts).unwrap().pixels().map(|p| p[3]).collect()
+    }
+
+    #[test]
+    fn antialias_off_renders_only_opaque_or_transparent_pixels() {
+        let alphas = alpha_values(AntiAlias::Off);
+        assert!(alphas.iter().all(|a| *a == 0 || *a == 255), "{:?}", alphas);
+        assert!(alphas.contains(&255));
+    }
+
+    #[test]
+    fn antialias_on_renders_partially_covered_pixels() {
+        assert!(alpha_values(AntiAlias::On).iter().any(|a| *a != 0 && *a != 255));
+    }
+
+    /// 以测试字体书写 HI、水平缩放为 h_scale 的页面中着墨像素的列数
+    fn ink_width(h_scale: f64) -> usize {
+        let text = TEXT.replace("Size=\"10\"", &format!("Size=\"10\" HScale=\"{}\"", h_scale));
+        let mut doc = OfdBuilder::new()
+            .resources("<ofd:Fonts><ofd:Font ID=\"10\" FontName=\"Fixture\"><ofd:FontFile>font.ttf</ofd:FontFile></ofd:Font></ofd:Fonts>")
+            .file("Doc_0/Res/font.ttf", fixture::truetype_font(None))
+            .page(&text)
+            .open();
+        let opts = RenderOptions { background: None, ..pixel_per_mm() };
+        let image = doc.render_page(0, &opts).unwrap();
+        (0..image.width()).filter(|x| (0..image.height()).any(|y| image.get_pixel(*x, y)[3] > 128)).count()
+    }
+
+    #[test]
+    fn condensed_text_renders_narrower() {
+        let normal = ink_width(1.0);
+        let condensed = ink_width(0.5);
+        assert!(normal > 0);
+        assert!(condensed * 3 < normal * 2, "condensed {} vs normal {}", condensed, normal);
+    }
+
+    #[test]
+    fn svg_scales_each_glyph_of_condensed_text() {
+        let text = TEXT.replace("Size=\"10\"", "Size=\"10\" HScale=\"0.5\"");
+        let mut doc = OfdBuilder::new().page(&text).open();
+        let svg = doc.render_page_svg(0, &RenderOptions::default()).unwrap();
+        assert_eq!(svg.matches("scale(0.5 1)").count(), 2);
+        assert!(svg.contains("translate(2 20)") && svg.contains("translate(7 20)"), "{}", svg);
+    }
+
+    /// 含 count 个矩形路径的页面
+    fn many_objects(count: usize) -> String {
+        (0..count)
+            .map(|i| format!("<ofd:PathObject ID=\"{}\" Boundary=\"{} 0 1 1\" Fill=\"true\"><ofd:AbbreviatedData>M 0 0 L 1 0 L 1 1 C</ofd:AbbreviatedData></ofd:PathObject>", i + 1, i % 40))
+            .collect()
+    }
+
+    #[test]
+    fn presized_objects_match_and_never_grow() {
+        let mut doc = OfdBuilder::new().page(&many_objects(2000)).max_unit_id(3000).open();
+        let page = doc.read_page(0).unwrap();
+        let capacity = doc.objects_capacity();
+        assert_eq!(capacity, 3000);
+        let presized = page.objects_with_capacity(capacity);
+        // 容量不变即收集过程中没有重新分配
+        assert_eq!(presized.capacity(), capacity);
+        let ids: Vec<&str> = presized.iter().map(|o| o.id()).collect();
+        assert_eq!(ids, page.objects().iter().map(|o| o.id()).collect::<Vec<_>>());
+        assert_eq!(ids.len(), 2000);
+    }
+
+    #[test]
+    fn object_capacity_is_capped_for_large_max_unit_ids() {
+        let doc = OfdBuilder::new().page(SQUARE).max_unit_id(1_000_000).open();
+        assert_eq!(doc.objects_capacity(), 4096);
+    }
+
+    #[test]
+    fn presizing_leaves_rendering_unchanged() {
+        let objects = many_objects(50);
+        let small = OfdBuilder::new().page(&objects).max_unit_id(1000).open().render_page(0, &pixel_per_mm()).unwrap();
+        let large = OfdBuilder::new().page(&objects).max_unit_id(1_000_000).open().render_page(0, &pixel_per_mm()).unwrap();
+        assert_eq!(small, large);
+    }
+
+    #[test]
+    fn validate_finds_percent_encoded_entries() {
+        let mut doc = text_document(None).page(SQUARE).percent_encoded().open();
+        assert!(doc.validate().is_ok(), "{:?}", doc.validate());
+    }
+
+    #[test]
+    fn validate_reports_a_missing_page_entry() {
+        let mut doc = OfdBuilder::new().page(SQUARE).open();
+        doc.documents[0].pages.page[0].base_loc = "Pages/Page_9/Content.xml".to_string();
+        let issues = doc.validate().unwrap_err();
+        assert_eq!(issues.len(), 1, "{:?}", issues);
+        assert_eq!(issues[0].kind, ValidationIssueKind::MissingEntry);
+    }
+
+    #[test]
+    fn color_histogram_counts_fill_and_stroke_colors() {
+        let red = "<ofd:PathObject ID=\"21\" Boundary=\"0 0 40 30\" Fill=\"true\" Stroke=\"false\"><ofd:FillColor Value=\"255 0 0\"/>\
+            <ofd:AbbreviatedData>M 0 0 L 4 0 L 4 4 C</ofd:AbbreviatedData></ofd:PathObject>";
+        let mut doc = OfdBuilder::new().page(SQUARE).page(red).open();
+        assert_eq!(doc.color_histogram().unwrap(), vec![([255, 0, 0], 2), ([0, 0, 255], 1)]);
+    }
+
+    #[test]
+    fn page_count_and_refs_skip_template_pages() {
+        let doc = OfdBuilder::new().template(SQUARE, "Background").page("").page("").page("").open();
+        assert_eq!(doc.page_count(), 3);
+        let refs: Vec<PageInfo> = doc.page_refs().collect();
+        assert_eq!(refs.iter().map(|r| r.id.as_str()).collect::<Vec<_>>(), ["1", "2", "3"]);
+        assert_eq!(refs[2].location, "Doc_0/Pages/Page_2/Content.xml");
+        assert!(refs.iter().all(|r| !r.location.contains("Tpls")));
+    }
+
+    #[test]
+    fn highlighting_covers_only_the_object_boundary() {
+        let target = "<ofd:PathObject ID=\"21\" Boundary=\"5 5 10 10\" Fill=\"true\" Stroke=\"false\">\
+            <ofd:FillColor Value=\"0 0 255\"/><ofd:AbbreviatedData>M 0 0 L 10 0 L 10 10 L 0 10 C</ofd:AbbreviatedData></ofd:PathObject>";
+        let mut doc = OfdBuilder::new().page(target).open();
+        let plain = doc.render_page(0, &pixel_per_mm()).unwrap();
+        let highlighted = doc.render_page_highlighting(0, &["21".to_string()], [0, 255, 0, 255], &pixel_per_mm()).unwrap();
+        for (x, y, pixel) in highlighted.enumerate_pixels() {
+            let inside = (5..15).contains(&x) && (5..15).contains(&y);
+            if inside {
+                assert_eq!(pixel.0, [0, 255, 0, 255], "({}, {})", x, y);
+            } else if !(4..16).contains(&x) || !(4..16).contains(&y) {
+                assert_eq!(pixel, plain.get_pixel(x, y), "({}, {})", x, y);
+            }
+        }
+    }
+
+    /// 替换测试包中的 Doc_0/Document.xml，common_data 追加在 CommonData 末尾，pages 为 Pages 的内容
+    fn document_xml(common_data: &str, pages: &str) -> String {
+        format!(
+            "<?xml version=\"1.0\" encoding=\"UTF-8\"?><ofd:Document xmlns:ofd=\"http://www.ofdspec.org/2016\"><ofd:CommonData>\
+             <ofd:PageArea><ofd:PhysicalBox>0 0 40 30</ofd:PhysicalBox></ofd:PageArea>\
+             <ofd:PublicRes>PublicRes.xml</ofd:PublicRes><ofd:MaxUnitID>1000</ofd:MaxUnitID>{}\
+             </ofd:CommonData><ofd:Pages>{}</ofd:Pages></ofd:Document>",
+            common_data, pages,
+        )
+    }
+
+    /// 填满页面、颜色为 rgb 的路径
+    fn filled_page(rgb: &str) -> String {
+        format!("<ofd:PathObject ID=\"20\" Boundary=\"0 0 40 30\" Fill=\"true\" Stroke=\"false\"><ofd:FillColor Value=\"{}\"/>\
+            <ofd:AbbreviatedData>M 0 0 L 40 0 L 40 30 L 0 30 C</ofd:AbbreviatedData></ofd:PathObject>", rgb)
+    }
+
+    #[test]
+    fn pages_render_in_index_order() {
+        let pages = "<ofd:Page ID=\"3\" BaseLoc=\"Pages/Page_2/Content.xml\" Index=\"2\"/>\
+            <ofd:Page ID=\"1\" BaseLoc=\"Pages/Page_0/Content.xml\" Index=\"0\"/>\
+            <ofd:Page ID=\"2\" BaseLoc=\"Pages/Page_1/Content.xml\" Index=\"1\"/>";
+        let mut doc = OfdBuilder::new()
+            .page(&filled_page("255 0 0"))
+            .page(&filled_page("0 255 0"))
+            .page(&filled_page("0 0 255"))
+            .file("Doc_0/Document.xml", document_xml("", pages))
+            .open();
+        let colors: Vec<[u8; 4]> = (0..3).map(|i| doc.render_page(i, &pixel_per_mm()).unwrap().get_pixel(20, 15).0).collect();
+        assert_eq!(colors, [[255, 0, 0, 255], [0, 255, 0, 255], [0, 0, 255, 255]]);
+        assert_eq!(doc.page_refs().map(|r| r.id).collect::<Vec<_>>(), ["1", "2", "3"]);
+    }
+
+    #[test]
+    fn page_size_falls_back_to_the_document_page_area() {
+        let mut doc = OfdBuilder::new().page(SQUARE).open();
+        let size = doc.page_size(0).unwrap();
+        assert_eq!((size.x, size.y, size.w, size.h), (0.0, 0.0, 40.0, 30.0));
+        assert_eq!(doc.render_page(0, &pixel_per_mm()).unwrap().dimensions(), (40, 30));
+    }
+
+    #[test]
+    fn page_area_overrides_the_document_page_area() {
+        let mut doc = OfdBuilder::new()
+            .page("")
+            .file("Doc_0/Pages/Page_0/Content.xml", page_content("", "0 0 20 50"))
+            .open();
+        let size = doc.page_size(0).unwrap();
+        assert_eq!((size.w, size.h), (20.0, 50.0));
+        assert_eq!(doc.render_page(0, &pixel_per_mm()).unwrap().dimensions(), (20, 50));
+    }
+
+    /// 以 xml-rs 解析 SVG 文本，返回各元素的名称（不含前缀），文本不是合法的 XML 时失败
+    fn svg_elements(svg: &str) -> Vec<String> {
+        EventReader::from_str(svg)
+            .into_iter()
+            .filter_map(|event| match event.unwrap() {
+                XmlEvent::StartElement { name, .. } => Some(name.local_name),
+                _ => None,
+            })
+            .collect()
+    }
+
+    #[test]
+    fn to_svg_all_returns_one_svg_per_page() {
+        let mut doc = OfdBuilder::new().page(SQUARE).page("").page(TEXT).open();
+        let svgs = doc.to_svg_all(&RenderOptions::default()).unwrap();
+        assert_eq!(svgs.len(), doc.page_count());
+        for svg in &svgs {
+            assert_eq!(svg_elements(svg).first().map(String::as_str), Some("svg"));
+        }
+    }
+
+    #[test]
+    fn embedded_png_is_drawn_at_its_boundary() {
+        let mut logo = RgbaImage::new(2, 2);
+        logo.put_pixel(0, 0, image::Rgba([255, 0, 0, 255]));
+        logo.put_pixel(1, 0, image::Rgba([0, 255, 0, 255]));
+        logo.put_pixel(0, 1, image::Rgba([0, 0, 255, 255]));
+        logo.put_pixel(1, 1, image::Rgba([255, 255, 255, 255]));
+        let mut doc = OfdBuilder::new()
+            .resources("<ofd:MultiMedias><ofd:MultiMedia ID=\"5\" Type=\"Image\"><ofd:MediaFile>logo.png</ofd:MediaFile>\
+                </ofd:MultiMedia></ofd:MultiMedias>")
+            .file("Doc_0/Res/logo.png", png_bytes(&logo))
+            .page("<ofd:ImageObject ID=\"20\" Boundary=\"10 10 10 10\" CTM=\"10 0 0 10 0 0\" ResourceID=\"5\"/>")
+            .open();
+        let image = doc.render_page(0, &pixel_per_mm()).unwrap();
+        assert_eq!(image.get_pixel(12, 12).0, [255, 0, 0, 255]);
+        assert_eq!(image.get_pixel(17, 12).0, [0, 255, 0, 255]);
+        assert_eq!(image.get_pixel(12, 17).0, [0, 0, 255, 255]);
+        assert_eq!(image.get_pixel(17, 17).0, [255, 255, 255, 255]);
+        assert_eq!(image.get_pixel(5, 5).0, [255, 255, 255, 255]);
+    }
+
+    /// 替换测试包中的 OFD.xml，doc_info 为 DocInfo 的内容
+    fn ofd_xml(doc_info: &str) -> String {
+        format!(
+            "<?xml version=\"1.0\" encoding=\"UTF-8\"?><ofd:OFD xmlns:ofd=\"http://www.ofdspec.org/2016\" DocType=\"OFD\" Version=\"1.0\">\
+             <ofd:DocBody><ofd:DocInfo><ofd:DocID>fixture</ofd:DocID>{}</ofd:DocInfo>\
+             <ofd:DocRoot>Doc_0/Document.xml</ofd:DocRoot></ofd:DocBody></ofd:OFD>",
+            doc_info,
+        )
+    }
+
+    #[test]
+    fn doc_usage_reads_known_and_unknown_values() {
+        let usage = |value: &str| {
+            OfdBuilder::new().page("").file("OFD.xml", ofd_xml(&format!("<ofd:DocUsage>{}</ofd:DocUsage>", value))).open().doc_usage()
+        };
+        assert_eq!(usage("Normal"), DocUsage::Normal);
+        assert_eq!(usage("EBook"), DocUsage::EBook);
+        assert_eq!(usage("Brochure"), DocUsage::Other("Brochure".to_string()));
+        assert_eq!(OfdBuilder::new().page("").open().doc_usage(), DocUsage::Normal);
+    }
+
+    /// 带有页面区域 area（ofd:Area 的内容）与正文层图元 objects 的页面内容
+    fn page_with_area(area: &str, objects: &str) -> String {
+        format!(
+            "<ofd:Page xmlns:ofd=\"http://www.ofdspec.org/2016\"><ofd:Area>{}</ofd:Area>\
+             <ofd:Content><ofd:Layer ID=\"900\" Type=\"Body\">{}</ofd:Layer></ofd:Content></ofd:Page>",
+            area, objects,
+        )
+    }
+
+    #[test]
+    fn print_marks_are_drawn_outside_the_trim_box() {
+        let area = "<ofd:PhysicalBox>0 0 40 30</ofd:PhysicalBox><ofd:ContentBox>10 10 20 10</ofd:ContentBox>";
+        let mut doc = OfdBuilder::new()
+            .page("")
+            .file("Doc_0/Pages/Page_0/Content.xml", page_with_area(area, ""))
+            .open();
+        // 每毫米 10 像素
+        let opts = RenderOptions { target: RenderTarget::Dpi(254.0), print_marks: true, ..RenderOptions::default() };
+        let image = doc.render_page(0, &opts).unwrap();
+        let dark = |x: u32, y: u32| image.get_pixel(x, y).0[0] < 128;
+        // 版心左上角与右下角的裁切线，距版心 1 毫米，长 5 毫米
+        assert!(dark(99, 60) && dark(100, 60));
+        assert!(dark(60, 99) && dark(60, 100));
+        assert!(dark(299, 240) && dark(340, 199));
+        assert!(!dark(99, 95) && !dark(200, 150));
+        let plain = doc.render_page(0, &RenderOptions { print_marks: false, ..opts }).unwrap();
+        assert!(plain.pixels().all(|p| p.0 == [255, 255, 255, 255]));
+    }
+
+    #[test]
+    fn text_glyphs_sit_on_the_baseline() {
+        let mut doc = text_document(None).open();
+        let image = doc.render_page(0, &pixel_per_mm()).unwrap();
+        let dark = |x: u32, y: u32| image.get_pixel(x, y).0[0] < 128;
+        // 测试字体的字形宽 5 毫米、高 7 毫米，基线 y = 20，第一个字形从 x = 2.5 开始，第二个从 8.5 开始
+        assert!(dark(4, 19) && dark(4, 14) && dark(10, 19));
+        assert!(!dark(4, 21) && !dark(4, 12) && !dark(1, 19) && !dark(14, 19));
+    }
+
+    #[test]
+    fn background_template_is_drawn_under_page_text() {
+        let mut doc = text_document(None).template(SQUARE, "Background").open();
+        let image = doc.render_page(0, &pixel_per_mm()).unwrap();
+        // 模板的红色矩形与页面的文字都可见，两者重叠处文字在上
+        assert_eq!(image.get_pixel(25, 15).0, [255, 0, 0, 255]);
+        assert_eq!(image.get_pixel(4, 17).0, [0, 0, 0, 255]);
+        assert_eq!(image.get_pixel(11, 17).0, [0, 0, 0, 255]);
+    }
+
+    #[test]
+    fn dashed_stroke_alternates_ink_and_gaps() {
+        let line = "<ofd:PathObject ID=\"20\" Boundary=\"0 0 40 30\" LineWidth=\"2\" DashPattern=\"4 4\">\
+            <ofd:AbbreviatedData>M 0 15 L 40 15</ofd:AbbreviatedData></ofd:PathObject>";
+        let mut doc = OfdBuilder::new().page(line).open();
+        let image = doc.render_page(0, &pixel_per_mm()).unwrap();
+        let inked: Vec<bool> = (0..40).step_by(2).map(|x| image.get_pixel(x + 1, 15).0[0] < 128).collect();
+        // 每 2 毫米取样一次，4 毫米的线段与 4 毫米的间隔交替出现
+        let expected: Vec<bool> = (0..20).map(|i| i % 4 < 2).collect();
+        assert_eq!(inked, expected);
+    }
+
+    /// 覆盖 x 方向 [x, x + w)、整个页面高度，颜色为 rgb 并带有额外属性 attributes 的矩形
+    fn band(id: u32, x: f64, w: f64, rgb: &str, attributes: &str) -> String {
+        format!(
+            "<ofd:PathObject ID=\"{}\" Boundary=\"{} 0 {} 30\" Fill=\"true\" Stroke=\"false\" {}><ofd:FillColor Value=\"{}\"/>\
+             <ofd:AbbreviatedData>M 0 0 L {} 0 L {} 30 L 0 30 C</ofd:AbbreviatedData></ofd:PathObject>",
+            id, x, w, attributes, rgb, w, w,
+        )
+    }
+
+    #[test]
+    fn multiply_gray_darkens_the_content_underneath() {
+        let objects = format!("{}{}", band(20, 0.0, 20.0, "255 0 0", ""), band(21, 10.0, 30.0, "128 128 128", "BlendMode=\"Multiply\""));
+        let mut doc = OfdBuilder::new().page(&objects).open();
+        let image = doc.render_page(0, &pixel_per_mm()).unwrap();
+        // 红色与灰色相乘为暗红色，而不是被灰色覆盖
+        let [r, g, b, _] = image.get_pixel(15, 15).0;
+        assert!((126..=130).contains(&r) && g == 0 && b == 0, "{:?}", (r, g, b));
+        assert_eq!(image.get_pixel(5, 15).0, [255, 0, 0, 255]);
+        assert_eq!(image.get_pixel(30, 15).0, [128, 128, 128, 255]);
+    }
+
+    #[test]
+    fn screen_gray_lightens_the_content_underneath() {
+        let objects = format!("{}{}", band(20, 0.0, 20.0, "255 0 0", ""), band(21, 10.0, 30.0, "128 128 128", "BlendMode=\"Screen\""));
+        let mut doc = OfdBuilder::new().page(&objects).open();
+        let image = doc.render_page(0, &pixel_per_mm()).unwrap();
+        // 滤色使红色变浅为粉红色；白色背景上滤色后仍为白色
+        let [r, g, b, _] = image.get_pixel(15, 15).0;
+        assert!(r == 255 && (126..=130).contains(&g) && g == b, "{:?}", (r, g, b));
+        assert_eq!(image.get_pixel(5, 15).0, [255, 0, 0, 255]);
+        assert_eq!(image.get_pixel(30, 15).0, [255, 255, 255, 255]);
+
+        // 不支持的混合模式按 Normal 绘制并记录警告
+        let unknown = objects.replace("BlendMode=\"Screen\"", "BlendMode=\"Luminosity\"");
+        let mut doc = OfdBuilder::new().page(&unknown).open();
+        let (image, logs) = fixture::capture_logs(|| doc.render_page(0, &pixel_per_mm()).unwrap());
+        assert_eq!(image.get_pixel(15, 15).0, [128, 128, 128, 255]);
+        assert!(logs.contains(&(log::Level::Warn, "unsupported blend mode Luminosity, fallback to Normal".to_string())), "got {:?}", logs);
+    }
+
+    #[test]
+    fn clip_to_content_box_removes_content_outside_it() {
+        let area = "<ofd:PhysicalBox>0 0 40 30</ofd:PhysicalBox><ofd:ContentBox>10 10 20 10</ofd:ContentBox>";
+        let mut doc = OfdBuilder::new()
+            .page("")
+            .file("Doc_0/Pages/Page_0/Content.xml", page_with_area(area, &filled_page("255 0 0")))
+            .open();
+        let unclipped = doc.render_page(0, &pixel_per_mm()).unwrap();
+        assert_eq!(unclipped.get_pixel(5, 5).0, [255, 0, 0, 255]);
+        let opts = RenderOptions { clip_to_content_box: true, ..pixel_per_mm() };
+        let clipped = doc.render_page(0, &opts).unwrap();
+        assert_eq!(clipped.get_pixel(15, 15).0, [255, 0, 0, 255]);
+        for (x, y) in [(5, 5), (35, 25), (15, 5), (5, 15)] {
+            assert_eq!(clipped.get_pixel(x, y).0, [255, 255, 255, 255], "({}, {})", x, y);
+        }
+    }
+
+    #[test]
+    fn fill_rule_decides_whether_the_overlap_is_filled() {
+        // 两个同向的嵌套矩形，中心处绕数为 2
+        let center = |rule: &str| {
+            let path = format!(
+                "<ofd:PathObject ID=\"20\" Boundary=\"0 0 40 30\" Fill=\"true\" Stroke=\"false\" Rule=\"{}\">\
+                 <ofd:AbbreviatedData>M 5 5 L 35 5 L 35 25 L 5 25 C M 15 10 L 25 10 L 25 20 L 15 20 C</ofd:AbbreviatedData></ofd:PathObject>",
+                rule,
+            );
+            let image = OfdBuilder::new().page(&path).open().render_page(0, &pixel_per_mm()).unwrap();
+            assert_eq!(image.get_pixel(8, 8).0, [0, 0, 0, 255]);
+            image.get_pixel(20, 15).0
+        };
+        assert_eq!(center("NonZero"), [0, 0, 0, 255]);
+        assert_eq!(center("Even-Odd"), [255, 255, 255, 255]);
+    }
+
+    #[test]
+    fn searchable_pdf_text_matches_page_text() {
+        let mut doc = text_document(None).page(&TEXT.replace(">HI<", ">OK<")).open();
+        let pdf = doc.to_searchable_pdf(&low_res()).unwrap();
+        let words = |text: &str| text.split_whitespace().collect::<Vec<_>>().concat();
+        let expected: String = (0..doc.page_count()).map(|i| words(&doc.page_text(i).unwrap())).collect();
+        assert_eq!(expected, "HIOK");
+        assert_eq!(words(&pdf_extract::extract_text_from_mem(&pdf).unwrap()), expected);
+    }
+
+    #[test]
+    fn render_all_renders_every_page() {
+        let mut doc = OfdBuilder::new().page(SQUARE).page(&filled_page("0 0 255")).open();
+        let images = doc.render_all(25.4).unwrap();
+        assert_eq!(images.len(), 2);
+        for (index, image) in images.iter().enumerate() {
+            assert!(image.width() > 0 && image.height() > 0, "page {} is empty", index);
+            assert_eq!(image, &doc.render_page(index, &pixel_per_mm()).unwrap());
+        }
+    }
+
+    #[test]
+    fn parallel_rendering_matches_serial_rendering() {
+        let mut doc = text_document(None).page(SQUARE).page(&filled_page("0 128 0")).page(TEXT).open();
+        let serial = doc.render_all(36.0).unwrap();
+        let parallel = doc.render_all_parallel(36.0).unwrap();
+        assert_eq!(parallel.len(), 4);
+        assert!(serial == parallel, "parallel rendering differs from serial rendering");
+    }
+
+    #[test]
+    fn object_repeated_from_the_template_is_drawn_once() {
+        let translucent = filled_page("0 0 0").replace("Stroke=\"false\"", "Stroke=\"false\" Alpha=\"128\"");
+        let once = OfdBuilder::new().page(&translucent).open().render_page(0, &pixel_per_mm()).unwrap();
+        let mut doc = OfdBuilder::new().template(&translucent, "Background").page(&translucent).open();
+        let image = doc.render_page(0, &pixel_per_mm()).unwrap();
+        assert!((120..=135).contains(&image.get_pixel(20, 15)[0]), "got {:?}", image.get_pixel(20, 15));
+        assert_eq!(image, once);
+        let svg = doc.render_page_svg(0, &pixel_per_mm()).unwrap();
+        assert_eq!(svg_elements(&svg).iter().filter(|e| *e == "path").count(), 1);
+    }
+
+    #[test]
+    fn svg_export_writes_one_element_per_object() {
+        let arc = "<ofd:PathObject ID=\"30\" Boundary=\"0 0 40 30\">\
+            <ofd:AbbreviatedData>M 5 15 A 5 5 0 0 1 15 15</ofd:AbbreviatedData></ofd:PathObject>";
+        let mut doc = text_document(None).page(&format!("{}{}{}", SQUARE, arc, TEXT)).open();
+        let svg = doc.render_page_svg(1, &pixel_per_mm()).unwrap();
+        let elements = svg_elements(&svg);
+        assert_eq!(elements.iter().filter(|e| *e == "path").count(), 2);
+        assert_eq!(elements.iter().filter(|e| *e == "text").count(), 1);
+        assert!(svg.contains("A 5 5 0 0 1 15 15"), "arc missing from {}", svg);
+    }
+
+    #[test]
+    fn continuous_strip_stacks_pages_with_gaps_and_centers_narrow_pages() {
+        let narrow = page_with_area("<ofd:PhysicalBox>0 0 20 10</ofd:PhysicalBox>", &filled_page("0 0 255"));
+        let mut doc = OfdBuilder::new()
+            .page(&filled_page("255 0 0"))
+            .page("")
+            .file("Doc_0/Pages/Page_1/Content.xml", narrow)
+            .open();
+        let png = doc.render_continuous(&pixel_per_mm(), 5).unwrap();
+        let strip = image::load_from_memory(&png).unwrap().to_rgba8();
+        assert_eq!(strip.dimensions(), (40, 30 + 5 + 10));
+        assert_eq!(strip.get_pixel(20, 15).0, [255, 0, 0, 255]);
+        assert_eq!(strip.get_pixel(20, 32).0, [255, 255, 255, 255]);
+        // 窄页面居中，左右各留出 10 像素的背景
+        assert_eq!(strip.get_pixel(20, 40).0, [0, 0, 255, 255]);
+        assert_eq!(strip.get_pixel(5, 40).0, [255, 255, 255, 255]);
+        assert_eq!(strip.get_pixel(35, 40).0, [255, 255, 255, 255]);
+    }
+
+    #[test]
+    fn continuous_strip_taller_than_u32_is_rejected() {
+        let mut doc = OfdBuilder::new().page("").page("").open();
+        match doc.render_continuous(&low_res(), u32::MAX) {
+            Err(OfdError::BufferTooLarge { height, .. }) => assert_eq!(height, u32::MAX),
+            other => panic!("expected BufferTooLarge, got {:?}", other.map(|png| png.len())),
+        }
+    }
+
+    /// 以 char_direction 渲染单个字形 A，返回不透明像素外接矩形的宽与高
+    fn glyph_extent(char_direction: i32) -> (u32, u32) {
+        let text = format!(
+            "<ofd:TextObject ID=\"20\" Boundary=\"0 0 40 30\" Font=\"10\" Size=\"10\" CharDirection=\"{}\">\
+             <ofd:FillColor Value=\"0 0 0\"/><ofd:TextCode X=\"10\" Y=\"15\">A</ofd:TextCode></ofd:TextObject>",
+            char_direction,
+        );
+        let mut doc = text_document(None).page(&text).open();
+        let image = doc.render_page(1, &RenderOptions { background: None, ..pixel_per_mm() }).unwrap();
+        let inked: Vec<(u32, u32)> = image.enumerate_pixels().filter(|(_, _, p)| p[3] > 128).map(|(x, y, _)| (x, y)).collect();
+        let xs = inked.iter().map(|p| p.0);
+        let ys = inked.iter().map(|p| p.1);
+        (xs.clone().max().unwrap() - xs.min().unwrap() + 1, ys.clone().max().unwrap() - ys.min().unwrap() + 1)
+    }
+
+    #[test]
+    fn char_direction_rotates_glyphs() {
+        // 测试字体的字形宽 0.5、高 0.7 个字号
+        let (width, height) = glyph_extent(0);
+        assert!(height > width, "upright glyph is {}x{}", width, height);
+        let (width, height) = glyph_extent(90);
+        assert!(width > height, "rotated glyph is {}x{}", width, height);
+    }
+
+    #[test]
+    fn extracted_directory_renders_like_the_archive() {
+        let builder = text_document(None).page(SQUARE);
+        let dir = std::env::temp_dir().join(format!("ofd2img-extracted-{}", std::process::id()));
+        zip::ZipArchive::new(Cursor::new(builder.build())).unwrap().extract(&dir).unwrap();
+        let from_dir = OfdDoc::open(dir.to_str().unwrap()).and_then(|mut doc| {
+            assert_eq!(doc.page_count(), 2);
+            doc.render_all(25.4)
+        });
+        std::fs::remove_dir_all(&dir).unwrap();
+        assert!(from_dir.unwrap() == builder.open().render_all(25.4).unwrap());
+    }
+
+    #[test]
+    fn metadata_lists_every_keyword() {
+        let doc_info = "<ofd:Title>发票</ofd:Title><ofd:Keywords><ofd:Keyword>增值税</ofd:Keyword>\
+            <ofd:Keyword>电子发票</ofd:Keyword><ofd:Keyword>2024</ofd:Keyword></ofd:Keywords>";
+        let metadata = OfdBuilder::new().page("").file("OFD.xml", ofd_xml(doc_info)).open().metadata();
+        assert_eq!(metadata.title, "发票");
+        assert_eq!(metadata.keywords, ["增值税", "电子发票", "2024"]);
+        assert_eq!(OfdBuilder::new().page("").open().metadata().keywords, Vec::<String>::new());
+    }
+
+    #[test]
+    fn truncated_font_falls_back_with_a_warning() {
+        let mut font = fixture::truetype_font(None);
+        font.truncate(font.len() / 2);
+        let mut doc = text_document(None).file("Doc_0/Res/font.ttf", font).open();
+        let (image, logs) = fixture::capture_logs(|| doc.render_page(0, &pixel_per_mm()));
+        assert_eq!(image.unwrap().dimensions(), (40, 30));
+        assert!(
+            logs.iter().any(|(level, message)| *level == log::Level::Warn && message.contains("embedded font 10 is corrupt")),
+            "got {:?}",
+            logs,
+        );
+    }
+
+    #[test]
+    fn missing_entries_are_reported_by_name() {
+        let ofd = ofd_xml("").replace("Doc_0/Document.xml", "Doc_9/Document.xml");
+        let data = OfdBuilder::new().page("").file("OFD.xml", ofd).build();
+        match OfdDoc::open_from_bytes(&data) {
+            Err(OfdError::EntryNotFound { name }) => assert_eq!(name, "Doc_9/Document.xml"),
+            other => panic!("expected EntryNotFound, got {:?}", other.map(|doc| doc.page_count())),
+        }
+
+        let mut doc = OfdBuilder::new().page(SQUARE).open_with(&strict());
+        doc.documents[0].pages.page[0].base_loc = "Pages/Page_9/Content.xml".to_string();
+        match doc.read_page(0) {
+            Err(OfdError::EntryNotFound { name }) => assert_eq!(name, "Doc_0/Pages/Page_9/Content.xml"),
+            other => panic!("expected EntryNotFound, got {:?}", other.map(|page| page.objects().len())),
+        }
+    }
+
+    #[test]
+    fn tagged_objects_link_tags_to_page_objects() {
+        let custom_tags = "<ofd:CustomTags xmlns:ofd=\"http://www.ofdspec.org/2016\">\
+            <ofd:CustomTag TypeID=\"invoice\"><ofd:FileLoc>Invoice.xml</ofd:FileLoc></ofd:CustomTag></ofd:CustomTags>";
+        let invoice = "<fp:eInvoice xmlns:fp=\"http://www.edrm.org.cn/schema/e-invoice/2019\" xmlns:ofd=\"http://www.ofdspec.org/2016\">\
+            <fp:Buyer><fp:BuyerName><ofd:ObjectRef PageRef=\"1\">20</ofd:ObjectRef></fp:BuyerName></fp:Buyer></fp:eInvoice>";
+        let mut doc = text_document(None)
+            .document("<ofd:CustomTags>Tags/CustomTags.xml</ofd:CustomTags>")
+            .file("Doc_0/Tags/CustomTags.xml", custom_tags)
+            .file("Doc_0/Tags/Invoice.xml", invoice)
+            .open();
+        let tagged = doc.tagged_objects().unwrap();
+        assert_eq!(tagged.len(), 1);
+        assert_eq!(tagged[0].type_id, "invoice");
+        assert_eq!(tagged[0].tag, "Buyer/BuyerName");
+        assert_eq!((tagged[0].page_id.as_str(), tagged[0].object_id.as_str()), ("1", "20"));
+        assert!(doc.page_text(0).unwrap().contains("HI"));
+        assert!(OfdBuilder::new().page("").open().tagged_objects().unwrap().is_empty());
+    }
+
+    #[test]
+    fn fit_paper_outputs_a4_with_centered_content() {
+        let mut doc = OfdBuilder::new().page(&filled_page("255 0 0")).open();
+        let opts = RenderOptions { fit_paper: Some(PaperSize::A4), ..pixel_per_mm() };
+        let image = doc.render_page(0, &opts).unwrap();
+        assert_eq!(image.dimensions(), (210, 297));
+        // 40 × 30 毫米的页面放大 5.25 倍后宽 210、高 157.5 毫米，上下各留出约 70 毫米
+        let red_rows: Vec<u32> = (0..297).filter(|y| image.get_pixel(105, *y).0 == [255, 0, 0, 255]).collect();
+        let (top, bottom) = (red_rows[0], 296 - red_rows[red_rows.len() - 1]);
+        assert!((156..=158).contains(&red_rows.len()), "{} red rows", red_rows.len());
+        assert!(top.abs_diff(bottom) <= 1, "top margin {} and bottom margin {}", top, bottom);
+        assert_eq!(image.get_pixel(105, 30).0, [255, 255, 255, 255]);
+        assert_eq!(image.get_pixel(0, 148).0, [255, 0, 0, 255]);
+    }
+
+    /// 含两个文档的包：Doc_0 为一页 SQUARE，Doc_1 为两个 20 × 10 毫米的空白页
+    fn two_documents() -> OfdBuilder {
+        let ofd = "<ofd:OFD xmlns:ofd=\"http://www.ofdspec.org/2016\" DocType=\"OFD\" Version=\"1.0\">\
+            <ofd:DocBody><ofd:DocInfo><ofd:Title>first</ofd:Title></ofd:DocInfo><ofd:DocRoot>Doc_0/Document.xml</ofd:DocRoot></ofd:DocBody>\
+            <ofd:DocBody><ofd:DocInfo><ofd:Title>second</ofd:Title></ofd:DocInfo><ofd:DocRoot>Doc_1/Document.xml</ofd:DocRoot></ofd:DocBody>\
+            </ofd:OFD>";
+        let second = "<ofd:Document xmlns:ofd=\"http://www.ofdspec.org/2016\"><ofd:CommonData>\
+            <ofd:PageArea><ofd:PhysicalBox>0 0 20 10</ofd:PhysicalBox></ofd:PageArea><ofd:MaxUnitID>10</ofd:MaxUnitID></ofd:CommonData>\
+            <ofd:Pages><ofd:Page ID=\"1\" BaseLoc=\"Pages/Page_0/Content.xml\"/><ofd:Page ID=\"2\" BaseLoc=\"Pages/Page_1/Content.xml\"/></ofd:Pages>\
+            </ofd:Document>";
+        let page = "<ofd:Page xmlns:ofd=\"http://www.ofdspec.org/2016\"><ofd:Content><ofd:Layer ID=\"3\"/></ofd:Content></ofd:Page>";
+        OfdBuilder::new()
+            .page(SQUARE)
+            .file("OFD.xml", ofd)
+            .file("Doc_1/Document.xml", second)
+            .file("Doc_1/Pages/Page_0/Content.xml", page)
+            .file("Doc_1/Pages/Page_1/Content.xml", page)
+    }
+
+    #[test]
+    fn select_document_switches_between_doc_bodies() {
+        let mut doc = two_documents().open();
+        assert_eq!(doc.document_count(), 2);
+        assert_eq!((doc.current_document(), doc.page_count(), doc.metadata().title), (0, 1, "first".to_string()));
+        doc.select_document(1).unwrap();
+        assert_eq!((doc.current_document(), doc.page_count(), doc.metadata().title), (1, 2, "second".to_string()));
+        assert_eq!(doc.render_page(1, &pixel_per_mm()).unwrap().dimensions(), (20, 10));
+        assert!(matches!(doc.select_document(2), Err(OfdError::InvalidDocumentIndex(2))));
+        assert_eq!(doc.current_document(), 1);
+    }
+
+    #[test]
+    fn permissions_report_a_document_that_cannot_be_printed() {
+        let doc = OfdBuilder::new()
+            .page("")
+            .document("<ofd:Permissions><ofd:Edit>false</ofd:Edit><ofd:Print Printable=\"false\" Copies=\"0\"/></ofd:Permissions>")
+            .open();
+        let permissions = doc.permissions().unwrap();
+        assert!(!permissions.print);
+        assert_eq!(permissions.print_copies, Some(0));
+        assert!(!permissions.modify);
+        assert!(permissions.copy && permissions.annotate);
+        assert_eq!(OfdBuilder::new().page("").open().permissions(), None);
+    }
+
+    /// DER 编码的元素，内容较长时使用长格式的长度
+    fn der(tag: u8, content: &[u8]) -> Vec<u8> {
+        let mut out = vec![tag];
+        match content.len() {
+            len if len < 0x80 => out.push(len as u8),
+            len => {
+                let bytes: Vec<u8> = len.to_be_bytes().into_iter().skip_while(|b| *b == 0).collect();
+                out.push(0x80 | bytes.len() as u8);
+                out.extend(bytes);
+            }
+        }
+        out.extend_from_slice(content);
+        out
+    }
+
+    /// 在第一页 boundary 处加盖印章图像为 seal 的电子签章
+    fn sealed(builder: OfdBuilder, seal: &RgbaImage, boundary: &str) -> OfdBuilder {
+        let picture = der(0x30, &[der(0x16, b"png"), der(0x04, &png_bytes(seal))].concat());
+        builder
+            .doc_body("<ofd:Signatures>Doc_0/Signs/Signatures.xml</ofd:Signatures>")
+            .file("Doc_0/Signs/Signatures.xml", "<ofd:Signatures xmlns:ofd=\"http://www.ofdspec.org/2016\">\
+                <ofd:Signature ID=\"1\" BaseLoc=\"Sign_0/Signature.xml\"/></ofd:Signatures>")
+            .file("Doc_0/Signs/Sign_0/Signature.xml", format!(
+                "<ofd:Signature xmlns:ofd=\"http://www.ofdspec.org/2016\"><ofd:SignedInfo>\
+                 <ofd:StampAnnot ID=\"1\" PageRef=\"1\" Boundary=\"{}\"/></ofd:SignedInfo>\
+                 <ofd:SignedValue>SignedValue.dat</ofd:SignedValue></ofd:Signature>",
+                boundary,
+            ))
+            .file("Doc_0/Signs/Sign_0/SignedValue.dat", der(0x30, &picture))
+    }
+
+    #[test]
+    fn seal_is_drawn_at_its_boundary() {
+        let red = RgbaImage::from_pixel(4, 4, image::Rgba([255, 0, 0, 255]));
+        let mut doc = sealed(OfdBuilder::new().page(""), &red, "20 10 10 10").open();
+        let image = doc.render_page(0, &pixel_per_mm()).unwrap();
+        assert_eq!(image.get_pixel(25, 15).0, [255, 0, 0, 255]);
+        assert_eq!(image.get_pixel(21, 11).0, [255, 0, 0, 255]);
+        assert_eq!(image.get_pixel(15, 15).0, [255, 255, 255, 255]);
+        assert_eq!(image.get_pixel(25, 25).0, [255, 255, 255, 255]);
+        let unsealed = doc.render_page(0, &RenderOptions { draw_seals: false, ..pixel_per_mm() }).unwrap();
+        assert_eq!(unsealed.get_pixel(25, 15).0, [255, 255, 255, 255]);
+    }
+
+    #[test]
+    fn background_fills_only_uncovered_pixels() {
+        let mut doc = OfdBuilder::new().page(SQUARE).open();
+        let white = doc.render_page(0, &pixel_per_mm()).unwrap();
+        assert_eq!(white.get_pixel(0, 0).0, [255, 255, 255, 255]);
+        let transparent = doc.render_page(0, &RenderOptions { background: None, ..pixel_per_mm() }).unwrap();
+        assert_eq!(transparent.get_pixel(0, 0).0[3], 0);
+        assert_eq!(transparent.get_pixel(39, 29).0[3], 0);
+        // 图元覆盖处不受背景影响
+        assert_eq!(transparent.get_pixel(20, 15).0, [255, 0, 0, 255]);
+        assert_eq!(white.get_pixel(20, 15).0, [255, 0, 0, 255]);
+    }
+
+    #[test]
+    fn alpha_is_inherited_from_the_draw_param() {
+        let black = filled_page("0 0 0").replace("Stroke=\"false\"", "Stroke=\"false\" DrawParam=\"5\"");
+        let mut doc = OfdBuilder::new()
+            .resources("<ofd:DrawParams><ofd:DrawParam ID=\"5\" Alpha=\"128\"/></ofd:DrawParams>")
+            .page(&black)
+            .open();
+        let pixel = doc.render_page(0, &pixel_per_mm()).unwrap().get_pixel(20, 15).0;
+        // 半透明的黑色叠加在白色背景上
+        assert!((120..=135).contains(&pixel[0]), "got {:?}", pixel);
+        let own_alpha = black.replace("DrawParam=\"5\"", "DrawParam=\"5\" Alpha=\"255\"");
+        let mut doc = OfdBuilder::new()
+            .resources("<ofd:DrawParams><ofd:DrawParam ID=\"5\" Alpha=\"128\"/></ofd:DrawParams>")
+            .page(&own_alpha)
+            .open();
+        assert_eq!(doc.render_page(0, &pixel_per_mm()).unwrap().get_pixel(20, 15).0, [0, 0, 0, 255]);
+    }
+
+    #[test]
+    fn percent_encoded_entry_names_open_and_render() {
+        let builder = text_document(None).page(SQUARE);
+        let data = builder.clone().percent_encoded().build();
+        let names = zip::ZipArchive::new(Cursor::new(data.clone())).unwrap().file_names().map(str::to_string).collect::<Vec<_>>();
+        assert!(names.iter().any(|n| n == "Doc%5F0/Document.xml"), "{:?}", names);
+        let mut doc = OfdDoc::open_from_bytes(&data).unwrap();
+        assert_eq!(doc.page_count(), 2);
+        assert!(doc.render_all(25.4).unwrap() == builder.open().render_all(25.4).unwrap());
+    }
+
+    #[test]
+    fn layer_filter_skips_background_layers() {
+        let content = format!(
+            "<ofd:Page xmlns:ofd=\"http://www.ofdspec.org/2016\"><ofd:Content>\
+             <ofd:Layer ID=\"2\" Type=\"Body\">{}</ofd:Layer><ofd:Layer ID=\"3\" Type=\"Background\">{}</ofd:Layer>\
+             </ofd:Content></ofd:Page>",
+            band(20, 20.0, 20.0, "0 0 255", ""),
+            band(21, 0.0, 20.0, "255 0 0", ""),
+        );
+        let mut doc = OfdBuilder::new().page("").file("Doc_0/Pages/Page_0/Content.xml", content).open();
+        let all = doc.render_page(0, &pixel_per_mm()).unwrap();
+        assert_eq!(all.get_pixel(10, 15).0, [255, 0, 0, 255]);
+        assert_eq!(all.get_pixel(30, 15).0, [0, 0, 255, 255]);
+        let body_only = LayerFilter { body: true, background: false, foreground: false };
+        let image = doc.render_page(0, &RenderOptions { layer_filter: body_only, ..pixel_per_mm() }).unwrap();
+        assert_eq!(image.get_pixel(10, 15).0, [255, 255, 255, 255]);
+        assert_eq!(image.get_pixel(30, 15).0, [0, 0, 255, 255]);
+    }
+
+    #[test]
+    fn object_svg_path_matches_the_geometry() {
+        let square = SQUARE.replace("Boundary=\"0 0 40 30\"", "Boundary=\"5 3 35 27\"");
+        let mut doc = OfdBuilder::new().page(&square).open();
+        let svg = doc.object_to_svg(0, "20").unwrap().unwrap();
+        let paths: Vec<Vec<(String, String)>> = EventReader::from_str(svg.as_str())
+            .into_iter()
+            .filter_map(|event| match event.unwrap() {
+                XmlEvent::StartElement { name, attributes, .. } if name.local_name == "path" => {
+                    Some(attributes.into_iter().map(|a| (a.name.local_name, a.value)).collect())
+                }
+                _ => None,
+            })
+            .collect();
+        assert_eq!(paths.len(), 1);
+        let attribute = |key: &str| paths[0].iter().find(|(k, _)| k == key).map(|(_, v)| v.as_str());
+        assert_eq!(attribute("d"), Some("M 10 5 L 30 5 L 30 25 L 10 25 Z"));
+        assert_eq!(attribute("transform"), Some("matrix(1 0 0 1 5 3)"));
+        assert!(svg.contains("viewBox=\"5 3 35 27\""), "{}", svg);
+        assert_eq!(doc.object_to_svg(0, "99").unwrap(), None);
+    }
+
+    #[test]
+    fn page_without_an_area_uses_its_content_bounds() {
+        let document = "<ofd:Document xmlns:ofd=\"http://www.ofdspec.org/2016\"><ofd:CommonData><ofd:MaxUnitID>100</ofd:MaxUnitID></ofd:CommonData>\
+            <ofd:Pages><ofd:Page ID=\"1\" BaseLoc=\"Pages/Page_0/Content.xml\"/><ofd:Page ID=\"2\" BaseLoc=\"Pages/Page_1/Content.xml\"/></ofd:Pages>\
+            </ofd:Document>";
+        let objects = "<ofd:PathObject ID=\"20\" Boundary=\"5 5 20 10\"><ofd:AbbreviatedData>M 0 0 L 20 10</ofd:AbbreviatedData></ofd:PathObject>\
+            <ofd:PathObject ID=\"21\" Boundary=\"10 10 20 15\"><ofd:AbbreviatedData>M 0 0 L 20 15</ofd:AbbreviatedData></ofd:PathObject>";
+        let mut doc = OfdBuilder::new().page(objects).page("").file("Doc_0/Document.xml", document).open();
+        let (image, logs) = fixture::capture_logs(|| doc.render_page(0, &pixel_per_mm()));
+        assert_eq!(image.unwrap().dimensions(), (25, 20));
+        assert!(
+            logs.iter().any(|(level, message)| *level == log::Level::Warn && message.starts_with("page 0 has no page area")),
+            "got {:?}",
+            logs,
+        );
+        assert!(matches!(doc.render_page(1, &pixel_per_mm()), Err(OfdError::MissingPageArea(1))));
+    }
+
+    #[test]
+    fn clips_leave_pixels_outside_the_clip_untouched() {
+        let clipped = "<ofd:PathObject ID=\"21\" Boundary=\"0 0 40 30\" Fill=\"true\" Stroke=\"false\">\
+            <ofd:Clips><ofd:Clip><ofd:Area><ofd:Path Boundary=\"10 10 10 10\">\
+            <ofd:AbbreviatedData>M 0 0 L 10 0 L 10 10 L 0 10 C</ofd:AbbreviatedData></ofd:Path></ofd:Area></ofd:Clip></ofd:Clips>\
+            <ofd:FillColor Value=\"255 0 0\"/><ofd:AbbreviatedData>M 0 0 L 40 0 L 40 30 L 0 30 C</ofd:AbbreviatedData></ofd:PathObject>";
+        let mut doc = OfdBuilder::new().page(&format!("{}{}", band(20, 0.0, 40.0, "0 0 255", ""), clipped)).open();
+        let image = doc.render_page(0, &pixel_per_mm()).unwrap();
+        for (x, y) in [(10, 10), (15, 15), (19, 19)] {
+            assert_eq!(image.get_pixel(x, y).0, [255, 0, 0, 255], "inside the clip at ({}, {})", x, y);
+        }
+        for (x, y) in [(9, 15), (20, 15), (15, 9), (15, 20), (0, 0), (39, 29)] {
+            assert_eq!(image.get_pixel(x, y).0, [0, 0, 255, 255], "outside the clip at ({}, {})", x, y);
+        }
+    }
+
+    #[test]
+    fn opaque_seal_multiplies_over_text() {
+        let red = RgbaImage::from_pixel(4, 4, image::Rgba([255, 0, 0, 255]));
+        let mut doc = sealed(text_document(None), &red, "0 0 40 30").open();
+        let image = doc.render_page(0, &pixel_per_mm()).unwrap();
+        // 测试字体中 H 的字形覆盖 x 2.5 到 7.5、y 13 到 20 毫米
+        let text = image.get_pixel(5, 16).0;
+        let seal_only = image.get_pixel(30, 5).0;
+        assert_eq!(seal_only, [255, 0, 0, 255]);
+        assert_eq!(text, [0, 0, 0, 255]);
+        assert!(render::luminance(text) < render::luminance(seal_only));
+    }
+
+    #[test]
+    fn half_transparent_red_blends_over_blue() {
+        let blue = band(20, 0.0, 40.0, "0 0 255", "");
+        let pixel = |red: &str| {
+            let mut doc = OfdBuilder::new().page(&format!("{}{}", blue, red)).open();
+            doc.render_page(0, &pixel_per_mm()).unwrap().get_pixel(20, 15).0
+        };
+        let close = |a: [u8; 4], b: [u8; 4]| a.iter().zip(b).all(|(x, y)| x.abs_diff(y) <= 1);
+        let expected = [128, 0, 127, 255];
+        let by_attribute = pixel(&band(21, 0.0, 40.0, "255 0 0", "Alpha=\"128\""));
+        assert!(close(by_attribute, expected), "got {:?}", by_attribute);
+        let by_value = pixel(&band(21, 0.0, 40.0, "255 0 0 128", ""));
+        assert!(close(by_value, expected), "got {:?}", by_value);
+        assert_eq!(pixel(&band(21, 0.0, 40.0, "255 0 0", "")), [255, 0, 0, 255]);
+    }
+
+    #[test]
+    fn page_text_of_the_invoice_sample_contains_its_fields() {
+        let mut doc = OfdDoc::open(concat!(env!("CARGO_MANIFEST_DIR"), "/data/fapiao.ofd")).unwrap();
+        let text = doc.page_text(0).unwrap();
+        for expected in ["24412000000243455703", "珠海仓链医药有限公司", "*中成药*小柴胡颗粒", "壹佰捌拾圆整"] {
+            assert!(text.contains(expected), "{:?} not found in {:?}", expected, text);
+        }
+        // 模板页中的栏目名称同样属于页面文字
+        assert!(text.contains("发票号码"), "{:?}", text);
+    }
+
+    #[test]
+    fn search_hits_lie_inside_the_physical_box() {
+        let inside = |bbox: STBox, page: STBox| {
+            bbox.w > 0.0 && bbox.h > 0.0
+                && bbox.x >= page.x && bbox.y >= page.y
+                && bbox.x + bbox.w <= page.x + page.w && bbox.y + bbox.h <= page.y + page.h
+        };
+        let mut doc = text_document(None).open();
+        let hits = doc.search("I").unwrap();
+        assert_eq!(hits.len(), 1);
+        assert_eq!((hits[0].page_index, hits[0].text.as_str()), (0, "I"));
+        // 没有字符间距时按一个字号估算，第二个字符从 2 + 10 毫米处开始
+        assert_eq!(hits[0].bbox.x, 12.0);
+        assert!(inside(hits[0].bbox, doc.page_size(0).unwrap()), "{:?}", hits[0].bbox);
+
+        let mut invoice = OfdDoc::open(concat!(env!("CARGO_MANIFEST_DIR"), "/data/fapiao.ofd")).unwrap();
+        let hits = invoice.search("小柴胡").unwrap();
+        assert_eq!(hits.len(), 1);
+        let page = invoice.page_size(0).unwrap();
+        assert!(inside(hits[0].bbox, page), "{:?} outside {:?}", hits[0].bbox, page);
+    }
+
+    #[test]
+    fn pdf_export_has_one_pdf_page_per_ofd_page() {
+        let mut doc = OfdBuilder::new().page(SQUARE).page("").page(&filled_page("0 128 0")).open();
+        let pdf = doc.to_pdf(&low_res()).unwrap();
+        assert!(pdf.starts_with(b"%PDF-"));
+        let parsed = pdf_extract::Document::load_mem(&pdf).unwrap();
+        assert_eq!(parsed.get_pages().len(), doc.page_count());
+    }
+
+    /// 填充整个页面的渐变，shading 为 ofd:AxialShd 或 ofd:RadialShd 节点
+    fn gradient_page(shading: &str) -> String {
+        format!(
+            "<ofd:PathObject ID=\"20\" Boundary=\"0 0 40 30\" Fill=\"true\" Stroke=\"false\"><ofd:FillColor>{}</ofd:FillColor>\
+             <ofd:AbbreviatedData>M 0 0 L 40 0 L 40 30 L 0 30 C</ofd:AbbreviatedData></ofd:PathObject>",
+            shading,
+        )
+    }
+
+    /// 从左边缘的红色到右边缘的蓝色的轴向渐变
+    const RED_TO_BLUE: &str = "<ofd:AxialShd StartPoint=\"0 0\" EndPoint=\"40 0\" Extend=\"3\">\
+        <ofd:Segment Position=\"0\"><ofd:Color Value=\"255 0 0\"/></ofd:Segment>\
+        <ofd:Segment Position=\"1\"><ofd:Color Value=\"0 0 255\"/></ofd:Segment></ofd:AxialShd>";
+
+    #[test]
+    fn quantized_gradient_has_at_most_the_requested_colors() {
+        let mut doc = OfdBuilder::new().page(&gradient_page(RED_TO_BLUE)).open();
+        let colors = |image: &RgbaImage| image.pixels().map(|p| p.0).collect::<HashSet<_>>().len();
+        let opts = RenderOptions { target: RenderTarget::Dpi(254.0), ..RenderOptions::default() };
+        let full = doc.render_page(0, &opts).unwrap();
+        assert!(colors(&full) > 16, "the gradient has only {} colors", colors(&full));
+        let quantized = doc.render_page(0, &RenderOptions { quantize_colors: Some(16), ..opts }).unwrap();
+        assert_eq!(quantized.dimensions(), full.dimensions());
+        assert!(colors(&quantized) <= 16, "{} colors after quantizing", colors(&quantized));
+    }
+
+    #[test]
+    fn other_namespace_prefixes_are_accepted() {
+        let builder = OfdBuilder::new().page(SQUARE);
+        let mut archive = zip::ZipArchive::new(Cursor::new(builder.build())).unwrap();
+        let mut writer = zip::ZipWriter::new(Cursor::new(Vec::new()));
+        for i in 0..archive.len() {
+            let mut entry = archive.by_index(i).unwrap();
+            let mut xml = String::new();
+            entry.read_to_string(&mut xml).unwrap();
+            writer.start_file(entry.name(), zip::write::SimpleFileOptions::default()).unwrap();
+            io::Write::write_all(&mut writer, xml.replace("ofd:", "o:").replace("xmlns:ofd", "xmlns:o").as_bytes()).unwrap();
+        }
+        let data = writer.finish().unwrap().into_inner();
+
+        let mut doc = OfdDoc::open_from_bytes(&data).unwrap();
+        assert_eq!(doc.namespaces().get("o").map(String::as_str), Some("http://www.ofdspec.org/2016"));
+        assert!(!doc.namespaces().contains_key("ofd"));
+        assert_eq!(doc.page_count(), 1);
+        assert_eq!(doc.render_page(0, &pixel_per_mm()).unwrap(), builder.open().render_page(0, &pixel_per_mm()).unwrap());
+    }
+
+    #[test]
+    fn tiff_has_one_directory_per_page() {
+        let mut doc = OfdBuilder::new().page(SQUARE).page("").page(&filled_page("0 128 0")).open();
+        let data = doc.to_tiff(&pixel_per_mm()).unwrap();
+        let mut decoder = ::tiff::decoder::Decoder::new(Cursor::new(data)).unwrap();
+        let mut directories = 1;
+        assert_eq!(decoder.dimensions().unwrap(), (40, 30));
+        while decoder.more_images() {
+            decoder.next_image().unwrap();
+            assert_eq!(decoder.dimensions().unwrap(), (40, 30));
+            directories += 1;
+        }
+        assert_eq!(directories, doc.page_count());
+    }
+
+    #[test]
+    fn gradients_reach_their_edge_colors() {
+        let close = |actual: [u8; 4], expected: [u8; 4]| actual.iter().zip(expected).all(|(a, e)| a.abs_diff(e) <= 16);
+        let mut doc = OfdBuilder::new().page(&gradient_page(RED_TO_BLUE)).open();
+        let axial = doc.render_page(0, &pixel_per_mm()).unwrap();
+        assert!(close(axial.get_pixel(0, 15).0, [255, 0, 0, 255]), "left edge {:?}", axial.get_pixel(0, 15));
+        assert!(close(axial.get_pixel(39, 15).0, [0, 0, 255, 255]), "right edge {:?}", axial.get_pixel(39, 15));
+        assert!(close(axial.get_pixel(20, 15).0, [128, 0, 128, 255]), "middle {:?}", axial.get_pixel(20, 15));
+
+        let radial = "<ofd:RadialShd StartPoint=\"20 15\" StartRadius=\"0\" EndPoint=\"20 15\" EndRadius=\"15\" Extend=\"3\">\
+            <ofd:Segment Position=\"0\"><ofd:Color Value=\"255 0 0\"/></ofd:Segment>\
+            <ofd:Segment Position=\"1\"><ofd:Color Value=\"0 0 255\"/></ofd:Segment></ofd:RadialShd>";
+        let mut doc = OfdBuilder::new().page(&gradient_page(radial)).open();
+        let image = doc.render_page(0, &pixel_per_mm()).unwrap();
+        assert!(close(image.get_pixel(20, 15).0, [255, 0, 0, 255]), "center {:?}", image.get_pixel(20, 15));
+        // 终止圆之外按 Extend 延续终止颜色
+        assert_eq!(image.get_pixel(0, 0).0, [0, 0, 255, 255]);
+        assert_eq!(image.get_pixel(39, 29).0, [0, 0, 255, 255]);
+    }
+
+    #[test]
+    fn render_documents_renders_only_the_chosen_document() {
+        let mut doc = two_documents().open();
+        let first = doc.render_documents(Some(0), &pixel_per_mm()).unwrap();
+        assert_eq!(first.len(), 1);
+        assert_eq!(first[0].dimensions(), (40, 30));
+        let second = doc.render_documents(Some(1), &pixel_per_mm()).unwrap();
+        assert_eq!(second.iter().map(|i| i.dimensions()).collect::<Vec<_>>(), [(20, 10), (20, 10)]);
+        assert_eq!(doc.render_documents(None, &pixel_per_mm()).unwrap().len(), 3);
+        assert_eq!(doc.current_document(), 0);
+        assert!(doc.render_documents(Some(2), &pixel_per_mm()).is_err());
+    }
+
+    #[test]
+    fn vertical_text_stacks_glyphs_along_y() {
+        // 字号 8 的字形宽 4、高 5.6 毫米，竖排时每个字符下移一个字号
+        let text = "<ofd:TextObject ID=\"20\" Boundary=\"0 0 40 30\" Font=\"10\" Size=\"8\" ReadDirection=\"90\">\
+            <ofd:FillColor Value=\"0 0 0\"/><ofd:TextCode X=\"10\" Y=\"8\">AB</ofd:TextCode></ofd:TextObject>";
+        let mut doc = text_document(None).page(text).open();
+        let image = doc.render_page(1, &pixel_per_mm()).unwrap();
+        let dark = |x: u32, y: u32| image.get_pixel(x, y).0[0] < 128;
+        assert!(dark(12, 5), "first glyph");
+        assert!(dark(12, 13), "second glyph below the first");
+        assert!(!dark(12, 9), "gap between the glyphs");
+        assert!(!dark(20, 5) && !dark(20, 13), "nothing to the right of the column");
+    }
+
+    /// 宽高各 5 毫米、内容为红色正方形的矢量图形，资源标识为 7
+    const RED_UNIT: &str = "<ofd:CompositeGraphicUnits><ofd:CompositeGraphicUnit ID=\"7\" Width=\"5\" Height=\"5\"><ofd:Content>\
+        <ofd:PathObject ID=\"8\" Boundary=\"0 0 5 5\" Fill=\"true\" Stroke=\"false\"><ofd:FillColor Value=\"255 0 0\"/>\
+        <ofd:AbbreviatedData>M 0 0 L 5 0 L 5 5 L 0 5 C</ofd:AbbreviatedData></ofd:PathObject>\
+        </ofd:Content></ofd:CompositeGraphicUnit></ofd:CompositeGraphicUnits>";
+
+    /// 在 (x, y) 处引用 RED_UNIT 的复合对象
+    fn composite(id: u32, x: f64, y: f64) -> String {
+        format!("<ofd:CompositeObject ID=\"{}\" Boundary=\"{} {} 5 5\" ResourceID=\"7\"/>", id, x, y)
+    }
+
+    #[test]
+    fn composite_used_twice_is_drawn_at_both_positions() {
+        let mut doc = OfdBuilder::new()
+            .resources(RED_UNIT)
+            .page(&format!("{}{}", composite(20, 5.0, 5.0), composite(21, 25.0, 15.0)))
+            .open();
+        let image = doc.render_page(0, &pixel_per_mm()).unwrap();
+        for (x, y) in [(5, 5), (7, 7), (9, 9), (25, 15), (27, 17), (29, 19)] {
+            assert_eq!(image.get_pixel(x, y).0, [255, 0, 0, 255], "at ({}, {})", x, y);
+        }
+        for (x, y) in [(4, 7), (10, 7), (17, 12), (27, 14), (27, 20)] {
+            assert_eq!(image.get_pixel(x, y).0, [255, 255, 255, 255], "at ({}, {})", x, y);
+        }
+    }
+
+    #[test]
+    fn synthetic_bold_inks_more_pixels_than_regular() {
+        let bold = TEXT.replace("Size=\"10\"", "Size=\"10\" Weight=\"700\"");
+        let mut doc = text_document(None).page(&bold).open();
+        let inked = |doc: &mut OfdDoc, page: usize, synthesize_font_styles: bool| {
+            let opts = RenderOptions { target: RenderTarget::Dpi(254.0), background: None, synthesize_font_styles, ..RenderOptions::default() };
+            doc.render_page(page, &opts).unwrap().pixels().filter(|p| p[3] > 128).count()
+        };
+        let regular = inked(&mut doc, 0, true);
+        assert!(inked(&mut doc, 1, true) > regular + regular / 20, "bold text is not heavier than {} pixels", regular);
+        assert_eq!(inked(&mut doc, 1, false), regular);
+    }
+
+    #[test]
+    fn raw_pixels_match_render_page() {
+        let mut doc = text_document(None).page(SQUARE).open();
+        for index in 0..doc.page_count() {
+            let raw = doc.render_page_raw(index, &low_res()).unwrap();
+            assert!(raw.width > 0 && raw.height > 0);
+            assert_eq!(raw.pixels.len(), raw.width as usize * raw.height as usize * 4);
+            let image = doc.render_page(index, &low_res()).unwrap();
+            assert_eq!((raw.width, raw.height), image.dimensions());
+            assert!(raw.pixels == image.into_raw(), "page {} differs", index);
+        }
+    }
+
+    /// 点击时打开 url 的图形对象
+    fn linked_path(id: u32, url: &str) -> String {
+        format!(
+            "<ofd:PathObject ID=\"{}\" Boundary=\"5 5 10 10\"><ofd:Actions><ofd:Action Event=\"CLICK\">\
+             <ofd:URI URI=\"{}\"/></ofd:Action></ofd:Actions><ofd:AbbreviatedData>M 0 0 L 10 10</ofd:AbbreviatedData></ofd:PathObject>",
+            id, url,
+        )
+    }
+
+    #[test]
+    fn links_report_their_page_indices() {
+        let mut doc = OfdBuilder::new()
+            .page(&linked_path(20, "https://example.com/a"))
+            .page(SQUARE)
+            .page(&format!("{}{}", linked_path(20, "https://example.com/b"), linked_path(21, "https://example.com/c")))
+            .open();
+        let links: Vec<(usize, String)> = doc.links().unwrap().into_iter().map(|l| (l.page_index, l.url)).collect();
+        assert_eq!(links, [
+            (0, "https://example.com/a".to_string()),
+            (2, "https://example.com/b".to_string()),
+            (2, "https://example.com/c".to_string()),
+        ]);
+    }
+
+    #[test]
+    fn layer_id_renders_a_single_layer() {
+        let content = format!(
+            "<ofd:Page xmlns:ofd=\"http://www.ofdspec.org/2016\"><ofd:Content>\
+             <ofd:Layer ID=\"2\">{}</ofd:Layer><ofd:Layer ID=\"3\">{}</ofd:Layer></ofd:Content></ofd:Page>",
+            band(20, 0.0, 20.0, "255 0 0", ""),
+            band(21, 20.0, 20.0, "0 0 255", ""),
+        );
+        let mut doc = OfdBuilder::new().page("").file("Doc_0/Pages/Page_0/Content.xml", content).open();
+        let mut render = |layer_id: Option<&str>| {
+            let opts = RenderOptions { layer_id: layer_id.map(str::to_string), ..pixel_per_mm() };
+            let image = doc.render_page(0, &opts).unwrap();
+            (image.get_pixel(10, 15).0, image.get_pixel(30, 15).0)
+        };
+        let (red, blue, white) = ([255, 0, 0, 255], [0, 0, 255, 255], [255, 255, 255, 255]);
+        assert_eq!(render(None), (red, blue));
+        assert_eq!(render(Some("2")), (red, white));
+        assert_eq!(render(Some("3")), (white, blue));
+        assert!(matches!(doc.render_layer(0, "4", &pixel_per_mm()), Err(OfdError::LayerNotFound { .. })));
+    }
+
+    #[test]
+    fn open_with_password_decrypts_encrypted_package() {
+        let data = OfdBuilder::new().page(SQUARE).build_encrypted("secret");
+
+        let mut doc = OfdDoc::open_from_bytes_with_password(&data, b"secret").unwrap();
+        assert_eq!(doc.page_count(), 1);
+        assert_eq!(doc.render_page(0, &pixel_per_mm()).unwrap().dimensions(), (40, 30));
+
+        match OfdDoc::open_from_bytes(&data) {
+            Err(OfdError::PasswordRequired { name }) => assert_eq!(name, "OFD.xml"),
+            other => panic!("expected PasswordRequired, got {:?}", other.err()),
+        }
+        assert!(matches!(OfdDoc::open_from_bytes_with_password(&data, b"wrong"), Err(OfdError::InvalidPassword)));
+
+        let plain = OfdBuilder::new().page(SQUARE).build();
+        assert!(matches!(OfdDoc::open_from_bytes_with_password(&plain, b"secret"), Err(OfdError::NotEncrypted)));
+    }
+
+    #[test]
+    fn attachments_are_listed_and_read_relative_to_attachments_xml() {
+        let mut doc = OfdBuilder::new()
+            .page(SQUARE)
+            .document("<ofd:Attachments>Attachs/Attachments.xml</ofd:Attachments>")
+            .file("Doc_0/Attachs/Attachments.xml", "<ofd:Attachments xmlns:ofd=\"http://www.ofdspec.org/2016\">\
+                <ofd:Attachment ID=\"30\" Name=\"invoice\" Format=\"xml\" FileLoc=\"invoice.xml\"/>\
+                <ofd:Attachment ID=\"31\" Name=\"notes\" FileLoc=\"notes.txt\"/>\
+                </ofd:Attachments>")
+            .file("Doc_0/Attachs/invoice.xml", "<Invoice/>")
+            .file("Doc_0/Attachs/notes.txt", "hello")
+            .open();
+
+        let attachments = doc.attachments();
+        assert_eq!(attachments, vec![
+            AttachmentInfo {
+                id: "30".to_string(),
+                name: "invoice".to_string(),
+                format: "xml".to_string(),
+                location: "Doc_0/Attachs/invoice.xml".to_string(),
+            },
+            AttachmentInfo {
+                id: "31".to_string(),
+                name: "notes".to_string(),
+                format: String::new(),
+                location: "Doc_0/Attachs/notes.txt".to_string(),
+            },
+        ]);
+        assert_eq!(doc.read_attachment("invoice").unwrap(), b"<Invoice/>");
+        assert_eq!(doc.read_attachment("notes").unwrap(), b"hello");
+        assert!(matches!(doc.read_attachment("missing"), Err(OfdError::ResourceNotFound(_))));
+    }
+
+    /// 第一页带有 annots 注释的文档，注释文件为 Doc_0/Annots/Page_0/Annotation.xml
+    fn annotated(page: &str, annots: &str) -> OfdBuilder {
+        OfdBuilder::new()
+            .page(page)
+            .document("<ofd:Annotations>Annots/Annotations.xml</ofd:Annotations>")
+            .file("Doc_0/Annots/Annotations.xml", "<ofd:Annotations xmlns:ofd=\"http://www.ofdspec.org/2016\">\
+                <ofd:Page PageID=\"1\"><ofd:FileLoc>Page_0/Annotation.xml</ofd:FileLoc></ofd:Page></ofd:Annotations>")
+            .file("Doc_0/Annots/Page_0/Annotation.xml", format!(
+                "<ofd:PageAnnot xmlns:ofd=\"http://www.ofdspec.org/2016\">{}</ofd:PageAnnot>", annots,
+            ))
+    }
+
+    /// 外接矩形为 5 5 20 10 的高亮注释，外观中的黄色半透明矩形比外接矩形宽
+    const HIGHLIGHT: &str = "<ofd:Annot ID=\"40\" Type=\"Highlight\"><ofd:Appearance Boundary=\"5 5 20 10\">\
+        <ofd:PathObject ID=\"41\" Boundary=\"0 0 30 10\" Fill=\"true\" Stroke=\"false\">\
+        <ofd:FillColor Value=\"255 255 0\" Alpha=\"128\"/>\
+        <ofd:AbbreviatedData>M 0 0 L 30 0 L 30 10 L 0 10 C</ofd:AbbreviatedData></ofd:PathObject>\
+        </ofd:Appearance></ofd:Annot>";
+
+    #[test]
+    fn highlight_annotation_blends_over_the_page_inside_its_boundary() {
+        let mut doc = annotated("", HIGHLIGHT).open();
+        let image = doc.render_page(0, &pixel_per_mm()).unwrap();
+        let highlighted = image.get_pixel(10, 10);
+        assert_eq!(&highlighted.0[..2], &[255, 255]);
+        assert!((125..=130).contains(&highlighted[2]), "got {:?}", highlighted);
+        // 外观中超出外接矩形的部分被裁剪，外接矩形以外保持白色
+        assert_eq!(image.get_pixel(27, 10), &image::Rgba([255, 255, 255, 255]));
+        assert_eq!(image.get_pixel(10, 20), &image::Rgba([255, 255, 255, 255]));
+
+        let plain = doc.render_page(0, &RenderOptions { draw_annotations: false, ..pixel_per_mm() }).unwrap();
+        assert_eq!(plain.get_pixel(10, 10), &image::Rgba([255, 255, 255, 255]));
+    }
+
+    #[test]
+    fn image_is_decoded_once_until_the_cache_is_cleared() {
+        let logo = RgbaImage::from_pixel(4, 4, image::Rgba([0, 128, 0, 255]));
+        let image_object = "<ofd:ImageObject ID=\"20\" Boundary=\"0 0 10 10\" CTM=\"10 0 0 10 0 0\" ResourceID=\"5\"/>";
+        let mut doc = OfdBuilder::new()
+            .resources("<ofd:MultiMedias><ofd:MultiMedia ID=\"5\" Type=\"Image\"><ofd:MediaFile>logo.png</ofd:MediaFile>\
+                </ofd:MultiMedia></ofd:MultiMedias>")
+            .file("Doc_0/Res/logo.png", png_bytes(&logo))
+            .page(image_object)
+            .page(image_object)
+            .open();
+        let decodes = |logs: &[(log::Level, String)]| logs.iter().filter(|(_, m)| m.starts_with("decoding image 5")).count();
+
+        let (_, logs) = fixture::capture_logs(|| {
+            for page_index in [0, 1, 0] {
+                doc.render_page(page_index, &pixel_per_mm()).unwrap();
+            }
+        });
+        assert_eq!(decodes(&logs), 1);
+
+        doc.clear_cache();
+        let (image, logs) = fixture::capture_logs(|| doc.render_page(1, &pixel_per_mm()).unwrap());
+        assert_eq!(decodes(&logs), 1);
+        assert_eq!(image.get_pixel(5, 5), &image::Rgba([0, 128, 0, 255]));
+    }
+
+    #[test]
+    fn page_rotate_turns_the_rendered_page_clockwise() {
+        let marker = "<ofd:PathObject ID=\"20\" Boundary=\"0 0 10 10\" Fill=\"true\" Stroke=\"false\">\
+            <ofd:FillColor Value=\"255 0 0\"/><ofd:AbbreviatedData>M 0 0 L 10 0 L 10 10 L 0 10 C</ofd:AbbreviatedData></ofd:PathObject>";
+        let red = image::Rgba([255, 0, 0, 255]);
+        let white = image::Rgba([255, 255, 255, 255]);
+        // 左上角的标记随页面顺时针旋转后所在的位置
+        for (rotate, size, corner) in [(0, (40, 30), (5, 5)), (90, (30, 40), (25, 5)), (180, (40, 30), (35, 25)), (270, (30, 40), (5, 35))] {
+            let content = page_with_area("<ofd:PhysicalBox>0 0 40 30</ofd:PhysicalBox>", marker)
+                .replace("<ofd:Area>", &format!("<ofd:Rotate>{}</ofd:Rotate><ofd:Area>", rotate));
+            let mut doc = OfdBuilder::new().page("").file("Doc_0/Pages/Page_0/Content.xml", content).open();
+            assert_eq!(doc.page_rotation(0).unwrap(), rotate);
+            let image = doc.render_page(0, &pixel_per_mm()).unwrap();
+            assert_eq!(image.dimensions(), size, "rotate {}", rotate);
+            assert_eq!(image.get_pixel(corner.0, corner.1), &red, "rotate {}", rotate);
+            let opposite = (size.0 - 1 - corner.0, size.1 - 1 - corner.1);
+            assert_eq!(image.get_pixel(opposite.0, opposite.1), &white, "rotate {}", rotate);
+        }
+    }
+
+    #[test]
+    fn missing_font_id_logs_a_warning() {
+        let text = TEXT.replace("Font=\"10\"", "Font=\"77\"");
+        let mut doc = OfdBuilder::new().page(&text).open();
+        let (image, logs) = fixture::capture_logs(|| doc.render_page(0, &pixel_per_mm()));
+        assert_eq!(image.unwrap().dimensions(), (40, 30));
+        assert!(
+            logs.contains(&(log::Level::Warn, "text object references unknown font 77, falling back".to_string())),
+            "got {:?}",
+            logs,
+        );
+        assert!(logs.iter().any(|(level, message)| *level == log::Level::Debug && message.starts_with("reading zip entry")));
+
+        let mut clean = OfdBuilder::new().page(SQUARE).open();
+        let (_, logs) = fixture::capture_logs(|| clean.render_page(0, &pixel_per_mm()).unwrap());
+        assert!(logs.iter().all(|(level, _)| *level > log::Level::Warn), "got {:?}", logs);
+    }
+
+    #[test]
+    fn registered_fallback_and_default_fonts_draw_text_without_an_embedded_font() {
+        let embedded = text_document(None).open().render_page(0, &pixel_per_mm()).unwrap();
+        assert_eq!(embedded.get_pixel(5, 16), &image::Rgba([0, 0, 0, 255]));
+
+        let mut doc = OfdBuilder::new()
+            .resources("<ofd:Fonts><ofd:Font ID=\"10\" FontName=\"宋体\" FamilyName=\"SimSun\"/></ofd:Fonts>")
+            .page(TEXT)
+            .open();
+        assert!(matches!(doc.register_fallback("SimSun", b"not a font".to_vec()), Err(OfdError::InvalidFont(_))));
+        doc.register_fallback("simsun", fixture::truetype_font(None)).unwrap();
+        assert_eq!(doc.render_page(0, &pixel_per_mm()).unwrap(), embedded);
+
+        // 字体标识无法解析时没有字族可查，使用缺省字体
+        let mut doc = OfdBuilder::new().page(&TEXT.replace("Font=\"10\"", "Font=\"77\"")).open();
+        doc.register_fallback("SimSun", fixture::truetype_font(None)).unwrap();
+        doc.set_default_font(fixture::truetype_font(None)).unwrap();
+        assert_eq!(doc.render_page(0, &pixel_per_mm()).unwrap(), embedded);
+    }
+
+    /// 旋转 45 度、半透明的文字对象，CTM 由 ctm 给出时替换旋转
+    fn watermark_text_object(id: u32, y: f64, ctm: &str) -> String {
+        format!(
+            "<ofd:TextObject ID=\"{}\" Boundary=\"0 {} 40 10\" Font=\"10\" Size=\"5\" CTM=\"{}\" Alpha=\"64\">\
+             <ofd:TextCode X=\"0\" Y=\"5\">机密</ofd:TextCode></ofd:TextObject>",
+            id, y, ctm,
+        )
+    }
+
+    #[test]
+    fn has_watermark_detects_repeated_tilted_translucent_text_in_a_template() {
+        const TILTED: &str = "0.7071 0.7071 -0.7071 0.7071 0 0";
+        let repeated = format!("{}{}", watermark_text_object(30, 0.0, TILTED), watermark_text_object(31, 15.0, TILTED));
+        let mut doc = text_document(None).template(&repeated, "Background").open();
+        assert!(doc.has_watermark(0).unwrap());
+        assert_eq!(doc.watermark_text(0).unwrap().as_deref(), Some("机密"));
+
+        // 正文层中的文字、水平放置的文字与只出现一次的文字都不是水印
+        let mut body = text_document(None).page(&repeated).open();
+        assert!(!body.has_watermark(1).unwrap());
+        let level = format!("{}{}", watermark_text_object(30, 0.0, "1 0 0 1 0 0"), watermark_text_object(31, 15.0, "1 0 0 1 0 0"));
+        assert!(!text_document(None).template(&level, "Background").open().has_watermark(0).unwrap());
+        let single = watermark_text_object(30, 0.0, TILTED);
+        assert!(!text_document(None).template(&single, "Background").open().has_watermark(0).unwrap());
+        assert!(!text_document(None).open().has_watermark(0).unwrap());
+    }
+
+    #[test]
+    fn svg_defines_a_composite_once_and_uses_it_twice() {
+        let mut doc = OfdBuilder::new()
+            .resources(RED_UNIT)
+            .page(&format!("{}{}", composite(20, 5.0, 5.0), composite(21, 25.0, 15.0)))
+            .open();
+        let svg = doc.render_page_svg(0, &pixel_per_mm()).unwrap();
+        let mut group_ids = Vec::new();
+        let mut hrefs = Vec::new();
+        for event in EventReader::from_str(svg.as_str()) {
+            let XmlEvent::StartElement { name, attributes, .. } = event.unwrap() else {
+                continue;
+            };
+            let attribute = |key: &str| attributes.iter().find(|a| a.name.local_name == key).map(|a| a.value.clone());
+            match name.local_name.as_str() {
+                "g" => group_ids.extend(attribute("id")),
+                "use" => hrefs.extend(attribute("href")),
+                _ => {}
+            }
+        }
+        assert_eq!(group_ids, ["vg-7"]);
+        assert_eq!(hrefs, ["#vg-7", "#vg-7"]);
+        assert_eq!(svg_elements(&svg).iter().filter(|e| *e == "path").count(), 1);
+    }
+
+    #[test]
+    fn objects_at_tests_fills_and_strokes_and_lists_the_topmost_first() {
+        let fill = filled_page("0 0 255").replace("ID=\"20\"", "ID=\"23\"");
+        let outline = "<ofd:PathObject ID=\"21\" Boundary=\"0 0 40 30\" LineWidth=\"1\">\
+            <ofd:AbbreviatedData>M 10 10 L 30 10 L 30 20 L 10 20 C</ofd:AbbreviatedData></ofd:PathObject>";
+        let square = SQUARE.replace("Stroke=\"true\"", "Stroke=\"false\"");
+        let mut doc = OfdBuilder::new()
+            .template(&fill, "Background")
+            .page(&format!("{}{}", square, outline))
+            .open();
+        let ids = |doc: &mut OfdDoc, x: f64, y: f64| -> Vec<String> {
+            doc.objects_at(0, STPos { x, y }).unwrap().into_iter().map(|o| o.id).collect()
+        };
+        // 只勾边的路径在线条内部不命中，线条上命中；填充的路径在内部命中
+        assert_eq!(ids(&mut doc, 20.0, 15.0), ["20", "23"]);
+        assert_eq!(ids(&mut doc, 10.0, 15.0), ["21", "20", "23"]);
+        assert_eq!(ids(&mut doc, 2.0, 2.0), ["23"]);
+
+        let hits = doc.objects_at(0, STPos { x: 10.0, y: 15.0 }).unwrap();
+        assert_eq!(hits[0].kind, page::ObjectKind::Path);
+        assert_eq!(hits[1].boundary, STBox { x: 0.0, y: 0.0, w: 40.0, h: 30.0 });
+    }
+
+    #[test]
+    fn render_context_parses_resources_once_for_all_renders() {
+        let logo = RgbaImage::from_pixel(4, 4, image::Rgba([0, 128, 0, 255]));
+        let image_object = "<ofd:ImageObject ID=\"20\" Boundary=\"0 0 10 10\" CTM=\"10 0 0 10 0 0\" ResourceID=\"5\"/>";
+        let mut doc = OfdBuilder::new()
+            .resources("<ofd:MultiMedias><ofd:MultiMedia ID=\"5\" Type=\"Image\"><ofd:MediaFile>logo.png</ofd:MediaFile>\
+                </ofd:MultiMedia></ofd:MultiMedias>")
+            .file("Doc_0/Res/logo.png", png_bytes(&logo))
+            .page(image_object)
+            .page(image_object)
+            .open();
+        let reads = |logs: &[(log::Level, String)], entry: &str| {
+            logs.iter().filter(|(_, m)| *m == format!("reading zip entry {}", entry)).count()
+        };
+
+        // 直接渲染时每次都重新读取资源表
+        let (_, logs) = fixture::capture_logs(|| {
+            doc.render_page(0, &pixel_per_mm()).unwrap();
+            doc.render_page(1, &pixel_per_mm()).unwrap();
+        });
+        assert_eq!(reads(&logs, "Doc_0/PublicRes.xml"), 2);
+
+        doc.clear_cache();
+        let (context, logs) = fixture::capture_logs(|| doc.render_context().unwrap());
+        assert_eq!(reads(&logs, "Doc_0/PublicRes.xml"), 1);
+        assert_eq!(reads(&logs, "Doc_0/Res/logo.png"), 1);
+
+        let (images, logs) = fixture::capture_logs(|| {
+            let images = [context.render_page(0, &pixel_per_mm()).unwrap(), context.render_page(1, &low_res()).unwrap()];
+            context.render_page_svg(1, &pixel_per_mm()).unwrap();
+            images
+        });
+        assert!(logs.iter().all(|(_, m)| !m.starts_with("reading zip entry")), "got {:?}", logs);
+        assert_eq!(images[0].get_pixel(5, 5), &image::Rgba([0, 128, 0, 255]));
+        assert_eq!(images[1].dimensions(), doc.render_page(1, &low_res()).unwrap().dimensions());
+    }
+
+    /// 外接矩形为 5 5 30 20、外观为空、带有线宽为 2 的红色边框的注释，dash 为边框的其他属性
+    fn bordered_annotation(dash: &str) -> String {
+        format!(
+            "<ofd:Annot ID=\"40\" Type=\"Stamp\"><ofd:Border LineWidth=\"2\" {}><ofd:BorderColor Value=\"255 0 0\"/></ofd:Border>\
+             <ofd:Appearance Boundary=\"5 5 30 20\"/></ofd:Annot>",
+            dash,
+        )
+    }
+
+    #[test]
+    fn dashed_annotation_border_alternates_red_and_background() {
+        let red = image::Rgba([255, 0, 0, 255]);
+        let white = image::Rgba([255, 255, 255, 255]);
+        let mut dashed = annotated("", &bordered_annotation("DashPattern=\"4 4\"")).open();
+        let image = dashed.render_page(0, &pixel_per_mm()).unwrap();
+        // 上边从 x = 5 开始，线段与间隔各 4 毫米，线条覆盖 y = 4 到 6
+        for x in [6, 14, 22] {
+            assert_eq!(image.get_pixel(x, 4), &red, "dash at x = {}", x);
+            assert_eq!(image.get_pixel(x, 5), &red, "dash at x = {}", x);
+        }
+        for x in [10, 18, 26] {
+            assert_eq!(image.get_pixel(x, 4), &white, "gap at x = {}", x);
+        }
+        assert_eq!(image.get_pixel(20, 15), &white);
+
+        let mut solid = annotated("", &bordered_annotation("")).open();
+        let image = solid.render_page(0, &pixel_per_mm()).unwrap();
+        for x in [6, 10, 14, 18] {
+            assert_eq!(image.get_pixel(x, 4), &red, "solid border at x = {}", x);
+        }
+    }
+
+    #[test]
+    fn pixel_targets_set_the_output_dimensions() {
+        let mut doc = OfdBuilder::new().page(&filled_page("255 0 0")).open();
+        let render = |doc: &mut OfdDoc, target| doc.render_page(0, &RenderOptions { target, ..RenderOptions::default() }).unwrap();
+        assert_eq!(render(&mut doc, RenderTarget::Width(600)).dimensions(), (600, 450));
+        assert_eq!(render(&mut doc, RenderTarget::Height(60)).dimensions(), (80, 60));
+
+        // 40 × 30 的页面缩放到 100 × 75，上下各留 12.5 像素的背景
+        let fitted = render(&mut doc, RenderTarget::Fit { w: 100, h: 100 });
+        assert_eq!(fitted.dimensions(), (100, 100));
+        assert_eq!(fitted.get_pixel(50, 5), &image::Rgba([255, 255, 255, 255]));
+        assert_eq!(fitted.get_pixel(50, 50), &image::Rgba([255, 0, 0, 255]));
+        assert_eq!(fitted.get_pixel(0, 20), &image::Rgba([255, 0, 0, 255]));
+        assert_eq!(fitted.get_pixel(50, 94), &image::Rgba([255, 255, 255, 255]));
+    }
+
+    #[test]
+    fn media_lists_audio_and_video_and_reads_their_bytes() {
+        let mut doc = OfdBuilder::new()
+            .resources("<ofd:MultiMedias>\
+                <ofd:MultiMedia ID=\"6\" Type=\"Video\" Format=\"MP4\"><ofd:MediaFile>clip.mp4</ofd:MediaFile></ofd:MultiMedia>\
+                <ofd:MultiMedia ID=\"5\" Type=\"Audio\"><ofd:MediaFile>sound.mp3</ofd:MediaFile></ofd:MultiMedia>\
+                <ofd:MultiMedia ID=\"4\" Type=\"Image\"><ofd:MediaFile>logo.png</ofd:MediaFile></ofd:MultiMedia>\
+                </ofd:MultiMedias>")
+            .file("Doc_0/Res/clip.mp4", b"\x00\x00\x00\x18ftypmp42".to_vec())
+            .file("Doc_0/Res/sound.mp3", b"ID3\x04".to_vec())
+            .page("<ofd:ImageObject ID=\"20\" Boundary=\"0 0 10 10\" CTM=\"10 0 0 10 0 0\" ResourceID=\"6\"/>")
+            .open();
+
+        assert_eq!(doc.media().unwrap(), vec![
+            MediaInfo {
+                id: "5".to_string(),
+                media_type: MediaType::Audio,
+                format: String::new(),
+                location: "Doc_0/Res/sound.mp3".to_string(),
+            },
+            MediaInfo {
+                id: "6".to_string(),
+                media_type: MediaType::Video,
+                format: "MP4".to_string(),
+                location: "Doc_0/Res/clip.mp4".to_string(),
+            },
+        ]);
+        assert_eq!(doc.read_media("6").unwrap(), b"\x00\x00\x00\x18ftypmp42");
+        assert_eq!(doc.read_media("5").unwrap(), b"ID3\x04");
+        assert!(matches!(doc.read_media("4"), Err(OfdError::ResourceNotFound(id)) if id == "4"));
+        // 引用视频的图像对象不绘制，页面照常渲染
+        let image = doc.render_page(0, &pixel_per_mm()).unwrap();
+        assert_eq!(image.get_pixel(5, 5), &image::Rgba([255, 255, 255, 255]));
+    }
+
+    #[test]
+    fn render_region_matches_the_same_quadrant_of_the_full_render() {
+        let mut doc = OfdBuilder::new().page(SQUARE).open();
+        // 每毫米两个像素，右下四分之一从全页的 (40, 30) 像素开始
+        let full = doc.render_page(0, &RenderOptions { target: RenderTarget::Dpi(50.8), ..RenderOptions::default() }).unwrap();
+        assert_eq!(full.dimensions(), (80, 60));
+        let quadrant = doc.render_region(0, STBox { x: 20.0, y: 15.0, w: 20.0, h: 15.0 }, 50.8).unwrap();
+        assert_eq!(quadrant.dimensions(), (40, 30));
+        assert_eq!(quadrant, image::imageops::crop_imm(&full, 40, 30, 40, 30).to_image());
+        // 正方形的右下角与蓝色边框都在该区域内
+        assert_eq!(quadrant.get_pixel(5, 5), &image::Rgba([255, 0, 0, 255]));
+        assert_eq!(quadrant.get_pixel(20, 5), &image::Rgba([0, 0, 255, 255]));
+    }
+
+    #[test]
+    fn next_unit_id_counts_up_from_max_unit_id_per_document() {
+        let mut doc = two_documents().max_unit_id(50).open();
+        assert_eq!(doc.next_unit_id(), 51);
+        assert_eq!(doc.next_unit_id(), 52);
+        doc.select_document(1).unwrap();
+        assert_eq!(doc.next_unit_id(), 11);
+        // 切换回来后继续之前的计数，文档声明的 MaxUnitID 不变
+        doc.select_document(0).unwrap();
+        assert_eq!(doc.next_unit_id(), 53);
+        assert_eq!(doc.max_unit_id(), 50);
+    }
+
+    #[test]
+    fn saved_and_encoded_png_decode_to_the_rendered_page() {
+        let mut doc = OfdBuilder::new().page(SQUARE).open();
+        let rendered = doc.render_page(0, &pixel_per_mm()).unwrap();
+        let encoded = doc.encode_page(0, ImageFormat::Png, DEFAULT_QUALITY, &pixel_per_mm()).unwrap();
+        let decoded = image::load_from_memory_with_format(&encoded, image::ImageFormat::Png).unwrap().to_rgba8();
+        assert_eq!(decoded, rendered);
+
+        let dir = std::env::temp_dir().join(format!("ofd2img-save-page-{}", std::process::id()));
+        std::fs::create_dir_all(&dir).unwrap();
+        let png = dir.join("page.PNG");
+        let saved = doc.save_page(0, png.to_str().unwrap(), &pixel_per_mm()).map(|_| std::fs::read(&png));
+        let unsupported = doc.save_page(0, dir.join("page.tga").to_str().unwrap(), &pixel_per_mm());
+        std::fs::remove_dir_all(&dir).unwrap();
+        let saved = saved.unwrap().unwrap();
+        assert_eq!(image::load_from_memory(&saved).unwrap().to_rgba8(), rendered);
+        assert!(matches!(unsupported, Err(OfdError::UnsupportedOutputFormat { ext }) if ext == "tga"));
+    }
+
+    #[test]
+    fn every_document_res_is_loaded_relative_to_its_base_loc() {
+        let logo = RgbaImage::from_pixel(4, 4, image::Rgba([0, 128, 0, 255]));
+        let common_data = "<ofd:DocumentRes>DocRes.xml</ofd:DocumentRes><ofd:DocumentRes>Res2/DocRes2.xml</ofd:DocumentRes>";
+        let image_object = "<ofd:ImageObject ID=\"21\" Boundary=\"20 0 10 10\" CTM=\"10 0 0 10 0 0\" ResourceID=\"5\"/>";
+        let mut doc = OfdBuilder::new()
+            .page(&format!("{}{}", TEXT, image_object))
+            .file("Doc_0/Document.xml", document_xml(common_data, "<ofd:Page ID=\"1\" BaseLoc=\"Pages/Page_0/Content.xml\"/>"))
+            .file("Doc_0/DocRes.xml", "<ofd:Res xmlns:ofd=\"http://www.ofdspec.org/2016\" BaseLoc=\"Fonts\">\
+                <ofd:Fonts><ofd:Font ID=\"10\" FontName=\"Fixture\"><ofd:FontFile>font.ttf</ofd:FontFile></ofd:Font></ofd:Fonts></ofd:Res>")
+            .file("Doc_0/Res2/DocRes2.xml", "<ofd:Res xmlns:ofd=\"http://www.ofdspec.org/2016\" BaseLoc=\"Media\">\
+                <ofd:MultiMedias><ofd:MultiMedia ID=\"5\" Type=\"Image\"><ofd:MediaFile>logo.png</ofd:MediaFile>\
+                </ofd:MultiMedia></ofd:MultiMedias></ofd:Res>")
+            .file("Doc_0/Fonts/font.ttf", fixture::truetype_font(None))
+            .file("Doc_0/Res2/Media/logo.png", png_bytes(&logo))
+            .open();
+        let resources = doc.load_resources().unwrap();
+        assert_eq!(resources.font_file("10"), Some("Doc_0/Fonts/font.ttf"));
+        assert_eq!(resources.media_file("5"), Some("Doc_0/Res2/Media/logo.png"));
+
+        let image = doc.render_page(0, &pixel_per_mm()).unwrap();
+        assert_eq!(image.get_pixel(5, 16), &image::Rgba([0, 0, 0, 255]));
+        assert_eq!(image.get_pixel(25, 5), &image::Rgba([0, 128, 0, 255]));
+    }
+
+    #[test]
+    fn page_label_uses_the_declared_label_or_the_page_number() {
+        let pages = "<ofd:Page ID=\"1\" BaseLoc=\"Pages/Page_0/Content.xml\" Label=\"封面\"/>\
+            <ofd:Page ID=\"2\" BaseLoc=\"Pages/Page_1/Content.xml\" Label=\"  \"/>\
+            <ofd:Page ID=\"3\" BaseLoc=\"Pages/Page_2/Content.xml\"/>";
+        let doc = OfdBuilder::new()
+            .page("")
+            .page("")
+            .page("")
+            .file("Doc_0/Document.xml", document_xml("", pages))
+            .open();
+        assert_eq!(doc.page_label(0), "封面");
+        // 空白的 Label 与未声明的一样，按从 1 开始的序号显示
+        assert_eq!(doc.page_label(1), "2");
+        assert_eq!(doc.page_label(2), "3");
+    }
+
+    #[test]
+    fn concat_takes_page_one_from_the_second_document() {
+        let first = OfdBuilder::new().page(&filled_page("255 0 0")).open();
+        let second = OfdBuilder::new().page(&filled_page("0 0 255")).page(&filled_page("0 255 0")).open();
+        let mut merged = OfdDoc::concat(vec![first, second]);
+        assert_eq!(merged.page_count(), 3);
+        assert_eq!(merged.source_count(), 2);
+        assert_eq!(merged.locate(1), Some((1, 0)));
+        assert_eq!(merged.locate(2), Some((1, 1)));
+        assert_eq!(merged.locate(3), None);
+
+        let colors: Vec<[u8; 4]> = merged.render_all(&pixel_per_mm()).unwrap().iter().map(|image| image.get_pixel(20, 15).0).collect();
+        assert_eq!(colors, [[255, 0, 0, 255], [0, 0, 255, 255], [0, 255, 0, 255]]);
+        assert!(matches!(merged.render_page(3, &pixel_per_mm()), Err(OfdError::InvalidPageIndex(3))));
+
+        let pdf = merged.to_pdf(&RenderOptions::default()).unwrap();
+        assert_eq!(pdf_extract::Document::load_mem(&pdf).unwrap().get_pages().len(), 3);
+        let mut docs = merged.into_inner();
+        assert_eq!(docs[1].render_page(0, &pixel_per_mm()).unwrap().get_pixel(20, 15).0, [0, 0, 255, 255]);
+    }
+
+    /// 长边竖直向下、短边在底部向右伸出的 L 形，上下颠倒时短边会出现在顶部
+    const L_SHAPE: &str = "<ofd:PathObject ID=\"20\" Boundary=\"0 0 40 30\" Fill=\"true\" Stroke=\"false\">\
+        <ofd:FillColor Value=\"255 0 0\"/>\
+        <ofd:AbbreviatedData>M 5 5 L 9 5 L 9 21 L 15 21 L 15 25 L 5 25 C</ofd:AbbreviatedData></ofd:PathObject>";
+
+    #[test]
+    fn l_shaped_path_keeps_its_long_arm_pointing_down() {
+        let mut doc = OfdBuilder::new().page(L_SHAPE).open();
+        let image = doc.render_page(0, &pixel_per_mm()).unwrap();
+        let red = image::Rgba([255, 0, 0, 255]);
+        let white = image::Rgba([255, 255, 255, 255]);
+        for (x, y) in [(7, 6), (7, 15), (7, 23), (13, 23)] {
+            assert_eq!(image.get_pixel(x, y), &red, "at ({}, {})", x, y);
+        }
+        // Y 轴翻转后短边位于 y = 5 到 9
+        assert_eq!(image.get_pixel(13, 7), &white);
+        assert_eq!(image.get_pixel(7, 27), &white);
+
+        // SVG 直接使用页面坐标，路径只有外接矩形的平移，不经过任何翻转
+        let svg = doc.render_page_svg(0, &pixel_per_mm()).unwrap();
+        assert!(svg.contains("viewBox=\"0 0 40 30\""), "{}", svg);
+        assert_eq!(svg.matches("transform=").count(), 1, "{}", svg);
+        assert!(svg.contains("<path transform=\"matrix(1 0 0 1 0 0)\""), "{}", svg);
+    }
+
+    #[test]
+    fn object_coordinates_start_at_the_boundary_origin() {
+        // 路径比外接矩形大，平移到 (10, 5) 后裁剪到外接矩形内
+        let offset = "<ofd:PathObject ID=\"20\" Boundary=\"10 5 10 10\" Fill=\"true\" Stroke=\"false\"><ofd:FillColor Value=\"255 0 0\"/>\
+            <ofd:AbbreviatedData>M 0 0 L 20 0 L 20 20 L 0 20 C</ofd:AbbreviatedData></ofd:PathObject>";
+        // 未声明外接矩形的图元以页面区域为外接矩形
+        let unbounded = "<ofd:PathObject ID=\"21\" Fill=\"true\" Stroke=\"false\"><ofd:FillColor Value=\"0 0 255\"/>\
+            <ofd:AbbreviatedData>M 30 20 L 35 20 L 35 25 L 30 25 C</ofd:AbbreviatedData></ofd:PathObject>";
+        let mut doc = OfdBuilder::new().page(&format!("{}{}", offset, unbounded)).open();
+        let image = doc.render_page(0, &pixel_per_mm()).unwrap();
+        let white = image::Rgba([255, 255, 255, 255]);
+        assert_eq!(image.get_pixel(10, 5), &image::Rgba([255, 0, 0, 255]));
+        assert_eq!(image.get_pixel(19, 14), &image::Rgba([255, 0, 0, 255]));
+        assert_eq!(image.get_pixel(9, 10), &white);
+        assert_eq!(image.get_pixel(20, 10), &white);
+        assert_eq!(image.get_pixel(15, 15), &white);
+        assert_eq!(image.get_pixel(32, 22), &image::Rgba([0, 0, 255, 255]));
+        assert_eq!(image.get_pixel(36, 22), &white);
+    }
+
+    #[test]
+    fn fill_color_falls_back_to_the_layer_draw_param_then_black() {
+        let uncolored = |id: u32, x: f64, attributes: &str| band(id, x, 10.0, "0 0 0", attributes).replace("<ofd:FillColor Value=\"0 0 0\"/>", "");
+        let content = format!(
+            "<ofd:Page xmlns:ofd=\"http://www.ofdspec.org/2016\"><ofd:Content>\
+             <ofd:Layer ID=\"900\" Type=\"Body\" DrawParam=\"5\">{}{}{}</ofd:Layer>\
+             <ofd:Layer ID=\"901\" Type=\"Body\">{}</ofd:Layer></ofd:Content></ofd:Page>",
+            uncolored(20, 0.0, ""),
+            band(21, 10.0, 10.0, "255 0 0", ""),
+            uncolored(22, 20.0, "DrawParam=\"6\""),
+            uncolored(23, 30.0, ""),
+        );
+        let mut doc = OfdBuilder::new()
+            .resources("<ofd:DrawParams>\
+                <ofd:DrawParam ID=\"5\"><ofd:FillColor Value=\"0 255 0\"/></ofd:DrawParam>\
+                <ofd:DrawParam ID=\"6\"><ofd:FillColor Value=\"0 0 255\"/></ofd:DrawParam>\
+                </ofd:DrawParams>")
+            .page("")
+            .file("Doc_0/Pages/Page_0/Content.xml", content)
+            .open();
+        let image = doc.render_page(0, &pixel_per_mm()).unwrap();
+        let colors: Vec<[u8; 4]> = [5, 15, 25, 35].iter().map(|&x| image.get_pixel(x, 15).0).collect();
+        // 依次为：图层的绘制参数、图元自身的颜色、图元引用的绘制参数、规范缺省的黑色
+        assert_eq!(colors, [[0, 255, 0, 255], [255, 0, 0, 255], [0, 0, 255, 255], [0, 0, 0, 255]]);
+    }
+
+    #[test]
+    fn resolved_ctm_composes_object_ctm_boundary_and_page_rotation() {
+        let scaled = "<ofd:PathObject ID=\"20\" Boundary=\"10 5 10 10\" CTM=\"2 0 0 2 0 0\">\
+            <ofd:AbbreviatedData>M 0 0 L 5 5</ofd:AbbreviatedData></ofd:PathObject>";
+        let content = page_with_area("<ofd:PhysicalBox>0 0 40 30</ofd:PhysicalBox>", scaled)
+            .replace("<ofd:Area>", "<ofd:Rotate>90</ofd:Rotate><ofd:Area>");
+        let mut doc = OfdBuilder::new().page("").file("Doc_0/Pages/Page_0/Content.xml", content).open();
+        let objects: Vec<ResolvedObject> = doc.iter_objects().collect::<Result<_, _>>().unwrap();
+        assert_eq!(objects.len(), 1);
+        // 图元坐标先放大 2 倍、平移到 (10, 5)，再随页面顺时针旋转 90 度：(x, y) 变为 (30 - y, x)
+        assert_eq!(objects[0].ctm, STMatrix { a: 0.0, b: 2.0, c: -2.0, d: 0.0, e: 25.0, f: 10.0 });
+        assert_eq!(objects[0].boundary, STBox { x: 10.0, y: 5.0, w: 10.0, h: 10.0 });
+        let (x, y) = (1.0, 1.0);
+        let m = objects[0].ctm;
+        assert_eq!((m.a * x + m.c * y + m.e, m.b * x + m.d * y + m.f), (23.0, 12.0));
+    }
+
+    #[test]
+    fn list_entries_lists_files_but_not_directories() {
+        let builder = OfdBuilder::new().page(SQUARE);
+        let mut zip = zip::ZipWriter::new_append(Cursor::new(builder.build())).unwrap();
+        zip.add_directory("Doc_0/", zip::write::SimpleFileOptions::default()).unwrap();
+        zip.add_directory("Doc_0/Pages/", zip::write::SimpleFileOptions::default()).unwrap();
+        let data = zip.finish().unwrap().into_inner();
+
+        let mut doc = OfdDoc::open_from_bytes(&data).unwrap();
+        let entries = doc.list_entries();
+        assert!(entries.contains(&"OFD.xml".to_string()), "{:?}", entries);
+        assert!(entries.contains(&"Doc_0/Pages/Page_0/Content.xml".to_string()), "{:?}", entries);
+        assert!(entries.iter().all(|e| !e.ends_with('/')), "{:?}", entries);
+        assert!(entries.windows(2).all(|w| w[0] < w[1]), "{:?}", entries);
+        assert!(doc.read_entry("OFD.xml").unwrap().starts_with(b"<ofd:OFD"));
+        assert!(matches!(doc.read_entry("Doc_0"), Err(OfdError::EntryNotFound { .. })));
+
+        // 已解压的目录递归列出同样的文件
+        let dir = std::env::temp_dir().join(format!("ofd2img-list-entries-{}", std::process::id()));
+        zip::ZipArchive::new(Cursor::new(data)).unwrap().extract(&dir).unwrap();
+        let from_dir = OfdDoc::open(dir.to_str().unwrap()).map(|doc| doc.list_entries());
+        std::fs::remove_dir_all(&dir).unwrap();
+        assert_eq!(from_dir.unwrap(), entries);
+    }
+
+    #[test]
+    fn overprinted_cmyk_fill_multiplies_only_when_simulated() {
+        let cmyk = |id: u32, x: f64, w: f64, value: &str, overprint: &str| {
+            band(id, x, w, value, "").replace(
+                &format!("<ofd:FillColor Value=\"{}\"/>", value),
+                &format!("<ofd:FillColor ColorSpace=\"1\" Value=\"{}\" {}/>", value, overprint),
+            )
+        };
+        let doc = |overprint: &str| {
+            OfdBuilder::new()
+                .resources("<ofd:ColorSpaces><ofd:ColorSpace ID=\"1\" Type=\"CMYK\"/></ofd:ColorSpaces>")
+                .page(&format!("{}{}", cmyk(20, 0.0, 30.0, "0 0 255 0", ""), cmyk(21, 10.0, 30.0, "255 0 0 0", overprint)))
+                .open()
+        };
+        let simulated = RenderOptions { simulate_overprint: true, ..pixel_per_mm() };
+        let yellow_then_cyan = |doc: &mut OfdDoc, opts: &RenderOptions| {
+            let image = doc.render_page(0, opts).unwrap();
+            [image.get_pixel(5, 15).0, image.get_pixel(15, 15).0, image.get_pixel(35, 15).0]
+        };
+        const YELLOW: [u8; 4] = [255, 255, 0, 255];
+        const CYAN: [u8; 4] = [0, 255, 255, 255];
+
+        // 叠印的青色不挖空下层的黄色，两者叠加为绿色；白色背景上仍为青色
+        let mut overprinted = doc("Overprint=\"true\"");
+        assert_eq!(yellow_then_cyan(&mut overprinted, &simulated), [YELLOW, [0, 255, 0, 255], CYAN]);
+        assert_eq!(yellow_then_cyan(&mut overprinted, &pixel_per_mm()), [YELLOW, CYAN, CYAN]);
+        let mut knockout = doc("");
+        assert_eq!(yellow_then_cyan(&mut knockout, &simulated), [YELLOW, CYAN, CYAN]);
+    }
 }
@@ -0,0 +1,274 @@
+use std::collections::HashMap;
+use std::fmt;
+use std::fs::File;
+use std::io::{self, Read, Seek};
+use std::path::PathBuf;
+
+use zip::result::ZipError;
+use zip::ZipArchive;
+
+use crate::ofd::OfdError;
+
+/// 读取包内文件时的大小限制，防止压缩率极高的条目（压缩炸弹）或超大的 XML 耗尽内存
+/// 处理不可信的文件时可以调低，超出限制时返回 ResourceTooLarge
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct ReadLimits {
+    /// 单个条目解压后的最大字节数
+    pub max_entry_size: u64,
+    /// 按 XML 解析的条目的最大字节数
+    pub max_xml_size: u64,
+    /// 打开文档或设置限制以来累计读取的最大字节数，多次渲染时重复读取的条目重复计算
+    pub max_total_size: u64,
+}
+
+impl Default for ReadLimits {
+    fn default() -> Self {
+        ReadLimits {
+            max_entry_size: 256 << 20,
+            max_xml_size: 64 << 20,
+            max_total_size: 4 << 30,
+        }
+    }
+}
+
+/// OFD 包的数据来源，name 为包内以 / 分隔的文件名
+/// 包内不存在该文件时返回 EntryNotFound，内容超过 limit 字节时返回 ResourceTooLarge，且读取的数据不超过 limit + 1 字节
+pub(crate) trait PackageSource: fmt::Debug + Send {
+    fn read_entry(&mut self, name: &str, limit: u64) -> Result<Vec<u8>, OfdError>;
+
+    /// 包内所有文件的名称，不含目录本身，按名称排序
+    fn entry_names(&self) -> Vec<String>;
+}
+
+/// 按大小限制读取数据来源中的条目，累计读取的字节数
+#[derive(Debug)]
+pub(crate) struct Package {
+    source: Box<dyn PackageSource>,
+    limits: ReadLimits,
+    total_read: u64,
+}
+
+impl Package {
+    pub(crate) fn new(source: Box<dyn PackageSource>) -> Package {
+        Package { source, limits: ReadLimits::default(), total_read: 0 }
+    }
+
+    pub(crate) fn limits(&self) -> ReadLimits {
+        self.limits
+    }
+
+    /// 设置大小限制，并重新开始累计读取的字节数
+    pub(crate) fn set_limits(&mut self, limits: ReadLimits) {
+        self.limits = limits;
+        self.total_read = 0;
+    }
+
+    pub(crate) fn read_entry(&mut self, name: &str) -> Result<Vec<u8>, OfdError> {
+        self.read_limited(name, self.limits.max_entry_size)
+    }
+
+    pub(crate) fn entry_names(&self) -> Vec<String> {
+        self.source.entry_names()
+    }
+
+    /// 读取文本内容，内容必须为 UTF-8 编码，大小受 XML 的限制
+    pub(crate) fn read_entry_string(&mut self, name: &str) -> Result<String, OfdError> {
+        let data = self.read_limited(name, self.limits.max_xml_size.min(self.limits.max_entry_size))?;
+        String::from_utf8(data).map_err(|e| OfdError::IoError(io::Error::new(io::ErrorKind::InvalidData, e)))
+    }
+
+    /// 超出的是累计读取的限制时，错误中的 limit 为 max_total_size
+    fn read_limited(&mut self, name: &str, limit: u64) -> Result<Vec<u8>, OfdError> {
+        let remaining = self.limits.max_total_size.saturating_sub(self.total_read);
+        let data = self.source.read_entry(name, limit.min(remaining)).map_err(|e| match e {
+            OfdError::ResourceTooLarge { name, .. } if remaining < limit => {
+                OfdError::ResourceTooLarge { name, limit: self.limits.max_total_size }
+            }
+            e => e,
+        })?;
+        self.total_read += data.len() as u64;
+        Ok(data)
+    }
+}
+
+/// 至多读取 limit + 1 字节，超出 limit 时返回 ResourceTooLarge
+fn read_to_limit(reader: impl Read, name: &str, size_hint: u64, limit: u64) -> Result<Vec<u8>, OfdError> {
+    let mut content = Vec::with_capacity(size_hint.min(limit) as usize);
+    reader.take(limit.saturating_add(1)).read_to_end(&mut content).map_err(OfdError::IoError)?;
+    if content.len() as u64 > limit {
+        return Err(OfdError::ResourceTooLarge { name: name.to_string(), limit });
+    }
+    Ok(content)
+}
+
+pub(crate) trait ReadSeek: Read + Seek + Send {}
+
+impl<T: Read + Seek + Send> ReadSeek for T {}
+
+impl fmt::Debug for dyn ReadSeek {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str("ReadSeek")
+    }
+}
+
+/// 以 zip 压缩包形式存储的 OFD 文件
+#[derive(Debug)]
+pub(crate) struct ZipSource {
+    archive: ZipArchive<Box<dyn ReadSeek>>,
+    /// 百分号解码后的文件名到压缩包中原始文件名的映射，首次按原名找不到文件时建立
+    decoded_names: Option<HashMap<String, String>>,
+    /// 解密加密条目的密码，未加密的条目忽略密码直接读取
+    password: Option<Vec<u8>>,
+}
+
+impl ZipSource {
+    pub(crate) fn new(reader: Box<dyn ReadSeek>) -> Result<ZipSource, OfdError> {
+        let archive = ZipArchive::new(reader).map_err(OfdError::ZipError)?;
+        Ok(ZipSource { archive, decoded_names: None, password: None })
+    }
+
+    /// 打开带密码的压缩包，压缩包中没有任何加密的条目时返回 NotEncrypted
+    pub(crate) fn with_password(reader: Box<dyn ReadSeek>, password: &[u8]) -> Result<ZipSource, OfdError> {
+        let mut source = ZipSource::new(reader)?;
+        let mut encrypted = false;
+        for index in 0..source.archive.len() {
+            if source.archive.by_index_raw(index).map_err(OfdError::ZipError)?.encrypted() {
+                encrypted = true;
+                break;
+            }
+        }
+        if !encrypted {
+            return Err(OfdError::NotEncrypted);
+        }
+        source.password = Some(password.to_vec());
+        Ok(source)
+    }
+
+    /// 部分生成器写入的文件名经过百分号编码（如 Doc%5F0），而文档中引用的是解码后的名称，反之亦然
+    /// 按原名找不到时，比较双方解码后的名称
+    fn entry_name(&mut self, name: &str) -> Option<String> {
+        if self.archive.index_for_name(name).is_some() {
+            return Some(name.to_string());
+        }
+        let archive = &self.archive;
+        let decoded_names = self.decoded_names.get_or_insert_with(|| {
+            archive.file_names().map(|n| (percent_decode(n), n.to_string())).collect()
+        });
+        decoded_names.get(&percent_decode(name)).cloned()
+    }
+}
+
+impl PackageSource for ZipSource {
+    /// 条目声明的解压后大小不可信，先按声明的大小快速拒绝，再限制实际解压的字节数
+    fn read_entry(&mut self, name: &str, limit: u64) -> Result<Vec<u8>, OfdError> {
+        let entry_name = self.entry_name(name).ok_or_else(|| OfdError::EntryNotFound { name: name.to_string() })?;
+        log::debug!("reading zip entry {}", entry_name);
+        let file = match &self.password {
+            Some(password) => self.archive.by_name_decrypt(&entry_name, password),
+            None => self.archive.by_name(&entry_name),
+        };
+        let file = file.map_err(|e| match e {
+            ZipError::FileNotFound => OfdError::EntryNotFound { name: name.to_string() },
+            ZipError::InvalidPassword => OfdError::InvalidPassword,
+            ZipError::UnsupportedArchive(ZipError::PASSWORD_REQUIRED) => OfdError::PasswordRequired { name: name.to_string() },
+            e => OfdError::ZipError(e),
+        })?;
+        let size = file.size();
+        if size > limit {
+            return Err(OfdError::ResourceTooLarge { name: name.to_string(), limit });
+        }
+        read_to_limit(file, name, size, limit)
+    }
+
+    fn entry_names(&self) -> Vec<String> {
+        let mut names: Vec<String> = self.archive.file_names()
+            .filter(|n| !n.ends_with('/'))
+            .map(str::to_string)
+            .collect();
+        names.sort();
+        names
+    }
+}
+
+/// 已解压到目录中的 OFD 包，目录下直接包含 OFD.xml
+#[derive(Debug)]
+pub(crate) struct DirSource {
+    root: PathBuf,
+}
+
+impl DirSource {
+    pub(crate) fn new(root: impl Into<PathBuf>) -> DirSource {
+        DirSource { root: root.into() }
+    }
+}
+
+impl PackageSource for DirSource {
+    /// 包内文件名已由 resolve_path 规范化，不会越出根目录
+    fn read_entry(&mut self, name: &str, limit: u64) -> Result<Vec<u8>, OfdError> {
+        let path = name.split('/').filter(|s| !s.is_empty()).fold(self.root.clone(), |path, s| path.join(s));
+        log::debug!("reading file {}", path.display());
+        let file = File::open(path).map_err(|e| match e.kind() {
+            io::ErrorKind::NotFound => OfdError::EntryNotFound { name: name.to_string() },
+            _ => OfdError::IoError(e),
+        })?;
+        let size = file.metadata().map_err(OfdError::IoError)?.len();
+        if size > limit {
+            return Err(OfdError::ResourceTooLarge { name: name.to_string(), limit });
+        }
+        read_to_limit(file, name, size, limit)
+    }
+
+    /// 递归列出目录下的文件，名称相对于根目录并以 / 分隔；无法读取的子目录记录警告后跳过
+    fn entry_names(&self) -> Vec<String> {
+        let mut names = Vec::new();
+        let mut pending = vec![(self.root.clone(), String::new())];
+        while let Some((dir, prefix)) = pending.pop() {
+            let entries = match std::fs::read_dir(&dir) {
+                Ok(entries) => entries,
+                Err(e) => {
+                    log::warn!("failed to list directory {}: {}", dir.display(), e);
+                    continue;
+                }
+            };
+            for entry in entries.flatten() {
+                let name = format!("{}{}", prefix, entry.file_name().to_string_lossy());
+                match entry.file_type() {
+                    Ok(t) if t.is_dir() => pending.push((entry.path(), format!("{}/", name))),
+                    Ok(_) => names.push(name),
+                    Err(e) => log::warn!("failed to inspect {}: {}", entry.path().display(), e),
+                }
+            }
+        }
+        names.sort();
+        names
+    }
+}
+
+/// 解码文件名中的 %XX，不合法的转义保持原样
+fn percent_decode(name: &str) -> String {
+    if !name.contains('%') {
+        return name.to_string();
+    }
+    let bytes = name.as_bytes();
+    let mut decoded = Vec::with_capacity(bytes.len());
+    let mut i = 0;
+    while i < bytes.len() {
+        let escaped = (bytes[i] == b'%')
+            .then(|| bytes.get(i + 1..i + 3))
+            .flatten()
+            .filter(|hex| hex.iter().all(u8::is_ascii_hexdigit))
+            .and_then(|hex| std::str::from_utf8(hex).ok())
+            .and_then(|hex| u8::from_str_radix(hex, 16).ok());
+        match escaped {
+            Some(byte) => {
+                decoded.push(byte);
+                i += 3;
+            }
+            None => {
+                decoded.push(bytes[i]);
+                i += 1;
+            }
+        }
+    }
+    String::from_utf8_lossy(&decoded).into_owned()
+}
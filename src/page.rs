@@ -1,6 +1,896 @@
+use std::collections::HashMap;
 
+use serde::Deserialize;
 
+use crate::action::{Actions, LinkTarget};
+use crate::color::CTColor;
+use crate::document::{PageArea, ZOrder};
+use crate::resource::{DrawParam, ResourceManager};
+use crate::st_types::{deserialize_st, deserialize_st_opt, STArray, STBox, STMatrix};
+use crate::render::DEFAULT_LINE_WIDTH;
+use crate::svg;
+
+/// 预分配图元容量的上限，MaxUnitID 可能远大于单页的图元数
+const MAX_OBJECTS_CAPACITY: usize = 4096;
+
+/// 按文档的 MaxUnitID 估计收集页面图元时预分配的容量
+/// 每个图元都占用一个对象标识，MaxUnitID 是单页图元数量的上界，容量不超过 MAX_OBJECTS_CAPACITY
+pub(crate) fn objects_capacity(max_unit_id: usize) -> usize {
+    max_unit_id.min(MAX_OBJECTS_CAPACITY)
+}
+
+/// 页面内容文件（Content.xml）的根节点 ofd:Page
+#[derive(Debug, Deserialize, Default)]
+#[serde(default, rename_all = "PascalCase")]
 pub(crate) struct Page {
-    id: String,
-    base_loc: String,
-}
\ No newline at end of file
+    pub(crate) area: Option<PageArea>,
+    /// 页面的顺时针旋转角度，部分扫描件以此将横向内容显示为纵向页面
+    pub(crate) rotate: Option<i32>,
+    pub(crate) template: Vec<PageTemplate>,
+    pub(crate) content: Option<Content>,
+    /// 图元标识到其位置的索引，位置依次为图层序号与各级 PageBlock 内的序号
+    #[serde(skip)]
+    object_index: HashMap<String, Vec<usize>>,
+    /// 解析时发现的重复图元标识，索引中保留先出现的图元
+    #[serde(skip)]
+    duplicate_ids: Vec<String>,
+}
+
+impl Page {
+    pub(crate) fn from_xml(xml: &str) -> Result<Page, serde_xml_rs::Error> {
+        let mut page: Page = serde_xml_rs::from_str(xml)?;
+        page.build_object_index();
+        Ok(page)
+    }
+
+    fn build_object_index(&mut self) {
+        let mut index = HashMap::new();
+        let mut duplicates = Vec::new();
+        if let Some(content) = &self.content {
+            for (i, layer) in content.layer.iter().enumerate() {
+                index_objects(&layer.objects, &mut vec![i], &mut index, &mut duplicates);
+            }
+        }
+        self.object_index = index;
+        self.duplicate_ids = duplicates;
+    }
+
+    /// 按标识查找图元，包括 PageBlock
+    pub(crate) fn object_by_id(&self, id: &str) -> Option<&PageObject> {
+        let path = self.object_index.get(id)?;
+        let (layer, rest) = path.split_first()?;
+        let mut objects = &self.content.as_ref()?.layer.get(*layer)?.objects;
+        let (last, blocks) = rest.split_last()?;
+        for i in blocks {
+            match objects.get(*i)? {
+                PageObject::PageBlock(block) => objects = &block.objects,
+                _ => return None,
+            }
+        }
+        objects.get(*last)
+    }
+
+    /// 页面中是否有该标识的图元，空标识视为不存在
+    pub(crate) fn contains_object(&self, id: &str) -> bool {
+        !id.is_empty() && self.object_index.contains_key(id)
+    }
+
+    /// 页面中重复出现的图元标识
+    pub(crate) fn duplicate_ids(&self) -> &[String] {
+        &self.duplicate_ids
+    }
+
+    /// 将指定图元输出为独立的 SVG，图元不存在或没有外接矩形时返回 None
+    pub(crate) fn object_to_svg(&self, object_id: &str, resources: &ResourceManager) -> Option<String> {
+        svg::render_object_svg(self.object_by_id(object_id)?, resources)
+    }
+
+    /// 按绘制顺序排列的图层：背景层、正文层、前景层，同类型的图层保持声明顺序
+    pub(crate) fn layers(&self) -> Vec<&Layer> {
+        let mut layers: Vec<&Layer> = self.content.iter().flat_map(|c| &c.layer).collect();
+        layers.sort_by_key(|layer| layer.layer_type.drawing_order());
+        layers
+    }
+
+    /// 按绘制顺序遍历页面中的所有图元，PageBlock 会被展开
+    pub(crate) fn objects(&self) -> Vec<&PageObject> {
+        self.objects_with_capacity(0)
+    }
+
+    /// 同 objects，按给定的容量预分配结果，避免大页面反复扩容；容量可由 objects_capacity 估计
+    pub(crate) fn objects_with_capacity(&self, capacity: usize) -> Vec<&PageObject> {
+        let mut result = Vec::with_capacity(capacity);
+        self.append_objects(&mut result);
+        result
+    }
+
+    /// 将页面中的图元按绘制顺序追加到 result 之后
+    pub(crate) fn append_objects<'a>(&'a self, result: &mut Vec<&'a PageObject>) {
+        for layer in self.layers() {
+            collect_objects(&layer.objects, result);
+        }
+    }
+
+    /// 未声明 Boundary 的图元按规范以页面区域 page_box 作为外接矩形，其坐标原点随之取页面区域的左上角
+    /// 解析时缺失的 Boundary 为全 0，与显式声明的全 0 无法区分，两者都按缺失处理
+    pub(crate) fn default_boundaries(&mut self, page_box: STBox) {
+        fn fill(objects: &mut [PageObject], page_box: STBox) {
+            for object in objects {
+                match object {
+                    PageObject::PageBlock(block) => fill(&mut block.objects, page_box),
+                    _ => {
+                        if let Some(boundary) = object.boundary_mut().filter(|b| **b == STBox::default()) {
+                            *boundary = page_box;
+                        }
+                    }
+                }
+            }
+        }
+        if let Some(content) = &mut self.content {
+            for layer in &mut content.layer {
+                fill(&mut layer.objects, page_box);
+            }
+        }
+    }
+
+    /// 页面中所有图元外接矩形的并集，没有可计算外接矩形的图元时返回 None
+    pub(crate) fn content_bounds(&self) -> Option<STBox> {
+        let (min_x, min_y, max_x, max_y) = self.objects().iter()
+            .filter_map(|o| o.boundary())
+            .filter(|b| b.w > 0.0 && b.h > 0.0)
+            .fold(None, |acc: Option<(f64, f64, f64, f64)>, b| {
+                let (x0, y0, x1, y1) = acc.unwrap_or((b.x, b.y, b.x + b.w, b.y + b.h));
+                Some((x0.min(b.x), y0.min(b.y), x1.max(b.x + b.w), y1.max(b.y + b.h)))
+            })?;
+        Some(STBox { x: min_x, y: min_y, w: max_x - min_x, h: max_y - min_y })
+    }
+}
+
+fn index_objects(
+    objects: &[PageObject],
+    path: &mut Vec<usize>,
+    index: &mut HashMap<String, Vec<usize>>,
+    duplicates: &mut Vec<String>,
+) {
+    for (i, object) in objects.iter().enumerate() {
+        path.push(i);
+        let id = object.id();
+        if !id.is_empty() {
+            if index.contains_key(id) {
+                duplicates.push(id.to_string());
+            } else {
+                index.insert(id.to_string(), path.clone());
+            }
+        }
+        if let PageObject::PageBlock(block) = object {
+            index_objects(&block.objects, path, index, duplicates);
+        }
+        path.pop();
+    }
+}
+
+pub(crate) fn collect_objects<'a>(objects: &'a [PageObject], result: &mut Vec<&'a PageObject>) {
+    for object in objects {
+        match object {
+            PageObject::PageBlock(block) => collect_objects(&block.objects, result),
+            _ => result.push(object),
+        }
+    }
+}
+
+/// 页面引用的模板，ZOrder 未指定时使用模板页声明的层次
+#[derive(Debug, Deserialize, Default)]
+pub(crate) struct PageTemplate {
+    #[serde(rename = "TemplateID")]
+    pub(crate) template_id: String,
+    #[serde(rename = "ZOrder")]
+    pub(crate) z_order: Option<ZOrder>,
+}
+
+#[derive(Debug, Deserialize, Default)]
+#[serde(default, rename_all = "PascalCase")]
+pub(crate) struct Content {
+    pub(crate) layer: Vec<Layer>,
+}
+
+#[derive(Debug, Deserialize, Default)]
+#[serde(default)]
+pub(crate) struct Layer {
+    #[serde(rename = "ID")]
+    pub(crate) id: String,
+    /// 图层的类型，决定图层之间的绘制顺序
+    #[serde(rename = "Type")]
+    pub(crate) layer_type: LayerType,
+    /// 图层引用的绘制参数，图层中的图元未指定绘制参数时继承
+    #[serde(rename = "DrawParam")]
+    pub(crate) draw_param: Option<String>,
+    #[serde(rename = "$value")]
+    pub(crate) objects: Vec<PageObject>,
+}
+
+/// 图层类型，按背景层、正文层、前景层的顺序绘制，缺省为正文层
+#[derive(Debug, Deserialize, Default, Clone, Copy, PartialEq, Eq)]
+pub(crate) enum LayerType {
+    Background,
+    Foreground,
+    #[default]
+    #[serde(other)]
+    Body,
+}
+
+impl LayerType {
+    fn drawing_order(self) -> u8 {
+        match self {
+            LayerType::Background => 0,
+            LayerType::Body => 1,
+            LayerType::Foreground => 2,
+        }
+    }
+}
+
+/// 页面中的图元
+#[derive(Debug, Deserialize)]
+#[allow(clippy::large_enum_variant)]
+pub(crate) enum PageObject {
+    TextObject(TextObject),
+    PathObject(PathObject),
+    ImageObject(ImageObject),
+    CompositeObject(CompositeObject),
+    PageBlock(PageBlock),
+}
+
+impl PageObject {
+    pub(crate) fn id(&self) -> &str {
+        match self {
+            PageObject::TextObject(o) => &o.id,
+            PageObject::PathObject(o) => &o.id,
+            PageObject::ImageObject(o) => &o.id,
+            PageObject::CompositeObject(o) => &o.id,
+            PageObject::PageBlock(o) => &o.id,
+        }
+    }
+
+    pub(crate) fn kind(&self) -> ObjectKind {
+        match self {
+            PageObject::TextObject(_) => ObjectKind::Text,
+            PageObject::PathObject(_) => ObjectKind::Path,
+            PageObject::ImageObject(_) => ObjectKind::Image,
+            PageObject::CompositeObject(_) => ObjectKind::Composite,
+            PageObject::PageBlock(_) => ObjectKind::PageBlock,
+        }
+    }
+
+    /// 图元的外接矩形，页块没有外接矩形
+    pub(crate) fn boundary(&self) -> Option<STBox> {
+        match self {
+            PageObject::TextObject(o) => Some(o.boundary),
+            PageObject::PathObject(o) => Some(o.boundary),
+            PageObject::ImageObject(o) => Some(o.boundary),
+            PageObject::CompositeObject(o) => Some(o.boundary),
+            PageObject::PageBlock(_) => None,
+        }
+    }
+
+    /// 图元的变换矩阵，页块没有变换矩阵
+    pub(crate) fn ctm(&self) -> Option<STMatrix> {
+        match self {
+            PageObject::TextObject(o) => o.ctm,
+            PageObject::PathObject(o) => o.ctm,
+            PageObject::ImageObject(o) => o.ctm,
+            PageObject::CompositeObject(o) => o.ctm,
+            PageObject::PageBlock(_) => None,
+        }
+    }
+
+    fn boundary_mut(&mut self) -> Option<&mut STBox> {
+        match self {
+            PageObject::TextObject(o) => Some(&mut o.boundary),
+            PageObject::PathObject(o) => Some(&mut o.boundary),
+            PageObject::ImageObject(o) => Some(&mut o.boundary),
+            PageObject::CompositeObject(o) => Some(&mut o.boundary),
+            PageObject::PageBlock(_) => None,
+        }
+    }
+
+    /// 图元的裁剪区，页块没有裁剪区
+    pub(crate) fn clips(&self) -> Option<&Clips> {
+        match self {
+            PageObject::TextObject(o) => o.clips(),
+            PageObject::PathObject(o) => o.clips.as_ref(),
+            PageObject::ImageObject(o) => o.clips.as_ref(),
+            PageObject::CompositeObject(o) => o.clips.as_ref(),
+            PageObject::PageBlock(_) => None,
+        }
+    }
+
+    /// 图元动作中的链接目标，没有 URI 或跳转动作时返回 None
+    pub(crate) fn link_target(&self) -> Option<LinkTarget> {
+        let actions = match self {
+            PageObject::TextObject(o) => o.children.iter().find_map(|c| match c {
+                TextObjectChild::Actions(actions) => Some(actions),
+                _ => None,
+            }),
+            PageObject::PathObject(o) => o.actions.as_ref(),
+            PageObject::ImageObject(o) => o.actions.as_ref(),
+            PageObject::CompositeObject(o) => o.actions.as_ref(),
+            PageObject::PageBlock(_) => None,
+        };
+        actions?.link_target()
+    }
+
+    /// 图元自身声明的透明度与绘制参数，页块没有这两个属性
+    pub(crate) fn alpha_and_draw_param(&self) -> (Option<u8>, Option<&str>) {
+        match self {
+            PageObject::TextObject(o) => (o.alpha, o.draw_param.as_deref()),
+            PageObject::PathObject(o) => (o.alpha, o.draw_param.as_deref()),
+            PageObject::ImageObject(o) => (o.alpha, o.draw_param.as_deref()),
+            PageObject::CompositeObject(o) => (o.alpha, o.draw_param.as_deref()),
+            PageObject::PageBlock(_) => (None, None),
+        }
+    }
+
+    /// 图元与背景的混合模式，未指定时为 None（即 Normal），页块没有该属性
+    pub(crate) fn blend_mode(&self) -> Option<&str> {
+        match self {
+            PageObject::TextObject(o) => o.blend_mode.as_deref(),
+            PageObject::PathObject(o) => o.blend_mode.as_deref(),
+            PageObject::ImageObject(o) => o.blend_mode.as_deref(),
+            PageObject::CompositeObject(o) => o.blend_mode.as_deref(),
+            PageObject::PageBlock(_) => None,
+        }
+    }
+
+    /// 图元实际使用的填充颜色，不填充的图元返回 None
+    /// 未指定颜色时使用规范默认的黑色
+    pub(crate) fn fill_color(&self) -> Option<CTColor> {
+        match self {
+            PageObject::TextObject(o) if o.fill => Some(o.fill_color().cloned().unwrap_or_default()),
+            PageObject::PathObject(o) if o.fill => Some(o.fill_color.clone().unwrap_or_default()),
+            _ => None,
+        }
+    }
+
+    /// 图元实际使用的勾边颜色，不勾边的图元返回 None
+    pub(crate) fn stroke_color(&self) -> Option<CTColor> {
+        match self {
+            PageObject::TextObject(o) if o.stroke => Some(o.stroke_color().cloned().unwrap_or_default()),
+            PageObject::PathObject(o) if o.stroke => Some(o.stroke_color.clone().unwrap_or_default()),
+            _ => None,
+        }
+    }
+}
+
+/// 图元的类型
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ObjectKind {
+    Text,
+    Path,
+    Image,
+    Composite,
+    PageBlock,
+}
+
+/// 图元的基本信息
+#[derive(Debug, Clone)]
+pub struct ObjectInfo {
+    pub id: String,
+    pub kind: ObjectKind,
+    /// 外接矩形，页面坐标系（毫米），页块没有外接矩形
+    pub boundary: Option<STBox>,
+}
+
+impl From<&PageObject> for ObjectInfo {
+    fn from(object: &PageObject) -> Self {
+        ObjectInfo {
+            id: object.id().to_string(),
+            kind: object.kind(),
+            boundary: object.boundary(),
+        }
+    }
+}
+
+/// 由 OfdDoc::iter_objects 逐个给出的图元及其完整的变换
+#[derive(Debug, Clone)]
+pub struct ResolvedObject {
+    /// 所在页面的序号，从 0 开始
+    pub page_index: usize,
+    pub object: ObjectInfo,
+    /// 图元坐标到显示坐标（毫米，以旋转后页面的左上角为原点）的变换，
+    /// 依次为图元的 CTM、外接矩形原点的平移与页面的旋转；图层与页块没有自身的变换
+    pub ctm: STMatrix,
+    /// 外接矩形，页面坐标系（毫米）
+    pub boundary: STBox,
+    /// 图元来自页面引用的模板页
+    pub from_template: bool,
+}
+
+fn default_true() -> bool {
+    true
+}
+
+/// 文字对象 ofd:TextObject
+#[derive(Debug, Deserialize, Default)]
+pub(crate) struct TextObject {
+    #[serde(rename = "ID", default)]
+    pub(crate) id: String,
+    #[serde(rename = "Boundary", deserialize_with = "deserialize_st", default)]
+    pub(crate) boundary: STBox,
+    #[serde(rename = "CTM", deserialize_with = "deserialize_st_opt", default)]
+    pub(crate) ctm: Option<STMatrix>,
+    /// 与背景的混合模式，缺省为 Normal
+    #[serde(rename = "BlendMode")]
+    pub(crate) blend_mode: Option<String>,
+    /// 图元的透明度，0 为全透明，255 为不透明；未指定时取绘制参数中的透明度
+    #[serde(rename = "Alpha")]
+    pub(crate) alpha: Option<u8>,
+    /// 引用资源中的绘制参数
+    #[serde(rename = "DrawParam")]
+    pub(crate) draw_param: Option<String>,
+    #[serde(rename = "Font", default)]
+    pub(crate) font: String,
+    #[serde(rename = "Size", default)]
+    pub(crate) size: f64,
+    /// 字形在水平方向的缩放比例，小于 1 为长体，大于 1 为扁体
+    #[serde(rename = "HScale")]
+    pub(crate) h_scale: Option<f64>,
+    #[serde(rename = "Fill", default = "default_true")]
+    pub(crate) fill: bool,
+    #[serde(rename = "Stroke", default)]
+    pub(crate) stroke: bool,
+    /// 阅读方向，即字符排列的方向，按顺时针旋转的角度，取 0、90、180、270，缺省为 0
+    #[serde(rename = "ReadDirection", default)]
+    pub(crate) read_direction: i32,
+    /// 字符方向，即字形按顺时针旋转的角度，取值同 ReadDirection
+    #[serde(rename = "CharDirection", default)]
+    pub(crate) char_direction: i32,
+    /// 文字的粗细，取 100 到 900，缺省为 400
+    #[serde(rename = "Weight")]
+    pub(crate) weight: Option<u16>,
+    #[serde(rename = "Italic", default)]
+    pub(crate) italic: bool,
+    /// 子节点中含有多个 TextCode，serde-xml-rs 无法在枚举内正确解析重复的具名子节点，
+    /// 因此按顺序整体解析后再通过访问方法读取
+    #[serde(rename = "$value", default)]
+    pub(crate) children: Vec<TextObjectChild>,
+}
+
+impl TextObject {
+    /// Weight 不小于 600 时按粗体处理
+    pub(crate) fn is_bold(&self) -> bool {
+        self.weight.unwrap_or(400) >= 600
+    }
+
+    /// 字形的水平缩放比例，未指定或不是正数时为 1
+    pub(crate) fn h_scale(&self) -> f64 {
+        self.h_scale.filter(|s| s.is_finite() && *s > 0.0).unwrap_or(1.0)
+    }
+
+    pub(crate) fn fill_color(&self) -> Option<&CTColor> {
+        self.children.iter().find_map(|c| match c {
+            TextObjectChild::FillColor(color) => Some(color),
+            _ => None,
+        })
+    }
+
+    pub(crate) fn stroke_color(&self) -> Option<&CTColor> {
+        self.children.iter().find_map(|c| match c {
+            TextObjectChild::StrokeColor(color) => Some(color),
+            _ => None,
+        })
+    }
+
+    /// 文字的颜色覆盖绘制参数后的样式，线条属性全部取自绘制参数
+    pub(crate) fn style(&self, param: &DrawParam) -> DrawStyle {
+        DrawStyle {
+            line_width: resolve_line_width(None, param),
+            cap: param.cap.unwrap_or_default(),
+            join: param.join.unwrap_or_default(),
+            miter_limit: param.miter_limit,
+            dash_offset: param.dash_offset,
+            dash_pattern: param.dash_pattern.clone(),
+            fill_color: resolve_fill_color(self.fill_color(), param),
+            stroke_color: resolve_stroke_color(self.stroke_color(), param),
+        }
+    }
+
+    pub(crate) fn clips(&self) -> Option<&Clips> {
+        self.children.iter().find_map(|c| match c {
+            TextObjectChild::Clips(clips) => Some(clips),
+            _ => None,
+        })
+    }
+
+    /// 按 CGTransform 指定了字形的字符，键为字符在全部 TextCode 中的序号，值为该字符绘制的字形索引
+    /// 字符数与字形数相同时逐一对应；否则全部字形归于范围内的第一个字符，其余字符不绘制字形
+    /// 字形列表无法解析的变换记录警告后忽略，这些字符仍按字体的 cmap 查找字形
+    pub(crate) fn glyph_overrides(&self) -> HashMap<usize, Vec<u16>> {
+        let mut overrides = HashMap::new();
+        for transform in self.children.iter().filter_map(|c| match c {
+            TextObjectChild::CGTransform(t) => Some(t),
+            _ => None,
+        }) {
+            let glyphs: Option<Vec<u16>> = transform.glyphs.split_whitespace().map(|g| g.parse().ok()).collect();
+            let Some(mut glyphs) = glyphs.filter(|g| !g.is_empty() && transform.code_count > 0) else {
+                log::warn!("ignoring invalid CGTransform on text object {}", self.id);
+                continue;
+            };
+            glyphs.truncate(transform.glyph_count.max(1));
+            let start = transform.code_position;
+            if glyphs.len() == transform.code_count {
+                for (i, glyph) in glyphs.into_iter().enumerate() {
+                    overrides.insert(start + i, vec![glyph]);
+                }
+            } else {
+                for i in 1..transform.code_count {
+                    overrides.insert(start + i, Vec::new());
+                }
+                overrides.insert(start, glyphs);
+            }
+        }
+        overrides
+    }
+
+    pub(crate) fn text_codes(&self) -> impl Iterator<Item = &TextCode> {
+        self.children.iter().filter_map(|c| match c {
+            TextObjectChild::TextCode(code) => Some(code),
+            _ => None,
+        })
+    }
+}
+
+/// 文字对象的子节点
+#[derive(Debug, Deserialize)]
+pub(crate) enum TextObjectChild {
+    FillColor(CTColor),
+    StrokeColor(CTColor),
+    TextCode(TextCode),
+    Clips(Clips),
+    CGTransform(CGTransform),
+    Actions(Actions),
+}
+
+/// 字符与字形的变换 CT_CGTransform，将从 CodePosition 起的 CodeCount 个字符映射为 Glyphs 中的 GlyphCount 个字形
+/// CodePosition 为字符在文字对象全部 TextCode 依次拼接后的序号
+#[derive(Debug, Deserialize, Default)]
+pub(crate) struct CGTransform {
+    #[serde(rename = "CodePosition", default)]
+    pub(crate) code_position: usize,
+    #[serde(rename = "CodeCount", default = "default_count")]
+    pub(crate) code_count: usize,
+    #[serde(rename = "GlyphCount", default = "default_count")]
+    pub(crate) glyph_count: usize,
+    #[serde(rename = "Glyphs", default)]
+    pub(crate) glyphs: String,
+}
+
+fn default_count() -> usize {
+    1
+}
+
+/// 图元的裁剪区 ofd:Clips，图元只在各 Clip 的交集内可见
+/// 与 Layer 相同，子节点按顺序整体解析，具名的重复子节点会使 serde-xml-rs 向后预读，
+/// 在 Clips 位于其他子节点之后时越过所在图元的结束标签
+#[derive(Debug, Deserialize, Default)]
+pub(crate) struct Clips {
+    #[serde(rename = "$value", default)]
+    pub(crate) clip: Vec<Clip>,
+}
+
+/// 一个裁剪区 ofd:Clip，由其中各 Area 的并集构成
+#[derive(Debug, Deserialize, Default)]
+pub(crate) struct Clip {
+    #[serde(rename = "$value", default)]
+    pub(crate) area: Vec<ClipArea>,
+}
+
+/// 裁剪区的一个组成部分，由图形对象描述；规范也允许使用文字对象，暂不支持
+/// 坐标以所在图元外接矩形的左上角为原点，CTM 作用于其中的图形之上
+#[derive(Debug, Deserialize, Default)]
+pub(crate) struct ClipArea {
+    #[serde(rename = "CTM", deserialize_with = "deserialize_st_opt", default)]
+    pub(crate) ctm: Option<STMatrix>,
+    #[serde(rename = "Path")]
+    pub(crate) path: Option<ClipPath>,
+}
+
+/// 描述裁剪区的图形，只关心其位置与形状
+#[derive(Debug, Deserialize, Default)]
+pub(crate) struct ClipPath {
+    #[serde(rename = "Boundary", deserialize_with = "deserialize_st", default)]
+    pub(crate) boundary: STBox,
+    #[serde(rename = "CTM", deserialize_with = "deserialize_st_opt", default)]
+    pub(crate) ctm: Option<STMatrix>,
+    #[serde(rename = "AbbreviatedData", default)]
+    pub(crate) abbreviated_data: String,
+}
+
+/// 暂不处理的子节点，只消费其内容
+#[derive(Debug, Deserialize, Default)]
+pub(crate) struct Ignored {}
+
+/// 文字对象中的一段文字 ofd:TextCode
+#[derive(Debug, Deserialize, Default)]
+pub(crate) struct TextCode {
+    #[serde(rename = "X", default)]
+    pub(crate) x: f64,
+    #[serde(rename = "Y", default)]
+    pub(crate) y: f64,
+    #[serde(rename = "DeltaX")]
+    pub(crate) delta_x: Option<String>,
+    #[serde(rename = "DeltaY")]
+    pub(crate) delta_y: Option<String>,
+    #[serde(rename = "$value", default)]
+    pub(crate) text: String,
+}
+
+/// 图形对象 ofd:PathObject
+#[derive(Debug, Deserialize, Default)]
+pub(crate) struct PathObject {
+    #[serde(rename = "ID", default)]
+    pub(crate) id: String,
+    #[serde(rename = "Boundary", deserialize_with = "deserialize_st", default)]
+    pub(crate) boundary: STBox,
+    #[serde(rename = "CTM", deserialize_with = "deserialize_st_opt", default)]
+    pub(crate) ctm: Option<STMatrix>,
+    /// 与背景的混合模式，缺省为 Normal
+    #[serde(rename = "BlendMode")]
+    pub(crate) blend_mode: Option<String>,
+    /// 图元的透明度，0 为全透明，255 为不透明；未指定时取绘制参数中的透明度
+    #[serde(rename = "Alpha")]
+    pub(crate) alpha: Option<u8>,
+    /// 引用资源中的绘制参数
+    #[serde(rename = "DrawParam")]
+    pub(crate) draw_param: Option<String>,
+    /// 图元的裁剪区
+    #[serde(rename = "Clips")]
+    pub(crate) clips: Option<Clips>,
+    /// 图元的动作，如点击时打开的链接
+    #[serde(rename = "Actions")]
+    pub(crate) actions: Option<Actions>,
+    #[serde(rename = "LineWidth")]
+    pub(crate) line_width: Option<f64>,
+    #[serde(rename = "Cap")]
+    pub(crate) cap: Option<LineCap>,
+    #[serde(rename = "Join")]
+    pub(crate) join: Option<LineJoin>,
+    #[serde(rename = "MiterLimit")]
+    pub(crate) miter_limit: Option<f64>,
+    #[serde(rename = "DashOffset")]
+    pub(crate) dash_offset: Option<f64>,
+    #[serde(rename = "DashPattern", deserialize_with = "deserialize_st_opt", default)]
+    pub(crate) dash_pattern: Option<STArray>,
+    #[serde(rename = "Stroke", default = "default_true")]
+    pub(crate) stroke: bool,
+    #[serde(rename = "Fill", default)]
+    pub(crate) fill: bool,
+    #[serde(rename = "Rule", default)]
+    pub(crate) rule: FillRule,
+    #[serde(rename = "FillColor")]
+    pub(crate) fill_color: Option<CTColor>,
+    #[serde(rename = "StrokeColor")]
+    pub(crate) stroke_color: Option<CTColor>,
+    #[serde(rename = "AbbreviatedData", default)]
+    pub(crate) abbreviated_data: String,
+}
+
+impl PathObject {
+    /// 图元自身的属性覆盖绘制参数后的线条与颜色样式
+    pub(crate) fn style(&self, param: &DrawParam) -> DrawStyle {
+        DrawStyle {
+            line_width: resolve_line_width(self.line_width, param),
+            cap: self.cap.or(param.cap).unwrap_or_default(),
+            join: self.join.or(param.join).unwrap_or_default(),
+            miter_limit: self.miter_limit.or(param.miter_limit),
+            dash_offset: self.dash_offset.or(param.dash_offset),
+            dash_pattern: self.dash_pattern.clone().or_else(|| param.dash_pattern.clone()),
+            fill_color: resolve_fill_color(self.fill_color.as_ref(), param),
+            stroke_color: resolve_stroke_color(self.stroke_color.as_ref(), param),
+        }
+    }
+}
+
+/// 图元绘制时实际使用的线条与颜色样式，颜色与线宽已按 resolve_* 的顺序确定，斜接限制等未指定时由绘制方使用规范的缺省值
+#[derive(Debug, Clone, Default)]
+pub(crate) struct DrawStyle {
+    pub(crate) line_width: f64,
+    pub(crate) cap: LineCap,
+    pub(crate) join: LineJoin,
+    pub(crate) miter_limit: Option<f64>,
+    pub(crate) dash_offset: Option<f64>,
+    pub(crate) dash_pattern: Option<STArray>,
+    /// 未指定颜色时使用规范默认的黑色
+    pub(crate) fill_color: CTColor,
+    pub(crate) stroke_color: CTColor,
+}
+
+/// 图元的填充颜色，依次取：图元自身的属性、图元引用的绘制参数、所在图层的绘制参数、规范缺省的黑色
+/// param 为 ResourceManager::effective_draw_param 合并后的绘制参数，已包含前两级绘制参数；
+/// OFD 没有文档级的缺省绘制属性，图层之后直接取规范缺省值
+pub(crate) fn resolve_fill_color(own: Option<&CTColor>, param: &DrawParam) -> CTColor {
+    own.or(param.fill_color.as_ref()).cloned().unwrap_or_default()
+}
+
+/// 图元的勾边颜色，取值顺序同 resolve_fill_color
+pub(crate) fn resolve_stroke_color(own: Option<&CTColor>, param: &DrawParam) -> CTColor {
+    own.or(param.stroke_color.as_ref()).cloned().unwrap_or_default()
+}
+
+/// 图元的线宽，取值顺序同 resolve_fill_color，最后为规范缺省的 0.353mm；0 或负数视为未指定
+pub(crate) fn resolve_line_width(own: Option<f64>, param: &DrawParam) -> f64 {
+    own.or(param.line_width)
+        .filter(|w| *w > 0.0)
+        .unwrap_or(DEFAULT_LINE_WIDTH)
+}
+
+/// 填充规则，缺省为非零绕数规则
+#[derive(Debug, Deserialize, Default, Clone, Copy, PartialEq, Eq)]
+pub(crate) enum FillRule {
+    #[serde(rename = "Even-Odd")]
+    EvenOdd,
+    #[default]
+    #[serde(other)]
+    NonZero,
+}
+
+/// 线端点样式，缺省为 Butt
+#[derive(Debug, Deserialize, Default, Clone, Copy, PartialEq, Eq)]
+pub(crate) enum LineCap {
+    Round,
+    Square,
+    #[default]
+    #[serde(other)]
+    Butt,
+}
+
+/// 线条连接样式，缺省为 Miter
+#[derive(Debug, Deserialize, Default, Clone, Copy, PartialEq, Eq)]
+pub(crate) enum LineJoin {
+    Round,
+    Bevel,
+    #[default]
+    #[serde(other)]
+    Miter,
+}
+
+/// 图像对象 ofd:ImageObject
+#[derive(Debug, Deserialize, Default)]
+pub(crate) struct ImageObject {
+    #[serde(rename = "ID", default)]
+    pub(crate) id: String,
+    #[serde(rename = "Boundary", deserialize_with = "deserialize_st", default)]
+    pub(crate) boundary: STBox,
+    #[serde(rename = "CTM", deserialize_with = "deserialize_st_opt", default)]
+    pub(crate) ctm: Option<STMatrix>,
+    /// 与背景的混合模式，缺省为 Normal
+    #[serde(rename = "BlendMode")]
+    pub(crate) blend_mode: Option<String>,
+    /// 图元的透明度，0 为全透明，255 为不透明；未指定时取绘制参数中的透明度
+    #[serde(rename = "Alpha")]
+    pub(crate) alpha: Option<u8>,
+    /// 引用资源中的绘制参数
+    #[serde(rename = "DrawParam")]
+    pub(crate) draw_param: Option<String>,
+    /// 图元的裁剪区
+    #[serde(rename = "Clips")]
+    pub(crate) clips: Option<Clips>,
+    /// 图元的动作，如点击时打开的链接
+    #[serde(rename = "Actions")]
+    pub(crate) actions: Option<Actions>,
+    #[serde(rename = "ResourceID", default)]
+    pub(crate) resource_id: String,
+}
+
+/// 复合对象 ofd:CompositeObject
+#[derive(Debug, Deserialize, Default)]
+pub(crate) struct CompositeObject {
+    #[serde(rename = "ID", default)]
+    pub(crate) id: String,
+    #[serde(rename = "Boundary", deserialize_with = "deserialize_st", default)]
+    pub(crate) boundary: STBox,
+    #[serde(rename = "CTM", deserialize_with = "deserialize_st_opt", default)]
+    pub(crate) ctm: Option<STMatrix>,
+    /// 与背景的混合模式，缺省为 Normal
+    #[serde(rename = "BlendMode")]
+    pub(crate) blend_mode: Option<String>,
+    /// 图元的透明度，0 为全透明，255 为不透明；未指定时取绘制参数中的透明度
+    #[serde(rename = "Alpha")]
+    pub(crate) alpha: Option<u8>,
+    /// 引用资源中的绘制参数
+    #[serde(rename = "DrawParam")]
+    pub(crate) draw_param: Option<String>,
+    /// 图元的裁剪区
+    #[serde(rename = "Clips")]
+    pub(crate) clips: Option<Clips>,
+    /// 图元的动作，如点击时打开的链接
+    #[serde(rename = "Actions")]
+    pub(crate) actions: Option<Actions>,
+    #[serde(rename = "ResourceID", default)]
+    pub(crate) resource_id: String,
+}
+
+/// 页块 ofd:PageBlock，可嵌套其他图元
+#[derive(Debug, Deserialize, Default)]
+pub(crate) struct PageBlock {
+    #[serde(rename = "ID", default)]
+    pub(crate) id: String,
+    #[serde(rename = "$value", default)]
+    pub(crate) objects: Vec<PageObject>,
+}
+
+impl PageBlock {
+    /// 按绘制顺序遍历其中的所有图元，嵌套的 PageBlock 会被展开
+    pub(crate) fn objects(&self) -> Vec<&PageObject> {
+        let mut result = Vec::new();
+        collect_objects(&self.objects, &mut result);
+        result
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// 两层嵌套的 PageBlock，内层与外层各有一个图元，标识 2 在内层重复出现
+    const NESTED_BLOCKS: &str = "<ofd:Page xmlns:ofd=\"http://www.ofdspec.org/2016\"><ofd:Content>\
+        <ofd:Layer ID=\"1\"><ofd:PathObject ID=\"2\" Boundary=\"0 0 10 10\"/>\
+        <ofd:PageBlock ID=\"3\"><ofd:PathObject ID=\"4\" Boundary=\"0 0 10 10\"/>\
+        <ofd:PageBlock ID=\"5\"><ofd:TextObject ID=\"6\" Boundary=\"0 0 10 10\" Font=\"7\" Size=\"3\"/>\
+        <ofd:PathObject ID=\"2\" Boundary=\"0 0 20 20\"/></ofd:PageBlock></ofd:PageBlock>\
+        </ofd:Layer></ofd:Content></ofd:Page>";
+
+    #[test]
+    fn object_by_id_finds_objects_in_nested_page_blocks() {
+        let page = Page::from_xml(NESTED_BLOCKS).unwrap();
+        assert!(matches!(page.object_by_id("4"), Some(PageObject::PathObject(o)) if o.id == "4"));
+        assert!(matches!(page.object_by_id("5"), Some(PageObject::PageBlock(b)) if b.objects.len() == 2));
+        assert!(matches!(page.object_by_id("6"), Some(PageObject::TextObject(o)) if o.id == "6"));
+        assert!(page.object_by_id("8").is_none());
+    }
+
+    #[test]
+    fn duplicate_ids_keep_the_first_object() {
+        let page = Page::from_xml(NESTED_BLOCKS).unwrap();
+        assert_eq!(page.duplicate_ids(), ["2".to_string()]);
+        let Some(PageObject::PathObject(first)) = page.object_by_id("2") else { panic!("ID 2 is not a path") };
+        assert_eq!(first.boundary.w, 10.0);
+    }
+
+    /// 正文层中只有一个文字对象 ID 2 的页面，transforms 为其中的 CGTransform
+    fn text_with_transforms(transforms: &str) -> Page {
+        Page::from_xml(&format!(
+            "<ofd:Page xmlns:ofd=\"http://www.ofdspec.org/2016\"><ofd:Content><ofd:Layer ID=\"1\">\
+             <ofd:TextObject ID=\"2\" Boundary=\"0 0 40 10\" Font=\"3\" Size=\"5\">{}\
+             <ofd:TextCode X=\"0\" Y=\"5\">ABCDEF</ofd:TextCode></ofd:TextObject></ofd:Layer></ofd:Content></ofd:Page>",
+            transforms,
+        )).unwrap()
+    }
+
+    fn overrides(page: &Page) -> HashMap<usize, Vec<u16>> {
+        let Some(PageObject::TextObject(text)) = page.object_by_id("2") else { panic!("ID 2 is not a text object") };
+        text.glyph_overrides()
+    }
+
+    #[test]
+    fn glyph_overrides_map_one_to_one_and_many_to_one() {
+        let page = text_with_transforms("<ofd:CGTransform CodePosition=\"0\" CodeCount=\"2\" GlyphCount=\"2\"><ofd:Glyphs>5 6</ofd:Glyphs></ofd:CGTransform>\
+            <ofd:CGTransform CodePosition=\"3\" CodeCount=\"3\" GlyphCount=\"1\"><ofd:Glyphs>9</ofd:Glyphs></ofd:CGTransform>");
+        let expected: HashMap<usize, Vec<u16>> = [
+            (0, vec![5]),
+            (1, vec![6]),
+            // 三个字符合成一个字形，字形归于第一个字符，其余字符不绘制
+            (3, vec![9]),
+            (4, vec![]),
+            (5, vec![]),
+        ].into_iter().collect();
+        assert_eq!(overrides(&page), expected);
+    }
+
+    #[test]
+    fn invalid_glyph_overrides_are_ignored() {
+        let page = text_with_transforms("<ofd:CGTransform CodePosition=\"0\" CodeCount=\"2\" GlyphCount=\"2\"><ofd:Glyphs>5 x</ofd:Glyphs></ofd:CGTransform>\
+            <ofd:CGTransform CodePosition=\"2\" CodeCount=\"1\" GlyphCount=\"1\"/>\
+            <ofd:CGTransform CodePosition=\"3\" CodeCount=\"0\" GlyphCount=\"1\"><ofd:Glyphs>7</ofd:Glyphs></ofd:CGTransform>\
+            <ofd:CGTransform CodePosition=\"4\" CodeCount=\"1\" GlyphCount=\"1\"><ofd:Glyphs>8</ofd:Glyphs></ofd:CGTransform>");
+        assert_eq!(overrides(&page), HashMap::from([(4, vec![8])]));
+    }
+}
@@ -0,0 +1,450 @@
+use std::f64::consts::PI;
+
+use crate::st_types::{PathElement, STBox, STMatrix, STPath, STPos};
+
+/// 路径片段，坐标均为绝对坐标，与具体的绘图后端无关
+/// 圆弧已分解为三次贝塞尔曲线，因此只有移动、直线、二次与三次贝塞尔曲线和闭合五种
+#[derive(Debug, Clone, PartialEq)]
+pub enum PathSegment {
+    MoveTo { to: STPos },
+    LineTo { to: STPos },
+    QuadTo { ctrl: STPos, to: STPos },
+    CubicTo { c1: STPos, c2: STPos, to: STPos },
+    Close,
+}
+
+/// 由 STPath 展开得到的可直接交给光栅化器的路径
+/// 圆弧已分解为三次贝塞尔曲线
+#[derive(Debug, Clone, Default)]
+pub(crate) struct RenderPath {
+    pub(crate) segments: Vec<PathSegment>,
+}
+
+impl RenderPath {
+    /// 对路径中的各点应用仿射变换，贝塞尔曲线在仿射变换下只需变换控制点
+    pub(crate) fn transform(&self, m: &STMatrix) -> RenderPath {
+        let segments = self.segments.iter().map(|segment| match segment {
+            PathSegment::MoveTo { to: p } => PathSegment::MoveTo { to: m.apply(*p) },
+            PathSegment::LineTo { to: p } => PathSegment::LineTo { to: m.apply(*p) },
+            PathSegment::QuadTo { ctrl: c, to: p } => PathSegment::QuadTo { ctrl: m.apply(*c), to: m.apply(*p) },
+            PathSegment::CubicTo { c1, c2, to: p } => PathSegment::CubicTo { c1: m.apply(*c1), c2: m.apply(*c2), to: m.apply(*p) },
+            PathSegment::Close => PathSegment::Close,
+        }).collect();
+        RenderPath { segments }
+    }
+
+    /// 路径的包围盒，曲线按实际极值点计算
+    /// 空路径返回 None
+    pub(crate) fn bounds(&self) -> Option<STBox> {
+        let mut min_x = f64::INFINITY;
+        let mut min_y = f64::INFINITY;
+        let mut max_x = f64::NEG_INFINITY;
+        let mut max_y = f64::NEG_INFINITY;
+        let mut include = |p: STPos| {
+            min_x = min_x.min(p.x);
+            min_y = min_y.min(p.y);
+            max_x = max_x.max(p.x);
+            max_y = max_y.max(p.y);
+        };
+
+        let mut current = STPos::default();
+        for segment in &self.segments {
+            match segment {
+                PathSegment::MoveTo { to: p } | PathSegment::LineTo { to: p } => {
+                    include(*p);
+                    current = *p;
+                }
+                PathSegment::QuadTo { ctrl: c, to: p } => {
+                    for t in quad_extrema(current, *c, *p) {
+                        include(quad_point(current, *c, *p, t));
+                    }
+                    include(*p);
+                    current = *p;
+                }
+                PathSegment::CubicTo { c1, c2, to: p } => {
+                    for t in cubic_extrema(current, *c1, *c2, *p) {
+                        include(cubic_point(current, *c1, *c2, *p, t));
+                    }
+                    include(*p);
+                    current = *p;
+                }
+                PathSegment::Close => {}
+            }
+        }
+
+        if min_x > max_x {
+            return None;
+        }
+        Some(STBox {
+            x: min_x,
+            y: min_y,
+            w: max_x - min_x,
+            h: max_y - min_y,
+        })
+    }
+}
+
+impl STPath {
+    /// 路径的片段序列，供使用其他绘图库的调用方自行绘制
+    pub fn segments(&self) -> Vec<PathSegment> {
+        self.to_render_path().segments
+    }
+
+    /// 将 STPath 转换为光栅化路径
+    /// S/M 开始新的子路径，C 闭合到当前子路径的起点，A 按 SVG 的算法分解为三次贝塞尔曲线
+    pub(crate) fn to_render_path(&self) -> RenderPath {
+        let mut builder = PathBuilder::with_capacity(self.elements.len());
+        for element in &self.elements {
+            match element {
+                PathElement::StartAt(e) => builder.move_to(e.pos),
+                PathElement::MoveTo(e) => builder.move_to(e.pos),
+                PathElement::LineTo(e) => {
+                    if builder.begin_segment(e.pos) {
+                        builder.segments.push(PathSegment::LineTo { to: e.pos });
+                    }
+                    builder.current = Some(e.pos);
+                }
+                PathElement::QuadraticBezierCurve(e) => {
+                    builder.begin_segment(e.pos1);
+                    builder.segments.push(PathSegment::QuadTo { ctrl: e.pos1, to: e.pos2 });
+                    builder.current = Some(e.pos2);
+                }
+                PathElement::CubicBezierCurve(e) => {
+                    builder.begin_segment(e.pos1);
+                    builder.segments.push(PathSegment::CubicTo { c1: e.pos1, c2: e.pos2, to: e.pos3 });
+                    builder.current = Some(e.pos3);
+                }
+                PathElement::EllipseArc(e) => {
+                    if builder.begin_segment(e.pos) {
+                        let start = builder.current.unwrap_or(e.pos);
+                        arc_to_cubics(start, e.rx, e.ry, e.angle, e.large, e.sweep, e.pos, &mut builder.segments);
+                    }
+                    builder.current = Some(e.pos);
+                }
+                PathElement::ClosePath(_) => builder.close(),
+            }
+        }
+        RenderPath { segments: builder.segments }
+    }
+}
+
+/// 逐段构建 RenderPath，记录当前点与当前子路径的起点
+/// 闭合后的下一段从被闭合子路径的起点开始新的子路径，并显式写出 MoveTo，
+/// 使得到的片段序列不依赖绘图库对闭合后隐式起点的处理
+struct PathBuilder {
+    segments: Vec<PathSegment>,
+    /// 当前点，尚未开始任何子路径时为 None
+    current: Option<STPos>,
+    /// 当前子路径的起点，C 闭合到此点
+    subpath_start: STPos,
+    /// 当前子路径已闭合，下一段需要先移动到 subpath_start
+    closed: bool,
+}
+
+impl PathBuilder {
+    fn with_capacity(capacity: usize) -> PathBuilder {
+        PathBuilder { segments: Vec::with_capacity(capacity), current: None, subpath_start: STPos::default(), closed: false }
+    }
+
+    fn move_to(&mut self, pos: STPos) {
+        self.segments.push(PathSegment::MoveTo { to: pos });
+        self.subpath_start = pos;
+        self.current = Some(pos);
+        self.closed = false;
+    }
+
+    /// 在添加一段之前确保已有当前点，返回是否已有当前点
+    /// 没有当前点时以 fallback 开始新的子路径；刚闭合时从被闭合子路径的起点开始新的子路径
+    fn begin_segment(&mut self, fallback: STPos) -> bool {
+        match self.current {
+            None => {
+                self.move_to(fallback);
+                false
+            }
+            Some(_) if self.closed => {
+                self.move_to(self.subpath_start);
+                true
+            }
+            Some(_) => true,
+        }
+    }
+
+    fn close(&mut self) {
+        if self.current.is_some() && !self.closed {
+            self.segments.push(PathSegment::Close);
+            self.current = Some(self.subpath_start);
+            self.closed = true;
+        }
+    }
+}
+
+/// 按 SVG 规范 F.6 的中心参数化方法，将椭圆弧分解为若干段三次贝塞尔曲线
+/// rx 或 ry 为 0 时退化为直线，半径不足以连接两端点时等比放大
+#[allow(clippy::too_many_arguments)]
+fn arc_to_cubics(
+    from: STPos,
+    rx: f64,
+    ry: f64,
+    angle: f64,
+    large: bool,
+    sweep: bool,
+    to: STPos,
+    segments: &mut Vec<PathSegment>,
+) {
+    // 起点与终点重合时忽略该圆弧
+    if from == to {
+        return;
+    }
+    let mut rx = rx.abs();
+    let mut ry = ry.abs();
+    if rx == 0.0 || ry == 0.0 {
+        segments.push(PathSegment::LineTo { to });
+        return;
+    }
+
+    let phi = angle.to_radians();
+    let (sin_phi, cos_phi) = phi.sin_cos();
+
+    // 第一步：计算 (x1', y1')
+    let dx2 = (from.x - to.x) / 2.0;
+    let dy2 = (from.y - to.y) / 2.0;
+    let x1p = cos_phi * dx2 + sin_phi * dy2;
+    let y1p = -sin_phi * dx2 + cos_phi * dy2;
+
+    // 半径过小时按比例放大
+    let lambda = (x1p * x1p) / (rx * rx) + (y1p * y1p) / (ry * ry);
+    if lambda > 1.0 {
+        let scale = lambda.sqrt();
+        rx *= scale;
+        ry *= scale;
+    }
+
+    // 第二步：计算 (cx', cy')
+    let rx2 = rx * rx;
+    let ry2 = ry * ry;
+    let num = rx2 * ry2 - rx2 * y1p * y1p - ry2 * x1p * x1p;
+    let den = rx2 * y1p * y1p + ry2 * x1p * x1p;
+    let mut coef = if den == 0.0 { 0.0 } else { (num / den).max(0.0).sqrt() };
+    if large == sweep {
+        coef = -coef;
+    }
+    let cxp = coef * rx * y1p / ry;
+    let cyp = -coef * ry * x1p / rx;
+
+    // 第三步：计算圆心 (cx, cy)
+    let cx = cos_phi * cxp - sin_phi * cyp + (from.x + to.x) / 2.0;
+    let cy = sin_phi * cxp + cos_phi * cyp + (from.y + to.y) / 2.0;
+
+    // 第四步：计算起始角与扫过的角度
+    let ux = (x1p - cxp) / rx;
+    let uy = (y1p - cyp) / ry;
+    let vx = (-x1p - cxp) / rx;
+    let vy = (-y1p - cyp) / ry;
+    let theta1 = vector_angle(1.0, 0.0, ux, uy);
+    let mut delta = vector_angle(ux, uy, vx, vy) % (2.0 * PI);
+    if !sweep && delta > 0.0 {
+        delta -= 2.0 * PI;
+    } else if sweep && delta < 0.0 {
+        delta += 2.0 * PI;
+    }
+
+    // 每段不超过 90°，保证贝塞尔近似的精度
+    let count = (delta.abs() / (PI / 2.0)).ceil().max(1.0) as usize;
+    let step = delta / count as f64;
+    let k = 4.0 / 3.0 * (step / 4.0).tan();
+
+    let map = |x: f64, y: f64| STPos {
+        x: cx + rx * cos_phi * x - ry * sin_phi * y,
+        y: cy + rx * sin_phi * x + ry * cos_phi * y,
+    };
+
+    let mut t1 = theta1;
+    for i in 0..count {
+        let t2 = t1 + step;
+        let (sin1, cos1) = t1.sin_cos();
+        let (sin2, cos2) = t2.sin_cos();
+        let c1 = map(cos1 - k * sin1, sin1 + k * cos1);
+        let c2 = map(cos2 + k * sin2, sin2 - k * cos2);
+        // 最后一段直接落在终点上，避免累积误差
+        let end = if i + 1 == count { to } else { map(cos2, sin2) };
+        segments.push(PathSegment::CubicTo { c1, c2, to: end });
+        t1 = t2;
+    }
+}
+
+/// 向量 u 到向量 v 的有符号夹角
+fn vector_angle(ux: f64, uy: f64, vx: f64, vy: f64) -> f64 {
+    let dot = ux * vx + uy * vy;
+    let len = (ux * ux + uy * uy).sqrt() * (vx * vx + vy * vy).sqrt();
+    let mut angle = (dot / len).clamp(-1.0, 1.0).acos();
+    if ux * vy - uy * vx < 0.0 {
+        angle = -angle;
+    }
+    angle
+}
+
+fn quad_point(p0: STPos, p1: STPos, p2: STPos, t: f64) -> STPos {
+    let mt = 1.0 - t;
+    STPos {
+        x: mt * mt * p0.x + 2.0 * mt * t * p1.x + t * t * p2.x,
+        y: mt * mt * p0.y + 2.0 * mt * t * p1.y + t * t * p2.y,
+    }
+}
+
+fn cubic_point(p0: STPos, p1: STPos, p2: STPos, p3: STPos, t: f64) -> STPos {
+    let mt = 1.0 - t;
+    let a = mt * mt * mt;
+    let b = 3.0 * mt * mt * t;
+    let c = 3.0 * mt * t * t;
+    let d = t * t * t;
+    STPos {
+        x: a * p0.x + b * p1.x + c * p2.x + d * p3.x,
+        y: a * p0.y + b * p1.y + c * p2.y + d * p3.y,
+    }
+}
+
+/// 二次贝塞尔曲线在 (0,1) 内的极值参数
+fn quad_extrema(p0: STPos, p1: STPos, p2: STPos) -> Vec<f64> {
+    let mut ts = Vec::new();
+    for (a, b, c) in [(p0.x, p1.x, p2.x), (p0.y, p1.y, p2.y)] {
+        let den = a - 2.0 * b + c;
+        if den != 0.0 {
+            let t = (a - b) / den;
+            if t > 0.0 && t < 1.0 {
+                ts.push(t);
+            }
+        }
+    }
+    ts
+}
+
+/// 三次贝塞尔曲线在 (0,1) 内的极值参数
+fn cubic_extrema(p0: STPos, p1: STPos, p2: STPos, p3: STPos) -> Vec<f64> {
+    let mut ts = Vec::new();
+    for (a, b, c, d) in [(p0.x, p1.x, p2.x, p3.x), (p0.y, p1.y, p2.y, p3.y)] {
+        // 导数 3[(b-a)(1-t)^2 + 2(c-b)(1-t)t + (d-c)t^2] 整理为 qa t^2 + qb t + qc
+        let qa = -a + 3.0 * b - 3.0 * c + d;
+        let qb = 2.0 * (a - 2.0 * b + c);
+        let qc = b - a;
+        if qa.abs() < 1e-12 {
+            if qb != 0.0 {
+                ts.push(-qc / qb);
+            }
+        } else {
+            let disc = qb * qb - 4.0 * qa * qc;
+            if disc >= 0.0 {
+                let sq = disc.sqrt();
+                ts.push((-qb + sq) / (2.0 * qa));
+                ts.push((-qb - sq) / (2.0 * qa));
+            }
+        }
+    }
+    ts.retain(|t| *t > 0.0 && *t < 1.0);
+    ts
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn bounds(path: &str) -> STBox {
+        path.parse::<STPath>().unwrap().to_render_path().bounds().unwrap()
+    }
+
+    fn assert_box(actual: STBox, expected: [f64; 4]) {
+        let actual = [actual.x, actual.y, actual.w, actual.h];
+        for (a, e) in actual.iter().zip(expected) {
+            assert!((a - e).abs() < 1e-6, "{:?} != {:?}", actual, expected);
+        }
+    }
+
+    #[test]
+    fn bounds_of_polyline() {
+        assert_box(bounds("M 2 3 L 10 3 L 10 8 C"), [2.0, 3.0, 8.0, 5.0]);
+    }
+
+    #[test]
+    fn empty_path_has_no_bounds() {
+        assert!("".parse::<STPath>().unwrap().to_render_path().bounds().is_none());
+    }
+
+    #[test]
+    fn cubic_bounds_use_curve_extrema_not_control_points() {
+        // 控制点的 y 为 10，曲线在 t = 0.5 处的最高点为 7.5
+        assert_box(bounds("M 0 0 B 0 10 10 10 10 0"), [0.0, 0.0, 10.0, 7.5]);
+    }
+
+    #[test]
+    fn quad_bounds_use_curve_extrema() {
+        assert_box(bounds("M 0 0 Q 5 10 10 0"), [0.0, 0.0, 10.0, 5.0]);
+    }
+
+    #[test]
+    fn half_circle_arc_reaches_its_extremum() {
+        // 两端点都在 y = 10 上，圆弧经过顶点 (10, 0)
+        let path: STPath = "M 0 10 A 10 10 0 0 1 20 10".parse().unwrap();
+        let render_path = path.to_render_path();
+        let cubics = render_path.segments.iter().filter(|s| matches!(s, PathSegment::CubicTo { .. })).count();
+        assert_eq!(cubics, 2);
+        assert_box(render_path.bounds().unwrap(), [0.0, 0.0, 20.0, 10.0]);
+    }
+
+    #[test]
+    fn arc_with_zero_radius_is_a_line() {
+        let segments = "M 0 0 A 0 5 0 0 1 10 0".parse::<STPath>().unwrap().segments();
+        assert_eq!(segments.last(), Some(&PathSegment::LineTo { to: STPos { x: 10.0, y: 0.0 } }));
+    }
+
+    #[test]
+    fn transform_moves_bounds() {
+        let path = "M 0 0 L 4 2".parse::<STPath>().unwrap().to_render_path();
+        let moved = path.transform(&STMatrix::translate(1.0, 2.0).then(&STMatrix::scale(2.0, 2.0)));
+        assert_box(moved.bounds().unwrap(), [2.0, 4.0, 8.0, 4.0]);
+    }
+
+    /// 按 MoveTo 切分片段序列，返回各轮廓的起点与是否闭合
+    fn contours(path: &str) -> Vec<(STPos, bool)> {
+        let mut contours: Vec<(STPos, bool)> = Vec::new();
+        for segment in path.parse::<STPath>().unwrap().segments() {
+            match segment {
+                PathSegment::MoveTo { to } => contours.push((to, false)),
+                PathSegment::Close => contours.last_mut().unwrap().1 = true,
+                _ => {}
+            }
+        }
+        contours
+    }
+
+    #[test]
+    fn each_close_returns_to_its_own_subpath_start() {
+        // 外框与内部的孔各为一个轮廓
+        let contours = contours("S 0 0 L 10 0 L 10 10 L 0 10 C M 3 3 L 7 3 L 7 7 C");
+        assert_eq!(contours, vec![(STPos { x: 0.0, y: 0.0 }, true), (STPos { x: 3.0, y: 3.0 }, true)]);
+    }
+
+    #[test]
+    fn drawing_after_close_reopens_at_the_closed_subpath_start() {
+        let segments = "M 0 0 L 10 0 M 3 3 L 7 3 C L 7 7".parse::<STPath>().unwrap().segments();
+        assert_eq!(segments.iter().filter(|s| matches!(s, PathSegment::MoveTo { .. })).count(), 3);
+        assert_eq!(segments[segments.len() - 2], PathSegment::MoveTo { to: STPos { x: 3.0, y: 3.0 } });
+    }
+
+    #[test]
+    fn arcs_flatten_to_move_line_cubic_and_close() {
+        let segments = "M 0 10 L 0 5 A 10 10 0 0 1 20 10 A 5 5 0 1 0 20 20 C".parse::<STPath>().unwrap().segments();
+        assert!(segments.iter().all(|s| matches!(
+            s,
+            PathSegment::MoveTo { .. } | PathSegment::LineTo { .. } | PathSegment::CubicTo { .. } | PathSegment::Close
+        )), "{:?}", segments);
+        assert!(segments.iter().filter(|s| matches!(s, PathSegment::CubicTo { .. })).count() >= 2);
+        // 每段圆弧都结束在其终点上
+        let ends: Vec<STPos> = segments.iter().filter_map(|s| match s {
+            PathSegment::CubicTo { to, .. } => Some(*to),
+            _ => None,
+        }).collect();
+        assert!(ends.iter().any(|p| (p.x - 20.0).abs() < 1e-6 && (p.y - 10.0).abs() < 1e-6), "{:?}", ends);
+        assert!(ends.iter().any(|p| (p.x - 20.0).abs() < 1e-6 && (p.y - 20.0).abs() < 1e-6), "{:?}", ends);
+        assert_eq!(segments.first(), Some(&PathSegment::MoveTo { to: STPos { x: 0.0, y: 10.0 } }));
+        assert_eq!(segments.last(), Some(&PathSegment::Close));
+    }
+}
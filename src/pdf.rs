@@ -0,0 +1,144 @@
+use std::sync::Arc;
+
+use cairo::freetype::Library;
+use cairo::{Context, FontFace, FontSlant, FontWeight, PdfSurface};
+use image::RgbaImage;
+
+use crate::document::PageArea;
+use crate::font::TrueTypeFont;
+use crate::render::{image_to_surface, set_page_coordinates};
+use crate::text::TextRun;
+
+/// 每毫米对应的 PDF 点数
+const PT_PER_MM: f64 = 72.0 / 25.4;
+
+/// 文字层使用的字体，只用于文字的选取与检索，不可见
+const TEXT_LAYER_FONT_FAMILY: &str = "sans-serif";
+
+/// 逐页写入位图与文字层的 PDF
+/// 文字先绘制在页面上，再用不透明的页面位图覆盖，使文字不可见但可以选取和检索
+pub(crate) struct SearchablePdfWriter {
+    surface: PdfSurface,
+}
+
+impl SearchablePdfWriter {
+    pub(crate) fn new() -> Result<SearchablePdfWriter, cairo::Error> {
+        // 页面尺寸在添加页面时设置
+        let surface = PdfSurface::for_stream(1.0, 1.0, Vec::<u8>::new())?;
+        Ok(SearchablePdfWriter { surface })
+    }
+
+    /// 添加一页，页面大小为旋转后的物理区域，image 铺满整个页面
+    pub(crate) fn add_page(&mut self, image: &RgbaImage, area: &PageArea, runs: &[TextRun]) -> Result<(), cairo::Error> {
+        let (width, height) = area.display_size();
+        self.surface.set_size(width * PT_PER_MM, height * PT_PER_MM)?;
+        let cr = Context::new(&self.surface)?;
+        cr.scale(PT_PER_MM, PT_PER_MM);
+        cr.save()?;
+        set_page_coordinates(&cr, area);
+
+        cr.select_font_face(TEXT_LAYER_FONT_FAMILY, FontSlant::Normal, FontWeight::Normal);
+        cr.set_source_rgb(0.0, 0.0, 0.0);
+        for run in runs.iter().filter(|r| r.size > 0.0) {
+            cr.set_font_size(run.size);
+            for (c, (x, y)) in run.text.chars().zip(&run.positions) {
+                cr.move_to(*x, *y);
+                cr.show_text(&c.to_string())?;
+            }
+        }
+
+        cr.restore()?;
+
+        if image.width() > 0 && image.height() > 0 {
+            let surface = image_to_surface(image)?;
+            cr.scale(width / image.width() as f64, height / image.height() as f64);
+            cr.set_source_surface(&surface, 0.0, 0.0)?;
+            cr.paint()?;
+        }
+        cr.show_page()
+    }
+
+    pub(crate) fn finish(self) -> Result<Vec<u8>, cairo::Error> {
+        let stream = self.surface.finish_output_stream().map_err(|_| cairo::Error::WriteError)?;
+        Ok(stream.downcast::<Vec<u8>>().map(|data| *data).unwrap_or_default())
+    }
+}
+
+/// 逐页写入矢量内容的 PDF，每页的坐标单位为毫米
+/// 路径以矢量写入，图像原样嵌入；允许嵌入的字体以文字写入，其余文字以字形轮廓写入
+pub(crate) struct PdfWriter {
+    surface: PdfSurface,
+    fonts: EmbeddedFonts,
+}
+
+impl PdfWriter {
+    pub(crate) fn new() -> Result<PdfWriter, cairo::Error> {
+        // 页面尺寸在开始新页面时设置
+        let surface = PdfSurface::for_stream(1.0, 1.0, Vec::<u8>::new())?;
+        Ok(PdfWriter { surface, fonts: EmbeddedFonts::new() })
+    }
+
+    /// 登记页面用到的嵌入字体，字体保留到 PDF 输出完成
+    pub(crate) fn retain_fonts<'a>(&mut self, fonts: impl Iterator<Item = &'a Arc<TrueTypeFont>>) {
+        for font in fonts {
+            self.fonts.add(font);
+        }
+    }
+
+    /// 已登记的嵌入字体，绘制页面时据此以文字写出
+    pub(crate) fn fonts(&self) -> &EmbeddedFonts {
+        &self.fonts
+    }
+
+    /// 开始宽 width、高 height（毫米）的新页面，返回坐标单位为毫米的绘图上下文
+    /// 页面内容绘制完成后调用返回的上下文的 show_page 结束该页
+    pub(crate) fn begin_page(&mut self, width: f64, height: f64) -> Result<Context, cairo::Error> {
+        self.surface.set_size(width * PT_PER_MM, height * PT_PER_MM)?;
+        let cr = Context::new(&self.surface)?;
+        cr.scale(PT_PER_MM, PT_PER_MM);
+        Ok(cr)
+    }
+
+    pub(crate) fn finish(self) -> Result<Vec<u8>, cairo::Error> {
+        let stream = self.surface.finish_output_stream().map_err(|_| cairo::Error::WriteError)?;
+        Ok(stream.downcast::<Vec<u8>>().map(|data| *data).unwrap_or_default())
+    }
+}
+
+/// 以文字写入 PDF 的嵌入字体，每个字体由 FreeType 从字形文件数据创建一个 cairo 字体，
+/// cairo 在输出完成时将用到的字形作为字体子集写入 PDF；字形编号即 TrueType 字体中的字形索引
+pub(crate) struct EmbeddedFonts {
+    /// 登记的字体及其 cairo 字体，不允许嵌入或 FreeType 无法加载时为 None，其文字以字形轮廓写出
+    faces: Vec<(Arc<TrueTypeFont>, Option<FontFace>)>,
+    /// 用于加载字体的 FreeType 实例，初始化失败时所有文字都以字形轮廓写出
+    library: Option<Library>,
+}
+
+impl EmbeddedFonts {
+    fn new() -> EmbeddedFonts {
+        let library = Library::init()
+            .inspect_err(|e| log::warn!("failed to initialize FreeType, text is written as outlines: {}", e))
+            .ok();
+        EmbeddedFonts { faces: Vec::new(), library }
+    }
+
+    fn add(&mut self, font: &Arc<TrueTypeFont>) {
+        if self.faces.iter().any(|(f, _)| Arc::ptr_eq(f, font)) {
+            return;
+        }
+        let face = match &self.library {
+            Some(library) if font.allows_embedding() => library.new_memory_face(font.data().to_vec(), 0)
+                .map_err(|e| e.to_string())
+                .and_then(|face| FontFace::create_from_ft(&face).map_err(|e| e.to_string()))
+                .inspect_err(|e| log::warn!("failed to load embedded font for PDF output, text is written as outlines: {}", e))
+                .ok(),
+            _ => None,
+        };
+        self.faces.push((font.clone(), face));
+    }
+
+    /// 以文字写出 font 时使用的 cairo 字体，字号由调用方设置；字体未登记、不允许嵌入或无法加载时返回 None
+    pub(crate) fn face(&self, font: &Arc<TrueTypeFont>) -> Option<&FontFace> {
+        self.faces.iter().find(|(f, _)| Arc::ptr_eq(f, font)).and_then(|(_, face)| face.as_ref())
+    }
+}
@@ -0,0 +1,88 @@
+use std::collections::HashMap;
+
+use image::{Rgba, RgbaImage};
+
+/// 用中位切分法将图像的颜色数减少到至多 max_colors 种，透明度与 RGB 一起参与切分
+/// 颜色数本就不超过 max_colors 时图像保持不变；max_colors 为 0 时按 1 处理
+pub(crate) fn median_cut(image: &mut RgbaImage, max_colors: usize) {
+    let max_colors = max_colors.max(1);
+    let mut histogram: HashMap<[u8; 4], u32> = HashMap::new();
+    for pixel in image.pixels() {
+        *histogram.entry(pixel.0).or_default() += 1;
+    }
+    if histogram.len() <= max_colors {
+        return;
+    }
+
+    let mut boxes: Vec<Vec<([u8; 4], u32)>> = vec![histogram.into_iter().collect()];
+    while boxes.len() < max_colors {
+        // 切分颜色跨度最大的一组，只剩单一颜色的组不再切分
+        let Some((index, channel)) = boxes
+            .iter()
+            .enumerate()
+            .filter(|(_, colors)| colors.len() > 1)
+            .map(|(index, colors)| {
+                let (channel, range) = widest_channel(colors);
+                (index, channel, range)
+            })
+            .max_by_key(|(_, _, range)| *range)
+            .map(|(index, channel, _)| (index, channel))
+        else {
+            break;
+        };
+        let mut colors = boxes.swap_remove(index);
+        colors.sort_unstable_by_key(|(color, _)| color[channel]);
+        let split = weighted_median(&colors);
+        let upper = colors.split_off(split);
+        boxes.push(colors);
+        boxes.push(upper);
+    }
+
+    let mut palette: HashMap<[u8; 4], [u8; 4]> = HashMap::new();
+    for colors in &boxes {
+        let average = average_color(colors);
+        palette.extend(colors.iter().map(|(color, _)| (*color, average)));
+    }
+    for pixel in image.pixels_mut() {
+        *pixel = Rgba(palette[&pixel.0]);
+    }
+}
+
+/// 颜色跨度最大的通道及其跨度
+fn widest_channel(colors: &[([u8; 4], u32)]) -> (usize, u8) {
+    (0..4)
+        .map(|channel| {
+            let (min, max) = colors.iter().fold((u8::MAX, u8::MIN), |(min, max), (color, _)| {
+                (min.min(color[channel]), max.max(color[channel]))
+            });
+            (channel, max - min)
+        })
+        .max_by_key(|(_, range)| *range)
+        .unwrap_or((0, 0))
+}
+
+/// 按像素数取中位的切分位置，保证两侧都至少有一种颜色
+fn weighted_median(colors: &[([u8; 4], u32)]) -> usize {
+    let total: u64 = colors.iter().map(|(_, count)| *count as u64).sum();
+    let mut accumulated = 0;
+    let mut split = colors.len() - 1;
+    for (index, (_, count)) in colors.iter().enumerate() {
+        accumulated += *count as u64;
+        if accumulated * 2 >= total {
+            split = index + 1;
+            break;
+        }
+    }
+    split.clamp(1, colors.len() - 1)
+}
+
+/// 按像素数加权的平均颜色
+fn average_color(colors: &[([u8; 4], u32)]) -> [u8; 4] {
+    let total: u64 = colors.iter().map(|(_, count)| *count as u64).sum::<u64>().max(1);
+    let mut average = [0u8; 4];
+    for (channel, value) in average.iter_mut().enumerate() {
+        let sum: u64 = colors.iter().map(|(color, count)| color[channel] as u64 * *count as u64).sum();
+        *value = ((sum + total / 2) / total) as u8;
+    }
+    average
+}
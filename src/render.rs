@@ -0,0 +1,1310 @@
+use std::cell::RefCell;
+use std::collections::HashMap;
+use std::sync::Arc;
+
+use cairo::{Context, Extend, FontOptions, FontSlant, FontWeight, Format, Glyph, Gradient, ImageSurface, LinearGradient, Matrix, Operator, RadialGradient, TextCluster, TextClusterFlags};
+use image::RgbaImage;
+
+use crate::annotation::Annot;
+use crate::bilevel::Bitmap;
+use crate::color::{CTColor, Color, MapType};
+use crate::document::{PageArea, ZOrder};
+use crate::font::TrueTypeFont;
+use crate::page::{Clip, CompositeObject, DrawStyle, FillRule, ImageObject, Layer, LayerType, LineCap, LineJoin, Page, PageObject, PathObject, TextObject};
+use crate::path::{PathSegment, RenderPath};
+use crate::pdf::EmbeddedFonts;
+use crate::resource::{DrawParam, ResourceManager};
+use crate::st_types::{STBox, STMatrix, STPath};
+use crate::text::{advance_along, delta_at, glyph_placement, parse_deltas};
+
+/// 每英寸的毫米数，OFD 的坐标单位为毫米
+const MM_PER_INCH: f64 = 25.4;
+
+/// 缺省的输出分辨率，画布大小为 0 而无法按像素尺寸计算缩放比例时同样使用
+pub(crate) const DEFAULT_DPI: f32 = 150.0;
+
+/// 规范规定的缺省线宽 0.353mm
+pub(crate) const DEFAULT_LINE_WIDTH: f64 = 0.353;
+
+/// 规范规定的缺省斜接限制
+pub(crate) const DEFAULT_MITER_LIMIT: f64 = 3.528;
+
+/// 字体缺失时使用的系统字体
+pub(crate) const DEFAULT_FONT_FAMILY: &str = "sans-serif";
+
+/// 模拟粗体时沿字形轮廓描边的线宽，以字号为单位
+const SYNTHETIC_BOLD_WIDTH: f64 = 0.04;
+
+/// 模拟斜体时字形的错切系数，约 11 度
+const SYNTHETIC_ITALIC_SHEAR: f64 = 0.2;
+
+/// 裁切标记的长度、与出血边的间距及线宽，单位毫米
+const CROP_MARK_LENGTH: f64 = 5.0;
+const CROP_MARK_GAP: f64 = 1.0;
+const PRINT_MARK_LINE_WIDTH: f64 = 0.25;
+
+/// 套准标记的半径，单位毫米
+const REGISTRATION_MARK_RADIUS: f64 = 2.0;
+
+/// 超采样倍数的上限，位图的内存占用随倍数的平方增长
+const MAX_SUPERSAMPLE: u8 = 4;
+
+/// 页面渲染参数
+#[derive(Debug, Clone)]
+pub struct RenderOptions {
+    /// 输出图像的大小，按分辨率或指定的像素尺寸
+    pub target: RenderTarget,
+    /// 背景色 RGBA，None 时输出透明背景
+    pub background: Option<[u8; 4]>,
+    /// 在版心区域外绘制裁切标记，空间足够时同时绘制套准标记，画布为页面的物理区域
+    pub print_marks: bool,
+    /// 将页面内容裁剪到版心区域，页面未定义版心区域时不裁剪
+    pub clip_to_content_box: bool,
+    /// 绘制页面上的注释
+    pub draw_annotations: bool,
+    /// 绘制页面上的电子签章
+    pub draw_seals: bool,
+    /// 按图层类型选择要绘制的图层，图层之间的绘制顺序不受影响
+    pub layer_filter: LayerFilter,
+    /// 只绘制标识为该值的图层（包括模板页中的图层），同时仍受 layer_filter 限制，None 时不按标识筛选
+    pub layer_id: Option<String>,
+    /// 多页拼接为长图时，宽度不足的页面水平居中，否则左对齐
+    pub center_pages: bool,
+    /// 输出为指定纸张大小的画布，页面保持宽高比缩放到纸张内并居中，None 时画布即页面的物理区域
+    pub fit_paper: Option<PaperSize>,
+    /// 渲染后用中位切分法将颜色减少到至多指定的种数，便于输出索引色的 PNG 或 GIF，None 时不处理
+    pub quantize_colors: Option<u16>,
+    /// 嵌入字体没有文字要求的粗体或斜体字形时，以加粗描边与错切变换模拟，关闭时按字形原样绘制
+    pub synthesize_font_styles: bool,
+    /// 输出图像的颜色模式
+    pub color_mode: ColorMode,
+    /// 导出 TIFF 时使用的压缩方式
+    pub tiff_compression: TiffCompression,
+    /// 位图输出的抗锯齿方式，SVG 与 PDF 输出不受影响
+    pub antialias: AntiAlias,
+    /// 模拟叠印：标记了叠印的 CMYK 颜色以正片叠底方式绘制，分量为 0 的通道不覆盖下层的颜色，
+    /// 近似印刷时不挖空下层油墨的效果；关闭时所有颜色照常覆盖下层
+    pub simulate_overprint: bool,
+}
+
+/// 输出图像的大小，只作用于位图输出，SVG 与 PDF 按页面的实际尺寸输出
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum RenderTarget {
+    /// 按分辨率缩放，每英寸像素数
+    Dpi(f32),
+    /// 输出指定的宽度，高度按画布的宽高比计算
+    Width(u32),
+    /// 输出指定的高度，宽度按画布的宽高比计算
+    Height(u32),
+    /// 输出恰为 w × h 的图像，画布保持宽高比缩放到其中并居中，空白处填充背景色
+    Fit { w: u32, h: u32 },
+}
+
+impl Default for RenderTarget {
+    fn default() -> Self {
+        RenderTarget::Dpi(DEFAULT_DPI)
+    }
+}
+
+impl RenderTarget {
+    /// 按分辨率输出时的分辨率，按像素尺寸输出时为 None
+    pub fn dpi(&self) -> Option<f32> {
+        match *self {
+            RenderTarget::Dpi(dpi) => Some(dpi),
+            _ => None,
+        }
+    }
+}
+
+/// 输出图像的颜色模式
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum ColorMode {
+    /// 保留原有颜色
+    #[default]
+    Color,
+    /// 灰度，保留透明度
+    Gray,
+    /// 黑白二值，按亮度阈值划分，透明处按白色处理
+    Bilevel,
+}
+
+/// 位图输出的抗锯齿方式
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum AntiAlias {
+    /// 不抗锯齿，像素只有完全覆盖与完全不覆盖两种，适合输出二值图像供 OCR 使用
+    Off,
+    /// cairo 缺省的抗锯齿
+    #[default]
+    On,
+    /// 以 n 倍分辨率渲染后按 n × n 的像素块取平均缩小，边缘更平滑；n 不超过 4，0 与 1 等同于 On
+    Supersample(u8),
+}
+
+impl AntiAlias {
+    /// 渲染时放大的倍数，不超采样时为 1
+    fn factor(self) -> i32 {
+        match self {
+            AntiAlias::Supersample(n) => n.clamp(1, MAX_SUPERSAMPLE) as i32,
+            AntiAlias::Off | AntiAlias::On => 1,
+        }
+    }
+
+    fn to_cairo(self) -> cairo::Antialias {
+        match self {
+            AntiAlias::Off => cairo::Antialias::None,
+            AntiAlias::On | AntiAlias::Supersample(_) => cairo::Antialias::Default,
+        }
+    }
+
+    /// 设置图形与文字的抗锯齿方式，cairo 绘制文字时使用字体选项中的设置
+    fn apply(self, cr: &Context) {
+        let antialias = self.to_cairo();
+        cr.set_antialias(antialias);
+        if let Ok(mut font_options) = FontOptions::new() {
+            font_options.set_antialias(antialias);
+            cr.set_font_options(&font_options);
+        }
+    }
+}
+
+/// 导出 TIFF 时的压缩方式
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum TiffCompression {
+    None,
+    #[default]
+    Lzw,
+    Deflate,
+    /// CCITT Group 4，只能用于二值图像，其他颜色模式下的页面也按二值图像输出
+    Group4,
+}
+
+/// 按图层类型选择绘制的图层，缺省时绘制全部图层
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct LayerFilter {
+    pub body: bool,
+    pub background: bool,
+    pub foreground: bool,
+}
+
+impl Default for LayerFilter {
+    fn default() -> Self {
+        LayerFilter { body: true, background: true, foreground: true }
+    }
+}
+
+impl LayerFilter {
+    pub(crate) fn includes(&self, layer_type: LayerType) -> bool {
+        match layer_type {
+            LayerType::Body => self.body,
+            LayerType::Background => self.background,
+            LayerType::Foreground => self.foreground,
+        }
+    }
+
+    /// 图层的类型被选中，且指定了 layer_id 时图层的标识与之相同
+    pub(crate) fn selects(&self, layer: &Layer, layer_id: Option<&str>) -> bool {
+        self.includes(layer.layer_type) && layer_id.is_none_or(|id| id == layer.id)
+    }
+}
+
+/// 常用纸张尺寸，均为纵向
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum PaperSize {
+    A3,
+    A4,
+    A5,
+    Letter,
+    Legal,
+    /// 自定义宽高，单位毫米
+    Custom { width: f64, height: f64 },
+}
+
+impl PaperSize {
+    /// 纸张的宽和高，单位毫米
+    pub fn size_mm(&self) -> (f64, f64) {
+        match *self {
+            PaperSize::A3 => (297.0, 420.0),
+            PaperSize::A4 => (210.0, 297.0),
+            PaperSize::A5 => (148.0, 210.0),
+            PaperSize::Letter => (215.9, 279.4),
+            PaperSize::Legal => (215.9, 355.6),
+            PaperSize::Custom { width, height } => (width, height),
+        }
+    }
+}
+
+impl Default for RenderOptions {
+    fn default() -> Self {
+        RenderOptions {
+            target: RenderTarget::default(),
+            background: Some([255, 255, 255, 255]),
+            print_marks: false,
+            clip_to_content_box: false,
+            draw_annotations: true,
+            draw_seals: true,
+            layer_filter: LayerFilter::default(),
+            layer_id: None,
+            center_pages: true,
+            fit_paper: None,
+            quantize_colors: None,
+            synthesize_font_styles: true,
+            color_mode: ColorMode::Color,
+            tiff_compression: TiffCompression::Lzw,
+            antialias: AntiAlias::On,
+            simulate_overprint: false,
+        }
+    }
+}
+
+/// 渲染结果的原始像素，便于通过 FFI 以指针加长度的形式传出
+/// pixels 为逐行紧密排列的 RGBA8 数据，行间没有填充：原点在左上角，按行自上而下、行内自左向右，
+/// 每个像素依次为 R、G、B、A 四个字节，颜色分量未预乘透明度，长度恰为 width * height * 4
+#[derive(Debug, Clone, PartialEq)]
+pub struct RenderedBuffer {
+    pub width: u32,
+    pub height: u32,
+    pub pixels: Vec<u8>,
+}
+
+impl From<RgbaImage> for RenderedBuffer {
+    fn from(image: RgbaImage) -> Self {
+        RenderedBuffer { width: image.width(), height: image.height(), pixels: image.into_raw() }
+    }
+}
+
+/// 按颜色模式转换渲染后的图像
+pub(crate) fn apply_color_mode(image: &mut RgbaImage, mode: ColorMode) {
+    match mode {
+        ColorMode::Color => {}
+        ColorMode::Gray => {
+            for pixel in image.pixels_mut() {
+                let gray = luminance(pixel.0);
+                pixel.0 = [gray, gray, gray, pixel.0[3]];
+            }
+        }
+        ColorMode::Bilevel => *image = Bitmap::from_rgba(image).to_rgba(),
+    }
+}
+
+/// 像素的亮度，不考虑透明度
+pub(crate) fn luminance([r, g, b, _]: [u8; 4]) -> u8 {
+    ((r as u32 * 299 + g as u32 * 587 + b as u32 * 114) / 1000) as u8
+}
+
+/// 将多个页面位图自上而下拼接为一张长图，页面之间留出 gap 像素的间隔
+/// 长图宽度取最宽页面的宽度，空白处填充背景色；总高度超出 u32 范围时返回 None
+pub(crate) fn stack_pages(images: &[RgbaImage], gap: u32, opts: &RenderOptions) -> Option<RgbaImage> {
+    let width = images.iter().map(|i| i.width()).max().unwrap_or(0);
+    let gaps = u32::try_from(images.len().saturating_sub(1)).ok()?.checked_mul(gap)?;
+    let height = images.iter().try_fold(gaps, |sum, i| sum.checked_add(i.height()))?;
+    let background = image::Rgba(opts.background.unwrap_or([0, 0, 0, 0]));
+    let mut canvas = RgbaImage::from_pixel(width, height, background);
+    let mut y = 0;
+    for image in images {
+        let x = if opts.center_pages { (width - image.width()) / 2 } else { 0 };
+        image::imageops::replace(&mut canvas, image, x as i64, y as i64);
+        y = y.saturating_add(image.height()).saturating_add(gap);
+    }
+    Some(canvas)
+}
+
+/// 画布的毫米尺寸，以及页面在画布上的缩放比例与左上角位置
+/// 未指定纸张时画布即旋转后的页面物理区域
+pub(crate) struct Canvas {
+    pub(crate) width: f64,
+    pub(crate) height: f64,
+    fit: f64,
+    offset_x: f64,
+    offset_y: f64,
+}
+
+impl Canvas {
+    pub(crate) fn new(area: &PageArea, opts: &RenderOptions) -> Canvas {
+        let (width, height) = area.display_size();
+        match opts.fit_paper {
+            Some(paper) if width > 0.0 && height > 0.0 => {
+                let (paper_w, paper_h) = paper.size_mm();
+                let fit = (paper_w / width).min(paper_h / height);
+                Canvas {
+                    width: paper_w,
+                    height: paper_h,
+                    fit,
+                    offset_x: (paper_w - width * fit) / 2.0,
+                    offset_y: (paper_h - height * fit) / 2.0,
+                }
+            }
+            _ => Canvas { width, height, fit: 1.0, offset_x: 0.0, offset_y: 0.0 },
+        }
+    }
+
+    /// 按输出目标将画布光栅化时的像素尺寸、每毫米的像素数与画布在图像中的偏移
+    /// 指定的像素尺寸为 0 或画布大小为 0 时按缺省分辨率输出
+    pub(crate) fn raster(&self, target: RenderTarget) -> Raster {
+        let by_scale = |scale: f64| Raster {
+            width: (self.width * scale).ceil().max(1.0) as i32,
+            height: (self.height * scale).ceil().max(1.0) as i32,
+            scale,
+            offset_x: 0.0,
+            offset_y: 0.0,
+        };
+        let (width, height) = (self.width, self.height);
+        match target {
+            RenderTarget::Dpi(dpi) => by_scale(dpi as f64 / MM_PER_INCH),
+            // 宽高之一为指定值时按该值取整，避免浮点误差使结果多出一个像素
+            RenderTarget::Width(w) if w > 0 && width > 0.0 => {
+                let scale = w as f64 / width;
+                Raster { width: w as i32, height: (height * scale).round().max(1.0) as i32, ..by_scale(scale) }
+            }
+            RenderTarget::Height(h) if h > 0 && height > 0.0 => {
+                let scale = h as f64 / height;
+                Raster { width: (width * scale).round().max(1.0) as i32, height: h as i32, ..by_scale(scale) }
+            }
+            RenderTarget::Fit { w, h } if w > 0 && h > 0 && width > 0.0 && height > 0.0 => {
+                let scale = (w as f64 / width).min(h as f64 / height);
+                Raster {
+                    width: w as i32,
+                    height: h as i32,
+                    scale,
+                    offset_x: (w as f64 - width * scale) / 2.0,
+                    offset_y: (h as f64 - height * scale) / 2.0,
+                }
+            }
+            _ => by_scale(DEFAULT_DPI as f64 / MM_PER_INCH),
+        }
+    }
+}
+
+/// 画布光栅化后的图像大小（像素）、每毫米的像素数，以及画布左上角在图像中的位置（像素）
+pub(crate) struct Raster {
+    pub(crate) width: i32,
+    pub(crate) height: i32,
+    pub(crate) scale: f64,
+    offset_x: f64,
+    offset_y: f64,
+}
+
+/// 将页面连同其引用的模板渲染到新建的位图上
+/// overlay 在页面内容绘制完成后调用，此时坐标系为页面坐标（毫米）
+pub(crate) fn render_page<F>(
+    page: &Page,
+    templates: &[(Page, ZOrder)],
+    area: &PageArea,
+    resources: &ResourceManager,
+    assets: &PageAssets,
+    opts: &RenderOptions,
+    overlay: F,
+) -> Result<ImageSurface, cairo::Error>
+where
+    F: FnOnce(&Context) -> Result<(), cairo::Error>,
+{
+    let raster = Canvas::new(area, opts).raster(opts.target);
+    let factor = opts.antialias.factor();
+    let surface = ImageSurface::create(Format::ARgb32, raster.width.saturating_mul(factor), raster.height.saturating_mul(factor))?;
+    {
+        let cr = Context::new(&surface)?;
+        opts.antialias.apply(&cr);
+        // 背景铺满整个图像，不受此处平移的影响，按 Fit 输出时的留白同样为背景色
+        cr.scale(factor as f64, factor as f64);
+        cr.translate(raster.offset_x, raster.offset_y);
+        cr.scale(raster.scale, raster.scale);
+        draw_page(&cr, page, templates, area, resources, assets, opts, None, overlay)?;
+    }
+    downsample(surface, factor)
+}
+
+/// 将超采样渲染的位图按 factor × factor 的像素块取平均缩小，预乘的分量可以直接平均
+/// factor 为 1 时原样返回
+fn downsample(mut surface: ImageSurface, factor: i32) -> Result<ImageSurface, cairo::Error> {
+    if factor <= 1 {
+        return Ok(surface);
+    }
+    surface.flush();
+    let (width, height) = (surface.width() / factor, surface.height() / factor);
+    let mut target = ImageSurface::create(Format::ARgb32, width, height)?;
+    let (src_stride, dst_stride) = (surface.stride() as usize, target.stride() as usize);
+    let (factor, samples) = (factor as usize, (factor * factor) as u32);
+    {
+        let src = surface.data().map_err(|_| cairo::Error::SurfaceFinished)?;
+        let mut dst = target.data().map_err(|_| cairo::Error::SurfaceFinished)?;
+        for y in 0..height as usize {
+            for x in 0..width as usize {
+                let mut sums = [0u32; 4];
+                for sy in y * factor..(y + 1) * factor {
+                    let row = &src[sy * src_stride..];
+                    for sx in x * factor..(x + 1) * factor {
+                        for (sum, value) in sums.iter_mut().zip(&row[sx * 4..sx * 4 + 4]) {
+                            *sum += *value as u32;
+                        }
+                    }
+                }
+                let px = &mut dst[y * dst_stride + x * 4..y * dst_stride + x * 4 + 4];
+                for (out, sum) in px.iter_mut().zip(sums) {
+                    *out = ((sum + samples / 2) / samples) as u8;
+                }
+            }
+        }
+    }
+    Ok(target)
+}
+
+/// 在坐标单位为毫米的画布上绘制页面连同其引用的模板、签章与印刷标记，画布的大小由 Canvas 给出
+/// 位图与矢量输出共用，overlay 在页面内容绘制完成后以页面坐标调用；
+/// 输出 PDF 时 embedded_fonts 为其中登记的嵌入字体，允许嵌入的字体以文字写出
+#[allow(clippy::too_many_arguments)]
+pub(crate) fn draw_page<F>(
+    cr: &Context,
+    page: &Page,
+    templates: &[(Page, ZOrder)],
+    area: &PageArea,
+    resources: &ResourceManager,
+    assets: &PageAssets,
+    opts: &RenderOptions,
+    embedded_fonts: Option<&EmbeddedFonts>,
+    overlay: F,
+) -> Result<(), cairo::Error>
+where
+    F: FnOnce(&Context) -> Result<(), cairo::Error>,
+{
+    let canvas = Canvas::new(area, opts);
+    if let Some([r, g, b, a]) = opts.background {
+        cr.set_source_rgba(channel(r), channel(g), channel(b), channel(a));
+        cr.paint()?;
+    }
+    cr.translate(canvas.offset_x, canvas.offset_y);
+    cr.scale(canvas.fit, canvas.fit);
+    // 页面的旋转放在基础坐标系中，图元自身的 CTM 在此之上叠加
+    set_page_coordinates(cr, area);
+
+    draw_content(cr, page, templates, area, resources, assets, opts, embedded_fonts, false)?;
+    if opts.print_marks {
+        draw_print_marks(cr, area)?;
+    }
+    overlay(cr)
+}
+
+/// 只将页面坐标系（毫米）中 region 范围内的内容渲染到新建的位图上，位图为 region 按页面旋转后的外接矩形
+/// 纸张适配与印刷标记不适用；外接矩形与 region 不相交的图元直接跳过，不参与光栅化
+#[allow(clippy::too_many_arguments)]
+pub(crate) fn render_region(
+    page: &Page,
+    templates: &[(Page, ZOrder)],
+    area: &PageArea,
+    resources: &ResourceManager,
+    assets: &PageAssets,
+    opts: &RenderOptions,
+    region: &STBox,
+    dpi: f32,
+) -> Result<ImageSurface, cairo::Error> {
+    let scale = dpi as f64 / MM_PER_INCH;
+    let display = region.transform(&page_transform(area));
+    let width = (display.w * scale).ceil().max(1.0) as i32;
+    let height = (display.h * scale).ceil().max(1.0) as i32;
+    let factor = opts.antialias.factor();
+    let surface = ImageSurface::create(Format::ARgb32, width.saturating_mul(factor), height.saturating_mul(factor))?;
+    {
+        let cr = Context::new(&surface)?;
+        opts.antialias.apply(&cr);
+        cr.scale(factor as f64, factor as f64);
+        if let Some([r, g, b, a]) = opts.background {
+            cr.set_source_rgba(channel(r), channel(g), channel(b), channel(a));
+            cr.paint()?;
+        }
+        cr.scale(scale, scale);
+        cr.translate(-display.x, -display.y);
+        set_page_coordinates(&cr, area);
+        cr.rectangle(region.x, region.y, region.w, region.h);
+        cr.clip();
+        draw_content(&cr, page, templates, area, resources, assets, opts, None, true)?;
+    }
+    downsample(surface, factor)
+}
+
+/// 在页面坐标系中绘制页面连同其引用的模板、注释与签章
+/// cull 为 true 时跳过外接矩形在当前裁剪区之外的图元
+#[allow(clippy::too_many_arguments)]
+fn draw_content(
+    cr: &Context,
+    page: &Page,
+    templates: &[(Page, ZOrder)],
+    area: &PageArea,
+    resources: &ResourceManager,
+    assets: &PageAssets,
+    opts: &RenderOptions,
+    embedded_fonts: Option<&EmbeddedFonts>,
+    cull: bool,
+) -> Result<(), cairo::Error> {
+    let renderer = PageRenderer { cr, resources, assets, layer_filter: opts.layer_filter,
+        layer_id: opts.layer_id.as_deref(),
+        synthesize_font_styles: opts.synthesize_font_styles,
+        simulate_overprint: opts.simulate_overprint,
+        embedded_fonts,
+        cull,
+        composites: RefCell::default(),
+    };
+    cr.save()?;
+    if let Some(content_box) = area.content_box.filter(|_| opts.clip_to_content_box) {
+        cr.rectangle(content_box.x, content_box.y, content_box.w, content_box.h);
+        cr.clip();
+    }
+    renderer.draw_page_with_templates(page, templates)?;
+    cr.restore()?;
+    if opts.draw_annotations {
+        renderer.draw_annotations(&assets.annotations)?;
+    }
+    if opts.draw_seals {
+        renderer.draw_seals(&assets.seals)?;
+    }
+    Ok(())
+}
+
+/// 在版心区域（缺省为物理区域）的四角外绘制裁切标记，有出血区域时标记从出血边外开始
+/// 版心与画布边缘之间的空间足够时，在四边中点外绘制套准标记
+fn draw_print_marks(cr: &Context, area: &PageArea) -> Result<(), cairo::Error> {
+    let trim = area.content_box.unwrap_or(area.physical_box);
+    let bleed = area.bleed_box.unwrap_or(trim);
+    let canvas = area.physical_box;
+
+    let left = trim.x.min(bleed.x) - CROP_MARK_GAP;
+    let top = trim.y.min(bleed.y) - CROP_MARK_GAP;
+    let right = (trim.x + trim.w).max(bleed.x + bleed.w) + CROP_MARK_GAP;
+    let bottom = (trim.y + trim.h).max(bleed.y + bleed.h) + CROP_MARK_GAP;
+
+    cr.save()?;
+    cr.set_source_rgb(0.0, 0.0, 0.0);
+    cr.set_line_width(PRINT_MARK_LINE_WIDTH);
+    for x in [trim.x, trim.x + trim.w] {
+        cr.move_to(x, top);
+        cr.line_to(x, top - CROP_MARK_LENGTH);
+        cr.move_to(x, bottom);
+        cr.line_to(x, bottom + CROP_MARK_LENGTH);
+    }
+    for y in [trim.y, trim.y + trim.h] {
+        cr.move_to(left, y);
+        cr.line_to(left - CROP_MARK_LENGTH, y);
+        cr.move_to(right, y);
+        cr.line_to(right + CROP_MARK_LENGTH, y);
+    }
+    cr.stroke()?;
+
+    let r = REGISTRATION_MARK_RADIUS;
+    let centers = [
+        (trim.x + trim.w / 2.0, top - r, top - 2.0 * r >= canvas.y),
+        (trim.x + trim.w / 2.0, bottom + r, bottom + 2.0 * r <= canvas.y + canvas.h),
+        (left - r, trim.y + trim.h / 2.0, left - 2.0 * r >= canvas.x),
+        (right + r, trim.y + trim.h / 2.0, right + 2.0 * r <= canvas.x + canvas.w),
+    ];
+    for (cx, cy, _) in centers.iter().filter(|c| c.2) {
+        cr.new_sub_path();
+        cr.arc(*cx, *cy, r * 0.6, 0.0, 2.0 * std::f64::consts::PI);
+        cr.move_to(cx - r, *cy);
+        cr.line_to(cx + r, *cy);
+        cr.move_to(*cx, cy - r);
+        cr.line_to(*cx, cy + r);
+    }
+    cr.stroke()?;
+    cr.restore()
+}
+
+/// 将 cairo 的预乘 ARGB 位图转换为非预乘的 RGBA 图像
+pub(crate) fn surface_to_image(surface: ImageSurface) -> Result<RgbaImage, cairo::BorrowError> {
+    let mut pixels = Vec::new();
+    let (width, height) = surface_to_buffer(surface, &mut pixels)?;
+    Ok(RgbaImage::from_raw(width, height, pixels).expect("buffer holds width * height pixels"))
+}
+
+/// 同 surface_to_image，将非预乘的 RGBA 像素写入 buffer 并返回宽高
+/// buffer 的长度调整为宽 × 高 × 4，已有的容量足够时不重新分配
+pub(crate) fn surface_to_buffer(mut surface: ImageSurface, buffer: &mut Vec<u8>) -> Result<(u32, u32), cairo::BorrowError> {
+    surface.flush();
+    let width = surface.width() as u32;
+    let height = surface.height() as u32;
+    let stride = surface.stride() as usize;
+    let data = surface.data()?;
+
+    buffer.clear();
+    buffer.reserve(width as usize * height as usize * 4);
+    for row in data.chunks(stride).take(height as usize) {
+        for px in row[..width as usize * 4].chunks_exact(4) {
+            let argb = u32::from_ne_bytes([px[0], px[1], px[2], px[3]]);
+            let a = (argb >> 24) & 0xff;
+            let unpremultiply = |c: u32| {
+                if a == 0 { 0 } else { ((c * 255 + a / 2) / a).min(255) as u8 }
+            };
+            buffer.extend_from_slice(&[
+                unpremultiply((argb >> 16) & 0xff),
+                unpremultiply((argb >> 8) & 0xff),
+                unpremultiply(argb & 0xff),
+                a as u8,
+            ]);
+        }
+    }
+    Ok((width, height))
+}
+
+/// 将非预乘的 RGBA 图像转换为 cairo 的预乘 ARGB 位图
+pub(crate) fn image_to_surface(image: &RgbaImage) -> Result<ImageSurface, cairo::Error> {
+    let mut surface = ImageSurface::create(Format::ARgb32, image.width() as i32, image.height() as i32)?;
+    let stride = surface.stride() as usize;
+    {
+        let mut data = surface.data().map_err(|_| cairo::Error::SurfaceFinished)?;
+        for (y, row) in image.rows().enumerate() {
+            let line = &mut data[y * stride..];
+            for (x, pixel) in row.enumerate() {
+                let [r, g, b, a] = pixel.0;
+                let premultiply = |c: u8| (c as u32 * a as u32 + 127) / 255;
+                let argb = (a as u32) << 24 | premultiply(r) << 16 | premultiply(g) << 8 | premultiply(b);
+                line[x * 4..x * 4 + 4].copy_from_slice(&argb.to_ne_bytes());
+            }
+        }
+    }
+    surface.mark_dirty();
+    Ok(surface)
+}
+
+/// 设置图元与背景的混合模式，无法识别的模式记录警告并按 Normal 处理
+fn set_blend_mode(cr: &Context, mode: Option<&str>) {
+    let operator = match mode.map(str::trim).unwrap_or("Normal") {
+        "" | "Normal" => Operator::Over,
+        "Multiply" => Operator::Multiply,
+        "Screen" => Operator::Screen,
+        "Overlay" => Operator::Overlay,
+        "Darken" => Operator::Darken,
+        "Lighten" => Operator::Lighten,
+        "ColorDodge" => Operator::ColorDodge,
+        "ColorBurn" => Operator::ColorBurn,
+        "HardLight" => Operator::HardLight,
+        "SoftLight" => Operator::SoftLight,
+        "Difference" => Operator::Difference,
+        "Exclusion" => Operator::Exclusion,
+        other => {
+            log::warn!("unsupported blend mode {}, fallback to Normal", other);
+            Operator::Over
+        }
+    };
+    cr.set_operator(operator);
+}
+
+/// 设置线宽、端点、连接与虚线样式，线宽与虚线长度在图元坐标系下，随 CTM 与分辨率缩放
+pub(crate) fn set_stroke_style(cr: &Context, style: &DrawStyle) {
+    cr.set_line_width(style.line_width);
+    cr.set_line_cap(match style.cap {
+        LineCap::Butt => cairo::LineCap::Butt,
+        LineCap::Round => cairo::LineCap::Round,
+        LineCap::Square => cairo::LineCap::Square,
+    });
+    cr.set_line_join(match style.join {
+        LineJoin::Miter => cairo::LineJoin::Miter,
+        LineJoin::Round => cairo::LineJoin::Round,
+        LineJoin::Bevel => cairo::LineJoin::Bevel,
+    });
+    cr.set_miter_limit(style.miter_limit.filter(|m| *m >= 1.0).unwrap_or(DEFAULT_MITER_LIMIT));
+    if let Some(dashes) = style.dash_pattern.as_ref().map(|p| &p.0).filter(|d| is_valid_dash(d)) {
+        cr.set_dash(dashes, style.dash_offset.unwrap_or(0.0));
+    }
+}
+
+/// 虚线的各段长度不能为负且不能全为 0，否则按实线处理
+pub(crate) fn is_valid_dash(dashes: &[f64]) -> bool {
+    !dashes.is_empty() && dashes.iter().all(|d| *d >= 0.0) && dashes.iter().sum::<f64>() > 0.0
+}
+
+fn channel(value: u8) -> f64 {
+    value as f64 / 255.0
+}
+
+/// 图元坐标到页面坐标的变换：先经过 CTM，再平移到外接矩形的原点
+pub(crate) fn object_matrix(boundary: &STBox, ctm: Option<STMatrix>) -> STMatrix {
+    ctm.unwrap_or_default()
+        .then(&STMatrix::translate(boundary.x, boundary.y))
+}
+
+/// 一个 Clip 在页面坐标系中的区域，各 Area 的图形合并为一条路径按非零绕数规则填充
+/// Area 中的图形与普通图形对象一样经自身的 CTM 与外接矩形定位，没有路径数据时取其外接矩形；
+/// 含有无法解析的 Area（如以文字描述的区域）时返回 None，该 Clip 不生效
+pub(crate) fn clip_path(boundary: &STBox, clip: &Clip) -> Option<RenderPath> {
+    let mut segments = Vec::new();
+    for area in &clip.area {
+        let path = area.path.as_ref()?;
+        let shape = if path.abbreviated_data.trim().is_empty() {
+            format!("M 0 0 L {w} 0 L {w} {h} L 0 {h} C", w = path.boundary.w, h = path.boundary.h)
+                .parse::<STPath>().ok()?
+        } else {
+            path.abbreviated_data.parse::<STPath>().ok()?
+        };
+        let matrix = object_matrix(&path.boundary, path.ctm)
+            .then(&area.ctm.unwrap_or_default())
+            .then(&STMatrix::translate(boundary.x, boundary.y));
+        segments.extend(shape.to_render_path().transform(&matrix).segments);
+    }
+    (!segments.is_empty()).then_some(RenderPath { segments })
+}
+
+pub(crate) fn to_cairo_matrix(m: &STMatrix) -> Matrix {
+    Matrix::new(m.a, m.b, m.c, m.d, m.e, m.f)
+}
+
+/// 页面坐标系到输出坐标系的变换，位图、SVG 与 PDF 输出均由此建立页面坐标系
+/// OFD 的原点位于页面左上角，X 轴向右、Y 轴向下，单位为毫米；输出坐标系同样以左上角为原点、Y 轴向下：
+/// cairo 的位图与 PDF 表面、以及 SVG 的用户坐标都是如此，PDF 左下角原点的换算由 cairo 在写出时完成。
+/// 因此这里只包含页面旋转与物理区域的平移，不翻转 Y 轴，后端也不得再自行翻转，否则输出会上下颠倒；
+/// 字形轮廓的 Y 轴向上，由绘制字形处单独翻转，与页面坐标系无关
+pub(crate) fn page_transform(area: &PageArea) -> STMatrix {
+    let [a, b, c, d, e, f] = area.display_transform();
+    STMatrix { a, b, c, d, e, f }
+}
+
+/// 在 cr 的当前变换上叠加 page_transform，此后以页面坐标绘制
+pub(crate) fn set_page_coordinates(cr: &Context, area: &PageArea) {
+    cr.transform(to_cairo_matrix(&page_transform(area)));
+}
+
+/// 将路径添加到 cairo 的当前路径中，二次贝塞尔曲线升阶为三次
+pub(crate) fn append_path(cr: &Context, path: &RenderPath) {
+    let mut current = (0.0, 0.0);
+    for segment in &path.segments {
+        match segment {
+            PathSegment::MoveTo { to: p } => {
+                cr.move_to(p.x, p.y);
+                current = (p.x, p.y);
+            }
+            PathSegment::LineTo { to: p } => {
+                cr.line_to(p.x, p.y);
+                current = (p.x, p.y);
+            }
+            PathSegment::QuadTo { ctrl: c, to: p } => {
+                let (x0, y0) = current;
+                cr.curve_to(
+                    x0 + 2.0 / 3.0 * (c.x - x0),
+                    y0 + 2.0 / 3.0 * (c.y - y0),
+                    p.x + 2.0 / 3.0 * (c.x - p.x),
+                    p.y + 2.0 / 3.0 * (c.y - p.y),
+                    p.x,
+                    p.y,
+                );
+                current = (p.x, p.y);
+            }
+            PathSegment::CubicTo { c1, c2, to: p } => {
+                cr.curve_to(c1.x, c1.y, c2.x, c2.y, p.x, p.y);
+                current = (p.x, p.y);
+            }
+            PathSegment::Close => {
+                cr.close_path();
+                if let Ok((x, y)) = cr.current_point() {
+                    current = (x, y);
+                }
+            }
+        }
+    }
+}
+
+/// 绘制页面前预先加载的资源文件内容
+#[derive(Debug, Default)]
+pub(crate) struct PageAssets {
+    /// 按资源标识索引的已解码图像
+    pub(crate) images: HashMap<String, Arc<RgbaImage>>,
+    /// 按资源标识索引的嵌入字体或后备字体，都没有的字体不在其中
+    pub(crate) fonts: HashMap<String, Arc<TrueTypeFont>>,
+    /// 页面上的签章图像及其在页面坐标系中的外接矩形，绘制在页面内容之上
+    pub(crate) seals: Vec<(STBox, RgbaImage)>,
+    /// 页面上的注释，绘制在页面内容之上、签章之下
+    pub(crate) annotations: Vec<Annot>,
+}
+
+/// 以文字写入 PDF 的字符与字形，每个字符对应一个字形
+#[derive(Default)]
+struct EmbeddedText {
+    text: String,
+    glyphs: Vec<Glyph>,
+    clusters: Vec<TextCluster>,
+}
+
+impl EmbeddedText {
+    fn push(&mut self, c: char, glyph: Glyph) {
+        self.text.push(c);
+        self.glyphs.push(glyph);
+        self.clusters.push(TextCluster::new(c.len_utf8() as i32, 1));
+    }
+}
+
+/// 在页面坐标系下绘制页面内容
+pub(crate) struct PageRenderer<'a> {
+    pub(crate) cr: &'a Context,
+    pub(crate) resources: &'a ResourceManager,
+    pub(crate) assets: &'a PageAssets,
+    pub(crate) layer_filter: LayerFilter,
+    pub(crate) layer_id: Option<&'a str>,
+    pub(crate) synthesize_font_styles: bool,
+    pub(crate) simulate_overprint: bool,
+    /// 输出 PDF 时以文字而非字形轮廓写出的嵌入字体
+    pub(crate) embedded_fonts: Option<&'a EmbeddedFonts>,
+    /// 跳过外接矩形与当前裁剪区不相交的图元，只渲染页面局部时使用
+    pub(crate) cull: bool,
+    /// 正在绘制的复合对象所引用的矢量图形，用于发现自引用
+    pub(crate) composites: RefCell<Vec<String>>,
+}
+
+impl PageRenderer<'_> {
+    pub(crate) fn draw_page(&self, page: &Page) -> Result<(), cairo::Error> {
+        self.draw_layers(page, None)
+    }
+
+    /// 先绘制背景层的模板，再绘制页面内容，最后绘制前景层的模板，同层模板按引用顺序绘制
+    /// 部分生成器会在页面中重复模板的图元，模板中与页面图元标识相同的图元不再绘制
+    pub(crate) fn draw_page_with_templates(&self, page: &Page, templates: &[(Page, ZOrder)]) -> Result<(), cairo::Error> {
+        for (template, _) in templates.iter().filter(|(_, z)| *z == ZOrder::Background) {
+            self.draw_layers(template, Some(page))?;
+        }
+        self.draw_page(page)?;
+        for (template, _) in templates.iter().filter(|(_, z)| *z == ZOrder::Foreground) {
+            self.draw_layers(template, Some(page))?;
+        }
+        Ok(())
+    }
+
+    /// 绘制页面的各图层，跳过在 exclude 中出现过的图元
+    fn draw_layers(&self, page: &Page, exclude: Option<&Page>) -> Result<(), cairo::Error> {
+        for layer in page.layers().into_iter().filter(|layer| self.layer_filter.selects(layer, self.layer_id)) {
+            log::trace!("drawing layer {} with {} objects", layer.id, layer.objects.len());
+            self.draw_objects(&layer.objects, exclude, layer.draw_param.as_deref())?;
+        }
+        Ok(())
+    }
+
+    /// layer_draw_param 为所在图层的绘制参数
+    fn draw_objects(&self, objects: &[PageObject], exclude: Option<&Page>, layer_draw_param: Option<&str>) -> Result<(), cairo::Error> {
+        for object in objects {
+            if exclude.is_some_and(|page| page.contains_object(object.id())) {
+                continue;
+            }
+            if self.cull && !self.is_in_clip(object)? {
+                continue;
+            }
+            let (alpha, draw_param) = object.alpha_and_draw_param();
+            let param = self.resources.effective_draw_param(draw_param, layer_draw_param);
+            // 半透明的图元先绘制到单独的组中，再整体按透明度合成，避免填充与勾边重叠处透明度叠加
+            // 透明度依次取图元自身的 Alpha、绘制参数中的透明度，都未指定时不透明
+            // 组的背景为透明，图元在组内的混合不起作用，合成到页面时再按图元的混合模式混合
+            let alpha = alpha.or(param.alpha).unwrap_or(255);
+            if alpha == 0 {
+                continue;
+            }
+            let clipped = self.apply_clips(object)?;
+            if alpha < 255 {
+                self.cr.push_group();
+            }
+            match object {
+                PageObject::PathObject(o) => self.draw_path(o, &param)?,
+                PageObject::TextObject(o) => self.draw_text(o, &param)?,
+                PageObject::ImageObject(o) => self.draw_image(o)?,
+                PageObject::CompositeObject(o) => self.draw_composite(o, layer_draw_param)?,
+                PageObject::PageBlock(block) => self.draw_objects(&block.objects, exclude, layer_draw_param)?,
+            }
+            if alpha < 255 {
+                self.cr.pop_group_to_source()?;
+                self.cr.save()?;
+                set_blend_mode(self.cr, object.blend_mode());
+                self.cr.paint_with_alpha(channel(alpha))?;
+                self.cr.restore()?;
+            }
+            if clipped {
+                self.cr.restore()?;
+            }
+        }
+        Ok(())
+    }
+
+    /// 图元的外接矩形与当前裁剪区（用户坐标系下）相交
+    /// 没有外接矩形或外接矩形为空的图元（如页块）无法判断，按相交处理
+    fn is_in_clip(&self, object: &PageObject) -> Result<bool, cairo::Error> {
+        let Some(b) = object.boundary().filter(|b| b.w > 0.0 && b.h > 0.0) else {
+            return Ok(true);
+        };
+        let (x1, y1, x2, y2) = self.cr.clip_extents()?;
+        Ok(b.x <= x2 && b.x + b.w >= x1 && b.y <= y2 && b.y + b.h >= y1)
+    }
+
+    /// 图元带有裁剪区时，将绘制区域限制在其外接矩形与各 Clip 的交集内，返回是否已保存绘制状态
+    fn apply_clips(&self, object: &PageObject) -> Result<bool, cairo::Error> {
+        let (clips, boundary) = match (object.clips(), object.boundary()) {
+            (Some(clips), Some(boundary)) if !clips.clip.is_empty() => (clips, boundary),
+            _ => return Ok(false),
+        };
+        let cr = self.cr;
+        cr.save()?;
+        cr.rectangle(boundary.x, boundary.y, boundary.w, boundary.h);
+        cr.clip();
+        cr.set_fill_rule(cairo::FillRule::Winding);
+        for clip in &clips.clip {
+            match clip_path(&boundary, clip) {
+                Some(path) => {
+                    append_path(cr, &path);
+                    cr.clip();
+                }
+                None => log::debug!("ignoring unsupported clip area on object {}", object.id()),
+            }
+        }
+        Ok(true)
+    }
+
+    /// param 为图元实际使用的绘制参数，图元自身的属性优先
+    fn draw_path(&self, object: &PathObject, param: &DrawParam) -> Result<(), cairo::Error> {
+        if !object.fill && !object.stroke {
+            return Ok(());
+        }
+        // 无法解析的路径数据直接跳过，不影响其他图元
+        let path: STPath = match object.abbreviated_data.parse() {
+            Ok(path) => path,
+            Err(e) => {
+                log::warn!("skipping path object {} with invalid path data: {}", object.id, e);
+                return Ok(());
+            }
+        };
+
+        let style = object.style(param);
+        let matrix = object_matrix(&object.boundary, object.ctm);
+        let cr = self.cr;
+        cr.save()?;
+        set_blend_mode(cr, object.blend_mode.as_deref());
+        // 图元的内容不超出外接矩形，线条在边界处按线宽与斜接留出余量，避免切掉贴边的半边线宽
+        let b = &object.boundary;
+        if b.w > 0.0 || b.h > 0.0 {
+            let margin = if object.stroke {
+                let width = style.line_width;
+                let miter = style.miter_limit.unwrap_or(DEFAULT_MITER_LIMIT).max(1.0);
+                width * miter * (matrix.a * matrix.d - matrix.b * matrix.c).abs().sqrt() / 2.0
+            } else {
+                0.0
+            };
+            cr.rectangle(b.x - margin, b.y - margin, b.w + 2.0 * margin, b.h + 2.0 * margin);
+            cr.clip();
+        }
+        cr.transform(to_cairo_matrix(&matrix));
+        append_path(cr, &path.to_render_path());
+        if object.fill {
+            cr.set_fill_rule(match object.rule {
+                FillRule::NonZero => cairo::FillRule::Winding,
+                FillRule::EvenOdd => cairo::FillRule::EvenOdd,
+            });
+            self.paint_with(&style.fill_color, Context::fill_preserve)?;
+        }
+        if object.stroke {
+            set_stroke_style(cr, &style);
+            self.paint_with(&style.stroke_color, Context::stroke_preserve)?;
+        }
+        cr.new_path();
+        cr.restore()
+    }
+
+    /// 逐字绘制文字，字形来自嵌入字体；字体缺失或不含该字符时使用系统字体
+    /// 字符间距取自 DeltaX/DeltaY，个数不足时沿用最后一个，未指定时按字形的步进排列
+    fn draw_text(&self, object: &TextObject, param: &DrawParam) -> Result<(), cairo::Error> {
+        if (!object.fill && !object.stroke) || object.size <= 0.0 {
+            return Ok(());
+        }
+        let matrix = object_matrix(&object.boundary, object.ctm);
+        if matrix.a * matrix.d - matrix.b * matrix.c == 0.0 {
+            log::debug!("skipping text object {} with singular CTM", object.id);
+            return Ok(());
+        }
+
+        let cr = self.cr;
+        let size = object.size;
+        let h_scale = object.h_scale();
+        let font_arc = self.assets.fonts.get(&object.font);
+        let font = font_arc.map(Arc::as_ref);
+        cr.save()?;
+        set_blend_mode(cr, object.blend_mode.as_deref());
+        cr.transform(to_cairo_matrix(&matrix));
+        let family = self.resources.get_font(&object.font)
+            .map(|f| f.family_name.clone().filter(|n| !n.is_empty()).unwrap_or_else(|| f.font_name.clone()))
+            .filter(|n| !n.is_empty())
+            .unwrap_or_else(|| DEFAULT_FONT_FAMILY.to_string());
+        // 嵌入字体只有常规字形时按需模拟粗体与斜体，系统字体则直接选用对应的字形
+        let font_res = self.resources.get_font(&object.font);
+        let (synthetic_bold, synthetic_italic) = match font {
+            Some(_) if self.synthesize_font_styles => (
+                object.is_bold() && !font_res.is_some_and(|f| f.bold),
+                object.italic && !font_res.is_some_and(|f| f.italic),
+            ),
+            _ => (false, false),
+        };
+        let (slant, weight) = match font {
+            None if object.italic && object.is_bold() => (FontSlant::Italic, FontWeight::Bold),
+            None if object.italic => (FontSlant::Italic, FontWeight::Normal),
+            None if object.is_bold() => (FontSlant::Normal, FontWeight::Bold),
+            _ => (FontSlant::Normal, FontWeight::Normal),
+        };
+        cr.select_font_face(&family, slant, weight);
+        cr.set_font_size(size);
+
+        // 字形绕各自的基线起点顺时针旋转，按阅读方向与字符方向决定字形相对当前位置的放置
+        let rotation = (object.char_direction.rem_euclid(360) as f64).to_radians();
+        // CGTransform 指定的字形只对嵌入字体有效，没有嵌入字体时仍按字符以系统字体绘制
+        let overrides = if font.is_some() { object.glyph_overrides() } else { HashMap::new() };
+        // 输出 PDF 时，允许嵌入的字体以文字写出，使文字可以选取与检索；
+        // 需要模拟粗体或斜体、勾边、旋转字形，或含有字体中没有的字符时，仍以字形轮廓写出
+        let embedded_face = font_arc
+            .filter(|_| object.fill && !object.stroke)
+            .filter(|_| !synthetic_bold && !synthetic_italic && rotation == 0.0 && h_scale == 1.0 && overrides.is_empty())
+            .filter(|f| object.text_codes().all(|code| code.text.chars().all(|c| c != '\0' && f.glyph_index(c).is_some())))
+            .and_then(|f| self.embedded_fonts?.face(f));
+        let mut embedded = embedded_face.map(|_| EmbeddedText::default());
+        let mut char_index = 0;
+        for code in object.text_codes() {
+            let delta_x = parse_deltas(code.delta_x.as_deref());
+            let delta_y = parse_deltas(code.delta_y.as_deref());
+            let (mut x, mut y) = (code.x, code.y);
+            let mut step = 0.0;
+            for (i, c) in code.text.chars().enumerate() {
+                if i > 0 {
+                    let (step_x, step_y) = advance_along(object.read_direction, step);
+                    x += delta_at(&delta_x, i - 1).unwrap_or(step_x);
+                    y += delta_at(&delta_y, i - 1).unwrap_or(step_y);
+                }
+                let mapped = overrides.get(&char_index);
+                char_index += 1;
+                let glyph = font.and_then(|font| font.glyph_index(c).map(|glyph| (font, glyph)));
+                let text = c.to_string();
+                // 未给出字符间距时，长体与扁体的字符按缩放后的字宽排列
+                let advance = h_scale * match (mapped, font, glyph) {
+                    (Some(glyphs), Some(font), _) => glyphs.iter().map(|g| font.advance(*g)).sum::<f64>() * size / font.units_per_em(),
+                    (None, _, Some((font, glyph))) => font.advance(glyph) * size / font.units_per_em(),
+                    _ => cr.text_extents(&text)?.x_advance(),
+                };
+                let ((offset_x, offset_y), next_step) =
+                    glyph_placement(object.read_direction, object.char_direction, advance, size);
+                step = next_step;
+
+                if let (Some(embedded), Some((_, index))) = (embedded.as_mut(), glyph) {
+                    embedded.push(c, Glyph::new(index as _, x + offset_x, y + offset_y));
+                    continue;
+                }
+                cr.save()?;
+                cr.translate(x + offset_x, y + offset_y);
+                cr.rotate(rotation);
+                if synthetic_italic {
+                    // y 轴向下，基线以上的部分向右倾斜
+                    cr.transform(Matrix::new(1.0, 0.0, -SYNTHETIC_ITALIC_SHEAR, 1.0, 0.0, 0.0));
+                }
+                // 水平缩放作用于字形自身的坐标系，字形旋转时随之旋转
+                cr.scale(h_scale, 1.0);
+                match (mapped, font, glyph) {
+                    (Some(glyphs), Some(font), _) => {
+                        let scale = size / font.units_per_em();
+                        cr.scale(scale, -scale);
+                        let mut pen = 0.0;
+                        for &glyph in glyphs {
+                            let outline = RenderPath { segments: font.outline(glyph) };
+                            append_path(cr, &outline.transform(&STMatrix::translate(pen, 0.0)));
+                            pen += font.advance(glyph);
+                        }
+                    }
+                    (None, _, Some((font, glyph))) => {
+                        let scale = size / font.units_per_em();
+                        cr.scale(scale, -scale);
+                        append_path(cr, &RenderPath { segments: font.outline(glyph) });
+                    }
+                    _ if !c.is_whitespace() => {
+                        cr.move_to(0.0, 0.0);
+                        cr.text_path(&text);
+                    }
+                    _ => {}
+                }
+                cr.restore()?;
+            }
+        }
+
+        let style = object.style(param);
+        if let (Some(embedded), Some(face)) = (embedded.filter(|e| !e.glyphs.is_empty()), embedded_face) {
+            self.set_color(&style.fill_color)?;
+            cr.set_font_face(face);
+            cr.set_font_size(size);
+            cr.show_text_glyphs(&embedded.text, &embedded.glyphs, &embedded.clusters, TextClusterFlags::None)?;
+        }
+        if object.fill {
+            self.paint_with(&style.fill_color, |cr| {
+                cr.fill_preserve()?;
+                if synthetic_bold {
+                    cr.set_line_width(size * SYNTHETIC_BOLD_WIDTH);
+                    cr.set_line_join(cairo::LineJoin::Round);
+                    cr.stroke_preserve()?;
+                }
+                Ok(())
+            })?;
+        }
+        if object.stroke {
+            set_stroke_style(cr, &style);
+            self.paint_with(&style.stroke_color, Context::stroke_preserve)?;
+        }
+        cr.new_path();
+        cr.restore()
+    }
+
+    /// 复合对象引用的矢量图形经 CTM 映射到外接矩形中，并裁剪到外接矩形与矢量图形的宽高范围
+    /// 矢量图形中的图元未指定绘制参数时继承复合对象的绘制参数；矢量图形直接或间接引用自身时记录警告后跳过
+    fn draw_composite(&self, object: &CompositeObject, layer_draw_param: Option<&str>) -> Result<(), cairo::Error> {
+        let Some(unit) = self.resources.vector_graphic(&object.resource_id) else {
+            log::warn!("composite object {} references unknown vector graphic {}", object.id, object.resource_id);
+            return Ok(());
+        };
+        if self.composites.borrow().contains(&object.resource_id) {
+            log::warn!("composite graphic unit {} references itself", object.resource_id);
+            return Ok(());
+        }
+        let boundary = &object.boundary;
+        let matrix = object_matrix(boundary, object.ctm);
+        if matrix.a * matrix.d - matrix.b * matrix.c == 0.0 {
+            log::debug!("skipping composite object {} with singular CTM", object.id);
+            return Ok(());
+        }
+
+        let cr = self.cr;
+        cr.save()?;
+        set_blend_mode(cr, object.blend_mode.as_deref());
+        cr.rectangle(boundary.x, boundary.y, boundary.w, boundary.h);
+        cr.clip();
+        cr.transform(to_cairo_matrix(&matrix));
+        if unit.width > 0.0 && unit.height > 0.0 {
+            cr.rectangle(0.0, 0.0, unit.width, unit.height);
+            cr.clip();
+        }
+        self.composites.borrow_mut().push(object.resource_id.clone());
+        let result = self.draw_objects(&unit.content.objects, None, object.draw_param.as_deref().or(layer_draw_param));
+        self.composites.borrow_mut().pop();
+        result?;
+        cr.restore()
+    }
+
+    /// 签章图像拉伸到外接矩形中，带透明通道的图像按透明度合成
+    /// 签名中没有混合模式的描述，不透明的印章图像（多为白底的 JPEG、BMP）按正片叠底绘制，
+    /// 使白色背景不遮挡页面内容，红色印文与下方的文字叠加后文字仍然可见
+    /// 注释外观中的图元以外接矩形的左上角为原点，并裁剪到外接矩形内
+    /// 定义了边框的注释在外观之上沿外接矩形勾边，边框不受裁剪
+    fn draw_annotations(&self, annotations: &[Annot]) -> Result<(), cairo::Error> {
+        let cr = self.cr;
+        for annot in annotations {
+            let Some(appearance) = annot.appearance.as_ref() else {
+                continue;
+            };
+            let boundary = &appearance.boundary;
+            cr.save()?;
+            cr.rectangle(boundary.x, boundary.y, boundary.w, boundary.h);
+            cr.clip();
+            cr.translate(boundary.x, boundary.y);
+            self.draw_objects(&appearance.objects, None, None)?;
+            cr.restore()?;
+            if let Some(border) = annot.border.as_ref().filter(|b| b.is_visible()) {
+                let style = border.style();
+                cr.save()?;
+                cr.new_path();
+                cr.rectangle(boundary.x, boundary.y, boundary.w, boundary.h);
+                set_stroke_style(cr, &style);
+                self.set_color(&style.stroke_color)?;
+                cr.stroke()?;
+                cr.restore()?;
+            }
+        }
+        Ok(())
+    }
+
+    fn draw_seals(&self, seals: &[(STBox, RgbaImage)]) -> Result<(), cairo::Error> {
+        let cr = self.cr;
+        for (boundary, image) in seals {
+            if image.width() == 0 || image.height() == 0 || boundary.w <= 0.0 || boundary.h <= 0.0 {
+                continue;
+            }
+            let surface = image_to_surface(image)?;
+            cr.save()?;
+            if image.pixels().all(|p| p.0[3] == 255) {
+                cr.set_operator(Operator::Multiply);
+            }
+            cr.translate(boundary.x, boundary.y);
+            cr.scale(boundary.w / image.width() as f64, boundary.h / image.height() as f64);
+            cr.set_source_surface(&surface, 0.0, 0.0)?;
+            cr.paint()?;
+            cr.restore()?;
+        }
+        Ok(())
+    }
+
+    /// 图像绘制在图元坐标系的单位正方形内，经 CTM 映射到外接矩形中，并裁剪到外接矩形
+    fn draw_image(&self, object: &ImageObject) -> Result<(), cairo::Error> {
+        let image = match self.assets.images.get(&object.resource_id) {
+            Some(image) if image.width() > 0 && image.height() > 0 => image,
+            _ => {
+                log::debug!("skipping image object {} without image data", object.id);
+                return Ok(());
+            }
+        };
+        // 奇异矩阵会使 cairo 进入错误状态，这类图像不可见，直接跳过
+        let boundary = &object.boundary;
+        let matrix = object_matrix(boundary, object.ctm);
+        if matrix.a * matrix.d - matrix.b * matrix.c == 0.0 {
+            log::debug!("skipping image object {} with singular CTM", object.id);
+            return Ok(());
+        }
+        let surface = image_to_surface(image)?;
+
+        let cr = self.cr;
+        cr.save()?;
+        set_blend_mode(cr, object.blend_mode.as_deref());
+        cr.rectangle(boundary.x, boundary.y, boundary.w, boundary.h);
+        cr.clip();
+        cr.transform(to_cairo_matrix(&matrix));
+        cr.scale(1.0 / image.width() as f64, 1.0 / image.height() as f64);
+        cr.set_source_surface(&surface, 0.0, 0.0)?;
+        cr.paint()?;
+        cr.restore()
+    }
+
+    /// 以 color 为源执行 paint，启用叠印模拟且颜色按叠印绘制时改用正片叠底：
+    /// 分量为 0 的通道转换为 RGB 后为白色，与下层相乘后保留下层的颜色
+    fn paint_with(&self, color: &CTColor, paint: impl FnOnce(&Context) -> Result<(), cairo::Error>) -> Result<(), cairo::Error> {
+        self.set_color(color)?;
+        if !(self.simulate_overprint && color.overprints(&self.resources.color_spaces)) {
+            return paint(self.cr);
+        }
+        self.cr.save()?;
+        self.cr.set_operator(Operator::Multiply);
+        let result = paint(self.cr);
+        self.cr.restore()?;
+        result
+    }
+
+    /// 渐变的坐标位于当前的用户坐标系中，调用前应已变换到图元坐标系
+    fn set_color(&self, color: &CTColor) -> Result<(), cairo::Error> {
+        let color_spaces = &self.resources.color_spaces;
+        let alpha = color.alpha(color_spaces);
+        let (gradient, map_type, extend): (Gradient, _, _) = match color.color() {
+            Color::Solid(_) => {
+                let [r, g, b] = color.to_rgb(color_spaces);
+                self.cr.set_source_rgba(channel(r), channel(g), channel(b), channel(alpha));
+                return Ok(());
+            }
+            Color::Axial(shd) => {
+                let (start, end) = (shd.start_point, shd.end_point);
+                let gradient = LinearGradient::new(start.x, start.y, end.x, end.y);
+                ((*gradient).clone(), shd.map_type, shd.extend)
+            }
+            Color::Radial(shd) => {
+                let (start, end) = (shd.start_point, shd.end_point);
+                let gradient = RadialGradient::new(start.x, start.y, shd.start_radius, end.x, end.y, shd.end_radius);
+                ((*gradient).clone(), shd.map_type, shd.extend)
+            }
+        };
+        // 各颜色段经所在的颜色空间转换为 RGB，透明度与整个颜色的透明度相乘
+        for (offset, stop) in color.color_stops() {
+            let [r, g, b] = stop.to_rgb(color_spaces);
+            let stop_alpha = stop.alpha(color_spaces) as f64 * alpha as f64 / 255.0;
+            gradient.add_color_stop_rgba(offset, channel(r), channel(g), channel(b), stop_alpha / 255.0);
+        }
+        // cairo 不能只在一侧延伸，Extend 为 1 或 2 时按两侧延伸处理
+        gradient.set_extend(match map_type {
+            MapType::Repeat => Extend::Repeat,
+            MapType::Reflect => Extend::Reflect,
+            MapType::Direct if extend == 0 => Extend::None,
+            MapType::Direct => Extend::Pad,
+        });
+        self.cr.set_source(&gradient)
+    }
+}
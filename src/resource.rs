@@ -1,22 +1,496 @@
+use std::collections::{HashMap, HashSet};
+use std::sync::Arc;
+
 use serde::Deserialize;
 
+use crate::attachment::{MediaInfo, MediaType};
+use crate::color::CTColor;
+use crate::icc::IccTransform;
+use crate::ofd::resolve_path;
+use crate::page::{LineCap, LineJoin, PageBlock, PageObject};
+use crate::st_types::{deserialize_st_opt, STArray};
 
 #[derive(Debug, Deserialize, Default)]
-#[serde(rename_all = "PascalCase")]
+#[serde(default, rename_all = "PascalCase")]
 pub(crate) struct Res{
-    color_spaces: ColorSpaces,
+    /// 资源文件的通用存储路径，资源中引用的文件均相对于该路径
+    pub(crate) base_loc: String,
+    pub(crate) color_spaces: ColorSpaces,
+    pub(crate) draw_params: DrawParams,
+    pub(crate) fonts: Fonts,
+    pub(crate) multi_medias: MultiMedias,
+    pub(crate) composite_graphic_units: CompositeGraphicUnits,
 }
 
-#[derive(Debug, Deserialize, Default)]
-#[serde(rename_all = "PascalCase")]
-struct ColorSpaces {
-    color_space: Vec<ColorSpace>,
+impl Res {
+    pub(crate) fn from_xml(xml: &str) -> Result<Res, serde_xml_rs::Error> {
+        serde_xml_rs::from_str(xml)
+    }
 }
 
 #[derive(Debug, Deserialize, Default)]
+#[serde(default, rename_all = "PascalCase")]
+pub(crate) struct ColorSpaces {
+    pub(crate) color_space: Vec<ColorSpace>,
+}
+
+/// 颜色空间 CT_ColorSpace，可带调色板，调色板中的颜色与颜色空间的类型一致
+#[derive(Debug, Deserialize, Default, Clone)]
 pub(crate) struct ColorSpace {
     #[serde(rename = "ID")]
-    id : String,
+    pub(crate) id : String,
     #[serde(rename = "Type")]
-    color_space_type : String,
-}
\ No newline at end of file
+    pub(crate) color_space_type : ColorSpaceType,
+    /// 每个颜色通道的位数，缺省为 8
+    #[serde(rename = "BitsPerComponent")]
+    pub(crate) bits_per_component: Option<u8>,
+    #[serde(rename = "Palette")]
+    pub(crate) palette: Option<Palette>,
+    /// 颜色空间的 ICC 描述文件，路径相对于资源文件的 BaseLoc
+    #[serde(rename = "Profile")]
+    pub(crate) profile: Option<String>,
+    /// 由描述文件建立的颜色转换，加载资源时设置，没有描述文件或无法解析时为 None
+    #[serde(skip)]
+    pub(crate) icc: Option<Arc<IccTransform>>,
+}
+
+/// 颜色空间的类型，无法识别的类型按 RGB 处理
+#[derive(Debug, Deserialize, Default, Clone, Copy, PartialEq, Eq)]
+#[allow(clippy::upper_case_acronyms)]
+pub(crate) enum ColorSpaceType {
+    GRAY,
+    CMYK,
+    #[default]
+    #[serde(other)]
+    RGB,
+}
+
+/// 调色板，CT_Color 的 Index 引用其中的颜色
+#[derive(Debug, Deserialize, Default, Clone)]
+#[serde(default)]
+pub(crate) struct Palette {
+    #[serde(rename = "CV")]
+    pub(crate) cv: Vec<String>,
+}
+
+impl ColorSpace {
+    /// 颜色空间的通道数
+    pub(crate) fn channels(&self) -> usize {
+        match self.color_space_type {
+            ColorSpaceType::GRAY => 1,
+            ColorSpaceType::RGB => 3,
+            ColorSpaceType::CMYK => 4,
+        }
+    }
+
+    /// 调色板中 index 处颜色的各通道分量，通道数不足的按 0 补齐
+    pub(crate) fn palette_components(&self, index: usize) -> Option<Vec<f64>> {
+        let value = self.palette.as_ref()?.cv.get(index)?;
+        let mut components = parse_components(value);
+        components.resize(self.channels(), 0.0);
+        Some(components)
+    }
+
+    /// 调色板中的颜色个数，没有调色板时为 0
+    pub(crate) fn palette_len(&self) -> usize {
+        self.palette.as_ref().map_or(0, |p| p.cv.len())
+    }
+
+    /// 分量的最大值，由 BitsPerComponent 决定，缺省为 8 位
+    pub(crate) fn component_max(&self) -> f64 {
+        ((1u32 << self.bits_per_component.unwrap_or(8).clamp(1, 16)) - 1) as f64
+    }
+
+    /// 将颜色分量转换为 RGB，分量取值范围由 BitsPerComponent 决定，未知类型按 RGB 处理
+    /// 带有 ICC 描述文件时经描述文件转换为 sRGB，否则按各类型的近似公式计算
+    pub(crate) fn to_rgb(&self, components: &[f64]) -> [u8; 3] {
+        let max = self.component_max();
+        let c = |i: usize| components.get(i).copied().unwrap_or(0.0).clamp(0.0, max) / max * 255.0;
+        if let Some(icc) = &self.icc {
+            let values: Vec<u8> = (0..self.channels()).map(|i| c(i).round() as u8).collect();
+            return icc.to_rgb(&values);
+        }
+        match self.color_space_type {
+            ColorSpaceType::GRAY => {
+                let g = c(0).round() as u8;
+                [g, g, g]
+            }
+            ColorSpaceType::CMYK => {
+                let k = 1.0 - c(3) / 255.0;
+                let r = 255.0 * (1.0 - c(0) / 255.0) * k;
+                let g = 255.0 * (1.0 - c(1) / 255.0) * k;
+                let b = 255.0 * (1.0 - c(2) / 255.0) * k;
+                [r.round() as u8, g.round() as u8, b.round() as u8]
+            }
+            ColorSpaceType::RGB => [c(0).round() as u8, c(1).round() as u8, c(2).round() as u8],
+        }
+    }
+}
+
+/// 解析以空格分隔的颜色分量，支持十进制与 # 开头的十六进制写法，无法解析的分量按 0 处理
+pub(crate) fn parse_components(value: &str) -> Vec<f64> {
+    value
+        .split_whitespace()
+        .map(|v| match v.strip_prefix('#') {
+            Some(hex) => u32::from_str_radix(hex, 16).map(f64::from).unwrap_or(0.0),
+            None => v.parse().unwrap_or(0.0),
+        })
+        .collect()
+}
+
+#[derive(Debug, Deserialize, Default)]
+#[serde(default, rename_all = "PascalCase")]
+pub(crate) struct MultiMedias {
+    pub(crate) multi_media: Vec<MultiMedia>,
+}
+
+/// 多媒体资源 CT_MultiMedia，图像对象通过 ResourceID 引用
+#[derive(Debug, Deserialize, Default, Clone)]
+pub(crate) struct MultiMedia {
+    #[serde(rename = "ID")]
+    pub(crate) id: String,
+    #[serde(rename = "Type", default)]
+    pub(crate) media_type: MediaType,
+    #[serde(rename = "Format")]
+    pub(crate) format: Option<String>,
+    #[serde(rename = "MediaFile", default)]
+    pub(crate) media_file: String,
+}
+
+#[derive(Debug, Deserialize, Default)]
+#[serde(default, rename_all = "PascalCase")]
+pub(crate) struct Fonts {
+    pub(crate) font: Vec<Font>,
+}
+
+/// 字型资源 CT_Font，FontFile 指向嵌入的字形文件
+#[derive(Debug, Deserialize, Default, Clone)]
+pub(crate) struct Font {
+    #[serde(rename = "ID")]
+    pub(crate) id: String,
+    #[serde(rename = "FontName", default)]
+    pub(crate) font_name: String,
+    #[serde(rename = "FamilyName")]
+    pub(crate) family_name: Option<String>,
+    #[serde(rename = "Charset")]
+    pub(crate) charset: Option<String>,
+    #[serde(rename = "FontFile")]
+    pub(crate) font_file: Option<String>,
+    /// 字形本身是否为粗体、斜体
+    #[serde(rename = "Bold", default)]
+    pub(crate) bold: bool,
+    #[serde(rename = "Italic", default)]
+    pub(crate) italic: bool,
+}
+
+#[derive(Debug, Deserialize, Default)]
+#[serde(default, rename_all = "PascalCase")]
+pub(crate) struct CompositeGraphicUnits {
+    pub(crate) composite_graphic_unit: Vec<VectorG>,
+}
+
+/// 矢量图形 CT_VectorG，由复合对象通过 ResourceID 引用，Content 中的图元位于宽 Width、高 Height 的坐标系中
+#[derive(Debug, Deserialize, Default)]
+pub(crate) struct VectorG {
+    #[serde(rename = "ID")]
+    pub(crate) id: String,
+    #[serde(rename = "Width", default)]
+    pub(crate) width: f64,
+    #[serde(rename = "Height", default)]
+    pub(crate) height: f64,
+    #[serde(rename = "Content", default)]
+    pub(crate) content: PageBlock,
+}
+
+#[derive(Debug, Deserialize, Default)]
+#[serde(default, rename_all = "PascalCase")]
+pub(crate) struct DrawParams {
+    pub(crate) draw_param: Vec<DrawParam>,
+}
+
+/// 绘制参数 CT_DrawParam，为引用它的图元提供线条与颜色的缺省值
+#[derive(Debug, Deserialize, Default, Clone)]
+pub(crate) struct DrawParam {
+    #[serde(rename = "ID", default)]
+    pub(crate) id: String,
+    /// 基础绘制参数，本参数未指定的属性从中继承
+    #[serde(rename = "Relative")]
+    pub(crate) relative: Option<String>,
+    #[serde(rename = "Alpha")]
+    pub(crate) alpha: Option<u8>,
+    #[serde(rename = "LineWidth")]
+    pub(crate) line_width: Option<f64>,
+    #[serde(rename = "Cap")]
+    pub(crate) cap: Option<LineCap>,
+    #[serde(rename = "Join")]
+    pub(crate) join: Option<LineJoin>,
+    #[serde(rename = "MiterLimit")]
+    pub(crate) miter_limit: Option<f64>,
+    #[serde(rename = "DashOffset")]
+    pub(crate) dash_offset: Option<f64>,
+    #[serde(rename = "DashPattern", deserialize_with = "deserialize_st_opt", default)]
+    pub(crate) dash_pattern: Option<STArray>,
+    #[serde(rename = "FillColor")]
+    pub(crate) fill_color: Option<CTColor>,
+    #[serde(rename = "StrokeColor")]
+    pub(crate) stroke_color: Option<CTColor>,
+}
+
+impl DrawParam {
+    /// 本参数未指定的属性取 parent 中的值
+    pub(crate) fn inherit(&mut self, parent: &DrawParam) {
+        self.alpha = self.alpha.or(parent.alpha);
+        self.line_width = self.line_width.or(parent.line_width);
+        self.cap = self.cap.or(parent.cap);
+        self.join = self.join.or(parent.join);
+        self.miter_limit = self.miter_limit.or(parent.miter_limit);
+        self.dash_offset = self.dash_offset.or(parent.dash_offset);
+        if self.dash_pattern.is_none() {
+            self.dash_pattern = parent.dash_pattern.clone();
+        }
+        if self.fill_color.is_none() {
+            self.fill_color = parent.fill_color.clone();
+        }
+        if self.stroke_color.is_none() {
+            self.stroke_color = parent.stroke_color.clone();
+        }
+    }
+}
+
+/// 文档的资源表，合并 PublicRes 与 DocumentRes 中的条目
+/// 资源引用的文件在加入时即解析为压缩包内的文件名
+#[derive(Debug, Default)]
+pub(crate) struct ResourceManager {
+    pub(crate) color_spaces: HashMap<String, ColorSpace>,
+    draw_params: HashMap<String, DrawParam>,
+    fonts: HashMap<String, Font>,
+    font_files: HashMap<String, String>,
+    profile_files: HashMap<String, String>,
+    media_files: HashMap<String, String>,
+    media_formats: HashMap<String, String>,
+    media_types: HashMap<String, MediaType>,
+    vector_graphics: HashMap<String, VectorG>,
+}
+
+impl ResourceManager {
+    /// 加入一个资源文件的内容，base_dir 为该资源文件中引用文件的基准目录
+    pub(crate) fn add(&mut self, base_dir: &str, res: Res) {
+        for color_space in res.color_spaces.color_space {
+            if let Some(profile) = color_space.profile.as_deref().map(str::trim).filter(|p| !p.is_empty()) {
+                self.profile_files.insert(color_space.id.clone(), resolve_path(base_dir, profile));
+            }
+            self.color_spaces.insert(color_space.id.clone(), color_space);
+        }
+        for draw_param in res.draw_params.draw_param {
+            self.draw_params.insert(draw_param.id.clone(), draw_param);
+        }
+        for font in res.fonts.font {
+            if let Some(file) = font.font_file.as_deref().filter(|f| !f.is_empty()) {
+                self.font_files.insert(font.id.clone(), resolve_path(base_dir, file));
+            }
+            self.fonts.insert(font.id.clone(), font);
+        }
+        for media in res.multi_medias.multi_media {
+            if let Some(format) = media.format.filter(|f| !f.trim().is_empty()) {
+                self.media_formats.insert(media.id.clone(), format);
+            }
+            self.media_types.insert(media.id.clone(), media.media_type);
+            self.media_files.insert(media.id, resolve_path(base_dir, &media.media_file));
+        }
+        for unit in res.composite_graphic_units.composite_graphic_unit {
+            self.vector_graphics.insert(unit.id.clone(), unit);
+        }
+    }
+
+    /// 资源表中所有资源的标识
+    pub(crate) fn resource_ids(&self) -> impl Iterator<Item = &str> {
+        self.color_spaces.keys()
+            .chain(self.draw_params.keys())
+            .chain(self.fonts.keys())
+            .chain(self.media_types.keys())
+            .chain(self.vector_graphics.keys())
+            .map(String::as_str)
+    }
+
+    pub(crate) fn get_font(&self, id: &str) -> Option<&Font> {
+        self.fonts.get(id)
+    }
+
+    pub(crate) fn get_draw_param(&self, id: &str) -> Option<&DrawParam> {
+        self.draw_params.get(id)
+    }
+
+    /// 沿 Relative 逐级合并后的绘制参数，下级的属性优先；引用不存在时停止，引用成环时记录警告后停止
+    pub(crate) fn resolve_draw_param(&self, id: &str) -> DrawParam {
+        let mut resolved = DrawParam { id: id.to_string(), ..DrawParam::default() };
+        let mut visited = HashSet::new();
+        let mut current = Some(id);
+        while let Some(id) = current {
+            if !visited.insert(id) {
+                log::warn!("draw param {} is part of a Relative cycle", id);
+                break;
+            }
+            let Some(param) = self.get_draw_param(id) else {
+                break;
+            };
+            resolved.inherit(param);
+            current = param.relative.as_deref();
+        }
+        resolved
+    }
+
+    /// 图元实际使用的绘制参数：图元引用的绘制参数优先，未指定的属性取所在图层的绘制参数
+    pub(crate) fn effective_draw_param(&self, object: Option<&str>, layer: Option<&str>) -> DrawParam {
+        let mut param = object.map(|id| self.resolve_draw_param(id)).unwrap_or_default();
+        if let Some(layer) = layer {
+            param.inherit(&self.resolve_draw_param(layer));
+        }
+        param
+    }
+
+    /// 复合对象引用的矢量图形
+    pub(crate) fn vector_graphic(&self, id: &str) -> Option<&VectorG> {
+        self.vector_graphics.get(id)
+    }
+
+    /// 在 objects 之后追加其中复合对象引用的矢量图形中的图元，用于收集绘制时需要的图像与字体
+    /// 矢量图形中嵌套引用的矢量图形同样展开，每个矢量图形只展开一次，引用成环时不会无限展开
+    pub(crate) fn with_composite_objects<'a>(&'a self, mut objects: Vec<&'a PageObject>) -> Vec<&'a PageObject> {
+        let mut expanded = HashSet::new();
+        let mut i = 0;
+        while i < objects.len() {
+            if let PageObject::CompositeObject(o) = objects[i] {
+                if let Some(unit) = self.vector_graphic(&o.resource_id).filter(|_| expanded.insert(o.resource_id.as_str())) {
+                    objects.extend(unit.content.objects());
+                }
+            }
+            i += 1;
+        }
+        objects
+    }
+
+    /// 嵌入字形文件在压缩包内的文件名
+    pub(crate) fn font_file(&self, id: &str) -> Option<&str> {
+        self.font_files.get(id).map(String::as_str)
+    }
+
+    /// 各颜色空间引用的 ICC 描述文件在压缩包内的文件名，按颜色空间标识
+    pub(crate) fn profile_files(&self) -> Vec<(String, String)> {
+        self.profile_files.iter().map(|(id, file)| (id.clone(), file.clone())).collect()
+    }
+
+    /// 为颜色空间设置由其描述文件建立的颜色转换
+    pub(crate) fn set_icc(&mut self, id: &str, icc: IccTransform) {
+        if let Some(color_space) = self.color_spaces.get_mut(id) {
+            color_space.icc = Some(Arc::new(icc));
+        }
+    }
+
+    /// 多媒体资源在压缩包内的文件名
+    pub(crate) fn media_file(&self, id: &str) -> Option<&str> {
+        self.media_files.get(id).map(String::as_str)
+    }
+
+    /// 多媒体资源声明的格式，如 PNG、JB2，未声明时返回 None
+    pub(crate) fn media_format(&self, id: &str) -> Option<&str> {
+        self.media_formats.get(id).map(String::as_str)
+    }
+
+    /// 多媒体资源的类型，资源不存在时返回 None
+    pub(crate) fn media_type(&self, id: &str) -> Option<MediaType> {
+        self.media_types.get(id).copied()
+    }
+
+    /// 资源中的音频与视频，按标识排序；图像类的多媒体资源由图像对象绘制，不包含在内
+    pub(crate) fn audio_video(&self) -> Vec<MediaInfo> {
+        let mut media: Vec<MediaInfo> = self.media_types.iter()
+            .filter(|(_, media_type)| **media_type != MediaType::Image)
+            .filter_map(|(id, media_type)| Some(MediaInfo {
+                id: id.clone(),
+                media_type: *media_type,
+                format: self.media_format(id).unwrap_or_default().to_string(),
+                location: self.media_file(id)?.to_string(),
+            }))
+            .collect();
+        media.sort_by(|a, b| a.id.cmp(&b.id));
+        media
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// 解析 ofd:Res 节点的内容，资源文件位于 Doc_0，BaseLoc 为 Res
+    fn manager(xml: &str) -> ResourceManager {
+        let res = Res::from_xml(&format!(
+            "<ofd:Res xmlns:ofd=\"http://www.ofdspec.org/2016\" BaseLoc=\"Res\">{}</ofd:Res>",
+            xml,
+        )).unwrap();
+        let mut resources = ResourceManager::default();
+        resources.add(&resolve_path("Doc_0", &res.base_loc), res);
+        resources
+    }
+
+    #[test]
+    fn parses_mixed_color_spaces_and_fonts() {
+        let resources = manager(
+            "<ofd:ColorSpaces><ofd:ColorSpace ID=\"1\" Type=\"GRAY\"/><ofd:ColorSpace ID=\"2\" Type=\"CMYK\" BitsPerComponent=\"16\"/>\
+             </ofd:ColorSpaces>\
+             <ofd:Fonts><ofd:Font ID=\"3\" FontName=\"宋体\" FamilyName=\"SimSun\" Charset=\"unicode\"/>\
+             <ofd:Font ID=\"4\" FontName=\"Fixture\" Bold=\"true\"><ofd:FontFile>font.ttf</ofd:FontFile></ofd:Font></ofd:Fonts>",
+        );
+        assert_eq!(resources.color_spaces["1"].color_space_type, ColorSpaceType::GRAY);
+        assert_eq!(resources.color_spaces["2"].color_space_type, ColorSpaceType::CMYK);
+        assert_eq!(resources.color_spaces["2"].component_max(), 65535.0);
+        let font = resources.get_font("3").unwrap();
+        assert_eq!(font.font_name, "宋体");
+        assert_eq!(font.family_name.as_deref(), Some("SimSun"));
+        assert_eq!(font.charset.as_deref(), Some("unicode"));
+        assert_eq!(resources.font_file("3"), None);
+        assert!(resources.get_font("4").unwrap().bold);
+        assert_eq!(resources.font_file("4"), Some("Doc_0/Res/font.ttf"));
+        assert!(resources.get_font("5").is_none());
+    }
+
+    fn color_space(color_space_type: ColorSpaceType) -> ColorSpace {
+        ColorSpace { color_space_type, ..ColorSpace::default() }
+    }
+
+    #[test]
+    fn cmyk_and_gray_convert_to_rgb() {
+        assert_eq!(color_space(ColorSpaceType::CMYK).to_rgb(&[255.0, 0.0, 0.0, 0.0]), [0, 255, 255]);
+        assert_eq!(color_space(ColorSpaceType::CMYK).to_rgb(&[0.0, 0.0, 0.0, 255.0]), [0, 0, 0]);
+        assert_eq!(color_space(ColorSpaceType::GRAY).to_rgb(&[128.0]), [128, 128, 128]);
+        // 1 位灰度的 0.5 即一半的亮度
+        let one_bit = ColorSpace { bits_per_component: Some(1), ..color_space(ColorSpaceType::GRAY) };
+        assert_eq!(one_bit.to_rgb(&[0.5]), [128, 128, 128]);
+    }
+
+    #[test]
+    fn draw_params_inherit_along_the_relative_chain() {
+        let resources = manager(
+            "<ofd:DrawParams><ofd:DrawParam ID=\"1\" LineWidth=\"3\" Alpha=\"100\"/>\
+             <ofd:DrawParam ID=\"2\" Relative=\"1\" LineWidth=\"2\"/>\
+             <ofd:DrawParam ID=\"3\" Relative=\"2\" MiterLimit=\"4\"/></ofd:DrawParams>",
+        );
+        let param = resources.resolve_draw_param("3");
+        assert_eq!(param.miter_limit, Some(4.0));
+        // 最近一级的 LineWidth 优先，Alpha 来自链的末端
+        assert_eq!(param.line_width, Some(2.0));
+        assert_eq!(param.alpha, Some(100));
+        assert_eq!(resources.resolve_draw_param("9").line_width, None);
+    }
+
+    #[test]
+    fn relative_cycles_terminate() {
+        let resources = manager(
+            "<ofd:DrawParams><ofd:DrawParam ID=\"1\" Relative=\"2\" LineWidth=\"1\"/>\
+             <ofd:DrawParam ID=\"2\" Relative=\"1\" Alpha=\"50\"/>\
+             <ofd:DrawParam ID=\"3\" Relative=\"3\" MiterLimit=\"2\"/></ofd:DrawParams>",
+        );
+        let param = resources.resolve_draw_param("1");
+        assert_eq!((param.line_width, param.alpha), (Some(1.0), Some(50)));
+        assert_eq!(resources.resolve_draw_param("3").miter_limit, Some(2.0));
+    }
+}
@@ -0,0 +1,131 @@
+use serde::Deserialize;
+
+use crate::st_types::{deserialize_st, STBox};
+
+/// 签名列表文件 Signatures.xml
+#[derive(Debug, Deserialize, Default)]
+#[serde(default, rename_all = "PascalCase")]
+pub(crate) struct Signatures {
+    pub(crate) signature: Vec<SignatureRef>,
+}
+
+impl Signatures {
+    pub(crate) fn from_xml(xml: &str) -> Result<Signatures, serde_xml_rs::Error> {
+        serde_xml_rs::from_str(xml)
+    }
+}
+
+/// 签名列表中的一项，BaseLoc 指向签名描述文件
+#[derive(Debug, Deserialize, Default)]
+pub(crate) struct SignatureRef {
+    #[serde(rename = "ID", default)]
+    pub(crate) id: String,
+    #[serde(rename = "BaseLoc", default)]
+    pub(crate) base_loc: String,
+}
+
+/// 签名描述文件 Signature.xml
+/// SignedValue 为签名值文件，其中的电子签章数据含有印章图像
+#[derive(Debug, Deserialize, Default)]
+#[serde(default, rename_all = "PascalCase")]
+pub(crate) struct Signature {
+    pub(crate) signed_info: SignedInfo,
+    pub(crate) signed_value: String,
+}
+
+impl Signature {
+    pub(crate) fn from_xml(xml: &str) -> Result<Signature, serde_xml_rs::Error> {
+        serde_xml_rs::from_str(xml)
+    }
+}
+
+#[derive(Debug, Deserialize, Default)]
+#[serde(default, rename_all = "PascalCase")]
+pub(crate) struct SignedInfo {
+    /// 签章在页面上的外观位置，一个签章可以出现在多个页面上
+    pub(crate) stamp_annot: Vec<StampAnnot>,
+    /// 电子印章文件，签名值中没有印章图像时使用
+    pub(crate) seal: Option<SealRef>,
+}
+
+#[derive(Debug, Deserialize, Default)]
+pub(crate) struct StampAnnot {
+    /// 签章所在页面的标识
+    #[serde(rename = "PageRef", default)]
+    pub(crate) page_ref: String,
+    /// 签章在页面坐标系中的外接矩形
+    #[serde(rename = "Boundary", deserialize_with = "deserialize_st", default)]
+    pub(crate) boundary: STBox,
+}
+
+#[derive(Debug, Deserialize, Default)]
+#[serde(default, rename_all = "PascalCase")]
+pub(crate) struct SealRef {
+    pub(crate) base_loc: String,
+}
+
+/// 印章图像，kind 为图像类型（小写），如 ofd、png、jpg
+#[derive(Debug)]
+pub(crate) struct SealPicture<'a> {
+    pub(crate) kind: String,
+    pub(crate) data: &'a [u8],
+}
+
+/// 在 DER 编码的签名值或电子印章中查找印章图像
+/// 印章图像信息的结构为 SEQUENCE { type IA5String, data OCTET STRING, width INTEGER, height INTEGER }，
+/// 不同版本的签章格式中其位置不同，因此遍历整个结构，取第一个符合该形式的节点
+pub(crate) fn find_seal_picture(der: &[u8]) -> Option<SealPicture<'_>> {
+    find_picture(der, 0)
+}
+
+/// DER 结构允许的最大嵌套层数，防止损坏的数据造成过深的递归
+const MAX_DER_DEPTH: usize = 32;
+
+const TAG_OCTET_STRING: u8 = 0x04;
+const TAG_IA5_STRING: u8 = 0x16;
+const CONSTRUCTED: u8 = 0x20;
+
+fn find_picture(data: &[u8], depth: usize) -> Option<SealPicture<'_>> {
+    if depth > MAX_DER_DEPTH {
+        return None;
+    }
+    for (tag, content) in der_elements(data) {
+        if tag & CONSTRUCTED == 0 {
+            continue;
+        }
+        let children: Vec<(u8, &[u8])> = der_elements(content).collect();
+        if let [(TAG_IA5_STRING, kind), (TAG_OCTET_STRING, data), ..] = children[..] {
+            if let Ok(kind) = std::str::from_utf8(kind) {
+                return Some(SealPicture { kind: kind.trim().to_lowercase(), data });
+            }
+        }
+        if let Some(picture) = find_picture(content, depth + 1) {
+            return Some(picture);
+        }
+    }
+    None
+}
+
+/// 依次读取 data 中的 DER 元素，返回标签与内容；遇到无法解析的数据时结束
+fn der_elements(mut data: &[u8]) -> impl Iterator<Item = (u8, &[u8])> {
+    std::iter::from_fn(move || {
+        let (&tag, rest) = data.split_first()?;
+        let (&first, rest) = rest.split_first()?;
+        let (length, rest) = if first & 0x80 == 0 {
+            (first as usize, rest)
+        } else {
+            let count = (first & 0x7F) as usize;
+            if count == 0 || count > std::mem::size_of::<usize>() || rest.len() < count {
+                return None;
+            }
+            let length = rest[..count].iter().fold(0usize, |length, b| (length << 8) | *b as usize);
+            (length, &rest[count..])
+        };
+        if rest.len() < length {
+            return None;
+        }
+        let (content, rest) = rest.split_at(length);
+        data = rest;
+        Some((tag, content))
+    })
+}
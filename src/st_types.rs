@@ -1,22 +1,95 @@
-use std::{num::ParseFloatError, str::FromStr};
+use std::{fmt, num::ParseFloatError, str::FromStr};
+
+use serde::{de, Deserialize, Deserializer};
 
 #[derive(Debug)]
-pub(crate) enum ParseSTError {
+pub enum ParseSTError {
     InvalidFormat,
     ParseFloatError(ParseFloatError),
 }
 
-#[derive(Debug, Default, Clone)]
-pub(crate) struct STPos {
-    pub(crate) x: f64,
-    pub(crate) y: f64,
+impl fmt::Display for ParseSTError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            ParseSTError::InvalidFormat => write!(f, "invalid format"),
+            ParseSTError::ParseFloatError(e) => write!(f, "{}", e),
+        }
+    }
+}
+
+/// 用于 serde 的 deserialize_with，将 XML 属性或节点文本按 ST 类型的 FromStr 解析
+pub(crate) fn deserialize_st<'de, D, T>(deserializer: D) -> Result<T, D::Error>
+where
+    D: Deserializer<'de>,
+    T: FromStr<Err = ParseSTError>,
+{
+    let s = String::deserialize(deserializer)?;
+    s.parse().map_err(de::Error::custom)
+}
+
+/// 同 deserialize_st，用于可选的属性
+pub(crate) fn deserialize_st_opt<'de, D, T>(deserializer: D) -> Result<Option<T>, D::Error>
+where
+    D: Deserializer<'de>,
+    T: FromStr<Err = ParseSTError>,
+{
+    let s = String::deserialize(deserializer)?;
+    s.parse().map(Some).map_err(de::Error::custom)
+}
+
+/// 将 ST 类型的文本拆分为各个分量，空白与逗号均可作为分隔符，连续的空白视为一个分隔符
+/// 逗号之间缺少数值时（如 `10.0,,20.0` 或末尾多出的逗号）视为格式错误，而不是静默跳过
+fn split_tokens(s: &str) -> Result<Vec<&str>, ParseSTError> {
+    let mut tokens = Vec::new();
+    let has_comma = s.contains(',');
+    for field in s.split(',') {
+        let before = tokens.len();
+        tokens.extend(field.split_whitespace());
+        if has_comma && tokens.len() == before {
+            return Err(ParseSTError::InvalidFormat);
+        }
+    }
+    Ok(tokens)
+}
+
+/// 解析圆弧的 large、sweep 标志，只允许 0 或 1
+fn parse_flag(s: &str) -> Result<bool, ParseSTError> {
+    let value: f64 = s.parse().map_err(ParseSTError::ParseFloatError)?;
+    if value == 0.0 {
+        Ok(false)
+    } else if value == 1.0 {
+        Ok(true)
+    } else {
+        Err(ParseSTError::InvalidFormat)
+    }
+}
+
+/// 每磅对应的毫米数
+const MM_PER_POINT: f64 = 25.4 / 72.0;
+
+/// 解析长度值，允许带 mm 或 pt 后缀，pt 换算为毫米；不带单位时按毫米处理
+fn parse_length(s: &str) -> Result<f64, ParseSTError> {
+    let (number, scale) = if let Some(number) = s.strip_suffix("mm") {
+        (number, 1.0)
+    } else if let Some(number) = s.strip_suffix("pt") {
+        (number, MM_PER_POINT)
+    } else {
+        (s, 1.0)
+    };
+    number.parse::<f64>().map(|v| v * scale).map_err(ParseSTError::ParseFloatError)
+}
+
+#[derive(Debug, Default, Clone, Copy, PartialEq)]
+pub struct STPos {
+    pub x: f64,
+    pub y: f64,
 }
 
 impl FromStr for STPos {
     type Err = ParseSTError;
 
     fn from_str(s: &str) -> Result<Self, Self::Err> {
-        let parts : Vec<&str> = s.split_whitespace().collect();
+        let parts = split_tokens(s)?;
         if parts.len() != 2 {
             return Err(ParseSTError::InvalidFormat);
         }
@@ -32,40 +105,136 @@ impl FromStr for STPos {
     }
 }
 
-#[derive(Debug, Default, Clone)]
-pub(crate) struct STBox {
-    pub(crate) x: f64,
-    pub(crate) y: f64,
-    pub(crate) w: f64,
-    pub(crate) h: f64,
+#[derive(Debug, Default, Clone, Copy, PartialEq)]
+pub struct STBox {
+    pub x: f64,
+    pub y: f64,
+    pub w: f64,
+    pub h: f64,
 }
 
 impl FromStr for STBox {
     type Err = ParseSTError;
 
     fn from_str(s: &str) -> Result<Self, Self::Err> {
-        let parts : Vec<&str> = s.split_whitespace().collect();
+        let parts = split_tokens(s)?;
         if parts.len() != 4 {
             return Err(ParseSTError::InvalidFormat);
         }
-        // 解析 x, y, w, h
-        let x = parts[0]
-            .parse::<f64>()
-            .map_err(ParseSTError::ParseFloatError)?;
-        let y = parts[1]
-            .parse::<f64>()
-            .map_err(ParseSTError::ParseFloatError)?;
-        let w = parts[2]
-            .parse::<f64>()
-            .map_err(ParseSTError::ParseFloatError)?;
-        let h = parts[3]
-            .parse::<f64>()
-            .map_err(ParseSTError::ParseFloatError)?;
+        // 解析 x, y, w, h，部分生成器会带上 mm 或 pt 单位
+        let x = parse_length(parts[0])?;
+        let y = parse_length(parts[1])?;
+        let w = parse_length(parts[2])?;
+        let h = parse_length(parts[3])?;
 
         Ok(STBox { x, y, w, h })
     }
 }
 
+impl STBox {
+    /// 四个角经 m 变换后的外接矩形，旋转或错切时结果大于原矩形
+    pub(crate) fn transform(&self, m: &STMatrix) -> STBox {
+        let corners = [(self.x, self.y), (self.x + self.w, self.y), (self.x, self.y + self.h), (self.x + self.w, self.y + self.h)]
+            .map(|(x, y)| m.apply(STPos { x, y }));
+        let (mut min_x, mut min_y, mut max_x, mut max_y) = (f64::INFINITY, f64::INFINITY, f64::NEG_INFINITY, f64::NEG_INFINITY);
+        for p in corners {
+            min_x = min_x.min(p.x);
+            min_y = min_y.min(p.y);
+            max_x = max_x.max(p.x);
+            max_y = max_y.max(p.y);
+        }
+        STBox { x: min_x, y: min_y, w: max_x - min_x, h: max_y - min_y }
+    }
+
+    /// 位置与大小同时乘以 factor，如将毫米换算为像素
+    pub fn scale(&self, factor: f64) -> STBox {
+        STBox { x: self.x * factor, y: self.y * factor, w: self.w * factor, h: self.h * factor }
+    }
+
+    /// 点位于矩形内或边上
+    pub fn contains(&self, p: &STPos) -> bool {
+        p.x >= self.x && p.y >= self.y && p.x <= self.x + self.w && p.y <= self.y + self.h
+    }
+}
+
+/// 变换矩阵 a b c d e f，对应
+/// | a b 0 |
+/// | c d 0 |
+/// | e f 1 |
+/// 点 (x, y) 变换后为 (a*x + c*y + e, b*x + d*y + f)
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct STMatrix {
+    pub a: f64,
+    pub b: f64,
+    pub c: f64,
+    pub d: f64,
+    pub e: f64,
+    pub f: f64,
+}
+
+impl Default for STMatrix {
+    fn default() -> Self {
+        STMatrix::identity()
+    }
+}
+
+impl STMatrix {
+    pub(crate) fn identity() -> STMatrix {
+        STMatrix { a: 1.0, b: 0.0, c: 0.0, d: 1.0, e: 0.0, f: 0.0 }
+    }
+
+    pub(crate) fn translate(tx: f64, ty: f64) -> STMatrix {
+        STMatrix { a: 1.0, b: 0.0, c: 0.0, d: 1.0, e: tx, f: ty }
+    }
+
+    pub(crate) fn scale(sx: f64, sy: f64) -> STMatrix {
+        STMatrix { a: sx, b: 0.0, c: 0.0, d: sy, e: 0.0, f: 0.0 }
+    }
+
+    /// 先应用 self 再应用 other
+    pub(crate) fn then(&self, other: &STMatrix) -> STMatrix {
+        STMatrix {
+            a: self.a * other.a + self.b * other.c,
+            b: self.a * other.b + self.b * other.d,
+            c: self.c * other.a + self.d * other.c,
+            d: self.c * other.b + self.d * other.d,
+            e: self.e * other.a + self.f * other.c + other.e,
+            f: self.e * other.b + self.f * other.d + other.f,
+        }
+    }
+
+    /// 逆矩阵，奇异矩阵没有逆矩阵，返回 None
+    pub(crate) fn invert(&self) -> Option<STMatrix> {
+        let det = self.a * self.d - self.b * self.c;
+        if det == 0.0 || !det.is_finite() {
+            return None;
+        }
+        Some(STMatrix {
+            a: self.d / det,
+            b: -self.b / det,
+            c: -self.c / det,
+            d: self.a / det,
+            e: (self.c * self.f - self.d * self.e) / det,
+            f: (self.b * self.e - self.a * self.f) / det,
+        })
+    }
+
+    pub(crate) fn apply(&self, p: STPos) -> STPos {
+        STPos {
+            x: self.a * p.x + self.c * p.y + self.e,
+            y: self.b * p.x + self.d * p.y + self.f,
+        }
+    }
+}
+
+impl FromStr for STMatrix {
+    type Err = ParseSTError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        s.parse::<STArray>()?.as_matrix().ok_or(ParseSTError::InvalidFormat)
+    }
+}
+
 #[derive(Debug, Clone)]
 pub(crate) enum PathElement {
     StartAt(StartAt),
@@ -123,17 +292,17 @@ pub(crate) struct CubicBezierCurve {
 /// 操作符 A 操作数 rx ry angle large sweep x y
 /// 从当前点连接一条到点(x,y)的圆弧，并将当前点移动到点(x,y)。
 /// rx表示椭圆的长轴长度，ry表示椭圆的短轴长度。
-/// anglc表示椭圆在当前坐标系下旋转的角度，正值为顺时针，负值为逆时针，
-/// largc为1时表示对应度数大于180°的弧，为0时表示对应度数小于180°的弧。
-/// swecp为1时表示由圆弧起始点到结束点是顺时针旋转，为0时表示由圆弧起始点到结束点是逆时针旋转
+/// angle表示椭圆在当前坐标系下旋转的角度，正值为顺时针，负值为逆时针，
+/// large为1时表示对应度数大于180°的弧，为0时表示对应度数小于180°的弧。
+/// sweep为1时表示由圆弧起始点到结束点是顺时针旋转，为0时表示由圆弧起始点到结束点是逆时针旋转
 /// A rx ry angle large sweep x y
 #[derive(Debug, Clone)]
 pub(crate) struct EllipseArc {
     pub(crate) rx: f64,
     pub(crate) ry: f64,
     pub(crate) angle: f64,
-    pub(crate) large: f64,
-    pub(crate) sweep: f64,
+    pub(crate) large: bool,
+    pub(crate) sweep: bool,
     pub(crate) pos: STPos,
 }
 
@@ -144,8 +313,9 @@ pub(crate) struct EllipseArc {
 pub(crate) struct ClosePath {}
 
 
+/// 路径数据，由 AbbreviatedData 等属性的字符串解析得到
 #[derive(Debug, Clone)]
-pub(crate) struct STPath {
+pub struct STPath {
     pub(crate) elements: Vec<PathElement>,
 }
 
@@ -154,7 +324,7 @@ impl FromStr for STPath {
 
     fn from_str(s: &str) -> Result<Self, Self::Err> {
         let mut elements = Vec::new();
-        let parts: Vec<&str> = s.split_whitespace().collect();
+        let parts = split_tokens(s)?;
         let mut tokens = parts.into_iter();
 
         while let Some(op) = tokens.next() {
@@ -237,11 +407,14 @@ impl FromStr for STPath {
                     let sweep_str = tokens.next().ok_or(ParseSTError::InvalidFormat)?;
                     let x_str = tokens.next().ok_or(ParseSTError::InvalidFormat)?;
                     let y_str = tokens.next().ok_or(ParseSTError::InvalidFormat)?;
-                    let rx = rx_str.parse().map_err(ParseSTError::ParseFloatError)?;
-                    let ry = ry_str.parse().map_err(ParseSTError::ParseFloatError)?;
+                    let rx: f64 = rx_str.parse().map_err(ParseSTError::ParseFloatError)?;
+                    let ry: f64 = ry_str.parse().map_err(ParseSTError::ParseFloatError)?;
+                    if rx < 0.0 || ry < 0.0 {
+                        return Err(ParseSTError::InvalidFormat);
+                    }
                     let angle = angle_str.parse().map_err(ParseSTError::ParseFloatError)?;
-                    let large = large_str.parse().map_err(ParseSTError::ParseFloatError)?;
-                    let sweep = sweep_str.parse().map_err(ParseSTError::ParseFloatError)?;
+                    let large = parse_flag(large_str)?;
+                    let sweep = parse_flag(sweep_str)?;
                     let pos = STPos {
                         x: x_str.parse().map_err(ParseSTError::ParseFloatError)?,
                         y: y_str.parse().map_err(ParseSTError::ParseFloatError)?,
@@ -266,30 +439,89 @@ impl FromStr for STPath {
     }
 }
 
+/// 以空格分隔的数值数组 ST_Array，如线条的虚线样式、变换矩阵、颜色分量
+#[derive(Debug, Clone, Default, PartialEq)]
+pub(crate) struct STArray(pub(crate) Vec<f64>);
+
+impl STArray {
+    pub(crate) fn get(&self, i: usize) -> Option<f64> {
+        self.0.get(i).copied()
+    }
+
+    /// 恰好 6 个数值时按 a b c d e f 的顺序组成变换矩阵
+    pub(crate) fn as_matrix(&self) -> Option<STMatrix> {
+        match self.0[..] {
+            [a, b, c, d, e, f] => Some(STMatrix { a, b, c, d, e, f }),
+            _ => None,
+        }
+    }
+}
+
+impl FromStr for STArray {
+    type Err = ParseSTError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let values = split_tokens(s)?
+            .into_iter()
+            .map(|item| item.parse().map_err(ParseSTError::ParseFloatError))
+            .collect::<Result<Vec<f64>, ParseSTError>>()?;
+        Ok(STArray(values))
+    }
+}
+
+/// g 语法展开后的最大个数，远大于一个文字对象中可能的字符数
+const MAX_DELTAS: usize = 1 << 20;
+
 /// 每个字符相对于前一个字符的偏移量
 /// 自动展开 g 语法的写法
-#[derive(Debug, Clone)]
+#[derive(Debug, Clone, Default)]
 pub(crate) struct STDeltas {
     pub(crate) deltas: Vec<f64>,
 }
 
+impl STDeltas {
+    /// 由起点依次累加偏移量得到各字符的绝对坐标：start, start+d0, start+d0+d1, ...
+    /// 结果比偏移量多一个
+    pub(crate) fn to_positions(&self, start: f64) -> Vec<f64> {
+        let mut positions = Vec::with_capacity(self.deltas.len() + 1);
+        positions.push(start);
+        let mut position = start;
+        for delta in &self.deltas {
+            position += delta;
+            positions.push(position);
+        }
+        positions
+    }
+
+    /// 偏移量足以确定 glyph_count 个字符的位置，即至少有 glyph_count - 1 个
+    pub(crate) fn covers(&self, glyph_count: usize) -> bool {
+        self.deltas.len() + 1 >= glyph_count
+    }
+}
+
 impl FromStr for STDeltas {
     type Err = ParseSTError;
 
     fn from_str(s: &str) -> Result<Self, Self::Err> {
-        let parts : Vec<&str> = s.split_whitespace().collect();
+        // 未使用 g 语法时即为普通的数值数组
+        let parts = split_tokens(s)?;
+        if !parts.contains(&"g") {
+            return s.parse::<STArray>().map(|array| STDeltas { deltas: array.0 });
+        }
         let mut tokens = parts.into_iter();
         let mut deltas : Vec<f64> = Vec::new();
         while let Some(item) = tokens.next() {
             match item {
                 "g" => {
+                    // 重复次数须为非负整数，展开后的总个数不能超过上限，避免异常数据耗尽内存
                     let count_str = tokens.next().ok_or(ParseSTError::InvalidFormat)?;
-                    let count: f64 = count_str.parse().map_err(ParseSTError::ParseFloatError)?;
+                    let count: usize = count_str.parse().map_err(|_| ParseSTError::InvalidFormat)?;
+                    if count > MAX_DELTAS - deltas.len() {
+                        return Err(ParseSTError::InvalidFormat);
+                    }
                     let delta_str = tokens.next().ok_or(ParseSTError::InvalidFormat)?;
                     let delta = delta_str.parse().map_err(ParseSTError::ParseFloatError)?;
-                    for _ in 0..count as i32 {
-                        deltas.push(delta);
-                    }
+                    deltas.resize(deltas.len() + count, delta);
                 }
                 _ => {
                     let delta = item.parse().map_err(ParseSTError::ParseFloatError)?;
@@ -300,4 +532,178 @@ impl FromStr for STDeltas {
         Ok(STDeltas { deltas })
     }
     
-}
\ No newline at end of file
+}
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn empty_array_has_no_values() {
+        assert_eq!("".parse::<STArray>().unwrap(), STArray(vec![]));
+        assert_eq!("   ".parse::<STArray>().unwrap(), STArray(vec![]));
+    }
+
+    #[test]
+    fn array_accepts_scientific_notation() {
+        assert_eq!("1e3 -2.5E-1 0".parse::<STArray>().unwrap(), STArray(vec![1000.0, -0.25, 0.0]));
+    }
+
+    #[test]
+    fn array_with_an_invalid_value_is_rejected() {
+        assert!("1 abc 3".parse::<STArray>().is_err());
+        assert!("1 2 3x".parse::<STArray>().is_err());
+    }
+
+    #[test]
+    fn array_of_six_values_is_a_matrix() {
+        let array: STArray = "1 0 0 1 10 20".parse().unwrap();
+        assert_eq!(array.get(4), Some(10.0));
+        assert_eq!(array.get(6), None);
+        assert_eq!(array.as_matrix(), Some(STMatrix::translate(10.0, 20.0)));
+        assert_eq!("1 0 0 1 10".parse::<STArray>().unwrap().as_matrix(), None);
+    }
+
+    #[test]
+    fn comma_separated_values_are_accepted() {
+        assert_eq!("10.0,20.0".parse::<STPos>().unwrap(), STPos { x: 10.0, y: 20.0 });
+        assert_eq!("1, 2, 3, 4".parse::<STBox>().unwrap(), STBox { x: 1.0, y: 2.0, w: 3.0, h: 4.0 });
+        assert_eq!("M,1,2,L,3,4".parse::<STPath>().unwrap().elements.len(), 2);
+    }
+
+    #[test]
+    fn tab_separated_values_are_accepted() {
+        assert_eq!("10\t20".parse::<STPos>().unwrap(), STPos { x: 10.0, y: 20.0 });
+        assert_eq!("1\t2 \t3\t\t4".parse::<STBox>().unwrap(), STBox { x: 1.0, y: 2.0, w: 3.0, h: 4.0 });
+        assert_eq!("1\tg\t2\t3".parse::<STDeltas>().unwrap().deltas, vec![1.0, 3.0, 3.0]);
+    }
+
+    #[test]
+    fn surrounding_whitespace_is_ignored() {
+        assert_eq!("  10 20\n".parse::<STPos>().unwrap(), STPos { x: 10.0, y: 20.0 });
+        assert_eq!(" 1,2,3,4 ".parse::<STBox>().unwrap(), STBox { x: 1.0, y: 2.0, w: 3.0, h: 4.0 });
+    }
+
+    #[test]
+    fn empty_fields_between_commas_are_rejected() {
+        assert!("10.0,,20.0".parse::<STPos>().is_err());
+        assert!("10.0,20.0,".parse::<STPos>().is_err());
+        assert!("1,2,3".parse::<STBox>().is_err());
+    }
+
+    #[test]
+    fn valid_arc_is_parsed() {
+        let path: STPath = "M 0 10 A 10 5 30 1 0 20 10".parse().unwrap();
+        match &path.elements[1] {
+            PathElement::EllipseArc(arc) => {
+                assert_eq!((arc.rx, arc.ry, arc.angle), (10.0, 5.0, 30.0));
+                assert!(arc.large);
+                assert!(!arc.sweep);
+                assert_eq!(arc.pos, STPos { x: 20.0, y: 10.0 });
+            }
+            other => panic!("expected an arc, got {:?}", other),
+        }
+    }
+
+    #[test]
+    fn arc_flag_other_than_zero_or_one_is_rejected() {
+        assert!(matches!("M 0 0 A 10 10 0 2 0 20 0".parse::<STPath>(), Err(ParseSTError::InvalidFormat)));
+        assert!(matches!("M 0 0 A 10 10 0 0 0.5 20 0".parse::<STPath>(), Err(ParseSTError::InvalidFormat)));
+    }
+
+    #[test]
+    fn arc_with_a_negative_radius_is_rejected() {
+        assert!(matches!("M 0 0 A -10 10 0 0 1 20 0".parse::<STPath>(), Err(ParseSTError::InvalidFormat)));
+        assert!(matches!("M 0 0 A 10 -1 0 0 1 20 0".parse::<STPath>(), Err(ParseSTError::InvalidFormat)));
+    }
+
+    #[test]
+    fn deltas_reject_a_negative_g_count() {
+        assert!(matches!("g -3 0.5".parse::<STDeltas>(), Err(ParseSTError::InvalidFormat)));
+    }
+
+    #[test]
+    fn deltas_reject_a_fractional_g_count() {
+        assert!(matches!("g 2.9 0.5".parse::<STDeltas>(), Err(ParseSTError::InvalidFormat)));
+        assert!(matches!("1 g 2e1 0.5".parse::<STDeltas>(), Err(ParseSTError::InvalidFormat)));
+    }
+
+    #[test]
+    fn deltas_reject_a_huge_g_count() {
+        assert!(matches!("g 99999999999 1".parse::<STDeltas>(), Err(ParseSTError::InvalidFormat)));
+        // 多个 g 的总数同样受限
+        let total = format!("g {} 1 g 1 1", MAX_DELTAS);
+        assert!(matches!(total.parse::<STDeltas>(), Err(ParseSTError::InvalidFormat)));
+        assert_eq!(format!("g {} 1", MAX_DELTAS).parse::<STDeltas>().unwrap().deltas.len(), MAX_DELTAS);
+    }
+
+    #[test]
+    fn deltas_expand_a_valid_g_count() {
+        assert_eq!("1 g 3 0.5 2".parse::<STDeltas>().unwrap().deltas, vec![1.0, 0.5, 0.5, 0.5, 2.0]);
+        assert!("g 0 0.5".parse::<STDeltas>().unwrap().deltas.is_empty());
+    }
+
+    #[test]
+    fn positions_accumulate_from_the_start() {
+        let deltas: STDeltas = "2 2 2".parse().unwrap();
+        assert_eq!(deltas.to_positions(10.0), vec![10.0, 12.0, 14.0, 16.0]);
+    }
+
+    #[test]
+    fn g_form_yields_the_same_positions() {
+        let plain: STDeltas = "2 2 2".parse().unwrap();
+        let compressed: STDeltas = "g 3 2".parse().unwrap();
+        assert_eq!(compressed.to_positions(10.0), plain.to_positions(10.0));
+    }
+
+    #[test]
+    fn deltas_cover_one_fewer_than_the_glyph_count() {
+        let deltas: STDeltas = "2 2 2".parse().unwrap();
+        assert!(deltas.covers(4));
+        assert!(!deltas.covers(5));
+        assert!(STDeltas::default().covers(1));
+    }
+
+    /// 顺时针旋转 90°（y 轴向下）：(x, y) 变为 (-y, x)
+    const ROTATE_90: STMatrix = STMatrix { a: 0.0, b: 1.0, c: -1.0, d: 0.0, e: 0.0, f: 0.0 };
+
+    #[test]
+    fn box_rotated_by_90_degrees_swaps_its_dimensions() {
+        let rect = STBox { x: 10.0, y: 20.0, w: 40.0, h: 30.0 };
+        assert_eq!(rect.transform(&ROTATE_90), STBox { x: -50.0, y: 10.0, w: 30.0, h: 40.0 });
+    }
+
+    #[test]
+    fn box_rotated_by_45_degrees_is_enlarged() {
+        let (sin, cos) = std::f64::consts::FRAC_PI_4.sin_cos();
+        let rotate = STMatrix { a: cos, b: sin, c: -sin, d: cos, e: 0.0, f: 0.0 };
+        let rotated = STBox { x: 0.0, y: 0.0, w: 10.0, h: 10.0 }.transform(&rotate);
+        assert!((rotated.w - 10.0 * std::f64::consts::SQRT_2).abs() < 1e-9);
+        assert!((rotated.h - rotated.w).abs() < 1e-9);
+    }
+
+    #[test]
+    fn box_contains_points_inside_and_on_its_edges() {
+        let rect = STBox { x: 10.0, y: 20.0, w: 40.0, h: 30.0 }.transform(&ROTATE_90);
+        assert!(rect.contains(&STPos { x: -35.0, y: 30.0 }));
+        assert!(rect.contains(&STPos { x: -50.0, y: 10.0 }));
+        assert!(rect.contains(&STPos { x: -20.0, y: 50.0 }));
+        assert!(!rect.contains(&STPos { x: 30.0, y: 35.0 }));
+        assert!(!rect.contains(&STPos { x: -35.0, y: 50.5 }));
+    }
+
+    #[test]
+    fn box_scale_multiplies_position_and_size() {
+        let rect = STBox { x: 1.0, y: 2.0, w: 3.0, h: 4.0 };
+        assert_eq!(rect.scale(2.0), STBox { x: 2.0, y: 4.0, w: 6.0, h: 8.0 });
+    }
+
+    #[test]
+    fn box_in_points_converts_to_millimetres() {
+        let a4 = "0pt 0pt 595pt 842pt".parse::<STBox>().unwrap();
+        assert_eq!((a4.x, a4.y), (0.0, 0.0));
+        assert!((a4.w - 210.0).abs() < 0.2, "width {}", a4.w);
+        assert!((a4.h - 297.0).abs() < 0.2, "height {}", a4.h);
+        assert_eq!("0mm 0mm 210mm 297mm".parse::<STBox>().unwrap(), STBox { x: 0.0, y: 0.0, w: 210.0, h: 297.0 });
+        assert!("0 0 210cm 297".parse::<STBox>().is_err());
+    }
+}
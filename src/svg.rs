@@ -0,0 +1,433 @@
+use std::fmt::Write;
+
+use crate::color::CTColor;
+use crate::document::{PageArea, ZOrder};
+use crate::action::LinkTarget;
+use crate::page::{CompositeObject, DrawStyle, FillRule, LineCap, LineJoin, Page, PageObject, PathObject, TextObject};
+use crate::render::{is_valid_dash, object_matrix, page_transform, RenderOptions, DEFAULT_FONT_FAMILY, DEFAULT_MITER_LIMIT};
+use crate::resource::ResourceManager;
+use crate::st_types::{PathElement, STBox, STPath};
+use crate::text::{glyph_placement, glyph_positions};
+
+/// 将页面输出为 SVG 文本，坐标单位为毫米，viewBox 即页面的物理区域
+/// 页面有旋转时 viewBox 为旋转后的页面，页面内容放在带旋转变换的分组中
+/// 带有 URI 或跳转动作的图元包在 <a> 中，链接区域为图元的外接矩形；跳转到文档内页面的链接指向 page_anchor_id 给出的锚点，
+/// 根 <svg> 的 id 即本页（page_id）的锚点，多个页面的 SVG 拼接在一起时页面间的跳转可以直接定位
+pub(crate) fn render_page_svg(
+    page_id: &str,
+    page: &Page,
+    templates: &[(Page, ZOrder)],
+    area: &PageArea,
+    resources: &ResourceManager,
+    opts: &RenderOptions,
+) -> String {
+    let view_box = match area.rotation {
+        0 => area.physical_box,
+        _ => {
+            let (w, h) = area.display_size();
+            STBox { x: 0.0, y: 0.0, w, h }
+        }
+    };
+    let mut svg = String::new();
+    let _ = write!(
+        svg,
+        r#"<svg xmlns="http://www.w3.org/2000/svg" xmlns:xlink="http://www.w3.org/1999/xlink" id="{id}" width="{w}mm" height="{h}mm" viewBox="{x} {y} {w} {h}">"#,
+        id = page_anchor_id(page_id),
+        x = view_box.x,
+        y = view_box.y,
+        w = view_box.w,
+        h = view_box.h,
+    );
+    if let Some([r, g, b, a]) = opts.background {
+        let _ = write!(
+            svg,
+            r#"<rect x="{}" y="{}" width="{}" height="{}" fill="rgb({},{},{})" fill-opacity="{}"/>"#,
+            view_box.x,
+            view_box.y,
+            view_box.w,
+            view_box.h,
+            r,
+            g,
+            b,
+            opacity(a),
+        );
+    }
+    if area.rotation != 0 {
+        let m = page_transform(area);
+        let _ = write!(svg, r#"<g transform="matrix({} {} {} {} {} {})">"#, m.a, m.b, m.c, m.d, m.e, m.f);
+    }
+
+    let mut writer = SvgWriter::new(&mut svg, resources);
+    // 模板中与页面图元标识相同的图元不再输出，避免重复
+    let backgrounds = templates.iter().filter(|(_, z)| *z == ZOrder::Background).map(|(t, _)| (t, Some(page)));
+    let foregrounds = templates.iter().filter(|(_, z)| *z == ZOrder::Foreground).map(|(t, _)| (t, Some(page)));
+    let pages = backgrounds.chain([(page, None)]).chain(foregrounds);
+    for (p, exclude) in pages {
+        for layer in p.layers().into_iter().filter(|layer| opts.layer_filter.selects(layer, opts.layer_id.as_deref())) {
+            writer.write_objects(&layer.objects, exclude, layer.draw_param.as_deref());
+        }
+    }
+    let defs = writer.finish();
+    if area.rotation != 0 {
+        svg.push_str("</g>");
+    }
+    svg.push_str(&defs);
+    svg.push_str("</svg>");
+    svg
+}
+
+/// 将单个图元输出为独立的 SVG 文本，viewBox 即图元的外接矩形，坐标仍为页面坐标（毫米）
+/// 页块没有外接矩形，返回 None
+pub(crate) fn render_object_svg(object: &PageObject, resources: &ResourceManager) -> Option<String> {
+    let boundary = object.boundary()?;
+    let mut svg = String::new();
+    let _ = write!(
+        svg,
+        r#"<svg xmlns="http://www.w3.org/2000/svg" xmlns:xlink="http://www.w3.org/1999/xlink" width="{w}mm" height="{h}mm" viewBox="{x} {y} {w} {h}">"#,
+        x = boundary.x,
+        y = boundary.y,
+        w = boundary.w,
+        h = boundary.h,
+    );
+    let mut writer = SvgWriter::new(&mut svg, resources);
+    writer.write_objects(std::slice::from_ref(object), None, None);
+    let defs = writer.finish();
+    svg.push_str(&defs);
+    svg.push_str("</svg>");
+    Some(svg)
+}
+
+/// 在页面坐标系下输出页面内容
+struct SvgWriter<'a> {
+    svg: &'a mut String,
+    resources: &'a ResourceManager,
+    /// 正在输出的复合对象所引用的矢量图形，用于发现自引用
+    composites: Vec<String>,
+    /// 已定义的矢量图形：资源标识与继承的绘制参数、定义的 id、定义的内容
+    definitions: Vec<(String, Option<String>, String, String)>,
+}
+
+impl<'a> SvgWriter<'a> {
+    fn new(svg: &'a mut String, resources: &'a ResourceManager) -> Self {
+        SvgWriter { svg, resources, composites: Vec::new(), definitions: Vec::new() }
+    }
+
+    /// 输出过程中定义的矢量图形，组成 defs 节点，没有定义时为空字符串
+    fn finish(self) -> String {
+        if self.definitions.is_empty() {
+            return String::new();
+        }
+        let mut defs = String::from("<defs>");
+        for (_, _, id, content) in &self.definitions {
+            let _ = write!(defs, r#"<g id="{}">{}</g>"#, id, content);
+        }
+        defs.push_str("</defs>");
+        defs
+    }
+
+    /// layer_draw_param 为所在图层的绘制参数
+    fn write_objects(&mut self, objects: &[PageObject], exclude: Option<&Page>, layer_draw_param: Option<&str>) {
+        for object in objects {
+            if exclude.is_some_and(|page| page.contains_object(object.id())) {
+                continue;
+            }
+            let param = self.resources.effective_draw_param(object.alpha_and_draw_param().1, layer_draw_param);
+            let link = object.link_target().zip(object.boundary());
+            if let Some((target, _)) = &link {
+                let href = match target {
+                    LinkTarget::Uri(uri) => escape(uri),
+                    LinkTarget::Page(page_id) => format!("#{}", page_anchor_id(page_id)),
+                };
+                let _ = write!(self.svg, r#"<a href="{0}" xlink:href="{0}">"#, href);
+            }
+            match object {
+                PageObject::PathObject(o) => self.write_path(o, &o.style(&param)),
+                PageObject::TextObject(o) => self.write_text(o, &o.style(&param)),
+                PageObject::CompositeObject(o) => self.write_composite(o, layer_draw_param),
+                PageObject::PageBlock(block) => self.write_objects(&block.objects, exclude, layer_draw_param),
+                _ => {}
+            }
+            if let Some((_, b)) = link {
+                // 透明的矩形使整个外接矩形都可点击，图像等未输出的图元同样有可点击的区域
+                let _ = write!(self.svg, r#"<rect x="{}" y="{}" width="{}" height="{}" fill-opacity="0"/></a>"#, b.x, b.y, b.w, b.h);
+            }
+        }
+    }
+
+    /// 矢量图形只在 defs 中定义一次，各复合对象以经 CTM 与外接矩形变换的 use 引用
+    /// 矢量图形中的图元继承复合对象的绘制参数，继承的绘制参数不同时分别定义；直接或间接引用自身的矢量图形不再输出
+    fn write_composite(&mut self, object: &CompositeObject, layer_draw_param: Option<&str>) {
+        let resources = self.resources;
+        let Some(unit) = resources.vector_graphic(&object.resource_id) else {
+            return;
+        };
+        if self.composites.contains(&object.resource_id) {
+            log::warn!("composite graphic unit {} references itself", object.resource_id);
+            return;
+        }
+        let draw_param = object.draw_param.as_deref().or(layer_draw_param);
+        let defined = self.definitions.iter()
+            .find(|(resource_id, param, _, _)| *resource_id == object.resource_id && param.as_deref() == draw_param)
+            .map(|(_, _, id, _)| id.clone());
+        let id = match defined {
+            Some(id) => id,
+            None => {
+                // 定义的内容先写入 svg，完成后换回外层的内容
+                let outer = std::mem::take(self.svg);
+                self.composites.push(object.resource_id.clone());
+                self.write_objects(&unit.content.objects, None, draw_param);
+                self.composites.pop();
+                let content = std::mem::replace(self.svg, outer);
+                let id = self.definition_id(&object.resource_id);
+                self.definitions.push((object.resource_id.clone(), draw_param.map(str::to_string), id.clone(), content));
+                id
+            }
+        };
+        let m = object_matrix(&object.boundary, object.ctm);
+        let _ = write!(self.svg, r##"<use href="#{}" transform="matrix({} {} {} {} {} {})"/>"##, id, m.a, m.b, m.c, m.d, m.e, m.f);
+    }
+
+    /// 矢量图形定义的 id，由资源标识生成，同一矢量图形的其他定义依次加上序号，不依赖于输出时的地址等易变信息
+    fn definition_id(&self, resource_id: &str) -> String {
+        let name: String = resource_id.chars()
+            .map(|c| if c.is_ascii_alphanumeric() || c == '-' || c == '_' { c } else { '_' })
+            .collect();
+        let base = format!("vg-{}", name);
+        let mut id = base.clone();
+        let mut n = 1;
+        while self.definitions.iter().any(|(_, _, existing, _)| *existing == id) {
+            n += 1;
+            id = format!("{}-{}", base, n);
+        }
+        id
+    }
+
+    fn write_path(&mut self, object: &PathObject, style: &DrawStyle) {
+        if !object.fill && !object.stroke {
+            return;
+        }
+        // 无法解析的路径数据直接跳过，不影响其他图元
+        let path: STPath = match object.abbreviated_data.parse() {
+            Ok(path) => path,
+            Err(_) => return,
+        };
+
+        let m = object_matrix(&object.boundary, object.ctm);
+        let _ = write!(
+            self.svg,
+            r#"<path transform="matrix({} {} {} {} {} {})" d="{}""#,
+            m.a,
+            m.b,
+            m.c,
+            m.d,
+            m.e,
+            m.f,
+            path_data(&path),
+        );
+        if object.fill {
+            self.write_color("fill", &style.fill_color);
+            if object.rule == FillRule::EvenOdd {
+                self.svg.push_str(r#" fill-rule="evenodd""#);
+            }
+        } else {
+            self.svg.push_str(r#" fill="none""#);
+        }
+        if object.stroke {
+            self.write_color("stroke", &style.stroke_color);
+            let width = style.line_width;
+            let _ = write!(self.svg, r#" stroke-width="{}""#, width);
+            match style.cap {
+                LineCap::Butt => {}
+                LineCap::Round => self.svg.push_str(r#" stroke-linecap="round""#),
+                LineCap::Square => self.svg.push_str(r#" stroke-linecap="square""#),
+            }
+            match style.join {
+                LineJoin::Miter => {}
+                LineJoin::Round => self.svg.push_str(r#" stroke-linejoin="round""#),
+                LineJoin::Bevel => self.svg.push_str(r#" stroke-linejoin="bevel""#),
+            }
+            let miter_limit = style.miter_limit.filter(|m| *m >= 1.0).unwrap_or(DEFAULT_MITER_LIMIT);
+            let _ = write!(self.svg, r#" stroke-miterlimit="{}""#, miter_limit);
+            if let Some(dashes) = style.dash_pattern.as_ref().map(|p| &p.0).filter(|d| is_valid_dash(d)) {
+                let dashes: Vec<String> = dashes.iter().map(f64::to_string).collect();
+                let _ = write!(self.svg, r#" stroke-dasharray="{}""#, dashes.join(" "));
+                if let Some(offset) = style.dash_offset {
+                    let _ = write!(self.svg, r#" stroke-dashoffset="{}""#, offset);
+                }
+            }
+        }
+        self.svg.push_str("/>");
+    }
+
+    /// 文字按字符逐个定位，字体引用资源中的字体名称；有水平缩放（HScale）时每个字形单独输出并缩放
+    /// 字号取文字对象的 Size，不支持同一文字对象内各段字号不同
+    fn write_text(&mut self, object: &TextObject, style: &DrawStyle) {
+        if (!object.fill && !object.stroke) || object.size <= 0.0 {
+            return;
+        }
+        let m = object_matrix(&object.boundary, object.ctm);
+        let family = self.resources.get_font(&object.font)
+            .map(|f| f.family_name.clone().filter(|n| !n.is_empty()).unwrap_or_else(|| f.font_name.clone()))
+            .filter(|n| !n.is_empty())
+            .unwrap_or_else(|| DEFAULT_FONT_FAMILY.to_string());
+        // 各段文字共用的字体、字形与颜色属性
+        let mut attrs = String::new();
+        let _ = write!(attrs, r#" font-family="{}" font-size="{}""#, escape(&family), object.size);
+        if object.is_bold() {
+            attrs.push_str(r#" font-weight="bold""#);
+        }
+        if object.italic {
+            attrs.push_str(r#" font-style="italic""#);
+        }
+        // 字形位于当前位置之前（如从右向左阅读）时，以字形的末端对齐各字符位置
+        if glyph_placement(object.read_direction, object.char_direction, 1.0, 1.0).0 != (0.0, 0.0) {
+            attrs.push_str(r#" text-anchor="end""#);
+        }
+        if object.fill {
+            attrs.push_str(&self.color_attrs("fill", &style.fill_color));
+        } else {
+            attrs.push_str(r#" fill="none""#);
+        }
+        if object.stroke {
+            attrs.push_str(&self.color_attrs("stroke", &style.stroke_color));
+            let _ = write!(attrs, r#" stroke-width="{}""#, style.line_width);
+        }
+
+        let rotation = object.char_direction.rem_euclid(360);
+        let h_scale = object.h_scale();
+        for code in object.text_codes().filter(|c| !c.text.is_empty()) {
+            // 未给出字符间距时，按缩放后的字号估计字宽
+            let positions = glyph_positions(code, object.read_direction, object.size * h_scale);
+            if h_scale != 1.0 {
+                // SVG 的 text 没有水平缩放属性，每个字形单独输出，在其自身坐标系中缩放
+                for (c, (x, y)) in code.text.chars().zip(positions) {
+                    let _ = write!(
+                        self.svg,
+                        r#"<text xml:space="preserve" transform="matrix({} {} {} {} {} {}) translate({} {}) rotate({}) scale({} 1)"{}>{}</text>"#,
+                        m.a,
+                        m.b,
+                        m.c,
+                        m.d,
+                        m.e,
+                        m.f,
+                        x,
+                        y,
+                        rotation,
+                        h_scale,
+                        attrs,
+                        escape(&c.to_string()),
+                    );
+                }
+                continue;
+            }
+            let _ = write!(
+                self.svg,
+                r#"<text xml:space="preserve" transform="matrix({} {} {} {} {} {})" x="{}" y="{}""#,
+                m.a,
+                m.b,
+                m.c,
+                m.d,
+                m.e,
+                m.f,
+                join_numbers(positions.iter().map(|p| p.0)),
+                join_numbers(positions.iter().map(|p| p.1)),
+            );
+            if rotation != 0 {
+                let _ = write!(self.svg, r#" rotate="{}""#, rotation);
+            }
+            let _ = write!(self.svg, "{}>{}</text>", attrs, escape(&code.text));
+        }
+    }
+
+    fn write_color(&mut self, attr: &str, color: &CTColor) {
+        let color = self.color_attrs(attr, color);
+        self.svg.push_str(&color);
+    }
+
+    /// 颜色对应的 SVG 属性，不透明时不输出透明度
+    fn color_attrs(&self, attr: &str, color: &CTColor) -> String {
+        let [r, g, b] = color.to_rgb(&self.resources.color_spaces);
+        let mut attrs = format!(r#" {attr}="rgb({r},{g},{b})""#);
+        let alpha = color.alpha(&self.resources.color_spaces);
+        if alpha < 255 {
+            let _ = write!(attrs, r#" {attr}-opacity="{}""#, opacity(alpha));
+        }
+        attrs
+    }
+}
+
+/// 将 STPath 转换为 SVG 的 d 属性，OFD 的路径操作符与 SVG 基本一一对应
+/// 没有起点的绘制操作以其终点作为起点
+fn path_data(path: &STPath) -> String {
+    let mut d = String::new();
+    let mut started = false;
+    for element in &path.elements {
+        if !d.is_empty() {
+            d.push(' ');
+        }
+        let _ = match element {
+            PathElement::StartAt(e) => write!(d, "M {} {}", e.pos.x, e.pos.y),
+            PathElement::MoveTo(e) => write!(d, "M {} {}", e.pos.x, e.pos.y),
+            PathElement::LineTo(e) if !started => write!(d, "M {} {}", e.pos.x, e.pos.y),
+            PathElement::LineTo(e) => write!(d, "L {} {}", e.pos.x, e.pos.y),
+            PathElement::QuadraticBezierCurve(e) if !started => write!(d, "M {} {}", e.pos2.x, e.pos2.y),
+            PathElement::QuadraticBezierCurve(e) => {
+                write!(d, "Q {} {} {} {}", e.pos1.x, e.pos1.y, e.pos2.x, e.pos2.y)
+            }
+            PathElement::CubicBezierCurve(e) if !started => write!(d, "M {} {}", e.pos3.x, e.pos3.y),
+            PathElement::CubicBezierCurve(e) => write!(
+                d,
+                "C {} {} {} {} {} {}",
+                e.pos1.x, e.pos1.y, e.pos2.x, e.pos2.y, e.pos3.x, e.pos3.y
+            ),
+            PathElement::EllipseArc(e) if !started => write!(d, "M {} {}", e.pos.x, e.pos.y),
+            PathElement::EllipseArc(e) => write!(
+                d,
+                "A {} {} {} {} {} {} {}",
+                e.rx,
+                e.ry,
+                e.angle,
+                u8::from(e.large),
+                u8::from(e.sweep),
+                e.pos.x,
+                e.pos.y
+            ),
+            PathElement::ClosePath(_) if !started => continue,
+            PathElement::ClosePath(_) => write!(d, "Z"),
+        };
+        started = true;
+    }
+    d
+}
+
+/// 页面在 SVG 中的锚点 id，写在页面根 <svg> 上，文档内跳转的链接指向该 id
+pub(crate) fn page_anchor_id(page_id: &str) -> String {
+    let name: String = page_id.chars()
+        .map(|c| if c.is_ascii_alphanumeric() || c == '-' || c == '_' { c } else { '_' })
+        .collect();
+    format!("ofd-page-{}", name)
+}
+
+/// 转义 XML 文本与属性中的特殊字符
+fn escape(text: &str) -> String {
+    let mut escaped = String::with_capacity(text.len());
+    for c in text.chars() {
+        match c {
+            '&' => escaped.push_str("&amp;"),
+            '<' => escaped.push_str("&lt;"),
+            '>' => escaped.push_str("&gt;"),
+            '"' => escaped.push_str("&quot;"),
+            '\'' => escaped.push_str("&apos;"),
+            _ => escaped.push(c),
+        }
+    }
+    escaped
+}
+
+fn join_numbers(values: impl Iterator<Item = f64>) -> String {
+    values.map(|v| v.to_string()).collect::<Vec<String>>().join(" ")
+}
+
+fn opacity(alpha: u8) -> f64 {
+    alpha as f64 / 255.0
+}
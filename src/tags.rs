@@ -0,0 +1,78 @@
+use serde::Deserialize;
+use xml::reader::{EventReader, XmlEvent};
+
+/// 自定义标引入口文件 CustomTags.xml
+#[derive(Debug, Deserialize, Default)]
+#[serde(default, rename_all = "PascalCase")]
+pub(crate) struct CustomTags {
+    pub(crate) custom_tag: Vec<CustomTag>,
+}
+
+impl CustomTags {
+    pub(crate) fn from_xml(xml: &str) -> Result<CustomTags, serde_xml_rs::Error> {
+        serde_xml_rs::from_str(xml)
+    }
+}
+
+/// 一类自定义标引，FileLoc 为标引内容文件，路径相对于 CustomTags.xml 所在目录
+/// SchemaLoc 指向的模式定义只用于校验标引内容，不读取
+#[derive(Debug, Deserialize, Default)]
+#[serde(default, rename_all = "PascalCase")]
+pub(crate) struct CustomTag {
+    #[serde(rename = "TypeID")]
+    pub(crate) type_id: String,
+    pub(crate) file_loc: String,
+}
+
+/// 标引内容中通过 ObjectRef 关联到页面图元的一个标引节点
+#[derive(Debug, Clone)]
+pub struct TaggedObject {
+    /// 标引类型，对应 CustomTag 的 TypeID
+    pub type_id: String,
+    /// 从根节点（不含）到引用所在节点的路径，以 / 分隔，如 Buyer/BuyerName
+    pub tag: String,
+    /// 被引用图元所在页面的标识
+    pub page_id: String,
+    /// 被引用图元的标识
+    pub object_id: String,
+}
+
+/// 解析标引内容文件，按出现顺序收集其中的 ObjectRef
+/// 标引内容的结构由各自的模式定义，因此只按节点名称逐层记录路径，不解析具体字段
+pub(crate) fn collect_tagged_objects(type_id: &str, xml: &str) -> Result<Vec<TaggedObject>, serde_xml_rs::Error> {
+    let mut tagged = Vec::new();
+    let mut path: Vec<String> = Vec::new();
+    let mut object_ref: Option<(String, String)> = None;
+    for event in EventReader::from_str(xml) {
+        match event? {
+            XmlEvent::StartElement { name, attributes, .. } => {
+                if name.local_name == "ObjectRef" {
+                    let page_id = attributes.iter()
+                        .find(|a| a.name.local_name == "PageRef")
+                        .map(|a| a.value.clone())
+                        .unwrap_or_default();
+                    object_ref = Some((page_id, String::new()));
+                }
+                path.push(name.local_name);
+            }
+            XmlEvent::Characters(text) => {
+                if let Some((_, object_id)) = object_ref.as_mut() {
+                    object_id.push_str(&text);
+                }
+            }
+            XmlEvent::EndElement { .. } => {
+                path.pop();
+                if let Some((page_id, object_id)) = object_ref.take() {
+                    tagged.push(TaggedObject {
+                        type_id: type_id.to_string(),
+                        tag: path.iter().skip(1).cloned().collect::<Vec<String>>().join("/"),
+                        page_id,
+                        object_id: object_id.trim().to_string(),
+                    });
+                }
+            }
+            _ => {}
+        }
+    }
+    Ok(tagged)
+}
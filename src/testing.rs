@@ -0,0 +1,443 @@
+//! 视觉回归测试的辅助工具
+
+use std::io::Cursor;
+
+use image::{ImageFormat, Rgba, RgbaImage};
+
+use crate::ofd::OfdError;
+
+/// 相同像素淡化时保留的原色比例，其余部分混合为白色
+const FADE_RATIO: f64 = 0.25;
+
+/// 标记不同像素的颜色
+const DIFF_COLOR: Rgba<u8> = Rgba([255, 0, 0, 255]);
+
+/// FNV-1a 的 64 位初始值与质数
+const FNV_OFFSET_BASIS: u64 = 0xcbf2_9ce4_8422_2325;
+const FNV_PRIME: u64 = 0x0000_0100_0000_01b3;
+
+/// 两张图像的比较结果
+#[derive(Debug, Clone)]
+pub struct DiffResult {
+    /// 重叠区域内 RGBA 任一通道不同的像素数
+    pub changed_pixels: u64,
+    /// 重叠区域的像素数
+    pub total_pixels: u64,
+    /// 重叠区域的差异图：以淡化后的图像 a 为底，不同的像素标为红色
+    pub diff_image: RgbaImage,
+    /// 两张图像的尺寸不同，此时只比较左上角对齐后的重叠区域
+    pub size_mismatch: bool,
+}
+
+/// 图像内容的 64 位哈希，可用于判断两次渲染的结果是否相同
+/// 依次对宽、高（小端序 u32）与 RGBA 像素数据计算 FNV-1a，结果与运行平台和运行次数无关
+pub fn image_hash(image: &RgbaImage) -> u64 {
+    let (width, height) = image.dimensions();
+    width.to_le_bytes().iter()
+        .chain(&height.to_le_bytes())
+        .chain(image.as_raw())
+        .fold(FNV_OFFSET_BASIS, |hash, byte| (hash ^ *byte as u64).wrapping_mul(FNV_PRIME))
+}
+
+/// 比较两张编码后的图像（如渲染得到的 PNG），返回 PNG 编码的差异图：
+/// 以淡化后的图像 a 为底，RGBA 任一通道不同的像素标为红色；两张图像尺寸不同时返回错误
+pub fn visual_diff(a: &[u8], b: &[u8]) -> Result<Vec<u8>, OfdError> {
+    let a = image::load_from_memory(a).map_err(OfdError::ImageError)?.to_rgba8();
+    let b = image::load_from_memory(b).map_err(OfdError::ImageError)?.to_rgba8();
+    if a.dimensions() != b.dimensions() {
+        return Err(OfdError::ImageSizeMismatch { a: a.dimensions(), b: b.dimensions() });
+    }
+
+    let diff = diff_images(&a, &b).diff_image;
+    let mut png = Cursor::new(Vec::new());
+    diff.write_to(&mut png, ImageFormat::Png).map_err(OfdError::ImageError)?;
+    Ok(png.into_inner())
+}
+
+/// 逐像素比较两张图像，尺寸不同时比较左上角对齐后的重叠区域
+pub fn diff_images(a: &RgbaImage, b: &RgbaImage) -> DiffResult {
+    let width = a.width().min(b.width());
+    let height = a.height().min(b.height());
+    let mut changed_pixels = 0;
+    let diff_image = RgbaImage::from_fn(width, height, |x, y| {
+        let (pa, pb) = (a.get_pixel(x, y), b.get_pixel(x, y));
+        if pa != pb {
+            changed_pixels += 1;
+            return DIFF_COLOR;
+        }
+        // 先与白色按透明度合成，再向白色淡化
+        let [r, g, bl, alpha] = pa.0.map(f64::from);
+        let fade = |c: f64| {
+            let over_white = c * alpha / 255.0 + 255.0 * (1.0 - alpha / 255.0);
+            (over_white * FADE_RATIO + 255.0 * (1.0 - FADE_RATIO)).round() as u8
+        };
+        Rgba([fade(r), fade(g), fade(bl), 255])
+    });
+    DiffResult {
+        changed_pixels,
+        total_pixels: width as u64 * height as u64,
+        diff_image,
+        size_mismatch: a.dimensions() != b.dimensions(),
+    }
+}
+
+/// 在内存中构造测试用的 OFD 包：包内只有一个文档 Doc_0，页面区域为 PAGE_WIDTH × PAGE_HEIGHT 毫米，
+/// 公共资源文件为 Doc_0/PublicRes.xml，其 BaseLoc 为 Res
+#[cfg(test)]
+pub(crate) mod fixture {
+    use std::cell::RefCell;
+    use std::collections::BTreeMap;
+    use std::io::{Cursor, Write};
+    use std::sync::Once;
+
+    use zip::write::SimpleFileOptions;
+    use zip::AesMode;
+    use zip::ZipWriter;
+
+    use crate::ofd::{OfdDoc, OpenOptions};
+    use crate::package::ZipSource;
+
+    pub(crate) const PAGE_WIDTH: f64 = 40.0;
+    pub(crate) const PAGE_HEIGHT: f64 = 30.0;
+
+    const NAMESPACE: &str = "http://www.ofdspec.org/2016";
+
+    #[derive(Debug, Clone)]
+    pub(crate) struct OfdBuilder {
+        pages: Vec<String>,
+        /// 模板页的图元与缺省层次
+        templates: Vec<(String, String)>,
+        resources: String,
+        doc_body: String,
+        document: String,
+        max_unit_id: u32,
+        files: BTreeMap<String, Vec<u8>>,
+        percent_encoded: bool,
+    }
+
+    impl OfdBuilder {
+        pub(crate) fn new() -> OfdBuilder {
+            OfdBuilder {
+                pages: Vec::new(),
+                templates: Vec::new(),
+                resources: String::new(),
+                doc_body: String::new(),
+                document: String::new(),
+                max_unit_id: 1000,
+                files: BTreeMap::new(),
+                percent_encoded: false,
+            }
+        }
+
+        /// 添加一页，objects 为正文层中的图元
+        pub(crate) fn page(mut self, objects: &str) -> OfdBuilder {
+            self.pages.push(objects.to_string());
+            self
+        }
+
+        /// 添加一个模板页，objects 为其正文层中的图元，z_order 为 Background 或 Foreground；
+        /// 模板页依次为 Doc_0/Tpls/Tpl_N/Content.xml，标识为 101 + N，每个页面都引用全部模板页
+        pub(crate) fn template(mut self, objects: &str, z_order: &str) -> OfdBuilder {
+            self.templates.push((objects.to_string(), z_order.to_string()));
+            self
+        }
+
+        /// 公共资源文件中 ofd:Res 节点的内容
+        pub(crate) fn resources(mut self, xml: &str) -> OfdBuilder {
+            self.resources = xml.to_string();
+            self
+        }
+
+        /// 追加在 OFD.xml 的 DocBody 中 DocRoot 之后的内容，如 ofd:Thumbnail
+        pub(crate) fn doc_body(mut self, xml: &str) -> OfdBuilder {
+            self.doc_body = xml.to_string();
+            self
+        }
+
+        /// 追加在 Document.xml 中 Pages 之后的内容，如 ofd:Annotations
+        pub(crate) fn document(mut self, xml: &str) -> OfdBuilder {
+            self.document = xml.to_string();
+            self
+        }
+
+        pub(crate) fn max_unit_id(mut self, max_unit_id: u32) -> OfdBuilder {
+            self.max_unit_id = max_unit_id;
+            self
+        }
+
+        /// 压缩包中的文件名按百分号编码存储（如 Doc%5F0），文档中的引用仍为解码后的名称
+        pub(crate) fn percent_encoded(mut self) -> OfdBuilder {
+            self.percent_encoded = true;
+            self
+        }
+
+        /// 添加包内文件，与生成的文件同名时替换生成的文件
+        pub(crate) fn file(mut self, name: &str, data: impl Into<Vec<u8>>) -> OfdBuilder {
+            self.files.insert(name.to_string(), data.into());
+            self
+        }
+
+        /// 生成的包内文件，页面依次为 Doc_0/Pages/Page_N/Content.xml，页面标识为 N + 1
+        fn entries(&self) -> BTreeMap<String, Vec<u8>> {
+            let mut entries = BTreeMap::new();
+            let mut add = |name: String, xml: String| {
+                entries.insert(name, format!("<?xml version=\"1.0\" encoding=\"UTF-8\"?>{}", xml).into_bytes());
+            };
+            add("OFD.xml".to_string(), format!(
+                "<ofd:OFD xmlns:ofd=\"{NAMESPACE}\" DocType=\"OFD\" Version=\"1.0\"><ofd:DocBody>\
+                 <ofd:DocInfo><ofd:DocID>fixture</ofd:DocID></ofd:DocInfo>\
+                 <ofd:DocRoot>Doc_0/Document.xml</ofd:DocRoot>{}</ofd:DocBody></ofd:OFD>",
+                self.doc_body,
+            ));
+            let page_refs: String = (0..self.pages.len())
+                .map(|i| format!("<ofd:Page ID=\"{}\" BaseLoc=\"Pages/Page_{}/Content.xml\"/>", i + 1, i))
+                .collect();
+            let template_refs: String = self.templates.iter().enumerate()
+                .map(|(i, (_, z_order))| format!("<ofd:TemplatePage ID=\"{}\" ZOrder=\"{}\" BaseLoc=\"Tpls/Tpl_{}/Content.xml\"/>", 101 + i, z_order, i))
+                .collect();
+            add("Doc_0/Document.xml".to_string(), format!(
+                "<ofd:Document xmlns:ofd=\"{NAMESPACE}\"><ofd:CommonData>\
+                 <ofd:PageArea><ofd:PhysicalBox>0 0 {PAGE_WIDTH} {PAGE_HEIGHT}</ofd:PhysicalBox></ofd:PageArea>\
+                 <ofd:PublicRes>PublicRes.xml</ofd:PublicRes><ofd:MaxUnitID>{}</ofd:MaxUnitID>{}\
+                 </ofd:CommonData><ofd:Pages>{}</ofd:Pages>{}</ofd:Document>",
+                self.max_unit_id, template_refs, page_refs, self.document,
+            ));
+            add("Doc_0/PublicRes.xml".to_string(), format!(
+                "<ofd:Res xmlns:ofd=\"{NAMESPACE}\" BaseLoc=\"Res\">{}</ofd:Res>",
+                self.resources,
+            ));
+            let uses: String = (0..self.templates.len())
+                .map(|i| format!("<ofd:Template TemplateID=\"{}\"/>", 101 + i))
+                .collect();
+            for (i, objects) in self.pages.iter().enumerate() {
+                add(format!("Doc_0/Pages/Page_{}/Content.xml", i), format!(
+                    "<ofd:Page xmlns:ofd=\"{NAMESPACE}\">{}<ofd:Content>\
+                     <ofd:Layer ID=\"{}\" Type=\"Body\">{}</ofd:Layer></ofd:Content></ofd:Page>",
+                    uses, 900 + i, objects,
+                ));
+            }
+            for (i, (objects, _)) in self.templates.iter().enumerate() {
+                add(format!("Doc_0/Tpls/Tpl_{}/Content.xml", i), format!(
+                    "<ofd:Page xmlns:ofd=\"{NAMESPACE}\"><ofd:Content>\
+                     <ofd:Layer ID=\"{}\" Type=\"Body\">{}</ofd:Layer></ofd:Content></ofd:Page>",
+                    950 + i, objects,
+                ));
+            }
+            entries.extend(self.files.clone());
+            entries
+        }
+
+        /// 打包为 OFD 文件的字节
+        pub(crate) fn build(&self) -> Vec<u8> {
+            let mut zip = ZipWriter::new(Cursor::new(Vec::new()));
+            for (name, data) in self.entries() {
+                let name = if self.percent_encoded { name.replace('_', "%5F") } else { name };
+                zip.start_file(name, SimpleFileOptions::default()).unwrap();
+                zip.write_all(&data).unwrap();
+            }
+            zip.finish().unwrap().into_inner()
+        }
+
+        /// 打包为以 password 进行 AES 加密的 OFD 文件的字节
+        pub(crate) fn build_encrypted(&self, password: &str) -> Vec<u8> {
+            let mut zip = ZipWriter::new(Cursor::new(Vec::new()));
+            for (name, data) in self.entries() {
+                let options = SimpleFileOptions::default().with_aes_encryption(AesMode::Aes256, password);
+                zip.start_file(name, options).unwrap();
+                zip.write_all(&data).unwrap();
+            }
+            zip.finish().unwrap().into_inner()
+        }
+
+        pub(crate) fn open(&self) -> OfdDoc {
+            OfdDoc::open_from_bytes(&self.build()).unwrap()
+        }
+
+        /// 按 options 打开，用于严格模式与读取限制
+        pub(crate) fn open_with(&self, options: &OpenOptions) -> OfdDoc {
+            let source = ZipSource::new(Box::new(Cursor::new(self.build()))).unwrap();
+            OfdDoc::open_package(Box::new(source), options).unwrap()
+        }
+    }
+
+    /// 测试字体的字形步进，字体单位，每 em 为 1000 单位
+    pub(crate) const GLYPH_ADVANCE: u16 = 600;
+
+    /// 构造只含大写字母 A–Z 的 TrueType 字体，每个字母（字形 1–26）都是同一个实心矩形，
+    /// 左右各留 50 单位，高 700 单位；fs_type 为 Some 时带有含该嵌入许可的 OS/2 表
+    pub(crate) fn truetype_font(fs_type: Option<u16>) -> Vec<u8> {
+        const GLYPHS: u16 = 27;
+        fn be16(out: &mut Vec<u8>, values: &[u16]) {
+            for v in values {
+                out.extend_from_slice(&v.to_be_bytes());
+            }
+        }
+
+        let mut head = Vec::new();
+        be16(&mut head, &[1, 0, 1, 0, 0, 0, 0x5F0F, 0x3CF5, 0x000B, 1000]);
+        head.extend_from_slice(&[0; 16]);
+        be16(&mut head, &[50, 0, 550, 700, 0, 8, 2, 0, 0]);
+
+        let mut hhea = Vec::new();
+        be16(&mut hhea, &[1, 0, 800, (-200i16) as u16, 0, GLYPH_ADVANCE, 50, 50, 550, 1, 0, 0, 0, 0, 0, 0, 0, GLYPHS]);
+
+        let mut maxp = Vec::new();
+        be16(&mut maxp, &[1, 0, GLYPHS, 4, 1, 0, 0, 2, 0, 0, 0, 0, 0, 0, 0, 0]);
+
+        // 一个轮廓、四个落在曲线上的点，坐标均以 16 位增量给出
+        let mut rectangle = Vec::new();
+        be16(&mut rectangle, &[1, 50, 0, 550, 700, 3, 0]);
+        rectangle.extend_from_slice(&[1; 4]);
+        be16(&mut rectangle, &[50, 500, 0, (-500i16) as u16, 0, 0, 700, 0]);
+        let glyf = rectangle.repeat(GLYPHS as usize - 1);
+
+        let mut loca = Vec::new();
+        be16(&mut loca, &[0]);
+        for i in 0..GLYPHS {
+            be16(&mut loca, &[(i as usize * rectangle.len() / 2) as u16]);
+        }
+
+        let mut hmtx = Vec::new();
+        for _ in 0..GLYPHS {
+            be16(&mut hmtx, &[GLYPH_ADVANCE, 50]);
+        }
+
+        // Windows Unicode 的 format 4 子表，A–Z 映射到字形 1–26
+        let mut cmap = Vec::new();
+        be16(&mut cmap, &[0, 1, 3, 1, 0, 12]);
+        be16(&mut cmap, &[4, 32, 0, 4, 4, 1, 0, 0x5A, 0xFFFF, 0, 0x41, 0xFFFF, 1u16.wrapping_sub(0x41), 1, 0, 0]);
+
+        let name_string: Vec<u16> = "Fixture".encode_utf16().collect();
+        let mut name = Vec::new();
+        be16(&mut name, &[0, 1, 18, 3, 1, 0x409, 6, name_string.len() as u16 * 2, 0]);
+        be16(&mut name, &name_string);
+
+        let mut post = Vec::new();
+        be16(&mut post, &[3, 0]);
+        post.extend_from_slice(&[0; 28]);
+
+        let mut tables: Vec<(&[u8; 4], Vec<u8>)> = vec![
+            (b"cmap", cmap), (b"glyf", glyf), (b"head", head), (b"hhea", hhea), (b"hmtx", hmtx),
+            (b"loca", loca), (b"maxp", maxp), (b"name", name), (b"post", post),
+        ];
+        if let Some(fs_type) = fs_type {
+            let mut os2 = Vec::new();
+            be16(&mut os2, &[0, 500, 400, 5, fs_type]);
+            os2.resize(78, 0);
+            tables.insert(0, (b"OS/2", os2));
+        }
+
+        let mut font = Vec::new();
+        be16(&mut font, &[1, 0, tables.len() as u16, 0, 0, 0]);
+        let mut offset = 12 + tables.len() * 16;
+        let mut body = Vec::new();
+        for (tag, data) in &tables {
+            font.extend_from_slice(*tag);
+            font.extend_from_slice(&[0; 4]);
+            font.extend_from_slice(&(offset as u32).to_be_bytes());
+            font.extend_from_slice(&(data.len() as u32).to_be_bytes());
+            body.extend_from_slice(data);
+            while body.len() % 4 != 0 {
+                body.push(0);
+            }
+            offset = 12 + tables.len() * 16 + body.len();
+        }
+        font.extend_from_slice(&body);
+        font
+    }
+
+    thread_local! {
+        /// 当前线程记录到的日志，测试各自在独立的线程中运行，互不干扰
+        static LOG_RECORDS: RefCell<Vec<(log::Level, String)>> = const { RefCell::new(Vec::new()) };
+    }
+
+    /// 将日志记录到当前线程的 LOG_RECORDS
+    struct CaptureLogger;
+
+    impl log::Log for CaptureLogger {
+        fn enabled(&self, _: &log::Metadata) -> bool {
+            true
+        }
+
+        fn log(&self, record: &log::Record) {
+            LOG_RECORDS.with(|records| records.borrow_mut().push((record.level(), record.args().to_string())));
+        }
+
+        fn flush(&self) {}
+    }
+
+    static CAPTURE_LOGGER: CaptureLogger = CaptureLogger;
+
+    /// 执行 f 并返回其结果与执行期间当前线程输出的日志，其他线程（如 rayon 工作线程）的日志不记录
+    pub(crate) fn capture_logs<T>(f: impl FnOnce() -> T) -> (T, Vec<(log::Level, String)>) {
+        static INSTALL: Once = Once::new();
+        INSTALL.call_once(|| {
+            log::set_logger(&CAPTURE_LOGGER).expect("no other logger is installed in tests");
+            log::set_max_level(log::LevelFilter::Trace);
+        });
+        LOG_RECORDS.with(|records| records.borrow_mut().clear());
+        let result = f();
+        (result, LOG_RECORDS.with(|records| records.take()))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// 含纯红色像素的 8 × 8 测试图像，用于确认相同的红色像素不会被误标为差异
+    fn sample() -> RgbaImage {
+        RgbaImage::from_fn(8, 8, |x, y| match (x + y) % 3 {
+            0 => Rgba([255, 0, 0, 255]),
+            1 => Rgba([0, 0, 255, 255]),
+            _ => Rgba([x as u8 * 30, y as u8 * 30, 0, 128]),
+        })
+    }
+
+    fn png(image: &RgbaImage) -> Vec<u8> {
+        let mut data = Cursor::new(Vec::new());
+        image.write_to(&mut data, ImageFormat::Png).unwrap();
+        data.into_inner()
+    }
+
+    fn diff_pixels(a: &RgbaImage, b: &RgbaImage) -> RgbaImage {
+        image::load_from_memory(&visual_diff(&png(a), &png(b)).unwrap()).unwrap().to_rgba8()
+    }
+
+    #[test]
+    fn identical_images_have_no_marked_pixels() {
+        let diff = diff_pixels(&sample(), &sample());
+        assert_eq!(diff.dimensions(), (8, 8));
+        assert!(diff.pixels().all(|p| *p != DIFF_COLOR));
+    }
+
+    #[test]
+    fn changed_pixel_is_marked_red() {
+        let mut changed = sample();
+        changed.put_pixel(3, 5, Rgba([0, 255, 0, 255]));
+        let diff = diff_pixels(&sample(), &changed);
+        let marked: Vec<(u32, u32)> = diff.enumerate_pixels().filter(|(_, _, p)| **p == DIFF_COLOR).map(|(x, y, _)| (x, y)).collect();
+        assert_eq!(marked, [(3, 5)]);
+    }
+
+    #[test]
+    fn images_of_different_sizes_are_rejected() {
+        let small = RgbaImage::new(4, 4);
+        assert!(matches!(visual_diff(&png(&sample()), &png(&small)), Err(OfdError::ImageSizeMismatch { .. })));
+    }
+
+    #[test]
+    fn image_hash_is_fixed_and_changes_with_one_pixel() {
+        let image = RgbaImage::from_fn(2, 1, |x, _| if x == 0 { Rgba([255, 0, 0, 255]) } else { Rgba([0, 0, 255, 255]) });
+        // FNV-1a 的结果固定，不随平台或运行次数变化
+        assert_eq!(image_hash(&image), 0x226f_de2b_8d0b_756a);
+        assert_eq!(image_hash(&image.clone()), image_hash(&image));
+
+        let mut edited = image.clone();
+        edited.put_pixel(1, 0, Rgba([0, 0, 254, 255]));
+        assert_ne!(image_hash(&edited), image_hash(&image));
+        // 像素数据相同而尺寸不同时哈希也不同
+        let transposed = RgbaImage::from_raw(1, 2, image.as_raw().clone()).unwrap();
+        assert_ne!(image_hash(&transposed), image_hash(&image));
+    }
+}
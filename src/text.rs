@@ -0,0 +1,356 @@
+use crate::page::{Page, PageObject, TextCode, TextObject};
+use crate::render::object_matrix;
+use crate::st_types::{STBox, STDeltas, STPos};
+
+/// 页面中的一段文字，对应一个 TextCode
+#[derive(Debug, Clone)]
+pub struct TextRun {
+    /// 所属文字对象的标识
+    pub object_id: String,
+    pub text: String,
+    /// 所用字体资源的标识
+    pub font: String,
+    /// 页面坐标系下的字号，毫米
+    pub size: f64,
+    /// 每个字符在页面坐标系下的基线起点，毫米
+    pub positions: Vec<(f64, f64)>,
+}
+
+/// 文字检索的一处匹配
+#[derive(Debug, Clone)]
+pub struct TextHit {
+    pub page_index: usize,
+    /// 页面中匹配到的原文
+    pub text: String,
+    /// 匹配文字在页面坐标系下的外接矩形，毫米
+    pub bbox: STBox,
+}
+
+/// 文字检索参数
+#[derive(Debug, Clone, Default)]
+pub struct SearchOptions {
+    /// 忽略大小写
+    pub case_insensitive: bool,
+}
+
+/// 字符框在基线以上与以下所占的高度，以字号为单位
+const GLYPH_ASCENT: f64 = 0.8;
+const GLYPH_DESCENT: f64 = 0.2;
+
+/// 未给出字符间距时按一个字号的宽度估算字符位置
+pub(crate) fn collect_text_runs(pages: &[&Page]) -> Vec<TextRun> {
+    let mut runs = Vec::new();
+    for object in pages.iter().flat_map(|page| page.objects()) {
+        if let PageObject::TextObject(o) = object {
+            append_text_runs(o, &mut runs);
+        }
+    }
+    runs
+}
+
+fn append_text_runs(object: &TextObject, runs: &mut Vec<TextRun>) {
+    let matrix = object_matrix(&object.boundary, object.ctm);
+    let scale = (matrix.a * matrix.d - matrix.b * matrix.c).abs().sqrt();
+    for code in object.text_codes() {
+        let positions = glyph_positions(code, object.read_direction, object.size)
+            .into_iter()
+            .map(|(x, y)| {
+                let p = matrix.apply(STPos { x, y });
+                (p.x, p.y)
+            })
+            .collect();
+        runs.push(TextRun {
+            object_id: object.id.clone(),
+            text: code.text.clone(),
+            font: object.font.clone(),
+            size: object.size * scale,
+            positions,
+        });
+    }
+}
+
+/// 每个字符在文字对象坐标系下的基线起点，未给出字符间距时沿阅读方向按 advance 排列
+pub(crate) fn glyph_positions(code: &TextCode, read_direction: i32, advance: f64) -> Vec<(f64, f64)> {
+    let (advance_x, advance_y) = advance_along(read_direction, advance);
+    let count = code.text.chars().count();
+    let xs = axis_positions(code.delta_x.as_deref(), code.x, advance_x, count);
+    let ys = axis_positions(code.delta_y.as_deref(), code.y, advance_y, count);
+    xs.into_iter().zip(ys).collect()
+}
+
+/// 一个坐标轴上 count 个字符的绝对坐标，偏移量不足 count - 1 个时沿用最后一个，没有偏移量时按 advance 补齐
+fn axis_positions(value: Option<&str>, start: f64, advance: f64, count: usize) -> Vec<f64> {
+    let mut deltas = value.and_then(|v| v.parse::<STDeltas>().ok()).unwrap_or_default();
+    if !deltas.covers(count) {
+        let fill = deltas.deltas.last().copied().unwrap_or(advance);
+        deltas.deltas.resize(count - 1, fill);
+    }
+    let mut positions = deltas.to_positions(start);
+    positions.truncate(count);
+    positions
+}
+
+pub(crate) fn advance_along(read_direction: i32, advance: f64) -> (f64, f64) {
+    match read_direction.rem_euclid(360) {
+        90 => (0.0, advance),
+        180 => (-advance, 0.0),
+        270 => (0.0, -advance),
+        _ => (advance, 0.0),
+    }
+}
+
+/// 字形相对于当前位置的放置方式，返回 (字形原点相对当前位置的偏移, 到下一字符的前进量)
+/// advance 为字形自身的步进，size 为字号；字形按 char_direction 顺时针旋转后：
+/// 步进方向与阅读方向相同时字形从当前位置向前排列；相反时（如从右向左阅读的正立字形）
+/// 字形原点前移一个步进，使字形同样位于当前位置之前；与阅读方向垂直时（如竖排的正立字形）
+/// 没有纵向步进可用，按一个字号前进
+pub(crate) fn glyph_placement(read_direction: i32, char_direction: i32, advance: f64, size: f64) -> ((f64, f64), f64) {
+    let (forward_x, forward_y) = advance_along(read_direction, 1.0);
+    let rotation = (char_direction.rem_euclid(360) as f64).to_radians();
+    let along = rotation.cos() * forward_x + rotation.sin() * forward_y;
+    if along > 0.5 {
+        ((0.0, 0.0), advance)
+    } else if along < -0.5 {
+        ((forward_x * advance, forward_y * advance), advance)
+    } else {
+        ((0.0, 0.0), size)
+    }
+}
+
+pub(crate) fn parse_deltas(value: Option<&str>) -> Vec<f64> {
+    value
+        .and_then(|v| v.parse::<STDeltas>().ok())
+        .map(|d| d.deltas)
+        .unwrap_or_default()
+}
+
+/// 第 index 个字符之后的偏移，超出个数时沿用最后一个
+pub(crate) fn delta_at(deltas: &[f64], index: usize) -> Option<f64> {
+    deltas.get(index).or(deltas.last()).copied()
+}
+
+/// 按阅读顺序拼接文字：先按基线自上而下分行，同一行内自左向右排列
+pub(crate) fn plain_text(runs: &[TextRun]) -> String {
+    group_lines(runs)
+        .iter()
+        .map(|line| line.iter().map(|run| run.text.as_str()).collect::<String>())
+        .collect::<Vec<_>>()
+        .join("\n")
+}
+
+/// 将文字按基线自上而下分行，行内自左向右排列
+/// 基线的纵向差距超过较小字号的一半时视为换行
+fn group_lines(runs: &[TextRun]) -> Vec<Vec<&TextRun>> {
+    let mut runs: Vec<(f64, f64, &TextRun)> = runs
+        .iter()
+        .filter(|run| !run.text.is_empty())
+        .filter_map(|run| run.positions.first().map(|&(x, y)| (x, y, run)))
+        .collect();
+    runs.sort_by(|a, b| a.1.total_cmp(&b.1));
+
+    let mut lines: Vec<Vec<(f64, f64, &TextRun)>> = Vec::new();
+    for item in runs {
+        match lines.last_mut() {
+            Some(line) if (item.1 - line[0].1).abs() <= line[0].2.size.min(item.2.size) / 2.0 => line.push(item),
+            _ => lines.push(vec![item]),
+        }
+    }
+    lines
+        .into_iter()
+        .map(|mut line| {
+            line.sort_by(|a, b| a.0.total_cmp(&b.0));
+            line.into_iter().map(|(_, _, run)| run).collect()
+        })
+        .collect()
+}
+
+/// 重建后的段落
+#[derive(Debug, Clone)]
+pub struct Paragraph {
+    /// 段落文字，各行以换行分隔
+    pub text: String,
+    /// 段落在页面坐标系下的外接矩形，毫米
+    pub bbox: STBox,
+}
+
+/// 相邻两行基线的最大间距，以字号为单位，超过时视为另起一段
+const MAX_LINE_SPACING: f64 = 2.0;
+
+/// 同一行中相邻文字的最大水平间距，以字号为单位，超过时视为不同栏中的文字
+const MAX_WORD_GAP: f64 = 2.0;
+
+/// 同一段落中各行字号允许的相对差异
+const SIZE_TOLERANCE: f64 = 0.1;
+
+/// 一行中连续的一段文字，取其中第一段文字的字体与字号
+struct Line {
+    text: String,
+    bbox: STBox,
+    baseline: f64,
+    font: String,
+    size: f64,
+}
+
+/// 将分散在多个文字对象中的文字重建为段落
+/// 先按基线分行，行内水平间距过大处断开；再把字体相同、字号相近、行距不超过两个字号且水平范围有重叠的上下两行合为一段
+pub(crate) fn paragraphs(runs: &[TextRun]) -> Vec<Paragraph> {
+    let mut paragraphs: Vec<(Paragraph, Line)> = Vec::new();
+    for line in group_lines(runs).iter().flat_map(|line| split_line(line)) {
+        match paragraphs.iter_mut().rev().find(|(_, last)| continues_paragraph(last, &line)) {
+            Some((paragraph, last)) => {
+                paragraph.text.push('\n');
+                paragraph.text.push_str(&line.text);
+                paragraph.bbox = union(paragraph.bbox, line.bbox);
+                *last = line;
+            }
+            None => paragraphs.push((Paragraph { text: line.text.clone(), bbox: line.bbox }, line)),
+        }
+    }
+    paragraphs.into_iter().map(|(paragraph, _)| paragraph).collect()
+}
+
+/// 在水平间距过大处将一行断开
+fn split_line(runs: &[&TextRun]) -> Vec<Line> {
+    let mut lines: Vec<Line> = Vec::new();
+    for run in runs {
+        let Some(bbox) = glyph_boxes(run).into_iter().map(|(_, b)| b).reduce(union) else {
+            continue;
+        };
+        match lines.last_mut() {
+            Some(line) if bbox.x - (line.bbox.x + line.bbox.w) <= line.size * MAX_WORD_GAP => {
+                line.text.push_str(&run.text);
+                line.bbox = union(line.bbox, bbox);
+            }
+            _ => lines.push(Line {
+                text: run.text.clone(),
+                bbox,
+                baseline: run.positions[0].1,
+                font: run.font.clone(),
+                size: run.size,
+            }),
+        }
+    }
+    lines
+}
+
+fn continues_paragraph(previous: &Line, line: &Line) -> bool {
+    let spacing = line.baseline - previous.baseline;
+    previous.font == line.font
+        && (line.size - previous.size).abs() <= previous.size * SIZE_TOLERANCE
+        && spacing > 0.0
+        && spacing <= previous.size * MAX_LINE_SPACING
+        && line.bbox.x < previous.bbox.x + previous.bbox.w
+        && previous.bbox.x < line.bbox.x + line.bbox.w
+}
+
+/// 在每个文字对象的全部文字中查找 query，返回各处不重叠的匹配原文与外接矩形
+/// 每个字符的框从基线起点沿到下一个字符的方向延伸，最后一个字符沿用前一个字符的间距，只有一个字符时取一个字号
+pub(crate) fn search_runs(runs: &[TextRun], query: &str, opts: &SearchOptions) -> Vec<(String, STBox)> {
+    let query: Vec<char> = query.chars().collect();
+    if query.is_empty() {
+        return Vec::new();
+    }
+    let mut hits = Vec::new();
+    for object_runs in runs.chunk_by(|a, b| a.object_id == b.object_id) {
+        let glyphs: Vec<(char, STBox)> = object_runs.iter().flat_map(glyph_boxes).collect();
+        let mut start = 0;
+        while start + query.len() <= glyphs.len() {
+            let matched = glyphs[start..start + query.len()]
+                .iter()
+                .zip(&query)
+                .all(|((c, _), q)| chars_match(*c, *q, opts.case_insensitive));
+            if !matched {
+                start += 1;
+                continue;
+            }
+            let matched = &glyphs[start..start + query.len()];
+            let text = matched.iter().map(|(c, _)| *c).collect();
+            let bbox = matched.iter().map(|(_, b)| *b).reduce(union).unwrap_or_default();
+            hits.push((text, bbox));
+            start += query.len();
+        }
+    }
+    hits
+}
+
+fn chars_match(a: char, b: char, case_insensitive: bool) -> bool {
+    a == b || (case_insensitive && a.to_lowercase().eq(b.to_lowercase()))
+}
+
+/// 一段文字中每个字符与其在页面坐标系下的框
+fn glyph_boxes(run: &TextRun) -> Vec<(char, STBox)> {
+    let mut advance = (run.size, 0.0);
+    run.text
+        .chars()
+        .zip(&run.positions)
+        .enumerate()
+        .map(|(i, (c, &(x, y)))| {
+            if let Some(&(next_x, next_y)) = run.positions.get(i + 1) {
+                advance = (next_x - x, next_y - y);
+            }
+            let (end_x, end_y) = (x + advance.0, y + advance.1);
+            // 竖排时字形正立，宽度按一个字号估算，纵向只占自身的上下范围
+            let (left, right, top, bottom) = if advance.1.abs() > advance.0.abs() {
+                (x, x + run.size, y - run.size * GLYPH_ASCENT, y + run.size * GLYPH_DESCENT)
+            } else {
+                (x.min(end_x), x.max(end_x), y.min(end_y) - run.size * GLYPH_ASCENT, y.max(end_y) + run.size * GLYPH_DESCENT)
+            };
+            (c, STBox { x: left, y: top, w: right - left, h: bottom - top })
+        })
+        .collect()
+}
+
+fn union(a: STBox, b: STBox) -> STBox {
+    let x = a.x.min(b.x);
+    let y = a.y.min(b.y);
+    let right = (a.x + a.w).max(b.x + b.w);
+    let bottom = (a.y + a.h).max(b.y + b.h);
+    STBox { x, y, w: right - x, h: bottom - y }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn boxes_advance_downward_for_vertical_reading() {
+        let code = TextCode { x: 5.0, y: 5.0, text: "AB".to_string(), ..TextCode::default() };
+        let positions = glyph_positions(&code, 90, 10.0);
+        assert_eq!(positions, [(5.0, 5.0), (5.0, 15.0)]);
+        let run = TextRun { object_id: "1".into(), text: code.text.clone(), font: String::new(), size: 10.0, positions };
+        let boxes = glyph_boxes(&run);
+        let (first, second) = (boxes[0].1, boxes[1].1);
+        assert_eq!((first.x, first.w), (second.x, second.w));
+        assert!(second.y > first.y, "{:?} should be below {:?}", second, first);
+        assert!(first.y + first.h <= second.y + 1e-9);
+    }
+
+    #[test]
+    fn placement_follows_the_character_direction() {
+        // 正立字形横排时按自身步进前进
+        assert_eq!(glyph_placement(0, 0, 6.0, 10.0), ((0.0, 0.0), 6.0));
+        // 旋转 90 度的字形与横排的阅读方向垂直，按一个字号前进
+        assert_eq!(glyph_placement(0, 90, 6.0, 10.0), ((0.0, 0.0), 10.0));
+        // 旋转 90 度的字形竖排时沿阅读方向按自身步进前进
+        assert_eq!(glyph_placement(90, 90, 6.0, 10.0), ((0.0, 0.0), 6.0));
+        // 倒置的字形横排时原点前移一个步进
+        assert_eq!(glyph_placement(0, 180, 6.0, 10.0), ((6.0, 0.0), 6.0));
+    }
+
+    /// 字体 1、字号 3、从 (x, baseline) 起按一个字号排列的文字
+    fn line(text: &str, x: f64, baseline: f64) -> TextRun {
+        let positions = (0..text.chars().count()).map(|i| (x + 3.0 * i as f64, baseline)).collect();
+        TextRun { object_id: format!("{}", baseline), text: text.to_string(), font: "1".into(), size: 3.0, positions }
+    }
+
+    #[test]
+    fn consecutive_lines_join_into_one_paragraph() {
+        let runs = [line("ABC", 2.0, 5.0), line("DEF", 2.0, 9.0), line("GH", 2.0, 13.0), line("XYZ", 2.0, 28.0)];
+        let paragraphs = paragraphs(&runs);
+        assert_eq!(paragraphs.len(), 2, "{:?}", paragraphs);
+        assert_eq!(paragraphs[0].text, "ABC\nDEF\nGH");
+        assert_eq!(paragraphs[0].text.matches('\n').count(), 2);
+        assert_eq!(paragraphs[1].text, "XYZ");
+        assert!(paragraphs[0].bbox.y + paragraphs[0].bbox.h <= paragraphs[1].bbox.y);
+    }
+}
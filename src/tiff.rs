@@ -0,0 +1,110 @@
+use std::io::{Seek, Write};
+
+use ::tiff::encoder::colortype::{ColorType, Gray8, RGB8};
+use ::tiff::encoder::compression::{Compression, CompressionAlgorithm, Deflate, Lzw, Uncompressed};
+use ::tiff::encoder::{Rational, TiffEncoder};
+use ::tiff::tags::{CompressionMethod, PhotometricInterpretation, ResolutionUnit, Tag};
+use ::tiff::TiffResult;
+use image::RgbaImage;
+
+use crate::bilevel::Bitmap;
+use crate::ccitt;
+use crate::render::{luminance, ColorMode, RenderOptions, TiffCompression};
+
+/// 将一页图像写入为 TIFF 的一个 IFD，颜色模式与压缩方式取自 opts，dpi 为写入文件的分辨率
+/// 图像应当不透明，透明度不写入文件
+pub(crate) fn write_page<W: Write + Seek>(encoder: &mut TiffEncoder<W>, image: &RgbaImage, opts: &RenderOptions, dpi: f64) -> TiffResult<()> {
+    let mode = match opts.tiff_compression {
+        TiffCompression::Group4 => ColorMode::Bilevel,
+        _ => opts.color_mode,
+    };
+    match mode {
+        ColorMode::Color => {
+            let samples: Vec<u8> = image.pixels().flat_map(|p| [p[0], p[1], p[2]]).collect();
+            write_samples::<RGB8, W>(encoder, image, &samples, opts, dpi)
+        }
+        ColorMode::Gray => {
+            let samples: Vec<u8> = image.pixels().map(|p| luminance(p.0)).collect();
+            write_samples::<Gray8, W>(encoder, image, &samples, opts, dpi)
+        }
+        ColorMode::Bilevel => write_bilevel(encoder, &Bitmap::from_rgba(image), opts, dpi),
+    }
+}
+
+fn resolution(dpi: f64) -> Rational {
+    Rational { n: (dpi * 100.0).round() as u32, d: 100 }
+}
+
+/// 按 8 位样本写入彩色或灰度图像
+fn write_samples<C: ColorType<Inner = u8>, W: Write + Seek>(
+    encoder: &mut TiffEncoder<W>,
+    image: &RgbaImage,
+    samples: &[u8],
+    opts: &RenderOptions,
+    dpi: f64,
+) -> TiffResult<()> {
+    fn write<C: ColorType<Inner = u8>, W: Write + Seek, D: Compression>(
+        encoder: &mut TiffEncoder<W>,
+        image: &RgbaImage,
+        samples: &[u8],
+        dpi: f64,
+        compression: D,
+    ) -> TiffResult<()> {
+        let mut page = encoder.new_image_with_compression::<C, D>(image.width(), image.height(), compression)?;
+        page.resolution(ResolutionUnit::Inch, resolution(dpi));
+        page.write_data(samples)
+    }
+    match opts.tiff_compression {
+        TiffCompression::None => write::<C, W, _>(encoder, image, samples, dpi, Uncompressed),
+        TiffCompression::Deflate => write::<C, W, _>(encoder, image, samples, dpi, Deflate::default()),
+        TiffCompression::Lzw | TiffCompression::Group4 => write::<C, W, _>(encoder, image, samples, dpi, Lzw),
+    }
+}
+
+/// 按每像素 1 位写入二值图像，黑色为 1（WhiteIsZero），整幅图像作为一个条带
+/// tiff 库不支持 1 位图像，因此自行压缩数据并写入各标签
+fn write_bilevel<W: Write + Seek>(encoder: &mut TiffEncoder<W>, bitmap: &Bitmap, opts: &RenderOptions, dpi: f64) -> TiffResult<()> {
+    let (method, data) = match opts.tiff_compression {
+        TiffCompression::Group4 => (CompressionMethod::Fax4, ccitt::encode_g4(bitmap)),
+        compression => {
+            let mut packed = Vec::with_capacity(bitmap.width.div_ceil(8) * bitmap.height);
+            for y in 0..bitmap.height {
+                for bits in bitmap.row(y).chunks(8) {
+                    packed.push(bits.iter().enumerate().fold(0u8, |byte, (i, bit)| byte | (bit << (7 - i))));
+                }
+            }
+            let mut data = Vec::new();
+            let method = match compression {
+                TiffCompression::None => {
+                    Uncompressed.write_to(&mut data, &packed)?;
+                    CompressionMethod::None
+                }
+                TiffCompression::Deflate => {
+                    Deflate::default().write_to(&mut data, &packed)?;
+                    CompressionMethod::Deflate
+                }
+                _ => {
+                    Lzw.write_to(&mut data, &packed)?;
+                    CompressionMethod::LZW
+                }
+            };
+            (method, data)
+        }
+    };
+
+    let mut directory = encoder.new_directory()?;
+    let offset = directory.write_data(data.as_slice())?;
+    directory.write_tag(Tag::ImageWidth, bitmap.width as u32)?;
+    directory.write_tag(Tag::ImageLength, bitmap.height as u32)?;
+    directory.write_tag(Tag::BitsPerSample, 1u16)?;
+    directory.write_tag(Tag::Compression, method.to_u16())?;
+    directory.write_tag(Tag::PhotometricInterpretation, PhotometricInterpretation::WhiteIsZero.to_u16())?;
+    directory.write_tag(Tag::StripOffsets, offset as u32)?;
+    directory.write_tag(Tag::SamplesPerPixel, 1u16)?;
+    directory.write_tag(Tag::RowsPerStrip, bitmap.height as u32)?;
+    directory.write_tag(Tag::StripByteCounts, data.len() as u32)?;
+    directory.write_tag(Tag::XResolution, resolution(dpi))?;
+    directory.write_tag(Tag::YResolution, resolution(dpi))?;
+    directory.write_tag(Tag::ResolutionUnit, ResolutionUnit::Inch.to_u16())?;
+    directory.finish()
+}
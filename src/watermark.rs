@@ -0,0 +1,59 @@
+use std::collections::HashMap;
+
+use crate::page::{collect_objects, LayerType, Page, PageObject, TextObject};
+use crate::resource::ResourceManager;
+
+/// 判定为水印所需的同一文字的最少出现次数
+const MIN_REPEATS: usize = 2;
+
+/// CTM 的旋转角与坐标轴的最小夹角（度），小于该值时视为水平或竖直排列
+const MIN_TILT_DEGREES: f64 = 1.0;
+
+/// 按启发式规则查找水印文字，pages 为按绘制顺序排列的页面与模板页，没有水印时返回 None
+/// 满足以下全部条件的文字对象视为水印候选：
+/// - 位于页面的背景层或前景层，或位于模板页中（模板页的内容不属于页面正文）
+/// - CTM 使文字倾斜，旋转角不是 90 度的整数倍
+/// - 半透明：图元、绘制参数或填充颜色的透明度小于 255
+///
+/// 同一文字在候选中出现至少两次时判定为水印，有多种文字时返回出现次数最多的
+pub(crate) fn detect_watermark(page: &Page, templates: &[&Page], resources: &ResourceManager) -> Option<String> {
+    let mut counts: HashMap<String, usize> = HashMap::new();
+    let layers = page.layers().into_iter()
+        .filter(|layer| layer.layer_type != LayerType::Body)
+        .chain(templates.iter().flat_map(|t| t.layers()));
+    for layer in layers {
+        let mut objects = Vec::new();
+        collect_objects(&layer.objects, &mut objects);
+        for object in objects {
+            let PageObject::TextObject(text) = object else {
+                continue;
+            };
+            if is_tilted(text) && is_translucent(text, layer.draw_param.as_deref(), resources) {
+                let content: String = text.text_codes().map(|code| code.text.as_str()).collect();
+                let content = content.trim();
+                if !content.is_empty() {
+                    *counts.entry(content.to_string()).or_default() += 1;
+                }
+            }
+        }
+    }
+    counts.into_iter()
+        .filter(|(_, count)| *count >= MIN_REPEATS)
+        .max_by(|a, b| a.1.cmp(&b.1).then_with(|| b.0.cmp(&a.0)))
+        .map(|(text, _)| text)
+}
+
+fn is_tilted(text: &TextObject) -> bool {
+    let Some(ctm) = text.ctm else {
+        return false;
+    };
+    let degrees = ctm.b.atan2(ctm.a).to_degrees().rem_euclid(90.0);
+    degrees.min(90.0 - degrees) >= MIN_TILT_DEGREES
+}
+
+fn is_translucent(text: &TextObject, layer_draw_param: Option<&str>, resources: &ResourceManager) -> bool {
+    let param = resources.effective_draw_param(text.draw_param.as_deref(), layer_draw_param);
+    let alpha = text.alpha.or(param.alpha).unwrap_or(255);
+    let color_alpha = text.style(&param).fill_color.alpha(&resources.color_spaces);
+    alpha < 255 || color_alpha < 255
+}
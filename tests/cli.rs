@@ -0,0 +1,50 @@
+use std::path::PathBuf;
+use std::process::Command;
+
+const BIN: &str = env!("CARGO_BIN_EXE_ofd2img");
+const TEST_OFD: &str = concat!(env!("CARGO_MANIFEST_DIR"), "/data/test.ofd");
+
+/// 每个测试独占的空输出目录
+fn output_dir(name: &str) -> PathBuf {
+    let dir = std::env::temp_dir().join(format!("ofd2img-cli-{}-{}", std::process::id(), name));
+    let _ = std::fs::remove_dir_all(&dir);
+    dir
+}
+
+#[test]
+fn converts_every_page_to_numbered_png_files() {
+    let dir = output_dir("png");
+    let status = Command::new(BIN).arg(TEST_OFD).arg(&dir).args(["--dpi", "36"]).status().unwrap();
+    assert!(status.success());
+    let page = dir.join("page_1.png");
+    assert!(page.is_file(), "{} was not written", page.display());
+    assert!(image::open(&page).is_ok());
+    assert!(!dir.join("page_2.png").exists());
+    std::fs::remove_dir_all(&dir).unwrap();
+}
+
+#[test]
+fn writes_pdf_output_as_a_single_file() {
+    let dir = output_dir("pdf");
+    std::fs::create_dir_all(&dir).unwrap();
+    let pdf = dir.join("out.pdf");
+    let status = Command::new(BIN).arg(TEST_OFD).arg(&pdf).status().unwrap();
+    assert!(status.success());
+    assert!(std::fs::read(&pdf).unwrap().starts_with(b"%PDF"));
+    std::fs::remove_dir_all(&dir).unwrap();
+}
+
+#[test]
+fn rejects_an_invalid_page_range() {
+    let dir = output_dir("pages");
+    let output = Command::new(BIN).arg(TEST_OFD).arg(&dir).args(["--pages", "3-1"]).output().unwrap();
+    assert!(!output.status.success());
+    assert!(String::from_utf8_lossy(&output.stderr).contains("invalid page range '3-1'"));
+    assert!(!dir.join("page_1.png").exists());
+}
+
+#[test]
+fn rejects_an_unknown_format() {
+    let output = Command::new(BIN).arg(TEST_OFD).args(["out", "--format", "gif"]).output().unwrap();
+    assert_eq!(output.status.code(), Some(2));
+}